@@ -161,36 +161,46 @@ pub fn builtins_table(input: TokenStream) -> TokenStream {
                 markdown.push_str(&syn::parse2::<DocComment>(attr.tokens).unwrap().0.value());
                 markdown.push_str("\n");
             } else {
-                attr_calls.extend(quote_spanned! { attr_span => .docs.#path });
-                attr_calls.extend(attr.tokens);
+                let tokens = attr.tokens;
+                attr_calls.extend(quote_spanned! { attr_span => docs.#path #tokens; });
             }
         }
 
         if let Some(markdown_span) = markdown_span {
             let lit = LitStr::new(&markdown, span);
-            attr_calls.extend(quote_spanned! { markdown_span => .docs.doc(#lit) });
+            attr_calls.extend(quote_spanned! { markdown_span => docs.doc(#lit); });
         }
 
-        let line = match entry.body {
+        let add = match entry.body {
             EntryBody::None => {
                 quote_spanned! { span =>
-                    tree.add_builtin_type(#path) #attr_calls;
+                    tree.add_builtin_type(#path)
                 }
             },
             EntryBody::Variable(None) => {
                 quote_spanned! { span =>
-                    tree.add_builtin_var(#path, None) #attr_calls;
+                    tree.add_builtin_var(#path, None)
                 }
             },
             EntryBody::Variable(Some(expr)) => {
                 quote_spanned! { span =>
-                    tree.add_builtin_var(#path, Some(#expr)) #attr_calls;
+                    tree.add_builtin_var(#path, Some(#expr))
                 }
             },
             EntryBody::Proc(args) => {
                 let args: Vec<_> = args.into_iter().map(|x| LitStr::new(&x.name.to_string(), x.name.span())).collect();
                 quote_spanned! { span =>
-                    tree.add_builtin_proc(#path, &[ #(#args),* ]) #attr_calls;
+                    tree.add_builtin_proc(#path, &[ #(#args),* ])
+                }
+            }
+        };
+        let line = if attr_calls.is_empty() {
+            quote_spanned! { span => #add; }
+        } else {
+            quote_spanned! { span =>
+                {
+                    let docs = &mut #add.docs;
+                    #attr_calls
                 }
             }
         };
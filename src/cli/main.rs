@@ -67,26 +67,37 @@ impl Context {
                 _ => dm::DEFAULT_ENV.into(),
             },
         };
-        println!("parsing {}", environment.display());
+        eprintln!("parsing {}", environment.display());
 
         if let Some(parent) = environment.parent() {
             self.icon_cache.set_icons_root(&parent);
         }
 
         self.dm_context.autodetect_config(&environment);
-        let pp = match dm::preprocessor::Preprocessor::new(&self.dm_context, environment) {
+        let preprocess_cache_file = dm::preprocess_cache::PreprocessCache::path_for(&environment);
+        let mut pp = match dm::preprocessor::Preprocessor::new(&self.dm_context, environment) {
             Ok(pp) => pp,
             Err(e) => {
                 eprintln!("i/o error opening environment:\n{}", e);
                 std::process::exit(1);
             }
         };
-        let indents = dm::indents::IndentProcessor::new(&self.dm_context, pp);
+        if opt.preprocess_cache {
+            pp.use_cache(dm::preprocess_cache::PreprocessCache::read(&preprocess_cache_file));
+        }
+        let parse_cache = pp.parse_cache();
+        let indents = dm::indents::IndentProcessor::new(&self.dm_context, &mut pp);
         let mut parser = dm::parser::Parser::new(&self.dm_context, indents);
         if self.procs {
             parser.enable_procs();
         }
+        parser.use_cache(parse_cache);
         self.objtree = parser.parse_object_tree();
+        if let Some(cache) = pp.take_cache().filter(|cache| cache.is_changed()) {
+            if let Err(e) = cache.write(&preprocess_cache_file) {
+                eprintln!("i/o error writing {}: {}", preprocess_cache_file.display(), e);
+            }
+        }
     }
 }
 
@@ -109,6 +120,11 @@ struct Opt {
     #[structopt(long="jobs", default_value="1")]
     jobs: usize,
 
+    /// Reuse preprocessed and parsed files from, and save them to, a cache
+    /// next to the environment file.
+    #[structopt(long="preprocess-cache")]
+    preprocess_cache: bool,
+
     #[structopt(subcommand)]
     command: Command,
 }
@@ -175,6 +191,17 @@ enum Command {
         /// The list of maps to show info on.
         files: Vec<String>,
     },
+    /// Dump the object tree as JSON.
+    #[structopt(name="dump-objtree")]
+    DumpObjtree {
+        /// Only include these types and their subtypes.
+        #[structopt(long="path")]
+        paths: Vec<String>,
+
+        /// Include vars and procs which are only defined by BYOND.
+        #[structopt(long="builtins")]
+        builtins: bool,
+    },
 }
 
 fn run(opt: &Opt, command: &Command, context: &mut Context) {
@@ -404,6 +431,17 @@ fn run(opt: &Opt, command: &Command, context: &mut Context) {
             output_json(&report);
         },
         // --------------------------------------------------------------------
+        Command::DumpObjtree {
+            ref paths, builtins,
+        } => {
+            context.objtree(opt);
+            let filter = dm::json::Filter {
+                paths: paths.clone(),
+                builtins,
+            };
+            output_json(&dm::json::Tree::new(&context.dm_context, &context.objtree, &filter));
+        },
+        // --------------------------------------------------------------------
     }
 }
 
@@ -48,6 +48,7 @@ fn main2() -> Result<(), Box<dyn std::error::Error>> {
     let mut output_path = "dmdoc".to_owned();
     let mut index_path = None;
     let mut dry_run = false;
+    let mut preprocess_cache = false;
 
     let mut args = std::env::args();
     let _ = args.next();  // skip executable name
@@ -63,6 +64,8 @@ fn main2() -> Result<(), Box<dyn std::error::Error>> {
             index_path = Some(args.next().expect("must specify a value for --index"));
         } else if arg == "--dry-run" {
             dry_run = true;
+        } else if arg == "--preprocess-cache" {
+            preprocess_cache = true;
         } else {
             return Err(format!("unknown argument: {}", arg).into());
         }
@@ -86,11 +89,20 @@ fn main2() -> Result<(), Box<dyn std::error::Error>> {
     context.autodetect_config(&environment);
     context.set_print_severity(Some(dm::Severity::Error));
     let mut pp = dm::preprocessor::Preprocessor::new(&context, environment.clone())?;
+    let preprocess_cache_file = dm::preprocess_cache::PreprocessCache::path_for(&environment);
+    if preprocess_cache {
+        pp.use_cache(dm::preprocess_cache::PreprocessCache::read(&preprocess_cache_file));
+    }
     let (objtree, module_docs) = {
+        let parse_cache = pp.parse_cache();
         let indents = dm::indents::IndentProcessor::new(&context, &mut pp);
-        let parser = dm::parser::Parser::new(&context, indents);
+        let mut parser = dm::parser::Parser::new(&context, indents);
+        parser.use_cache(parse_cache);
         parser.parse_with_module_docs()
     };
+    if let Some(cache) = pp.take_cache().filter(|cache| cache.is_changed()) {
+        cache.write(&preprocess_cache_file)?;
+    }
     let define_history = pp.finalize();
 
     println!("collating documented types");
@@ -0,0 +1,546 @@
+//! dmfmt, an opinionated code formatter for DreamMaker.
+//!
+//! Formatting works line by line on the
+//! [concrete syntax tree](../dreammaker/cst/index.html), so comments survive
+//! and the text of tokens is never altered. Code is indented with tabs,
+//! operators are spaced according to `Token::separate_from`, paths in `var/`
+//! declarations are joined, trailing whitespace is removed, and runs of blank
+//! lines are shortened. Preprocessor directives, including macro bodies and
+//! `#ifdef` lines, are kept as written.
+//!
+//! Lines which the preprocessor skips, such as those in an `#if 0` block or
+//! an `#ifdef` whose macro is defined elsewhere, are also kept as written,
+//! because their indentation cannot be checked.
+//!
+//! Because the same indentation can mean different things depending on its
+//! surroundings, [`format_checked`] re-parses the output and refuses any
+//! result whose tokens or syntax tree differ from the original.
+
+extern crate dreammaker as dm;
+
+use std::collections::HashSet;
+
+use dm::{Context, DMError, FileId, Location};
+use dm::config::DMFmt;
+use dm::cst::{ConcreteSyntaxTree, CstToken, TriviaKind};
+use dm::ast::Parameter;
+use dm::lexer::{Punctuation, Token};
+use dm::objtree::{Code, ObjectTree};
+use dm::preprocessor::Preprocessor;
+
+/// The path given to the preprocessor for code being formatted.
+const BUFFER_PATH: &str = "dmfmt.dm";
+
+/// Identifiers after which `-`, `+`, and `*` are unary operators.
+const KEYWORDS: &[&str] = &["return", "to", "step", "as", "throw", "del", "else"];
+
+/// Format DM source code.
+///
+/// The output is not verified; see [`format_checked`].
+pub fn format(source: &[u8], config: &DMFmt) -> Vec<u8> {
+    let context = Context::default();
+    let cst = ConcreteSyntaxTree::new(&context, FileId::default(), source);
+    let mut formatter = Formatter::new(&cst, config, active_lines(source));
+
+    let tokens = cst.tokens();
+    let mut start = 0;
+    for (i, token) in tokens.iter().enumerate() {
+        if token.token == Token::Punct(Punctuation::Newline) {
+            formatter.line(&tokens[start..i], token);
+            start = i + 1;
+        }
+    }
+    formatter.finish()
+}
+
+/// Format DM source code, returning an error instead if the formatted code
+/// would lex or parse differently from the original.
+pub fn format_checked(source: &[u8], config: &DMFmt) -> Result<Vec<u8>, DMError> {
+    let formatted = format(source, config);
+    verify(source, &formatted)?;
+    Ok(formatted)
+}
+
+/// Check that formatted code has the same tokens and syntax tree as the
+/// original, ignoring whitespace, comments, and locations.
+///
+/// Locations in the returned error refer to the formatted code.
+pub fn verify(original: &[u8], formatted: &[u8]) -> Result<(), DMError> {
+    let context = Context::default();
+    let before = ConcreteSyntaxTree::new(&context, FileId::default(), original);
+    let after = ConcreteSyntaxTree::new(&context, FileId::default(), formatted);
+    let mut before_tokens = significant_tokens(&before);
+    let mut after_tokens = significant_tokens(&after);
+    loop {
+        match (before_tokens.next(), after_tokens.next()) {
+            (None, None) => break,
+            (Some(old), Some(new)) => {
+                if normalize(&old.token) != normalize(&new.token) {
+                    return Err(DMError::new(new.location, format!("formatting changed `{}` to `{}`", old.token, new.token)));
+                }
+            }
+            (Some(old), None) => {
+                return Err(DMError::new(Location::default(), format!("formatting removed `{}`", old.token)));
+            }
+            (None, Some(new)) => {
+                return Err(DMError::new(new.location, format!("formatting added `{}`", new.token)));
+            }
+        }
+    }
+
+    let (before_tree, before_errors) = parse(original);
+    let (after_tree, after_errors) = parse(formatted);
+    if after_errors > before_errors {
+        return Err(DMError::new(Location::default(), format!(
+            "formatting introduced {} parse error(s)",
+            after_errors - before_errors,
+        )));
+    }
+    compare_trees(&before_tree, &after_tree)
+}
+
+fn significant_tokens<'a>(cst: &'a ConcreteSyntaxTree) -> impl Iterator<Item=&'a CstToken> + 'a {
+    cst.tokens().iter().filter(|t| t.token != Token::Punct(Punctuation::Newline))
+}
+
+/// Forget whether an identifier was followed by whitespace.
+fn normalize(token: &Token) -> Token {
+    match *token {
+        Token::Ident(ref name, _) => Token::Ident(name.clone(), false),
+        ref other => other.clone(),
+    }
+}
+
+/// The lines which have tokens that make it through the preprocessor.
+fn active_lines(source: &[u8]) -> HashSet<u32> {
+    let context = Context::default();
+    let pp = Preprocessor::from_buffer(&context, BUFFER_PATH.into(), String::from_utf8_lossy(source));
+    let file = context.get_file(BUFFER_PATH.as_ref());
+    pp.filter(|token| Some(token.location.file) == file && !token.token.is_whitespace())
+        .map(|token| token.location.line)
+        .collect()
+}
+
+fn parse(source: &[u8]) -> (ObjectTree, usize) {
+    let context = Context::default();
+    let pp = Preprocessor::from_buffer(&context, BUFFER_PATH.into(), String::from_utf8_lossy(source));
+    let indents = dm::indents::IndentProcessor::new(&context, pp);
+    let mut parser = dm::parser::Parser::new(&context, indents);
+    parser.enable_procs();
+    let tree = parser.parse_object_tree_without_builtins();
+    let errors = context.errors().len();
+    (tree, errors)
+}
+
+fn compare_trees(before: &ObjectTree, after: &ObjectTree) -> Result<(), DMError> {
+    for ty in before.iter_types() {
+        let other = match after.find(&ty.path) {
+            Some(other) => other,
+            None => return Err(DMError::new(Location::default(), format!("formatting removed the type {}", ty.path))),
+        };
+
+        for (name, var) in ty.vars.iter() {
+            let changed = DMError::new(other.location, format!("formatting changed the var {}/{}", ty.path, name));
+            let other_var = other.vars.get(name).ok_or_else(|| changed.clone())?;
+            if var.value.expression != other_var.value.expression
+                || var.declaration.as_ref().map(|d| &d.var_type) != other_var.declaration.as_ref().map(|d| &d.var_type)
+            {
+                return Err(DMError::new(other_var.value.location, changed.description()));
+            }
+        }
+
+        for (name, proc) in ty.procs.iter() {
+            let changed = DMError::new(other.location, format!("formatting changed the proc {}/{}", ty.path, name));
+            let other_proc = other.procs.get(name).ok_or_else(|| changed.clone())?;
+            if proc.value.len() != other_proc.value.len()
+                || proc.declaration.as_ref().map(|d| d.kind) != other_proc.declaration.as_ref().map(|d| d.kind)
+            {
+                return Err(changed);
+            }
+            for (value, other_value) in proc.value.iter().zip(other_proc.value.iter()) {
+                let same_code = match (&value.code, &other_value.code) {
+                    (Code::Present(a), Code::Present(b)) => a == b,
+                    (Code::Invalid(_), Code::Invalid(_)) |
+                    (Code::Builtin, Code::Builtin) |
+                    (Code::Disabled, Code::Disabled) => true,
+                    _ => false,
+                };
+                if !same_code || !same_parameters(&value.parameters, &other_value.parameters) {
+                    return Err(DMError::new(other_value.location, changed.description()));
+                }
+            }
+        }
+
+        if ty.vars.len() != other.vars.len() || ty.procs.len() != other.procs.len() {
+            return Err(DMError::new(other.location, format!("formatting added to the type {}", ty.path)));
+        }
+    }
+    if before.iter_types().count() != after.iter_types().count() {
+        return Err(DMError::new(Location::default(), "formatting added a type"));
+    }
+    Ok(())
+}
+
+fn same_parameters(before: &[Parameter], after: &[Parameter]) -> bool {
+    before.len() == after.len() && before.iter().zip(after.iter()).all(|(a, b)| {
+        Parameter { location: b.location, ..a.clone() } == *b
+    })
+}
+
+// ----------------------------------------------------------------------------
+// The formatter
+
+/// The indentation to give a comment or blank line, which is decided by the
+/// code line that follows it.
+enum PendingIndent {
+    /// Spaces and tabs as written, with how many there were.
+    Written(usize, Vec<u8>),
+    /// A known number of tabs.
+    Tabs(usize),
+}
+
+struct PendingLine {
+    indent: PendingIndent,
+    text: Vec<u8>,
+}
+
+struct Formatter<'a> {
+    cst: &'a ConcreteSyntaxTree<'a>,
+    config: &'a DMFmt,
+    active_lines: HashSet<u32>,
+    output: Vec<u8>,
+    newline: &'static [u8],
+    /// Comment and blank lines waiting for the next code line.
+    pending: Vec<PendingLine>,
+    blank_lines: usize,
+
+    // Mirrors of the indent processor's state.
+    current: Option<(usize, usize)>,
+    parentheses: usize,
+    /// The indentation of the line which opened the current parentheses.
+    statement_level: usize,
+
+    in_var_path: bool,
+}
+
+impl<'a> Formatter<'a> {
+    fn new(cst: &'a ConcreteSyntaxTree<'a>, config: &'a DMFmt, active_lines: HashSet<u32>) -> Formatter<'a> {
+        let source = cst.source();
+        let crlf = source.iter().position(|&b| b == b'\n').map_or(false, |i| i > 0 && source[i - 1] == b'\r');
+        Formatter {
+            cst,
+            config,
+            active_lines,
+            output: Vec::with_capacity(source.len()),
+            newline: if crlf { b"\r\n" } else { b"\n" },
+            pending: Vec::new(),
+            blank_lines: 0,
+            current: None,
+            parentheses: 0,
+            statement_level: 0,
+            in_var_path: false,
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        // Trailing blank lines are dropped, but trailing comments are not.
+        while self.pending.last().map_or(false, |line| line.text.is_empty()) {
+            self.pending.pop();
+        }
+        let unit = self.current.map(|(spaces, _)| spaces);
+        self.flush(unit);
+        self.output
+    }
+
+    /// Format one line, given its tokens and the newline which ends it.
+    fn line(&mut self, tokens: &[CstToken], newline: &CstToken) {
+        let (first, last) = match (tokens.first(), tokens.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return self.comment_line(newline),
+        };
+        let (spaces, indent_end) = match first.leading.first() {
+            Some(trivia) if trivia.kind == TriviaKind::Indent => (trivia.span.len(), trivia.span.end),
+            _ => (0, first.full_span().start),
+        };
+
+        if first.token == Token::Punct(Punctuation::Hash) || !self.active_lines.contains(&first.location.line) {
+            // Directives and skipped lines are kept as written.
+            let unit = self.current.map(|(spaces, _)| spaces);
+            self.flush(unit);
+            let start = first.full_span().start;
+            let mut text = self.cst.text(&(start..last.full_span().end)).to_owned();
+            trim_end(&mut text, last.span.end - start);
+            self.emit(b"", text);
+            return;
+        }
+
+        let before = self.current.map(|(spaces, _)| spaces);
+        let level = if self.parentheses > 0 {
+            match first.token {
+                Token::Punct(Punctuation::RParen) | Token::Punct(Punctuation::RBracket) => self.statement_level,
+                _ => self.statement_level + 1,
+            }
+        } else {
+            let level = match first.token {
+                // The indent processor ignores the indentation of these.
+                Token::Punct(Punctuation::LBrace) => self.current.map_or(0, |(_, indents)| indents),
+                Token::Punct(Punctuation::RBrace) => self.current.map_or(0, |(_, indents)| indents.saturating_sub(1)),
+                _ => self.indent(spaces),
+            };
+            self.statement_level = level;
+            level
+        };
+        self.flush(before.or_else(|| self.current.map(|(spaces, _)| spaces)));
+
+        let mut text = Vec::new();
+        let verbatim = tokens.iter()
+            .flat_map(|t| t.leading.iter().chain(t.trailing.iter()))
+            .any(|t| matches!(t.kind, TriviaKind::LineContinuation | TriviaKind::Skipped));
+        if verbatim {
+            text.extend_from_slice(self.cst.text(&(indent_end..last.full_span().end)));
+            trim_end(&mut text, last.span.end - indent_end);
+        } else {
+            self.code(tokens, indent_end, &mut text);
+        }
+        for token in tokens {
+            self.track_braces(&token.token);
+        }
+        self.emit(&vec![b'\t'; level], text);
+    }
+
+    /// Queue a line with no tokens, which is blank or holds only comments.
+    fn comment_line(&mut self, newline: &CstToken) {
+        let (spaces, rest) = match newline.leading.split_first() {
+            Some((first, rest)) if first.kind == TriviaKind::Indent => (first.span.clone(), rest),
+            _ => (0..0, &newline.leading[..]),
+        };
+        let mut text = Vec::new();
+        if rest.iter().any(|t| t.kind != TriviaKind::Whitespace) {
+            let end = newline.leading.last().map_or(spaces.end, |t| t.span.end);
+            text.extend_from_slice(self.cst.text(&(spaces.end..end)));
+            trim_end(&mut text, 0);
+        }
+
+        let indent = if self.parentheses > 0 {
+            PendingIndent::Tabs(self.statement_level + 1)
+        } else {
+            PendingIndent::Written(spaces.len(), self.cst.text(&spaces).to_owned())
+        };
+        self.pending.push(PendingLine { indent, text });
+    }
+
+    /// Write out pending comment and blank lines, indenting comments by the
+    /// spaces-per-indent of the surrounding code.
+    fn flush(&mut self, unit: Option<usize>) {
+        for line in std::mem::take(&mut self.pending) {
+            if line.text.is_empty() {
+                self.emit(b"", Vec::new());
+                continue;
+            }
+            let indent = match line.indent {
+                PendingIndent::Tabs(tabs) => vec![b'\t'; tabs],
+                PendingIndent::Written(0, _) => Vec::new(),
+                PendingIndent::Written(spaces, written) => match unit {
+                    Some(unit) if spaces % unit == 0 => vec![b'\t'; spaces / unit],
+                    _ => written,
+                },
+            };
+            self.emit(&indent, line.text);
+        }
+    }
+
+    /// Write a line, skipping extra blank lines.
+    fn emit(&mut self, indent: &[u8], text: Vec<u8>) {
+        if text.is_empty() {
+            self.blank_lines += 1;
+            if self.output.is_empty() || self.blank_lines > self.config.max_blank_lines {
+                return;
+            }
+        } else {
+            self.blank_lines = 0;
+            self.output.extend_from_slice(indent);
+        }
+        self.output.extend_from_slice(&text);
+        self.output.extend_from_slice(self.newline);
+    }
+
+    /// Mirror the indent processor's handling of a line's indentation,
+    /// returning the number of indents it has.
+    fn indent(&mut self, spaces: usize) -> usize {
+        if spaces == 0 {
+            self.current = None;
+            return 0;
+        }
+        let indents = match self.current {
+            None => 1,
+            Some((spaces_per_indent, _)) => spaces / spaces_per_indent,
+        };
+        self.current = Some((self.current.map_or(spaces, |(spaces_per_indent, _)| spaces_per_indent), indents));
+        indents
+    }
+
+    fn track_braces(&mut self, token: &Token) {
+        match *token {
+            Token::Punct(Punctuation::LBrace) => {
+                self.current = match self.current {
+                    None => Some((1, 1)),
+                    Some((x, y)) => Some((x, y + 1)),
+                };
+            }
+            Token::Punct(Punctuation::RBrace) => {
+                self.current = match self.current {
+                    None | Some((_, 1)) => None,
+                    Some((x, y)) => Some((x, y - 1)),
+                };
+            }
+            Token::Punct(Punctuation::LParen) => self.parentheses += 1,
+            Token::Punct(Punctuation::RParen) => self.parentheses = self.parentheses.saturating_sub(1),
+            _ => {}
+        }
+    }
+
+    /// Write the tokens of a code line with normalized spacing.
+    fn code(&mut self, tokens: &[CstToken], indent_end: usize, text: &mut Vec<u8>) {
+        self.in_var_path = false;
+        let first = &tokens[0];
+        let leading: Vec<_> = first.leading.iter().filter(|t| t.span.start >= indent_end).collect();
+        self.trivia(leading.into_iter(), text);
+
+        let mut protected = 0;
+        for (i, token) in tokens.iter().enumerate() {
+            if i > 0 {
+                let prev = &tokens[i - 1];
+                let prev2 = if i > 1 { Some(&tokens[i - 2].token) } else { None };
+                let gap: Vec<_> = prev.trailing.iter().chain(token.leading.iter()).collect();
+                if gap.iter().all(|t| t.kind == TriviaKind::Whitespace) {
+                    if let Token::DocComment(_) = token.token {
+                        // Keep comments after code aligned as they were.
+                        for trivia in gap {
+                            text.extend_from_slice(self.cst.text(&trivia.span));
+                        }
+                    } else if self.space_between(prev2, &prev.token, &token.token, !gap.is_empty()) {
+                        text.push(b' ');
+                    }
+                } else {
+                    self.trivia(gap.into_iter(), text);
+                }
+            }
+            text.extend_from_slice(self.cst.text(&token.span));
+            protected = text.len();
+        }
+
+        let last = &tokens[tokens.len() - 1];
+        self.trivia(last.trailing.iter(), text);
+        trim_end(text, protected);
+    }
+
+    /// Write trivia between tokens, collapsing whitespace except where it
+    /// aligns a trailing comment.
+    fn trivia<'t, I: Iterator<Item=&'t dm::cst::Trivia>>(&self, trivia: I, text: &mut Vec<u8>) {
+        let mut trivia = trivia.peekable();
+        while let Some(each) = trivia.next() {
+            if each.kind != TriviaKind::Whitespace {
+                text.extend_from_slice(self.cst.text(&each.span));
+            } else if trivia.peek().map_or(false, |next| next.kind == TriviaKind::LineComment) {
+                text.extend_from_slice(self.cst.text(&each.span));
+            } else {
+                text.push(b' ');
+            }
+        }
+    }
+
+    /// Decide whether to put a space between two tokens on the same line.
+    fn space_between(&mut self, prev2: Option<&Token>, prev: &Token, next: &Token, had_space: bool) -> bool {
+        use self::Punctuation::*;
+
+        // Paths in var declarations are written without spaces.
+        let var_path = match (prev, next) {
+            (Token::Ident(var, _), Token::Punct(Slash)) if var == "var" => {
+                !matches!(prev2, Some(Token::Punct(Dot)) | Some(Token::Punct(SafeDot)) | Some(Token::Punct(Scope)))
+            }
+            (Token::Punct(Slash), Token::Ident(..)) |
+            (Token::Ident(..), Token::Punct(Slash)) => self.in_var_path,
+            _ => false,
+        };
+        self.in_var_path = var_path;
+        if var_path {
+            return false;
+        }
+
+        match *next {
+            Token::Punct(RParen) | Token::Punct(RBracket) | Token::Punct(Comma) | Token::Punct(Semicolon) => return false,
+            _ => {}
+        }
+        match *prev {
+            Token::Punct(LParen) | Token::Punct(LBracket) => return false,
+            Token::Punct(Comma) | Token::Punct(Semicolon) => return true,
+            _ => {}
+        }
+
+        // Member access and scope operators keep their spacing, as do colons,
+        // which lex differently depending on the whitespace before them.
+        let keeps_spacing = |token: &Token| matches!(*token,
+            Token::Punct(Colon)
+            | Token::Punct(CloseColon)
+            | Token::Punct(Dot)
+            | Token::Punct(SafeDot)
+            | Token::Punct(SafeColon)
+            | Token::Punct(Scope)
+            | Token::Punct(Super)
+        );
+        if keeps_spacing(prev) || keeps_spacing(next) {
+            return had_space;
+        }
+
+        if let Token::Punct(Sub) | Token::Punct(Add) | Token::Punct(Mul) = *prev {
+            if is_unary_position(prev2) && is_operand_start(next) {
+                return false;
+            }
+        }
+
+        next.separate_from(prev) || had_space
+    }
+}
+
+/// Whether an operator following `prev` is unary.
+fn is_unary_position(prev: Option<&Token>) -> bool {
+    use self::Punctuation::*;
+    match prev {
+        None => true,
+        Some(Token::Punct(p)) => !matches!(p, RParen | RBracket | PlusPlus | MinusMinus | Dot | Super),
+        Some(Token::Ident(name, _)) => KEYWORDS.contains(&name.as_str()),
+        Some(Token::InterpStringBegin(_)) | Some(Token::InterpStringPart(_)) => true,
+        Some(_) => false,
+    }
+}
+
+/// Whether a unary operator can be joined to this token without forming a
+/// different operator.
+fn is_operand_start(token: &Token) -> bool {
+    matches!(*token,
+        Token::Ident(..)
+        | Token::Int(_)
+        | Token::Float(_)
+        | Token::String(_)
+        | Token::InterpStringBegin(_)
+        | Token::Resource(_)
+        | Token::Punct(Punctuation::LParen)
+    )
+}
+
+/// Remove trailing whitespace, but not from the first `keep` bytes or from
+/// before a backslash, which would then continue the line. Carriage returns,
+/// which even doc comments leave out, are always removed.
+fn trim_end(text: &mut Vec<u8>, keep: usize) {
+    while text.last() == Some(&b'\r') {
+        text.pop();
+    }
+    let mut end = text.len();
+    while end > keep && matches!(text[end - 1], b' ' | b'\t' | b'\r') {
+        end -= 1;
+    }
+    if end < text.len() && end > 0 && text[end - 1] == b'\\' {
+        return;
+    }
+    text.truncate(end);
+}
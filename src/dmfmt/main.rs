@@ -0,0 +1,124 @@
+//! dmfmt, an opinionated code formatter for DreamMaker.
+
+extern crate dreammaker as dm;
+extern crate dmfmt;
+
+use std::path::{Path, PathBuf};
+
+// ----------------------------------------------------------------------------
+// Command-line interface
+
+fn main() {
+    // command-line args
+    let mut config_file = None;
+    let mut check = false;
+    let mut paths = Vec::new();
+
+    let mut args = std::env::args();
+    let _ = args.next();  // skip executable name
+    while let Some(arg) = args.next() {
+        if arg == "-V" || arg == "--version" {
+            println!(
+                "dmfmt {}  Copyright (C) 2017-2021  Tad Hardesty",
+                env!("CARGO_PKG_VERSION")
+            );
+            println!("This program comes with ABSOLUTELY NO WARRANTY. This is free software,");
+            println!("and you are welcome to redistribute it under the conditions of the GNU");
+            println!("General Public License version 3.");
+            return;
+        } else if arg == "-c" {
+            config_file = Some(args.next().expect("must specify a file for -c"));
+        } else if arg == "--check" {
+            check = true;
+        } else if arg.starts_with('-') {
+            eprintln!("unknown argument: {}", arg);
+            return;
+        } else {
+            paths.push(PathBuf::from(arg));
+        }
+    }
+
+    let context = dm::Context::default();
+    if let Some(filepath) = config_file {
+        context.force_config(filepath.as_ref());
+    } else if let Ok(Some(dme)) = dm::detect_environment_default() {
+        context.autodetect_config(&dme);
+    }
+    let config = context.config().dmfmt.clone();
+
+    if paths.is_empty() {
+        paths.push(PathBuf::from("."));
+    }
+    let mut files = Vec::new();
+    for path in paths {
+        if let Err(e) = collect_files(&path, &mut files) {
+            eprintln!("{}: {}", path.display(), e);
+            std::process::exit(1);
+        }
+    }
+    files.retain(|file| !config.exclude.iter().any(|exclude| file.strip_prefix(".").unwrap_or(file).starts_with(exclude)));
+
+    let mut failed = false;
+    let mut changed = 0;
+    for file in files {
+        let original = match std::fs::read(&file) {
+            Ok(original) => original,
+            Err(e) => {
+                eprintln!("{}: {}", file.display(), e);
+                failed = true;
+                continue;
+            }
+        };
+        let formatted = match dmfmt::format_checked(&original, &config) {
+            Ok(formatted) => formatted,
+            Err(e) => {
+                let location = e.location();
+                eprintln!("{}, line {}, column {}: not formatted: {}", file.display(), location.line, location.column, e.description());
+                failed = true;
+                continue;
+            }
+        };
+        if formatted == original {
+            continue;
+        }
+
+        changed += 1;
+        if check {
+            println!("{}: would be reformatted", file.display());
+        } else if let Err(e) = std::fs::write(&file, &formatted) {
+            eprintln!("{}: {}", file.display(), e);
+            failed = true;
+        } else {
+            println!("{}: reformatted", file.display());
+        }
+    }
+
+    if check && changed > 0 {
+        println!("{} file(s) would be reformatted", changed);
+        std::process::exit(1);
+    }
+    if failed {
+        std::process::exit(1);
+    }
+}
+
+/// Find the `.dm` files at or within a path.
+fn collect_files(path: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    if !path.is_dir() {
+        files.push(path.to_owned());
+        return Ok(());
+    }
+    let mut entries = std::fs::read_dir(path)?.collect::<Result<Vec<_>, _>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let path = entry.path();
+        if path.is_dir() {
+            if !entry.file_name().to_string_lossy().starts_with('.') {
+                collect_files(&path, files)?;
+            }
+        } else if path.extension().map_or(false, |ext| ext == "dm") {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
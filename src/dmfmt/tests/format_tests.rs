@@ -0,0 +1,157 @@
+extern crate dreammaker as dm;
+extern crate dmfmt;
+
+use dm::config::DMFmt;
+
+fn check_format(code: &str, expected: &str) {
+    check_format_with_config(code, expected, &DMFmt::default());
+}
+
+fn check_format_with_config(code: &str, expected: &str, config: &DMFmt) {
+    let formatted = match dmfmt::format_checked(code.as_bytes(), config) {
+        Ok(formatted) => formatted,
+        Err(e) => panic!("formatting failed verification: {}", e.description()),
+    };
+    assert_eq!(String::from_utf8_lossy(&formatted), expected);
+
+    let again = dmfmt::format(&formatted, config);
+    assert_eq!(String::from_utf8_lossy(&again), expected, "formatting is not idempotent");
+}
+
+#[test]
+fn indentation() {
+    check_format(r#"
+/obj/item
+    name = "thing"
+    proc/use()
+        if(1)
+            return
+        // comment
+    proc/other()
+        return
+"#.trim_start(), "/obj/item\n\tname = \"thing\"\n\tproc/use()\n\t\tif(1)\n\t\t\treturn\n\t\t// comment\n\tproc/other()\n\t\treturn\n");
+
+    // Continuation lines are indented once more than their statement.
+    check_format(r#"
+/proc/test()
+    var/list/L = list(
+        1,
+            2,
+    )
+"#.trim_start(), "/proc/test()\n\tvar/list/L = list(\n\t\t1,\n\t\t2,\n\t)\n");
+}
+
+#[test]
+fn operator_spacing() {
+    check_format(r#"
+/proc/test(a,b=2)
+	var/x=a+b*-2
+	x+=(a?b : 1) ; x++
+	if(!x&&a>=b || x!=- 1)
+		return src:loc
+	return list( "key"=a.b , a ?.b)
+"#.trim_start(), r#"/proc/test(a, b = 2)
+	var/x = a + b * -2
+	x += (a ? b : 1); x++
+	if(!x && a >= b || x != -1)
+		return src:loc
+	return list("key" = a.b, a ?.b)
+"#);
+
+    // Spacing inside string interpolation follows the same rules.
+    check_format("var/s = \"[1+2] and [ 3 ]\"\n", "var/s = \"[1 + 2] and [ 3 ]\"\n");
+}
+
+#[test]
+fn var_paths() {
+    check_format(r#"
+/obj
+	var / list /stuff
+	var/static/ x = 1
+/proc/test(var /obj/O)
+	for(var / i in 1 to 10)
+		world << O / i
+"#.trim_start(), r#"/obj
+	var/list/stuff
+	var/static/x = 1
+/proc/test(var/obj/O)
+	for(var/i in 1 to 10)
+		world << O / i
+"#);
+}
+
+#[test]
+fn whitespace_and_blank_lines() {
+    check_format(
+        "\n\n/obj  \n\tname = 1 \t\n\n\n\n\n\tdesc = 2  // note  \n\n\n",
+        "/obj\n\tname = 1\n\n\n\tdesc = 2  // note\n",
+    );
+
+    let config = DMFmt {
+        max_blank_lines: 0,
+        ..Default::default()
+    };
+    check_format_with_config("/obj\n\n\tname = 1\n\n/mob\n", "/obj\n\tname = 1\n/mob\n", &config);
+
+    // Line endings are kept, and a missing final newline is added.
+    check_format("/obj \r\n\tname=1\r\n\r\n", "/obj\r\n\tname = 1\r\n");
+    check_format("/obj\n\tname=1", "/obj\n\tname = 1\n");
+}
+
+#[test]
+fn directives_unchanged() {
+    check_format(r#"
+#define ADD(a,b) ((a)+(b))
+#define LONG(x) \
+    list(x,   \
+        x)
+  #ifdef ADD
+/proc/test()
+    #if 0
+        this is not   code
+    #endif
+    return ADD(1,2)
+  #endif
+"#.trim_start(), r#"#define ADD(a,b) ((a)+(b))
+#define LONG(x) \
+    list(x,   \
+        x)
+  #ifdef ADD
+/proc/test()
+    #if 0
+        this is not   code
+    #endif
+	return ADD(1, 2)
+  #endif
+"#);
+}
+
+#[test]
+fn comments_and_strings() {
+    let code = concat!(
+        "/// Doc comment\n",
+        "/obj\n",
+        "    var/x = 1    //! enclosing doc\n",
+        "    var/y = {\"\n",
+        "  block   \n",
+        "\"} /* note */\n",
+        "    var/z = x /* inline */+y\n",
+    );
+    check_format(code, concat!(
+        "/// Doc comment\n",
+        "/obj\n",
+        "\tvar/x = 1    //! enclosing doc\n",
+        "\tvar/y = {\"\n",
+        "  block   \n",
+        "\"} /* note */\n",
+        "\tvar/z = x /* inline */+ y\n",
+    ));
+}
+
+#[test]
+fn verify_rejects_changes() {
+    let original = b"/proc/test()\n\tif(1)\n\t\treturn 1\n\treturn 2\n";
+    assert!(dmfmt::verify(original, b"/proc/test()\n\tif(1)\n\t\treturn 1\n\t\treturn 2\n").is_err());
+    assert!(dmfmt::verify(original, b"/proc/test()\n\tif(1)\n\t\treturn 1\n\treturn 3\n").is_err());
+    assert!(dmfmt::verify(original, b"/proc/test()\n  if (1)\n    return 1 // one\n  return 2\n").is_ok());
+}
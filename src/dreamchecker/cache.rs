@@ -0,0 +1,436 @@
+//! An on-disk cache of proc analysis results, reused across runs.
+//!
+//! A proc's cached results are reused if the file it is in, and every other
+//! file its diagnostics point into, has the same contents and define state as
+//! when they were cached. Any change to the declarations of types, vars, or
+//! procs, to `set` statements, or to the configuration discards the whole
+//! cache. Checks which span many procs, such as the call tree, are always
+//! re-run using the cached per-proc results.
+
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::fs::File;
+use std::hash::{Hash, Hasher};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use dm::{Component, Context, DMError, FileId, Location, Severity};
+use dm::ast::Statement;
+use dm::objtree::{Code, ObjectTree, ProcRef};
+
+use crate::{CalledAt, Environment, KwargInfo, ProcOutcome, ProcResults};
+use crate::hrefs::{HrefLink, HrefRead};
+
+/// Changed whenever the layout of the cache file changes.
+const FORMAT: u32 = 4;
+
+/// Per-proc analysis results from a previous run, and those of this run.
+#[derive(Default)]
+pub struct AnalysisCache {
+    previous: CacheFile,
+    previous_procs: HashMap<(String, String, usize), usize>,
+    /// For each of the previous files, its ID if it is unchanged.
+    unchanged_files: Vec<Option<FileId>>,
+
+    current: CacheFile,
+    current_files: HashMap<FileId, usize>,
+    file_states: HashMap<FileId, u64>,
+    hits: usize,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct CacheFile {
+    format: u32,
+    version: String,
+    declarations: u64,
+    files: Vec<CachedFile>,
+    procs: Vec<CachedProc>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedFile {
+    path: PathBuf,
+    state: u64,
+}
+
+/// A location, with `file` indexing the cache's file list or `None` for the
+/// builtins.
+#[derive(Serialize, Deserialize, Clone, Copy)]
+struct CachedLocation {
+    file: Option<usize>,
+    line: u32,
+    column: u16,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedProcRef {
+    type_path: String,
+    name: String,
+    index: usize,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedProc {
+    proc: CachedProcRef,
+    /// The files this proc's results depend on, its own first.
+    files: Vec<usize>,
+    errors: Vec<CachedError>,
+    calls: Vec<(CachedProcRef, CachedLocation, bool)>,
+    sleeping: Vec<(String, CachedLocation)>,
+    impure: Vec<(String, CachedLocation)>,
+    waitfor: bool,
+    kwargs: Vec<CachedKwargs>,
+    resources: Vec<(CachedLocation, String)>,
+    /// Links as their location, target type path, keys, and whether dynamic.
+    href_links: Vec<(CachedLocation, Option<String>, Vec<String>, bool)>,
+    href_reads: Vec<(CachedLocation, Option<String>)>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedError {
+    location: CachedLocation,
+    severity: u8,
+    dreamchecker: bool,
+    errortype: Option<String>,
+    description: String,
+    notes: Vec<(CachedLocation, String)>,
+    edits: Vec<(CachedLocation, String, String)>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedKwargs {
+    proc: String,
+    location: CachedLocation,
+    called_at: Vec<(String, CachedLocation, u32)>,
+}
+
+impl AnalysisCache {
+    /// Read a cache file, starting empty if it is missing or unreadable.
+    pub fn read(path: &Path) -> AnalysisCache {
+        let previous: CacheFile = match File::open(path) {
+            Ok(file) => serde_json::from_reader(io::BufReader::new(file)).unwrap_or_default(),
+            Err(_) => Default::default(),
+        };
+        AnalysisCache {
+            previous,
+            .. Default::default()
+        }
+    }
+
+    /// Write the results of this run to a cache file.
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        let file = io::BufWriter::new(File::create(path)?);
+        serde_json::to_writer(file, &self.current).map_err(io::Error::from)
+    }
+
+    /// The number of procs whose results were reused.
+    pub fn hits(&self) -> usize {
+        self.hits
+    }
+
+    /// Work out which previous results are still valid for this run.
+    pub(crate) fn prepare(&mut self, context: &Context, objtree: &ObjectTree, environment: &Environment) {
+        self.file_states = environment.define_states.iter()
+            .filter_map(|(&file, &defines)| {
+                let mut hasher = DefaultHasher::new();
+                environment.file_hashes.get(&file)?.hash(&mut hasher);
+                defines.hash(&mut hasher);
+                Some((file, hasher.finish()))
+            })
+            .collect();
+
+        self.current = CacheFile {
+            format: FORMAT,
+            version: env!("CARGO_PKG_VERSION").to_owned(),
+            declarations: declarations_hash(context, objtree),
+            files: Vec::new(),
+            procs: Vec::new(),
+        };
+        self.current_files.clear();
+        self.hits = 0;
+
+        if self.previous.format != self.current.format
+            || self.previous.version != self.current.version
+            || self.previous.declarations != self.current.declarations
+        {
+            self.previous = Default::default();
+        }
+
+        let file_states = &self.file_states;
+        self.unchanged_files = self.previous.files.iter()
+            .map(|file| context.get_file(&file.path).filter(|id| file_states.get(id) == Some(&file.state)))
+            .collect();
+        self.previous_procs = self.previous.procs.iter()
+            .enumerate()
+            .map(|(i, cached)| ((cached.proc.type_path.clone(), cached.proc.name.clone(), cached.proc.index), i))
+            .collect();
+    }
+
+    /// Get the cached outcome of analyzing this proc, if it is still valid.
+    pub(crate) fn get<'o>(&mut self, proc: ProcRef<'o>) -> Option<ProcOutcome<'o>> {
+        let key = (proc.ty().path.clone(), proc.name().to_owned(), proc.index());
+        let cached = &self.previous.procs[*self.previous_procs.get(&key)?];
+
+        let own_file = self.unchanged_files[*cached.files.first()?]?;
+        if own_file != proc.get().location.file {
+            return None;
+        }
+        for &file in cached.files.iter() {
+            self.unchanged_files[file]?;
+        }
+
+        let location = |loc: CachedLocation| self.location(loc);
+        let objtree = proc.tree();
+        let mut results = ProcResults::default();
+        for (callee, loc, new_context) in cached.calls.iter() {
+            let callee = objtree.find(&callee.type_path)?
+                .iter_self_procs()
+                .find(|each| each.name() == callee.name && each.index() == callee.index)?;
+            results.call_tree.entry(proc).or_default().push((callee, location(*loc)?, *new_context));
+        }
+        for (what, loc) in cached.sleeping.iter() {
+            results.sleeping_procs.insert_violator(proc, what, location(*loc)?);
+        }
+        for (what, loc) in cached.impure.iter() {
+            results.impure_procs.insert_violator(proc, what, location(*loc)?);
+        }
+        if cached.waitfor {
+            results.waitfor_procs.insert(proc);
+        }
+        for kwargs in cached.kwargs.iter() {
+            let mut info = KwargInfo {
+                location: location(kwargs.location)?,
+                .. Default::default()
+            };
+            for (name, loc, others) in kwargs.called_at.iter() {
+                info.called_at.insert(name.clone(), CalledAt {
+                    location: location(*loc)?,
+                    others: *others,
+                });
+            }
+            results.used_kwargs.insert(kwargs.proc.clone(), info);
+        }
+        for (loc, path) in cached.resources.iter() {
+            results.resources.push((location(*loc)?, path.clone()));
+        }
+        for (loc, target, keys, dynamic) in cached.href_links.iter() {
+            let target = match target {
+                Some(path) => Some(objtree.find(path)?),
+                None => None,
+            };
+            results.href_links.push(HrefLink {
+                location: location(*loc)?,
+                target,
+                keys: keys.clone(),
+                dynamic: *dynamic,
+            });
+        }
+        for (loc, key) in cached.href_reads.iter() {
+            results.href_reads.push(HrefRead {
+                location: location(*loc)?,
+                ty: proc.ty(),
+                key: key.clone(),
+            });
+        }
+
+        let mut errors = Vec::new();
+        for cached_error in cached.errors.iter() {
+            let mut error = DMError::new(location(cached_error.location)?, cached_error.description.clone())
+                .set_severity(severity_from_u8(cached_error.severity)?);
+            if cached_error.dreamchecker {
+                error = error.with_component(Component::DreamChecker);
+            }
+            // An errortype no longer in the registry means the proc must be
+            // analyzed again.
+            if let Some(errortype) = cached_error.errortype.as_ref() {
+                error = error.with_errortype(dm::diagnostics::errortype(errortype)?);
+            }
+            for (loc, note) in cached_error.notes.iter() {
+                error.add_note(location(*loc)?, note.clone());
+            }
+            for (loc, original, replacement) in cached_error.edits.iter() {
+                error = error.with_edit(location(*loc)?, original, replacement.clone());
+            }
+            errors.push(error);
+        }
+
+        self.hits += 1;
+        Some(ProcOutcome { errors, results })
+    }
+
+    /// Record the outcome of analyzing this proc, to be written out.
+    pub(crate) fn insert(&mut self, context: &Context, proc: ProcRef, outcome: &ProcOutcome) {
+        let mut files = Vec::new();
+        let cached = self.convert(context, proc, outcome, &mut files);
+        if let Some(mut cached) = cached {
+            files.sort_unstable();
+            files.dedup();
+            let own = files.iter().position(|&file| Some(file) == self.current_files.get(&proc.get().location.file).copied());
+            if let Some(own) = own {
+                files.swap(0, own);
+                cached.files = files;
+                self.current.procs.push(cached);
+            }
+        }
+    }
+
+    fn convert(&mut self, context: &Context, proc: ProcRef, outcome: &ProcOutcome, files: &mut Vec<usize>) -> Option<CachedProc> {
+        let mut location = |loc: Location| self.cached_location(context, loc, files);
+        location(proc.get().location)?;
+
+        let results = &outcome.results;
+        let mut calls = Vec::new();
+        for (callee, loc, new_context) in results.call_tree.get(&proc).into_iter().flatten() {
+            calls.push((proc_ref(*callee), location(*loc)?, *new_context));
+        }
+        let mut sleeping = Vec::new();
+        for (what, loc) in results.sleeping_procs.get_violators(proc).into_iter().flatten() {
+            sleeping.push((what.clone(), location(*loc)?));
+        }
+        let mut impure = Vec::new();
+        for (what, loc) in results.impure_procs.get_violators(proc).into_iter().flatten() {
+            impure.push((what.clone(), location(*loc)?));
+        }
+        let mut kwargs = Vec::new();
+        for (name, info) in results.used_kwargs.iter() {
+            let mut called_at = Vec::new();
+            for (arg, at) in info.called_at.iter() {
+                called_at.push((arg.clone(), location(at.location)?, at.others));
+            }
+            kwargs.push(CachedKwargs {
+                proc: name.clone(),
+                location: location(info.location)?,
+                called_at,
+            });
+        }
+        let mut resources = Vec::new();
+        for (loc, path) in results.resources.iter() {
+            resources.push((location(*loc)?, path.clone()));
+        }
+        let mut href_links = Vec::new();
+        for link in results.href_links.iter() {
+            href_links.push((location(link.location)?, link.target.map(|ty| ty.path.clone()), link.keys.clone(), link.dynamic));
+        }
+        let mut href_reads = Vec::new();
+        for read in results.href_reads.iter() {
+            href_reads.push((location(read.location)?, read.key.clone()));
+        }
+
+        let mut errors = Vec::new();
+        for error in outcome.errors.iter() {
+            let mut notes = Vec::new();
+            for note in error.notes().iter() {
+                notes.push((location(note.location())?, note.description().to_owned()));
+            }
+            let mut edits = Vec::new();
+            for edit in error.suggested_edits().iter() {
+                edits.push((location(edit.span().start)?, edit.original().to_owned(), edit.replacement().to_owned()));
+            }
+            errors.push(CachedError {
+                location: location(error.location())?,
+                severity: error.severity() as u8,
+                dreamchecker: error.component() == Component::DreamChecker,
+                errortype: error.errortype().map(ToOwned::to_owned),
+                description: error.description().to_owned(),
+                notes,
+                edits,
+            });
+        }
+
+        Some(CachedProc {
+            proc: proc_ref(proc),
+            files: Vec::new(),
+            errors,
+            calls,
+            sleeping,
+            impure,
+            waitfor: results.waitfor_procs.contains(&proc),
+            kwargs,
+            resources,
+            href_links,
+            href_reads,
+        })
+    }
+
+    fn cached_location(&mut self, context: &Context, location: Location, files: &mut Vec<usize>) -> Option<CachedLocation> {
+        if location.is_builtins() {
+            return Some(CachedLocation { file: None, line: location.line, column: location.column });
+        }
+        let index = match self.current_files.get(&location.file) {
+            Some(&index) => index,
+            None => {
+                let state = *self.file_states.get(&location.file)?;
+                let index = self.current.files.len();
+                self.current.files.push(CachedFile {
+                    path: context.file_path(location.file),
+                    state,
+                });
+                self.current_files.insert(location.file, index);
+                index
+            },
+        };
+        files.push(index);
+        Some(CachedLocation { file: Some(index), line: location.line, column: location.column })
+    }
+
+    fn location(&self, location: CachedLocation) -> Option<Location> {
+        let file = match location.file {
+            Some(index) => (*self.unchanged_files.get(index)?)?,
+            None => Location::builtins().file,
+        };
+        Some(Location { file, line: location.line, column: location.column })
+    }
+}
+
+fn proc_ref(proc: ProcRef) -> CachedProcRef {
+    CachedProcRef {
+        type_path: proc.ty().path.clone(),
+        name: proc.name().to_owned(),
+        index: proc.index(),
+    }
+}
+
+fn severity_from_u8(severity: u8) -> Option<Severity> {
+    match severity {
+        1 => Some(Severity::Error),
+        2 => Some(Severity::Warning),
+        3 => Some(Severity::Info),
+        4 => Some(Severity::Hint),
+        _ => None,
+    }
+}
+
+/// Hash everything outside a proc's own body which its analysis depends on,
+/// without any locations, so that editing other procs' bodies doesn't
+/// change it. Code is hashed as the DM source it displays as.
+fn declarations_hash(context: &Context, objtree: &ObjectTree) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    context.config().source().hash(&mut hasher);
+    objtree.root().recurse(&mut |ty| {
+        ty.path.hash(&mut hasher);
+        ty.parent_type().map(|parent| parent.get().path.as_str()).hash(&mut hasher);
+        for (name, var) in ty.get().vars.iter() {
+            name.hash(&mut hasher);
+            var.declaration.as_ref().map(|decl| decl.var_type.to_string()).hash(&mut hasher);
+            var.value.constant.hash(&mut hasher);
+        }
+        for proc in ty.iter_self_procs() {
+            proc.name().hash(&mut hasher);
+            proc.get_declaration().map(|decl| decl.kind.to_string()).hash(&mut hasher);
+            for param in proc.get().parameters.iter() {
+                param.to_string().hash(&mut hasher);
+                param.default.as_ref().map(ToString::to_string).hash(&mut hasher);
+            }
+            if let Code::Present(ref code) = proc.get().code {
+                for statement in code.iter() {
+                    if let Statement::Setting { .. } = statement.elem {
+                        statement.elem.to_string().hash(&mut hasher);
+                    }
+                }
+            }
+        }
+    });
+    hasher.finish()
+}
@@ -0,0 +1,330 @@
+//! Project-specific lints, from the `[[lint]]` sections of the configuration.
+//!
+//! Patterns are matched as proc bodies are analyzed, so they see the same
+//! static types and resolved procs as the other checks do.
+
+use std::collections::HashMap;
+
+use dm::{Context, DMError, Location, Severity};
+use dm::config::{CustomLint, WarningLevel};
+use dm::constants::Constant;
+use dm::lint_pattern::{LintPattern, ProcPattern, ValuePattern};
+use dm::objtree::{ObjectTree, ProcRef, TypeRef};
+
+use crate::{error, Analysis, Assumption, StaticType};
+
+/// The configured lints, with their paths resolved against the object tree.
+#[derive(Default)]
+pub(crate) struct CustomLints<'o> {
+    rules: Vec<Rule<'o>>,
+}
+
+struct Rule<'o> {
+    lint: CustomLint,
+    severity: Severity,
+    within: Vec<Scope<'o>>,
+    outside: Vec<Scope<'o>>,
+    /// The typepaths named by the pattern.
+    types: HashMap<String, TypeRef<'o>>,
+}
+
+/// A type, or a proc and its overrides, which code can be inside.
+enum Scope<'o> {
+    Type(TypeRef<'o>),
+    Proc(TypeRef<'o>, String),
+}
+
+impl<'o> CustomLints<'o> {
+    pub(crate) fn new(context: &Context, objtree: &'o ObjectTree) -> CustomLints<'o> {
+        let config = context.config();
+        let mut rules = Vec::new();
+        for lint in config.lints.iter() {
+            let severity = match lint.severity {
+                WarningLevel::Error => Severity::Error,
+                WarningLevel::Warning | WarningLevel::Unset => Severity::Warning,
+                WarningLevel::Info => Severity::Info,
+                WarningLevel::Hint => Severity::Hint,
+                WarningLevel::Disabled => continue,
+            };
+
+            let mut unknown = Vec::new();
+            let mut resolve_scopes = |paths: &[String]| {
+                let mut scopes = Vec::new();
+                for path in paths.iter() {
+                    match resolve_scope(objtree, path) {
+                        Some(scope) => scopes.push(scope),
+                        None => unknown.push(path.clone()),
+                    }
+                }
+                scopes
+            };
+            let within = resolve_scopes(&lint.within);
+            let outside = resolve_scopes(&lint.outside);
+
+            let mut paths = Vec::new();
+            match &lint.pattern {
+                LintPattern::Call { proc, args } => {
+                    if let ProcPattern::Path { ty, .. } = proc {
+                        paths.push(ty.clone());
+                    }
+                    for arg in args.iter() {
+                        value_paths(arg, &mut paths);
+                    }
+                }
+                LintPattern::Assign { value, .. } => value_paths(value, &mut paths),
+            }
+            let mut types = HashMap::new();
+            for path in paths {
+                match objtree.find(&path) {
+                    Some(ty) => { types.insert(path, ty); }
+                    None => unknown.push(path),
+                }
+            }
+
+            if !unknown.is_empty() {
+                let location = config.path()
+                    .map(|path| Location { file: context.register_file(path), line: 1, column: 1 })
+                    .unwrap_or_default();
+                DMError::new(location, format!("custom lint {:?} names unknown paths: {}", lint.name, unknown.join(", ")))
+                    .with_component(dm::Component::DreamChecker)
+                    .with_errortype("invalid_custom_lint")
+                    .set_severity(Severity::Warning)
+                    .register(context);
+                continue;
+            }
+
+            rules.push(Rule {
+                lint: lint.clone(),
+                severity,
+                within,
+                outside,
+                types,
+            });
+        }
+        CustomLints { rules }
+    }
+
+    /// Report rules matching a call to `proc` from within `caller`.
+    ///
+    /// `arg` looks up the analysis of a positional argument, or `None` if
+    /// it was not passed.
+    pub(crate) fn check_call<'a, F>(&self, context: &Context, caller: ProcRef<'o>, location: Location, proc: ProcRef<'o>, arg: F)
+    where
+        F: Fn(usize) -> Option<&'a Analysis<'o>>,
+        'o: 'a,
+    {
+        for rule in self.rules.iter() {
+            guard!(let LintPattern::Call { proc: proc_pattern, args } = &rule.lint.pattern else { continue });
+            let proc_matches = match proc_pattern {
+                ProcPattern::Name(name) => proc.name() == name,
+                ProcPattern::Path { ty, name } => proc.name() == name && {
+                    let ty = rule.types[ty];
+                    if ty.is_root() {
+                        proc.ty().is_root()
+                    } else {
+                        proc.ty().is_subtype_of(&ty)
+                    }
+                },
+            };
+            if !proc_matches || !rule.applies_at(context, caller, location) {
+                continue;
+            }
+            let null = Analysis::null();
+            let args_match = args.iter().enumerate().all(|(i, pattern)| {
+                rule.value_matches(pattern, arg(i).unwrap_or(&null)) == Some(true)
+            });
+            if args_match {
+                rule.report(context, location);
+            }
+        }
+    }
+
+    /// Report rules matching an assignment of `value` to the var `var`.
+    pub(crate) fn check_assign(&self, context: &Context, caller: ProcRef<'o>, location: Location, var: &str, value: &Analysis<'o>) {
+        for rule in self.rules.iter() {
+            guard!(let LintPattern::Assign { var: var_pattern, value: value_pattern } = &rule.lint.pattern else { continue });
+            if var_pattern == var
+                && rule.applies_at(context, caller, location)
+                && rule.value_matches(value_pattern, value) == Some(true)
+            {
+                rule.report(context, location);
+            }
+        }
+    }
+
+    pub(crate) fn has_assign_rules(&self) -> bool {
+        self.rules.iter().any(|rule| matches!(rule.lint.pattern, LintPattern::Assign { .. }))
+    }
+}
+
+impl<'o> Rule<'o> {
+    fn applies_at(&self, context: &Context, caller: ProcRef<'o>, location: Location) -> bool {
+        if !self.within.is_empty() && !self.within.iter().any(|scope| scope.contains(caller)) {
+            return false;
+        }
+        if self.outside.iter().any(|scope| scope.contains(caller)) {
+            return false;
+        }
+        if self.lint.files.is_empty() && self.lint.exclude_files.is_empty() {
+            return true;
+        }
+        let path = context.file_path(location.file).display().to_string().replace('\\', "/");
+        (self.lint.files.is_empty() || self.lint.files.iter().any(|filter| path_matches(filter, &path)))
+            && !self.lint.exclude_files.iter().any(|filter| path_matches(filter, &path))
+    }
+
+    fn report(&self, context: &Context, location: Location) {
+        error(location, format!("{}: {}", self.lint.name, self.lint.message))
+            .with_errortype("custom_lint")
+            .set_severity(self.severity)
+            .register(context);
+    }
+
+    /// Match a value against a pattern, or `None` if it can't be told.
+    fn value_matches(&self, pattern: &ValuePattern, value: &Analysis<'o>) -> Option<bool> {
+        let aset = &value.aset.set;
+        match pattern {
+            ValuePattern::Any => Some(true),
+            ValuePattern::Not(inner) => self.value_matches(inner, value).map(|matched| !matched),
+            ValuePattern::Or(options) => {
+                let mut result = Some(false);
+                for option in options.iter() {
+                    match self.value_matches(option, value) {
+                        Some(true) => return Some(true),
+                        Some(false) => {}
+                        None => result = None,
+                    }
+                }
+                result
+            }
+            ValuePattern::Text => match &value.value {
+                Some(constant) => Some(matches!(constant, Constant::String(_))),
+                None if aset.contains(&Assumption::IsText(true)) => Some(true),
+                None if is_not_primitive(value) => Some(false),
+                None => None,
+            },
+            ValuePattern::Num => match &value.value {
+                Some(constant) => Some(matches!(constant, Constant::Int(_) | Constant::Float(_))),
+                None if aset.contains(&Assumption::IsNum(true)) => Some(true),
+                None if is_not_primitive(value) => Some(false),
+                None => None,
+            },
+            ValuePattern::Null => match &value.value {
+                Some(constant) => Some(matches!(constant, Constant::Null(_))),
+                None if aset.contains(&Assumption::IsNull(true)) => Some(true),
+                None if aset.contains(&Assumption::IsNull(false)) => Some(false),
+                None => None,
+            },
+            ValuePattern::List => match (&value.value, &value.static_ty) {
+                (Some(constant), _) => Some(matches!(constant, Constant::List(_))),
+                (None, StaticType::List { .. }) => Some(true),
+                (None, StaticType::Type(ty)) => Some(ty.path == "/list"),
+                (None, StaticType::None) => None,
+            },
+            ValuePattern::Type(path) => {
+                let target = self.types[path];
+                let ty = match &value.static_ty {
+                    StaticType::Type(ty) => Some(*ty),
+                    StaticType::List { list, .. } => Some(*list),
+                    StaticType::None => aset.iter().find_map(|each| match each {
+                        Assumption::IsType(true, ty) => Some(*ty),
+                        _ => None,
+                    }),
+                };
+                match (ty, &value.value) {
+                    (Some(ty), _) if ty.is_subtype_of(&target) => Some(true),
+                    // A var of a parent type may hold the target type.
+                    (Some(ty), _) if target.is_subtype_of(&ty) => None,
+                    (Some(_), _) => Some(false),
+                    (None, Some(Constant::Null(_))) | (None, None) => None,
+                    (None, Some(_)) => Some(false),
+                }
+            }
+            ValuePattern::Number(number) => match &value.value {
+                Some(Constant::Int(value)) => Some(*value as f32 == *number),
+                Some(Constant::Float(value)) => Some(value == number),
+                Some(_) => Some(false),
+                None if is_not_primitive(value) => Some(false),
+                None => None,
+            },
+            ValuePattern::String(text) => match &value.value {
+                Some(Constant::String(value)) => Some(value == text),
+                Some(_) => Some(false),
+                None if is_not_primitive(value) => Some(false),
+                None => None,
+            },
+        }
+    }
+}
+
+/// Whether a value is known to be an object rather than text or a number.
+fn is_not_primitive(value: &Analysis) -> bool {
+    value.static_ty != StaticType::None
+        || value.aset.set.iter().any(|each| matches!(each, Assumption::IsType(true, _) | Assumption::IsPath(true, _)))
+}
+
+impl<'o> Scope<'o> {
+    fn contains(&self, proc: ProcRef<'o>) -> bool {
+        match self {
+            Scope::Type(ty) => proc.ty().is_subtype_of(ty),
+            Scope::Proc(ty, name) if ty.is_root() => proc.name() == name && proc.ty().is_root(),
+            Scope::Proc(ty, name) => proc.name() == name && proc.ty().is_subtype_of(ty),
+        }
+    }
+}
+
+fn resolve_scope<'o>(objtree: &'o ObjectTree, path: &str) -> Option<Scope<'o>> {
+    let path = path.trim_end_matches('/');
+    for keyword in &["/proc/", "/verb/"] {
+        if let Some(idx) = path.find(keyword) {
+            let ty = objtree.find(&path[..idx])?;
+            let name = &path[idx + keyword.len()..];
+            let mut exists = ty.get_proc(name).is_some();
+            ty.recurse(&mut |child| exists |= child.procs.contains_key(name));
+            return if exists { Some(Scope::Proc(ty, name.to_owned())) } else { None };
+        }
+    }
+    objtree.find(path).map(Scope::Type)
+}
+
+fn value_paths(pattern: &ValuePattern, paths: &mut Vec<String>) {
+    match pattern {
+        ValuePattern::Type(path) => paths.push(path.clone()),
+        ValuePattern::Not(inner) => value_paths(inner, paths),
+        ValuePattern::Or(options) => {
+            for option in options.iter() {
+                value_paths(option, paths);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Match a file path against a filter. Filters without wildcards match that
+/// file or any file in that directory. In globs, `*` matches within a single
+/// path segment and `**` matches across segments.
+fn path_matches(filter: &str, path: &str) -> bool {
+    let filter = filter.replace('\\', "/");
+    if !filter.contains('*') {
+        let filter = filter.trim_end_matches('/');
+        return path == filter || path.starts_with(&format!("{}/", filter));
+    }
+    glob_matches(filter.as_bytes(), path.as_bytes())
+}
+
+fn glob_matches(glob: &[u8], text: &[u8]) -> bool {
+    match glob {
+        [] => text.is_empty(),
+        [b'*', b'*', b'/', rest @ ..] => {
+            // `**/` matches zero or more whole directories.
+            glob_matches(rest, text) || text.iter().enumerate()
+                .any(|(i, &b)| b == b'/' && glob_matches(rest, &text[i + 1..]))
+        }
+        [b'*', b'*', rest @ ..] => (0..=text.len()).any(|i| glob_matches(rest, &text[i..])),
+        [b'*', rest @ ..] => {
+            let segment = text.iter().position(|&b| b == b'/').unwrap_or(text.len());
+            (0..=segment).any(|i| glob_matches(rest, &text[i..]))
+        }
+        [first, rest @ ..] => text.first() == Some(first) && glob_matches(rest, &text[1..]),
+    }
+}
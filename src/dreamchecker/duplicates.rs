@@ -0,0 +1,497 @@
+//! Copy-paste detection by hashing normalized proc body subtrees.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::mem::discriminant;
+
+use dm::{Context, DMError, Location, Severity};
+use dm::ast::*;
+use dm::config::DuplicateNormalization;
+use dm::objtree::ProcRef;
+
+/// A block which may be part of a cluster of duplicates.
+struct Candidate<'o> {
+    proc: ProcRef<'o>,
+    block: &'o [Spanned<Statement>],
+    location: Location,
+    /// The number of statements in the block, including nested statements.
+    statements: u32,
+    hash: u64,
+    /// The index of the enclosing block's candidate, if any.
+    parent: Option<usize>,
+}
+
+/// Collects every block in every proc body for clone detection.
+pub(crate) struct DuplicateFinder<'o> {
+    normalize: DuplicateNormalization,
+    candidates: Vec<Candidate<'o>>,
+    /// Whether nested blocks are walked in place rather than registered as
+    /// candidates and summarized by their hash.
+    inline: bool,
+}
+
+impl<'o> DuplicateFinder<'o> {
+    pub fn new(normalize: DuplicateNormalization) -> DuplicateFinder<'o> {
+        DuplicateFinder {
+            normalize,
+            candidates: Vec::new(),
+            inline: false,
+        }
+    }
+
+    pub fn add_proc(&mut self, proc: ProcRef<'o>, code: &'o [Spanned<Statement>]) {
+        self.block(proc, code, None);
+    }
+
+    /// Report every cluster of duplicate blocks of at least `min_statements`
+    /// statements. Clusters entirely contained within a larger reported
+    /// cluster are not reported again.
+    pub fn report(&self, context: &Context, min_statements: u32) {
+        let mut by_hash: HashMap<(u64, u32), Vec<usize>> = HashMap::new();
+        for (i, candidate) in self.candidates.iter().enumerate() {
+            if candidate.statements >= min_statements {
+                by_hash.entry((candidate.hash, candidate.statements)).or_default().push(i);
+            }
+        }
+
+        // Blocks which merely share a hash are split apart by comparing
+        // their normalized contents.
+        let mut encoder = DuplicateFinder {
+            normalize: self.normalize,
+            candidates: Vec::new(),
+            inline: true,
+        };
+        let mut clusters: Vec<Vec<usize>> = Vec::new();
+        for (_, members) in by_hash {
+            if members.len() < 2 {
+                continue;
+            }
+            let mut same: Vec<(Vec<u8>, Vec<usize>)> = Vec::new();
+            for i in members {
+                let encoding = encoder.encode(&self.candidates[i]);
+                match same.iter_mut().find(|(other, _)| *other == encoding) {
+                    Some((_, group)) => group.push(i),
+                    None => same.push((encoding, vec![i])),
+                }
+            }
+            clusters.extend(same.into_iter().map(|(_, group)| group).filter(|group| group.len() > 1));
+        }
+        // Largest first, so that enclosing clusters claim their children.
+        clusters.sort_by_key(|members| (std::cmp::Reverse(self.candidates[members[0]].statements), members[0]));
+
+        let mut covered = HashSet::new();
+        let mut reported = Vec::new();
+        for members in clusters {
+            let redundant = members.iter().all(|&i| match self.candidates[i].parent {
+                Some(parent) => covered.contains(&parent),
+                None => false,
+            });
+            covered.extend(members.iter().cloned());
+            if !redundant {
+                reported.push(members);
+            }
+        }
+        reported.sort_by_key(|members| members[0]);
+
+        for members in reported {
+            let first = &self.candidates[members[0]];
+            let mut error = DMError::new(first.location, format!(
+                "duplicate code: {} statements appear in {} places",
+                first.statements,
+                members.len(),
+            ))
+                .with_component(dm::Component::DreamChecker)
+                .with_errortype("duplicate_code")
+                .set_severity(Severity::Warning);
+            for &i in members.iter() {
+                let copy = &self.candidates[i];
+                error = error.with_note(copy.location, format!("copy in {}", copy.proc));
+            }
+            error.register(context);
+        }
+    }
+
+    /// The full normalized contents of a candidate, nested blocks included.
+    fn encode(&mut self, candidate: &Candidate<'o>) -> Vec<u8> {
+        let mut encoding = Encoding(Vec::new());
+        self.statements(&mut encoding, candidate.proc, candidate.block, 0);
+        encoding.0
+    }
+
+    // ------------------------------------------------------------------------
+    // Normalized hashing
+
+    /// Hash a block, registering it and its nested blocks as candidates.
+    /// Returns the hash and the number of statements.
+    fn block(&mut self, proc: ProcRef<'o>, block: &'o [Spanned<Statement>], parent: Option<usize>) -> (u64, u32) {
+        let mut h = DefaultHasher::new();
+        block.len().hash(&mut h);
+        if block.is_empty() {
+            return (h.finish(), 0);
+        }
+
+        let index = self.candidates.len();
+        self.candidates.push(Candidate {
+            proc,
+            block,
+            location: block[0].location,
+            statements: 0,
+            hash: 0,
+            parent,
+        });
+
+        let statements = self.statements(&mut h, proc, block, index);
+        let hash = h.finish();
+        let candidate = &mut self.candidates[index];
+        candidate.statements = statements;
+        candidate.hash = hash;
+        (hash, statements)
+    }
+
+    /// Hash the statements of a block. Returns the number of statements,
+    /// including nested statements.
+    fn statements<H: Hasher>(&mut self, h: &mut H, proc: ProcRef<'o>, block: &'o [Spanned<Statement>], parent: usize) -> u32 {
+        let mut statements = 0;
+        for stmt in block.iter() {
+            statements += 1 + self.statement(h, proc, &stmt.elem, parent);
+        }
+        statements
+    }
+
+    /// Hash a statement. Returns the number of statements nested within it.
+    fn statement<H: Hasher>(&mut self, h: &mut H, proc: ProcRef<'o>, statement: &'o Statement, parent: usize) -> u32 {
+        let mut nested = 0;
+        discriminant(statement).hash(h);
+        match statement {
+            Statement::Expr(expr) |
+            Statement::Throw(expr) |
+            Statement::Del(expr) => self.expression(h, expr),
+            Statement::Return(expr) |
+            Statement::Crash(expr) => self.opt_expression(h, expr.as_ref()),
+            Statement::While { condition, block: body } => {
+                self.expression(h, condition);
+                nested += self.sub_block(h, proc, body, parent);
+            },
+            Statement::DoWhile { block: body, condition } => {
+                nested += self.sub_block(h, proc, body, parent);
+                self.expression(h, &condition.elem);
+            },
+            Statement::If { arms, else_arm } => {
+                arms.len().hash(h);
+                for (condition, body) in arms.iter() {
+                    self.expression(h, &condition.elem);
+                    nested += self.sub_block(h, proc, body, parent);
+                }
+                else_arm.is_some().hash(h);
+                if let Some(body) = else_arm {
+                    nested += self.sub_block(h, proc, body, parent);
+                }
+            },
+            Statement::ForInfinite { block: body } => {
+                nested += self.sub_block(h, proc, body, parent);
+            },
+            Statement::ForLoop { init, test, inc, block: body } => {
+                init.is_some().hash(h);
+                if let Some(init) = init {
+                    nested += self.statement(h, proc, init, parent);
+                }
+                self.opt_expression(h, test.as_deref());
+                inc.is_some().hash(h);
+                if let Some(inc) = inc {
+                    nested += self.statement(h, proc, inc, parent);
+                }
+                nested += self.sub_block(h, proc, body, parent);
+            },
+            Statement::ForList { var_type, name, input_type, in_list, block: body } => {
+                self.opt_var_type(h, var_type.as_ref());
+                self.ident(h, name);
+                input_type.hash(h);
+                self.opt_expression(h, in_list.as_deref());
+                nested += self.sub_block(h, proc, body, parent);
+            },
+            Statement::ForRange { var_type, name, start, end, step, block: body } => {
+                self.opt_var_type(h, var_type.as_ref());
+                self.ident(h, name);
+                self.expression(h, start);
+                self.expression(h, end);
+                self.opt_expression(h, step.as_deref());
+                nested += self.sub_block(h, proc, body, parent);
+            },
+            Statement::Var(var) => self.var_statement(h, var),
+            Statement::Vars(vars) => {
+                vars.len().hash(h);
+                for var in vars.iter() {
+                    self.var_statement(h, var);
+                }
+            },
+            Statement::Setting { name, mode, value } => {
+                name.hash(h);
+                discriminant(mode).hash(h);
+                self.expression(h, value);
+            },
+            Statement::Spawn { delay, block: body } => {
+                self.opt_expression(h, delay.as_ref());
+                nested += self.sub_block(h, proc, body, parent);
+            },
+            Statement::Switch { input, cases, default } => {
+                self.expression(h, input);
+                cases.len().hash(h);
+                for (case, body) in cases.iter() {
+                    case.elem.len().hash(h);
+                    for each in case.elem.iter() {
+                        discriminant(each).hash(h);
+                        match each {
+                            Case::Exact(expr) => self.expression(h, expr),
+                            Case::Range(start, end) => {
+                                self.expression(h, start);
+                                self.expression(h, end);
+                            },
+                        }
+                    }
+                    nested += self.sub_block(h, proc, body, parent);
+                }
+                default.is_some().hash(h);
+                if let Some(body) = default {
+                    nested += self.sub_block(h, proc, body, parent);
+                }
+            },
+            Statement::TryCatch { try_block, catch_params, catch_block } => {
+                nested += self.sub_block(h, proc, try_block, parent);
+                catch_params.len().hash(h);
+                for param in catch_params.iter() {
+                    self.tree_path(h, param);
+                }
+                nested += self.sub_block(h, proc, catch_block, parent);
+            },
+            Statement::Continue(label) |
+            Statement::Break(label) => {
+                label.is_some().hash(h);
+                if let Some(label) = label {
+                    self.ident(h, label);
+                }
+            },
+            Statement::Goto(label) => self.ident(h, label),
+            Statement::Label { name, block: body } => {
+                self.ident(h, name);
+                nested += self.sub_block(h, proc, body, parent);
+            },
+        }
+        nested
+    }
+
+    fn sub_block<H: Hasher>(&mut self, h: &mut H, proc: ProcRef<'o>, block: &'o [Spanned<Statement>], parent: usize) -> u32 {
+        if self.inline {
+            block.len().hash(h);
+            return self.statements(h, proc, block, parent);
+        }
+        let (hash, statements) = self.block(proc, block, Some(parent));
+        hash.hash(h);
+        statements
+    }
+
+    fn var_statement<H: Hasher>(&self, h: &mut H, var: &VarStatement) {
+        self.var_type(h, &var.var_type);
+        self.ident(h, &var.name);
+        self.opt_expression(h, var.value.as_ref());
+    }
+
+    fn expression<H: Hasher>(&self, h: &mut H, expression: &Expression) {
+        discriminant(expression).hash(h);
+        match expression {
+            Expression::Base { unary, term, follow } => {
+                unary.len().hash(h);
+                for op in unary.iter() {
+                    discriminant(&op.elem).hash(h);
+                }
+                self.term(h, &term.elem);
+                follow.len().hash(h);
+                for each in follow.iter() {
+                    discriminant(&each.elem).hash(h);
+                    match &each.elem {
+                        Follow::Index(kind, expr) => {
+                            discriminant(kind).hash(h);
+                            self.expression(h, expr);
+                        },
+                        Follow::Field(kind, name) => {
+                            discriminant(kind).hash(h);
+                            self.ident(h, name);
+                        },
+                        Follow::Call(kind, name, args) => {
+                            discriminant(kind).hash(h);
+                            self.ident(h, name);
+                            self.expressions(h, args);
+                        },
+                        Follow::StaticField(name) => self.ident(h, name),
+                        Follow::ProcReference(kind, name) => {
+                            kind.hash(h);
+                            self.ident(h, name);
+                        },
+                    }
+                }
+            },
+            Expression::BinaryOp { op, lhs, rhs } => {
+                discriminant(op).hash(h);
+                self.expression(h, lhs);
+                self.expression(h, rhs);
+            },
+            Expression::AssignOp { op, lhs, rhs } => {
+                discriminant(op).hash(h);
+                self.expression(h, lhs);
+                self.expression(h, rhs);
+            },
+            Expression::TernaryOp { cond, if_, else_ } => {
+                self.expression(h, cond);
+                self.expression(h, if_);
+                self.expression(h, else_);
+            },
+        }
+    }
+
+    fn opt_expression<H: Hasher>(&self, h: &mut H, expression: Option<&Expression>) {
+        expression.is_some().hash(h);
+        if let Some(expression) = expression {
+            self.expression(h, expression);
+        }
+    }
+
+    fn expressions<H: Hasher>(&self, h: &mut H, expressions: &[Expression]) {
+        expressions.len().hash(h);
+        for each in expressions.iter() {
+            self.expression(h, each);
+        }
+    }
+
+    fn term<H: Hasher>(&self, h: &mut H, term: &Term) {
+        discriminant(term).hash(h);
+        match term {
+            Term::Null => {},
+            Term::Int(i) => self.literal(h, i),
+            Term::Float(f) => self.literal(h, &f.to_bits()),
+            Term::Ident(name) => self.ident(h, name),
+            Term::GlobalIdent(name) => self.ident(h, name),
+            Term::String(text) |
+            Term::Resource(text) => self.literal(h, text),
+            Term::As(input_type) => input_type.hash(h),
+            Term::Expr(expr) => self.expression(h, expr),
+            Term::Prefab(prefab) => self.prefab(h, prefab),
+            Term::InterpString(first, parts) => {
+                self.literal(h, first);
+                parts.len().hash(h);
+                for (expr, text) in parts.iter() {
+                    self.opt_expression(h, expr.as_ref());
+                    self.literal(h, text);
+                }
+            },
+            Term::Call(name, args) => {
+                self.ident(h, name);
+                self.expressions(h, args);
+            },
+            Term::GlobalCall(name, args) => {
+                self.ident(h, name);
+                self.expressions(h, args);
+            },
+            Term::SelfCall(args) |
+            Term::ParentCall(args) |
+            Term::List(args) => self.expressions(h, args),
+            Term::New { type_, args } => {
+                discriminant(type_).hash(h);
+                match type_ {
+                    NewType::Implicit => {},
+                    NewType::Prefab(prefab) => self.prefab(h, prefab),
+                    NewType::MiniExpr { ident, fields } => {
+                        self.ident(h, ident);
+                        fields.len().hash(h);
+                        for field in fields.iter() {
+                            discriminant(&field.kind).hash(h);
+                            self.ident(h, &field.ident);
+                        }
+                    },
+                }
+                args.is_some().hash(h);
+                if let Some(args) = args {
+                    self.expressions(h, args);
+                }
+            },
+            Term::Input { args, input_type, in_list } => {
+                self.expressions(h, args);
+                input_type.hash(h);
+                self.opt_expression(h, in_list.as_deref());
+            },
+            Term::Locate { args, in_list } => {
+                self.expressions(h, args);
+                self.opt_expression(h, in_list.as_deref());
+            },
+            Term::Pick(choices) => {
+                choices.len().hash(h);
+                for (weight, choice) in choices.iter() {
+                    self.opt_expression(h, weight.as_ref());
+                    self.expression(h, choice);
+                }
+            },
+            Term::DynamicCall(lhs_args, rhs_args) => {
+                self.expressions(h, lhs_args);
+                self.expressions(h, rhs_args);
+            },
+        }
+    }
+
+    fn prefab<H: Hasher>(&self, h: &mut H, prefab: &Prefab) {
+        if self.normalize < DuplicateNormalization::Literals {
+            prefab.path.len().hash(h);
+            for (op, name) in prefab.path.iter() {
+                discriminant(op).hash(h);
+                name.hash(h);
+            }
+        }
+        prefab.vars.len().hash(h);
+        for (name, value) in prefab.vars.iter() {
+            self.ident(h, name);
+            self.expression(h, value);
+        }
+    }
+
+    fn opt_var_type<H: Hasher>(&self, h: &mut H, var_type: Option<&VarType>) {
+        var_type.is_some().hash(h);
+        if let Some(var_type) = var_type {
+            self.var_type(h, var_type);
+        }
+    }
+
+    fn var_type<H: Hasher>(&self, h: &mut H, var_type: &VarType) {
+        var_type.flags.hash(h);
+        self.tree_path(h, &var_type.type_path);
+    }
+
+    fn tree_path<H: Hasher>(&self, h: &mut H, path: &[String]) {
+        if self.normalize < DuplicateNormalization::Identifiers {
+            path.hash(h);
+        }
+    }
+
+    fn ident<H: Hasher>(&self, h: &mut H, name: &str) {
+        if self.normalize < DuplicateNormalization::Identifiers {
+            name.hash(h);
+        }
+    }
+
+    fn literal<H: Hasher, T: Hash + ?Sized>(&self, h: &mut H, value: &T) {
+        if self.normalize < DuplicateNormalization::Literals {
+            value.hash(h);
+        }
+    }
+}
+
+/// A `Hasher` which keeps everything written to it, so that the normalized
+/// forms of two blocks can be compared exactly.
+struct Encoding(Vec<u8>);
+
+impl Hasher for Encoding {
+    fn write(&mut self, bytes: &[u8]) {
+        self.0.extend_from_slice(bytes);
+    }
+
+    fn finish(&self) -> u64 {
+        unreachable!("encodings are compared, not hashed")
+    }
+}
@@ -0,0 +1,276 @@
+//! Automatic fixes, built from the edits suggested by diagnostics.
+//!
+//! Each diagnostic's suggested edits form one fix, which is applied entirely
+//! or not at all. A fix is skipped if any of its edits overlaps an edit which
+//! was already accepted, or if the source no longer contains the text the edit
+//! expects to replace.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::io;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use dm::{Context, DMError, FileId, Location, SuggestedEdit};
+
+/// The result of applying fixes to one file.
+#[derive(Debug, Clone)]
+pub struct FixedFile {
+    /// The path to the file, relative to the environment directory.
+    pub path: PathBuf,
+    /// The contents of the file before any fixes.
+    pub original: String,
+    /// The contents of the file after the fixes.
+    pub fixed: String,
+    /// The number of diagnostics which were fixed.
+    pub fixes: usize,
+}
+
+/// Apply the suggested fixes of every registered diagnostic to the source
+/// files, relative to `root`, without writing them out.
+///
+/// Files which no fix could be applied to are omitted.
+pub fn plan(context: &Context, root: &Path) -> io::Result<Vec<FixedFile>> {
+    let errors = context.errors();
+    let mut by_file: BTreeMap<FileId, Vec<&[SuggestedEdit]>> = BTreeMap::new();
+    for error in errors.iter() {
+        if let Some(file) = fix_file(error) {
+            by_file.entry(file).or_default().push(error.suggested_edits());
+        }
+    }
+
+    let mut files = Vec::new();
+    for (file, fixes) in by_file {
+        let path = context.file_path(file);
+        let original = std::fs::read_to_string(root.join(&path))?;
+        let (fixed, count) = apply(&original, &fixes);
+        if count > 0 {
+            files.push(FixedFile {
+                path,
+                original,
+                fixed,
+                fixes: count,
+            });
+        }
+    }
+    Ok(files)
+}
+
+/// Get the file a diagnostic's fix applies to, if it has one.
+fn fix_file(error: &DMError) -> Option<FileId> {
+    let edits = error.suggested_edits();
+    let file = edits.first()?.span().start.file;
+    if edits.iter().all(|edit| edit.span().start.file == file && edit.span().end.file == file) {
+        Some(file)
+    } else {
+        None
+    }
+}
+
+/// Apply the given fixes to some source text, returning the new text and the
+/// number of fixes which were applied.
+pub fn apply(source: &str, fixes: &[&[SuggestedEdit]]) -> (String, usize) {
+    let mut line_starts = vec![0];
+    line_starts.extend(source.match_indices('\n').map(|(idx, _)| idx + 1));
+    let newline = if source.contains("\r\n") { "\r\n" } else { "\n" };
+
+    let mut accepted: Vec<(Range<usize>, String)> = Vec::new();
+    let mut count = 0;
+    'fixes: for &fix in fixes {
+        let mut pending = Vec::with_capacity(fix.len());
+        for edit in fix {
+            let span = edit.span();
+            guard!(let (Some(start), Some(end)) = (offset(source, &line_starts, span.start), offset(source, &line_starts, span.end)) else {
+                continue 'fixes;
+            });
+            if start > end || source.get(start..end) != Some(edit.original()) {
+                continue 'fixes;
+            }
+            let range = start..end;
+            if accepted.iter().chain(pending.iter()).any(|(other, _)| overlaps(&range, other)) {
+                continue 'fixes;
+            }
+            let line_start = line_starts[span.start.line as usize - 1];
+            let indent_len = source[line_start..].bytes().take_while(|&b| b == b' ' || b == b'\t').count();
+            let indent = &source[line_start..line_start + indent_len];
+            let replacement = edit.replacement().replace('\n', &format!("{}{}", newline, indent));
+            pending.push((range, replacement));
+        }
+        if !pending.is_empty() {
+            accepted.extend(pending);
+            count += 1;
+        }
+    }
+
+    accepted.sort_by_key(|(range, _)| (range.start, range.end));
+    let mut output = String::with_capacity(source.len());
+    let mut pos = 0;
+    for (range, replacement) in accepted {
+        output.push_str(&source[pos..range.start]);
+        output.push_str(&replacement);
+        pos = range.end;
+    }
+    output.push_str(&source[pos..]);
+    (output, count)
+}
+
+/// Convert a location to a byte offset. Columns count bytes, starting at 1.
+fn offset(source: &str, line_starts: &[usize], location: Location) -> Option<usize> {
+    let line_start = *line_starts.get((location.line as usize).checked_sub(1)?)?;
+    let line_end = source[line_start..].find('\n').map_or(source.len(), |idx| line_start + idx);
+    let offset = line_start + (location.column as usize).checked_sub(1)?;
+    if offset <= line_end && source.is_char_boundary(offset) {
+        Some(offset)
+    } else {
+        None
+    }
+}
+
+fn overlaps(a: &Range<usize>, b: &Range<usize>) -> bool {
+    // Two insertions at the same point would have an unclear order.
+    (a.start < b.end && b.start < a.end) || (a.start == b.start && (a.is_empty() || b.is_empty()))
+}
+
+// ----------------------------------------------------------------------------
+// Unified diffs
+
+/// The number of unchanged lines shown around each change.
+const CONTEXT: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DiffOp {
+    Equal,
+    Delete,
+    Insert,
+}
+
+/// Render the differences between two versions of a file as a unified diff.
+pub fn unified_diff(path: &Path, old: &str, new: &str) -> String {
+    let old_lines: Vec<&str> = old.split_inclusive('\n').collect();
+    let new_lines: Vec<&str> = new.split_inclusive('\n').collect();
+    let ops = diff_lines(&old_lines, &new_lines);
+
+    // Each op, along with the old and new line indices it applies to.
+    let mut positions = Vec::with_capacity(ops.len());
+    let (mut i, mut j) = (0, 0);
+    for &op in ops.iter() {
+        positions.push((op, i, j));
+        match op {
+            DiffOp::Equal => { i += 1; j += 1; }
+            DiffOp::Delete => i += 1,
+            DiffOp::Insert => j += 1,
+        }
+    }
+
+    let mut output = String::new();
+    let path = path.display().to_string().replace('\\', "/");
+    let _ = writeln!(output, "--- a/{}", path);
+    let _ = writeln!(output, "+++ b/{}", path);
+
+    let mut idx = 0;
+    while let Some(first_change) = positions[idx..].iter().position(|(op, _, _)| *op != DiffOp::Equal) {
+        let start = (idx + first_change).saturating_sub(CONTEXT).max(idx);
+        // Extend the hunk until a run of unchanged lines long enough to
+        // separate it from the next one.
+        let mut end = idx + first_change;
+        let mut last_change = end;
+        while end < positions.len() {
+            if positions[end].0 != DiffOp::Equal {
+                last_change = end;
+            } else if end - last_change > 2 * CONTEXT {
+                break;
+            }
+            end += 1;
+        }
+        let end = (last_change + 1 + CONTEXT).min(positions.len());
+
+        let hunk = &positions[start..end];
+        let old_count = hunk.iter().filter(|(op, _, _)| *op != DiffOp::Insert).count();
+        let new_count = hunk.iter().filter(|(op, _, _)| *op != DiffOp::Delete).count();
+        let (_, old_start, new_start) = hunk[0];
+        let _ = writeln!(
+            output,
+            "@@ -{} +{} @@",
+            hunk_range(old_start, old_count),
+            hunk_range(new_start, new_count),
+        );
+        for &(op, i, j) in hunk {
+            let (prefix, line) = match op {
+                DiffOp::Equal => (' ', old_lines[i]),
+                DiffOp::Delete => ('-', old_lines[i]),
+                DiffOp::Insert => ('+', new_lines[j]),
+            };
+            output.push(prefix);
+            output.push_str(line);
+            if !line.ends_with('\n') {
+                output.push_str("\n\\ No newline at end of file\n");
+            }
+        }
+        idx = end;
+    }
+    output
+}
+
+fn hunk_range(start: usize, count: usize) -> String {
+    if count == 0 {
+        format!("{},0", start)
+    } else {
+        format!("{},{}", start + 1, count)
+    }
+}
+
+/// Find a shortest edit script between two lists of lines, per Myers' "An
+/// O(ND) Difference Algorithm and Its Variations".
+fn diff_lines(old: &[&str], new: &[&str]) -> Vec<DiffOp> {
+    let (n, m) = (old.len() as isize, new.len() as isize);
+    let max = n + m;
+    let index = |k: isize| (k + max) as usize;
+
+    let mut v = vec![0isize; 2 * max as usize + 2];
+    let mut trace = Vec::new();
+    'search: for d in 0..=max {
+        trace.push(v.clone());
+        for k in (-d..=d).step_by(2) {
+            let mut x = if k == -d || (k != d && v[index(k - 1)] < v[index(k + 1)]) {
+                v[index(k + 1)]
+            } else {
+                v[index(k - 1)] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && old[x as usize] == new[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[index(k)] = x;
+            if x >= n && y >= m {
+                break 'search;
+            }
+        }
+    }
+
+    let mut ops = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for (d, v) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let k = x - y;
+        let prev_k = if k == -d || (k != d && v[index(k - 1)] < v[index(k + 1)]) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let prev_x = v[index(prev_k)];
+        let prev_y = prev_x - prev_k;
+        while x > prev_x && y > prev_y {
+            ops.push(DiffOp::Equal);
+            x -= 1;
+            y -= 1;
+        }
+        if d > 0 {
+            ops.push(if x == prev_x { DiffOp::Insert } else { DiffOp::Delete });
+        }
+        x = prev_x;
+        y = prev_y;
+    }
+    ops.reverse();
+    ops
+}
@@ -0,0 +1,276 @@
+//! Consistency of `Topic()` link keys.
+//!
+//! Links such as `"?src=[REF(src)];action=eject"` are found in string
+//! interpolations, and `href_list["action"]` reads in `Topic()` overrides.
+//! A key read by a type's `Topic()` which no link to that type produces, or a
+//! key produced by a link which no `Topic()` of its target reads, is likely a
+//! typo.
+
+use std::collections::BTreeMap;
+
+use dm::{Context, DMError, Location, Severity};
+use dm::ast::{Expression, Follow, Term};
+use dm::objtree::TypeRef;
+
+/// A link built by a string interpolation.
+#[derive(Debug, Clone)]
+pub(crate) struct HrefLink<'o> {
+    pub location: Location,
+    /// The type of the `src` the link is sent to, if known.
+    pub target: Option<TypeRef<'o>>,
+    /// The constant keys of the link's parameters, besides `src`.
+    pub keys: Vec<String>,
+    /// Whether some parameters have keys which are not constant.
+    pub dynamic: bool,
+}
+
+/// A use of `href_list` in a `Topic()` override.
+#[derive(Debug, Clone)]
+pub(crate) struct HrefRead<'o> {
+    pub location: Location,
+    /// The type whose `Topic()` this is.
+    pub ty: TypeRef<'o>,
+    /// The key read, or `None` if `href_list` was used other than by indexing
+    /// it with a constant key.
+    pub key: Option<String>,
+}
+
+/// The parts of a link found in a string, before its target is resolved.
+pub(crate) struct LinkParts<'e> {
+    /// The expression interpolated as the `src` parameter, unwrapped from
+    /// any `REF()` or `\ref` around it.
+    pub target: Option<&'e Expression>,
+    /// The index of the interpolated part which is the target itself, or
+    /// `None` if the target was unwrapped from within its part or there is
+    /// no target.
+    pub target_is_part: Option<usize>,
+    pub keys: Vec<String>,
+    pub dynamic: bool,
+}
+
+enum State {
+    Search,
+    Target,
+    Key(String, bool),
+    Value,
+}
+
+const TERMINATORS: &[char] = &['\'', '"', '>', ' ', '\\', '#'];
+
+/// Find the links built by an interpolated string.
+pub(crate) fn parse_links<'e>(first: &str, parts: &'e [(Option<Expression>, String)]) -> Vec<LinkParts<'e>> {
+    let mut links = Vec::new();
+    let mut current: Option<LinkParts> = None;
+    let mut state = State::Search;
+    let mut recent = String::new();
+
+    let texts = std::iter::once((None, first)).chain(parts.iter().enumerate().map(|(i, (expr, text))| (Some((i, expr)), text.as_str())));
+    for (part, text) in texts {
+        if let Some((i, expr)) = part {
+            match &mut state {
+                State::Target => {
+                    if let Some(link) = current.as_mut() {
+                        let (target, unwrapped) = match expr {
+                            Some(expr) => unwrap_ref(expr),
+                            None => (None, false),
+                        };
+                        link.target = target;
+                        link.target_is_part = if unwrapped { None } else { Some(i) };
+                    }
+                    state = State::Value;
+                }
+                State::Key(_, dynamic) => *dynamic = true,
+                _ => {}
+            }
+            recent.clear();
+        }
+
+        for ch in text.chars() {
+            state = match state {
+                State::Search => {
+                    recent.push(ch);
+                    if recent.len() > 5 {
+                        recent.remove(0);
+                    }
+                    if recent.ends_with("?src=") {
+                        current = Some(LinkParts {
+                            target: None,
+                            target_is_part: None,
+                            keys: Vec::new(),
+                            dynamic: false,
+                        });
+                        State::Target
+                    } else {
+                        State::Search
+                    }
+                }
+                // Allow for `\ref` before the target.
+                State::Target if ch == '\\' || ch.is_ascii_alphanumeric() => State::Target,
+                State::Target | State::Value if ch == ';' || ch == '&' => State::Key(String::new(), false),
+                State::Target | State::Value if TERMINATORS.contains(&ch) => {
+                    links.extend(current.take());
+                    State::Search
+                }
+                State::Target | State::Value => State::Value,
+                State::Key(key, dynamic) => {
+                    let ends = ch == '=' || ch == ';' || ch == '&' || TERMINATORS.contains(&ch);
+                    if !ends {
+                        let mut key = key;
+                        key.push(ch);
+                        State::Key(key, dynamic)
+                    } else {
+                        if let Some(link) = current.as_mut() {
+                            if dynamic {
+                                link.dynamic = true;
+                            } else if !key.is_empty() && !link.keys.contains(&key) {
+                                link.keys.push(key);
+                            }
+                        }
+                        if ch == '=' {
+                            State::Value
+                        } else if ch == ';' || ch == '&' {
+                            State::Key(String::new(), false)
+                        } else {
+                            links.extend(current.take());
+                            State::Search
+                        }
+                    }
+                }
+            };
+        }
+    }
+
+    if let State::Key(key, dynamic) = state {
+        if let Some(link) = current.as_mut() {
+            if dynamic {
+                link.dynamic = true;
+            } else if !key.is_empty() && !link.keys.contains(&key) {
+                link.keys.push(key);
+            }
+        }
+    }
+    links.extend(current);
+    links
+}
+
+/// Unwrap `REF(x)`, `ref(x)`, and `"\ref[x]"` to `x`.
+fn unwrap_ref(expr: &Expression) -> (Option<&Expression>, bool) {
+    match expr.as_term() {
+        Some(Term::Call(name, args)) if (name == "REF" || name == "ref") && args.len() == 1 => (Some(&args[0]), true),
+        Some(Term::InterpString(first, parts)) if first == "\\ref" && parts.len() == 1 && parts[0].1.is_empty() => {
+            (parts[0].0.as_ref(), true)
+        }
+        Some(Term::Expr(inner)) => unwrap_ref(inner),
+        _ => (Some(expr), false),
+    }
+}
+
+/// If this expression uses the `href_list` parameter, return the constant
+/// key it is indexed by, or `None` if it is used some other way.
+pub(crate) fn href_list_key(expr: &Expression, href_list: &str) -> Option<Option<String>> {
+    match expr {
+        Expression::Base { term, follow, .. } => match &term.elem {
+            Term::Ident(name) if name == href_list => Some(match follow.first().map(|each| &each.elem) {
+                Some(Follow::Index(_, index)) => match index.as_term() {
+                    Some(Term::String(key)) => Some(key.clone()),
+                    _ => None,
+                },
+                _ => None,
+            }),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Report keys which are read but never produced, and produced but never
+/// read, among the types which have both links and `Topic()` reads.
+pub(crate) fn check_hrefs(context: &Context, links: &[HrefLink], reads: &[HrefRead]) {
+    let related = |a: TypeRef, b: TypeRef| a.is_subtype_of(&b) || b.is_subtype_of(&a);
+
+    // Group the reads by the type whose Topic() they are in.
+    let mut by_type: BTreeMap<String, (TypeRef, Vec<&HrefRead>)> = BTreeMap::new();
+    for read in reads.iter() {
+        by_type.entry(read.ty.path.clone()).or_insert_with(|| (read.ty, Vec::new())).1.push(read);
+    }
+
+    for (ty, type_reads) in by_type.values() {
+        let related_links: Vec<&HrefLink> = links.iter()
+            .filter(|link| link.target.map_or(false, |target| related(target, *ty)))
+            .collect();
+        if related_links.is_empty() || related_links.iter().any(|link| link.dynamic) {
+            continue;
+        }
+        for read in type_reads.iter() {
+            guard!(let Some(key) = read.key.as_ref() else { continue });
+            if key == "src" {
+                continue;
+            }
+            let produced = links.iter()
+                .filter(|link| link.target.map_or(true, |target| related(target, *ty)))
+                .any(|link| link.keys.contains(key));
+            if !produced {
+                let mut error = DMError::new(read.location, format!("href_list key {:?} is read by {}/Topic() but no link produces it", key, ty))
+                    .with_component(dm::Component::DreamChecker)
+                    .with_errortype("href_key_not_produced")
+                    .set_severity(Severity::Warning);
+                if let Some(close) = closest_key(key, related_links.iter().flat_map(|link| link.keys.iter())) {
+                    error.add_note(read.location, format!("did you mean {:?}?", close));
+                }
+                error.register(context);
+            }
+        }
+    }
+
+    for link in links.iter() {
+        guard!(let Some(target) = link.target else { continue });
+        let handlers: Vec<&HrefRead> = reads.iter()
+            .filter(|read| target.is_subtype_of(&read.ty) || read.ty.is_subtype_of(&target))
+            .collect();
+        // Topic() may pass href_list on to be read elsewhere.
+        if handlers.is_empty() || handlers.iter().any(|read| read.key.is_none()) {
+            continue;
+        }
+        for key in link.keys.iter() {
+            if !handlers.iter().any(|read| read.key.as_ref() == Some(key)) {
+                let mut error = DMError::new(link.location, format!("link key {:?} is not read by the Topic() of {}", key, target))
+                    .with_component(dm::Component::DreamChecker)
+                    .with_errortype("href_key_not_handled")
+                    .set_severity(Severity::Warning);
+                if let Some(close) = closest_key(key, handlers.iter().filter_map(|read| read.key.as_ref())) {
+                    error.add_note(link.location, format!("did you mean {:?}?", close));
+                }
+                error.register(context);
+            }
+        }
+    }
+}
+
+/// Find a key within a small edit distance, for a suggestion.
+fn closest_key<'a, I: Iterator<Item = &'a String>>(key: &str, candidates: I) -> Option<&'a str> {
+    candidates
+        .filter(|candidate| *candidate != key)
+        .map(|candidate| (edit_distance(key, candidate), candidate))
+        .filter(|&(distance, _)| distance <= 2)
+        .min()
+        .map(|(_, candidate)| candidate.as_str())
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut previous = row[0];
+        row[0] = i + 1;
+        for (j, &cb) in b.iter().enumerate() {
+            let current = row[j + 1];
+            row[j + 1] = if ca == cb {
+                previous
+            } else {
+                1 + previous.min(row[j]).min(row[j + 1])
+            };
+            previous = current;
+        }
+    }
+    row[b.len()]
+}
@@ -0,0 +1,139 @@
+//! Checking constant `icon_state` values against the states in their `icon`.
+
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::path::PathBuf;
+
+use dm::{Context, DMError, Location, Severity};
+use dm::constants::Constant;
+use dm::dmi::Metadata;
+use dm::objtree::{ObjectTree, TypeRef};
+
+use crate::maps::MapVarEdit;
+use crate::resources::{Lookup, ResourceSearch};
+
+/// Check every type which sets `icon` or `icon_state`, and every map instance
+/// which edits `icon_state`, for states missing from the effective icon.
+pub(crate) fn check_icon_states(
+    context: &Context,
+    objtree: &ObjectTree,
+    resources: &mut ResourceSearch,
+    map_edits: &[MapVarEdit],
+) {
+    let mut checker = IconStateChecker {
+        context,
+        resources,
+        icons: Default::default(),
+    };
+
+    objtree.root().recurse(&mut |ty| {
+        let vars = &ty.get().vars;
+        let location = match (vars.get("icon_state"), vars.get("icon")) {
+            (Some(var), _) | (None, Some(var)) => var.value.location,
+            (None, None) => return,
+        };
+        if location.is_builtins() {
+            return;
+        }
+        if let (Some(icon), Some(icon_state)) = (constant_var(ty, "icon"), constant_var(ty, "icon_state")) {
+            checker.check(location, icon, icon_state);
+        }
+    });
+
+    let mut instances: HashMap<usize, Vec<&MapVarEdit>> = HashMap::new();
+    for edit in map_edits.iter() {
+        instances.entry(edit.instance).or_default().push(edit);
+    }
+    for edit in map_edits.iter() {
+        if edit.name != "icon_state" {
+            continue;
+        }
+        let edited_icon = instances[&edit.instance].iter().find(|each| each.name == "icon").map(|each| &each.value);
+        let icon = match edited_icon {
+            Some(icon) => Some(icon),
+            None => objtree.find(&edit.type_path).and_then(|ty| constant_var(ty, "icon")),
+        };
+        if let Some(icon) = icon {
+            checker.check(edit.location, icon, &edit.value);
+        }
+    }
+}
+
+fn constant_var<'a>(ty: TypeRef<'a>, name: &str) -> Option<&'a Constant> {
+    ty.get_value(name)?.constant.as_ref()
+}
+
+struct IconStateChecker<'c, 'r, 'o> {
+    context: &'c Context,
+    resources: &'r mut ResourceSearch<'o>,
+    icons: HashMap<PathBuf, Option<Metadata>>,
+}
+
+impl<'c, 'r, 'o> IconStateChecker<'c, 'r, 'o> {
+    fn check(&mut self, location: Location, icon: &Constant, icon_state: &Constant) {
+        let (icon, icon_state) = match (icon, icon_state) {
+            (Constant::Resource(icon), Constant::String(icon_state)) => (icon, icon_state),
+            _ => return,
+        };
+        // An empty or missing state falls back to the icon's default.
+        if icon_state.is_empty() || !icon.to_ascii_lowercase().ends_with(".dmi") {
+            return;
+        }
+
+        let path = match self.resources.lookup(self.context, location, icon) {
+            Lookup::Found(path) | Lookup::WrongCase(path) => path,
+            // reported as a `missing_resource` instead
+            Lookup::Missing => return,
+        };
+        let metadata = match self.icons.entry(path) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let metadata = Metadata::from_file(entry.key()).ok().map(|(_, metadata)| metadata);
+                entry.insert(metadata)
+            },
+        };
+        let metadata = match metadata {
+            Some(metadata) => metadata,
+            None => return,
+        };
+        if metadata.state_names.contains_key(icon_state.as_str()) {
+            return;
+        }
+
+        let mut description = format!("icon_state {:?} not found in '{}'", icon_state, icon);
+        if let Some(meant) = closest_match(icon_state, metadata.state_names.keys()) {
+            description.push_str(&format!(", did you mean {:?}?", meant));
+        }
+        DMError::new(location, description)
+            .with_component(dm::Component::DreamChecker)
+            .with_errortype("missing_icon_state")
+            .set_severity(Severity::Warning)
+            .register(self.context);
+    }
+}
+
+/// Find the candidate nearest to `name` by edit distance, if any is close
+/// enough to plausibly be a typo.
+fn closest_match<'a, I: IntoIterator<Item = &'a String>>(name: &str, candidates: I) -> Option<&'a str> {
+    let threshold = std::cmp::max(1, name.chars().count() / 3);
+    candidates.into_iter()
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|&(distance, _)| distance <= threshold)
+        .min_by_key(|&(distance, _)| distance)
+        .map(|(_, candidate)| candidate.as_str())
+}
+
+/// The Levenshtein distance between two strings.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, &cb) in b.iter().enumerate() {
+            let substitute = previous[j] + if ca == cb { 0 } else { 1 };
+            current.push(substitute.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
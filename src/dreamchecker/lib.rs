@@ -4,16 +4,48 @@
 #[macro_use] extern crate guard;
 
 extern crate dreammaker as dm;
-use dm::{Context, DMError, Location, Severity};
-use dm::objtree::{ObjectTree, TypeRef, ProcRef, Code};
+use dm::{Context, DMError, FileId, Location, Severity};
+use dm::objtree::{ObjectTree, TypeRef, ProcRef, Code, NavigatePathResult};
 use dm::constants::{Constant, ConstFn};
 use dm::ast::*;
 
 use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::path::PathBuf;
+
+use rayon::prelude::*;
 
 mod type_expr;
 use type_expr::TypeExpr;
 
+pub mod metrics;
+use metrics::ProcMetrics;
+
+mod duplicates;
+use duplicates::DuplicateFinder;
+
+pub mod maps;
+mod unused_vars;
+
+mod resources;
+use resources::ResourceSearch;
+mod icon_states;
+
+mod custom_lints;
+use custom_lints::CustomLints;
+
+mod hrefs;
+use hrefs::{HrefLink, HrefRead};
+
+mod verbs;
+use verbs::VerbName;
+
+mod text_macros;
+
+pub mod cache;
+use cache::AnalysisCache;
+
+pub mod fix;
+
 #[doc(hidden)]  // Intended for the tests only.
 pub mod test_helpers;
 
@@ -236,6 +268,28 @@ impl<'o> Analysis<'o> {
         }
     }
 
+    fn from_type_path(ty: TypeRef<'o>) -> Analysis<'o> {
+        let pop = dm::constants::Pop::from(ty.path.split("/").skip(1).map(ToOwned::to_owned).collect::<Vec<_>>());
+        Analysis {
+            static_ty: StaticType::None,
+            aset: assumption_set![Assumption::IsPath(true, ty)],
+            value: Some(Constant::Prefab(pop)),
+            fix_hint: None,
+            is_impure: None,
+        }
+    }
+
+    /// The type which the `::` operator looks on: the type named by a type
+    /// path, or otherwise the static type.
+    fn scope_type(&self) -> Option<TypeRef<'o>> {
+        self.aset.set.iter()
+            .find_map(|each| match each {
+                Assumption::IsPath(true, ty) => Some(*ty),
+                _ => None,
+            })
+            .or_else(|| self.static_ty.basic_type())
+    }
+
     fn with_fix_hint<S: Into<String>>(mut self, location: Location, desc: S) -> Self {
         if location != Location::default() {
             self.fix_hint = Some((location, desc.into()));
@@ -271,6 +325,99 @@ impl WithFilterArgs for DMError {
     }
 }
 
+/// Find the name of the var assigned to by the left side of an assignment,
+/// unless it is a local var.
+fn assigned_var<'e>(lhs: &'e Expression, local_vars: &HashMap<String, LocalVar>) -> Option<&'e str> {
+    match lhs {
+        Expression::Base { unary, term, follow } if unary.is_empty() => match follow.last() {
+            Some(Spanned { elem: Follow::Field(_, name), .. }) => Some(name),
+            Some(_) => None,
+            None => match &term.elem {
+                Term::Ident(name) if !local_vars.contains_key(name) => Some(name),
+                _ => None,
+            },
+        },
+        _ => None,
+    }
+}
+
+/// Builtins which take typepaths that may be written as strings, with the
+/// index of the first such argument. Only strings starting with `/` are
+/// checked, as others may be tags or refs. `text2path()` and `locate()` are
+/// checked separately, as they also infer the resulting type.
+const STRING_PATH_ARGS: &[(&str, usize)] = &[
+    ("ispath", 1),
+    ("istype", 1),
+    ("typesof", 0),
+];
+
+/// Fold a string literal or an interpolation of constants into its text.
+fn fold_string(expr: &Expression, location: Location) -> Option<String> {
+    match expr.as_term()? {
+        Term::String(text) => Some(text.to_owned()),
+        Term::InterpString(first, parts) => {
+            let mut text = first.to_owned();
+            for (expr, literal) in parts.iter() {
+                match expr.clone()?.simple_evaluate(location).ok()? {
+                    Constant::String(part) => text.push_str(&part),
+                    Constant::Prefab(pop) => text.push_str(&pop.to_string()),
+                    Constant::Int(i) => text.push_str(&i.to_string()),
+                    _ => return None,
+                }
+                text.push_str(literal);
+            }
+            Some(text)
+        },
+        _ => None,
+    }
+}
+
+/// The BYOND version which introduced a builtin type, or the builtin type a
+/// user type inherits from.
+fn type_since(ty: TypeRef) -> Option<u32> {
+    let mut current = Some(ty);
+    while let Some(ty) = current {
+        if ty.docs.since.is_some() {
+            return ty.docs.since;
+        }
+        current = ty.parent_type();
+    }
+    None
+}
+
+/// The BYOND version which introduced a builtin var, found on the type which
+/// declares it. Vars the project declares itself are not versioned.
+fn var_since(ty: TypeRef, name: &str) -> Option<u32> {
+    let mut current = Some(ty);
+    while let Some(ty) = current {
+        if let Some(var) = ty.vars.get(name) {
+            if let Some(ref decl) = var.declaration {
+                if !decl.location.is_builtins() {
+                    return None;
+                }
+                return var.value.docs.since;
+            }
+        }
+        current = ty.parent_type();
+    }
+    None
+}
+
+/// The BYOND version which introduced a builtin proc, found on its original
+/// declaration. Procs the project defines itself, such as polyfills for
+/// older versions, are not versioned.
+fn proc_since(mut proc: ProcRef) -> Option<u32> {
+    loop {
+        if !proc.get().location.is_builtins() {
+            return None;
+        }
+        match proc.parent_proc() {
+            Some(parent) => proc = parent,
+            None => return proc.get().docs.since,
+        }
+    }
+}
+
 /// Build an analysis from an assumption set.
 impl<'o> From<AssumptionSet<'o>> for Analysis<'o> {
     fn from(aset: AssumptionSet<'o>) -> Analysis<'o> {
@@ -307,15 +454,60 @@ impl<'o> From<StaticType<'o>> for Analysis<'o> {
 
 /// Run DreamChecker, registering diagnostics to the context.
 pub fn run(context: &Context, objtree: &ObjectTree) {
-    run_inner(context, objtree, false)
+    run_inner(context, objtree, &Environment::default(), None, false);
 }
 
 /// Run DreamChecker, registering diagnostics and printing progress to stdout.
-pub fn run_cli(context: &Context, objtree: &ObjectTree) {
-    run_inner(context, objtree, true)
+///
+/// Proc results are reused from `cache` where still valid, and the cache is
+/// updated with the results of this run. Returns the code metrics gathered
+/// for each proc body.
+pub fn run_cli(context: &Context, objtree: &ObjectTree, environment: &Environment, cache: Option<&mut AnalysisCache>) -> Vec<ProcMetrics> {
+    run_inner(context, objtree, environment, cache, true)
+}
+
+/// Files of the environment outside the object tree, which some checks
+/// consider. Checks which need these are skipped if they are empty.
+#[derive(Debug, Clone, Default)]
+pub struct Environment {
+    /// The `.dmm` files included by the environment.
+    pub maps: Vec<PathBuf>,
+    /// The directories searched for resource files: the `.dme` directory
+    /// followed by each `FILE_DIR`.
+    pub resource_dirs: Vec<PathBuf>,
+    /// The directory containing the `.dme`, which file paths are relative to.
+    pub root: PathBuf,
+    /// The state of the defines at the start of each file, used to tell
+    /// whether cached results for that file are still valid.
+    pub define_states: HashMap<FileId, u64>,
+    /// A hash of the contents of each file, for the same purpose.
+    pub file_hashes: HashMap<FileId, u64>,
+}
+
+impl Environment {
+    /// Collect the environment's files from a preprocessor which has been
+    /// run to completion.
+    pub fn from_preprocessor(preprocessor: &dm::preprocessor::Preprocessor) -> Environment {
+        let dme_dir = preprocessor.env_file().parent().map(ToOwned::to_owned).unwrap_or_default();
+        let mut resource_dirs = vec![dme_dir.clone()];
+        resource_dirs.extend(preprocessor.file_dirs().iter().cloned());
+        Environment {
+            maps: preprocessor.maps().to_owned(),
+            resource_dirs,
+            root: dme_dir,
+            define_states: preprocessor.define_states().clone(),
+            file_hashes: preprocessor.file_hashes().clone(),
+        }
+    }
 }
 
-fn run_inner(context: &Context, objtree: &ObjectTree, cli: bool) {
+fn run_inner(
+    context: &Context,
+    objtree: &ObjectTree,
+    environment: &Environment,
+    mut cache: Option<&mut AnalysisCache>,
+    cli: bool,
+) -> Vec<ProcMetrics> {
     macro_rules! cli_println {
         ($($rest:tt)*) => {
             if cli { println!($($rest)*) }
@@ -327,11 +519,18 @@ fn run_inner(context: &Context, objtree: &ObjectTree, cli: bool) {
 
     check_var_defs(&objtree, &context);
 
-    let mut analyzer = AnalyzeObjectTree::new(context, objtree);
+    let mut analyzer = AnalyzeObjectTree::new(context, objtree, &environment.resource_dirs);
 
     let mut present = 0;
     let mut invalid = 0;
     let mut builtin = 0;
+    let mut proc_metrics = Vec::new();
+
+    let (duplicate_min_statements, duplicate_normalize) = {
+        let config = context.config();
+        (config.code_standards.duplicate_code_min_statements, config.code_standards.duplicate_code_normalize)
+    };
+    let mut duplicates = duplicate_min_statements.map(|_| DuplicateFinder::new(duplicate_normalize));
 
     cli_println!("============================================================");
     cli_println!("Gathering proc settings...\n");
@@ -345,12 +544,17 @@ fn run_inner(context: &Context, objtree: &ObjectTree, cli: bool) {
 
     cli_println!("============================================================");
     cli_println!("Analyzing proc bodies...\n");
+    let mut procs = Vec::new();
     objtree.root().recurse(&mut |ty| {
         for proc in ty.iter_self_procs() {
             match proc.get().code {
                 Code::Present(ref code) => {
                     present += 1;
-                    analyzer.check_proc(proc, code);
+                    procs.push((proc, &code[..]));
+                    proc_metrics.push((proc, ProcMetrics::measure(proc, code)));
+                    if let Some(duplicates) = duplicates.as_mut() {
+                        duplicates.add_proc(proc, code);
+                    }
                 }
                 Code::Invalid(_) => invalid += 1,
                 Code::Builtin => builtin += 1,
@@ -358,8 +562,15 @@ fn run_inner(context: &Context, objtree: &ObjectTree, cli: bool) {
             }
         }
     });
+    if let Some(cache) = cache.as_mut() {
+        cache.prepare(context, objtree, environment);
+    }
+    analyzer.check_procs(&procs, cache.as_deref_mut());
 
     cli_println!("Procs analyzed: {}. Errored: {}. Builtins: {}.\n", present, invalid, builtin);
+    if let Some(cache) = cache.as_ref() {
+        cli_println!("Procs reused from cache: {}.\n", cache.hits());
+    }
 
     cli_println!("============================================================");
     cli_println!("Analyzing proc override validity...\n");
@@ -372,9 +583,50 @@ fn run_inner(context: &Context, objtree: &ObjectTree, cli: bool) {
 
     analyzer.finish_check_kwargs();
 
+    let check_unused_vars = context.config().is_opted_in("unused_type_var");
+    let map_edits = if analyzer.resources.is_enabled() || check_unused_vars {
+        maps::read_var_edits(context, &environment.maps)
+    } else {
+        Vec::new()
+    };
+
+    if analyzer.resources.is_enabled() {
+        cli_println!("============================================================");
+        cli_println!("Checking var resources...\n");
+        analyzer.resources.check_var_values(context, objtree);
+        icon_states::check_icon_states(context, objtree, &mut analyzer.resources, &map_edits);
+    }
+
     cli_println!("============================================================");
     cli_println!("Analyzing proc call tree...\n");
     analyzer.check_proc_call_tree();
+
+    cli_println!("============================================================");
+    cli_println!("Checking Topic() link keys...\n");
+    hrefs::check_hrefs(context, &analyzer.href_links, &analyzer.href_reads);
+
+    cli_println!("============================================================");
+    cli_println!("Checking verb names...\n");
+    verbs::check_duplicate_names(context, &analyzer.verb_names);
+
+    cli_println!("============================================================");
+    cli_println!("Gathering proc metrics...\n");
+    metrics::fill_call_tree(&mut proc_metrics, &analyzer.call_tree);
+    metrics::check_thresholds(context, &proc_metrics);
+
+    if let (Some(duplicates), Some(min_statements)) = (duplicates, duplicate_min_statements) {
+        cli_println!("============================================================");
+        cli_println!("Detecting duplicate code...\n");
+        duplicates.report(context, min_statements);
+    }
+
+    if check_unused_vars {
+        cli_println!("============================================================");
+        cli_println!("Finding unused vars...\n");
+        unused_vars::check_unused_vars(context, objtree, &map_edits);
+    }
+
+    proc_metrics.into_iter().map(|(_, each)| each).collect()
 }
 
 // ----------------------------------------------------------------------------
@@ -444,6 +696,13 @@ impl<'o> ProcDirective<'o> {
         self.directive.get(&proc)
     }
 
+    /// Iterate over the procs with this directive in order of definition.
+    pub fn in_order(&self) -> Vec<(&ProcRef<'o>, &(bool, Location))> {
+        let mut entries: Vec<_> = self.directive.iter().collect();
+        entries.sort_by_key(|(proc, _)| proc.get().location);
+        entries
+    }
+
     /// Check if this ProcRef or its parents have this directive
     pub fn get_self_or_parent(&self, proc: ProcRef<'o>) -> Option<(ProcRef<'o>, bool, Location)> {
         let mut next = Some(proc);
@@ -476,7 +735,8 @@ pub fn directive_value_to_truthy(expr: &Expression, location: Location) -> Resul
         Some(Term::Ident(i)) if i == "FALSE" => Ok(false),
         Some(Term::Ident(i)) if i == "TRUE" => Ok(true),
         _ => Err(error(location, format!("invalid value for set {:?}", expr))
-        .set_severity(Severity::Warning)),
+            .set_severity(Severity::Warning)
+            .with_errortype("invalid_lint_directive_value")),
     }
 }
 
@@ -534,6 +794,12 @@ impl<'o> ViolatingProcs<'o> {
     pub fn get_violators(&self, proc: ProcRef<'o>) -> Option<&Vec<(String, Location)>> {
         self.violators.get(&proc)
     }
+
+    fn merge(&mut self, other: ViolatingProcs<'o>) {
+        for (proc, violations) in other.violators {
+            self.violators.entry(proc).or_default().extend(violations);
+        }
+    }
 }
 
 #[derive(Default, Debug)]
@@ -556,6 +822,31 @@ pub struct AnalyzeObjectTree<'o> {
     context: &'o Context,
     objtree: &'o ObjectTree,
 
+    settings: ProcSettings<'o>,
+
+    // Debug(ProcRef) -> KwargInfo
+    used_kwargs: BTreeMap<String, KwargInfo>,
+
+    call_tree: HashMap<ProcRef<'o>, Vec<(ProcRef<'o>, Location, bool)>>,
+
+    sleeping_procs: ViolatingProcs<'o>,
+    impure_procs: ViolatingProcs<'o>,
+    waitfor_procs: HashSet<ProcRef<'o>>,
+
+    sleeping_overrides: ViolatingOverrides<'o>,
+    impure_overrides: ViolatingOverrides<'o>,
+
+    resources: ResourceSearch<'o>,
+
+    href_links: Vec<HrefLink<'o>>,
+    href_reads: Vec<HrefRead<'o>>,
+
+    verb_names: Vec<VerbName<'o>>,
+}
+
+/// Settings gathered from proc bodies, which are read by the analysis of
+/// every other proc.
+struct ProcSettings<'o> {
     return_type: HashMap<ProcRef<'o>, TypeExpr<'o>>,
     must_call_parent: ProcDirective<'o>,
     must_not_override: ProcDirective<'o>,
@@ -564,37 +855,68 @@ pub struct AnalyzeObjectTree<'o> {
     must_not_sleep: ProcDirective<'o>,
     sleep_exempt: ProcDirective<'o>,
     must_be_pure: ProcDirective<'o>,
+    must_use_result: ProcDirective<'o>,
     can_be_redefined: ProcDirective<'o>,
-    // Debug(ProcRef) -> KwargInfo
-    used_kwargs: BTreeMap<String, KwargInfo>,
+    custom_lints: CustomLints<'o>,
+    /// Procs which are not declared as verbs, but set verb settings.
+    verb_like: HashSet<ProcRef<'o>>,
+}
 
+/// Results of analyzing some proc bodies, gathered separately by each
+/// thread and merged in order afterwards.
+#[derive(Default)]
+pub(crate) struct ProcResults<'o> {
+    used_kwargs: BTreeMap<String, KwargInfo>,
     call_tree: HashMap<ProcRef<'o>, Vec<(ProcRef<'o>, Location, bool)>>,
-
     sleeping_procs: ViolatingProcs<'o>,
     impure_procs: ViolatingProcs<'o>,
     waitfor_procs: HashSet<ProcRef<'o>>,
+    /// Resource paths referenced, checked on disk once merged.
+    resources: Vec<(Location, String)>,
+    href_links: Vec<HrefLink<'o>>,
+    href_reads: Vec<HrefRead<'o>>,
+}
 
-    sleeping_overrides: ViolatingOverrides<'o>,
-    impure_overrides: ViolatingOverrides<'o>,
+/// The diagnostics and results of analyzing a single proc body.
+pub(crate) struct ProcOutcome<'o> {
+    errors: Vec<DMError>,
+    results: ProcResults<'o>,
 }
 
 impl<'o> AnalyzeObjectTree<'o> {
-    pub fn new(context: &'o Context, objtree: &'o ObjectTree) -> Self {
+    pub fn new(context: &'o Context, objtree: &'o ObjectTree, resource_dirs: &'o [PathBuf]) -> Self {
         let mut return_type = HashMap::default();
         return_type.insert(objtree.root().get_proc("get_step").unwrap(), StaticType::Type(objtree.expect("/turf")).into());
 
+        // Builtins which only compute a new value are pointless to call
+        // without using it.
+        let mut must_use_result = ProcDirective::new("SpacemanDMM_must_use_result", false, true, true);
+        let list = objtree.expect("/list");
+        let builtins = ["copytext", "copytext_char", "replacetext", "replacetextEx", "replacetext_char", "replacetextEx_char", "uppertext", "lowertext", "splittext", "jointext"];
+        let results_only = builtins.iter()
+            .filter_map(|name| objtree.root().get_proc(name))
+            .chain(list.get_proc("Copy"));
+        for proc in results_only {
+            let _ = must_use_result.insert(proc, true, proc.get().location);
+        }
+
         AnalyzeObjectTree {
             context,
             objtree,
-            return_type,
-            must_call_parent: ProcDirective::new("SpacemanDMM_should_call_parent", true, false, false),
-            must_not_override: ProcDirective::new("SpacemanDMM_should_not_override", false, false, false),
-            private: ProcDirective::new("SpacemanDMM_private_proc", false, true, false),
-            protected: ProcDirective::new("SpacemanDMM_protected_proc", false, true, false),
-            must_not_sleep: ProcDirective::new("SpacemanDMM_should_not_sleep", false, true, true),
-            sleep_exempt: ProcDirective::new("SpacemanDMM_allowed_to_sleep", false, true, true),
-            must_be_pure: ProcDirective::new("SpacemanDMM_should_be_pure", false, true, true),
-            can_be_redefined: ProcDirective::new("SpacemanDMM_can_be_redefined", false, false, false),
+            settings: ProcSettings {
+                return_type,
+                must_call_parent: ProcDirective::new("SpacemanDMM_should_call_parent", true, false, false),
+                must_not_override: ProcDirective::new("SpacemanDMM_should_not_override", false, false, false),
+                private: ProcDirective::new("SpacemanDMM_private_proc", false, true, false),
+                protected: ProcDirective::new("SpacemanDMM_protected_proc", false, true, false),
+                must_not_sleep: ProcDirective::new("SpacemanDMM_should_not_sleep", false, true, true),
+                sleep_exempt: ProcDirective::new("SpacemanDMM_allowed_to_sleep", false, true, true),
+                must_be_pure: ProcDirective::new("SpacemanDMM_should_be_pure", false, true, true),
+                must_use_result,
+                can_be_redefined: ProcDirective::new("SpacemanDMM_can_be_redefined", false, false, false),
+                custom_lints: CustomLints::new(context, objtree),
+                verb_like: Default::default(),
+            },
             used_kwargs: Default::default(),
             call_tree: Default::default(),
             sleeping_procs: Default::default(),
@@ -602,28 +924,111 @@ impl<'o> AnalyzeObjectTree<'o> {
             waitfor_procs: Default::default(),
             sleeping_overrides: Default::default(),
             impure_overrides: Default::default(),
+            resources: ResourceSearch::new(resource_dirs),
+            href_links: Default::default(),
+            href_reads: Default::default(),
+            verb_names: Default::default(),
         }
     }
 
     /// Analyze a specific proc
     pub fn check_proc(&mut self, proc: ProcRef<'o>, code: &'o [Spanned<Statement>]) {
-        self.must_not_sleep.try_copy_from_parent(proc);
-        self.must_be_pure.try_copy_from_parent(proc);
+        self.check_procs(&[(proc, code)], None);
+    }
+
+    /// Analyze many procs, across the rayon thread pool if it has more than
+    /// one thread, reusing and updating the results in `cache` if given.
+    ///
+    /// Diagnostics and results are merged in the order the procs are given,
+    /// so the output does not depend on the number of threads.
+    pub fn check_procs(&mut self, procs: &[(ProcRef<'o>, &'o [Spanned<Statement>])], mut cache: Option<&mut AnalysisCache>) {
+        for &(proc, _) in procs.iter() {
+            self.settings.must_not_sleep.try_copy_from_parent(proc);
+            self.settings.must_be_pure.try_copy_from_parent(proc);
+        }
+
+        let mut outcomes: Vec<Option<ProcOutcome<'o>>> = procs.iter()
+            .map(|&(proc, _)| cache.as_mut().and_then(|cache| cache.get(proc)))
+            .collect();
+        let misses: Vec<_> = procs.iter().zip(outcomes.iter())
+            .enumerate()
+            .filter(|(_, (_, outcome))| outcome.is_none())
+            .map(|(i, (&(proc, code), _))| (i, proc, code))
+            .collect();
+
+        if !misses.is_empty() {
+            // Contexts can't be shared between threads, so each chunk of procs
+            // registers diagnostics to its own fork of the context.
+            let chunk_count = rayon::current_num_threads() * 4;
+            let chunk_size = (misses.len() + chunk_count - 1) / chunk_count;
+            let forks: Vec<Context> = misses.chunks(chunk_size).map(|_| self.context.fork()).collect();
+            let settings = &self.settings;
+            let objtree = self.objtree;
+            let analyzed: Vec<Vec<(usize, ProcOutcome<'o>)>> = forks.into_par_iter()
+                .zip(misses.par_chunks(chunk_size))
+                .map(|(context, chunk)| {
+                    chunk.iter().map(|&(i, proc, code)| {
+                        let mut results = ProcResults::default();
+                        AnalyzeProc::new(settings, &mut results, &context, objtree, proc).run(code);
+                        (i, ProcOutcome { errors: context.take_errors(), results })
+                    }).collect()
+                })
+                .collect();
+            for (i, outcome) in analyzed.into_iter().flatten() {
+                outcomes[i] = Some(outcome);
+            }
+        }
 
-        AnalyzeProc::new(self, self.context, self.objtree, proc).run(code)
+        for (&(proc, _), outcome) in procs.iter().zip(outcomes) {
+            let outcome = outcome.expect("every proc was analyzed");
+            if let Some(cache) = cache.as_mut() {
+                cache.insert(self.context, proc, &outcome);
+            }
+            for error in outcome.errors {
+                self.context.register_error(error);
+            }
+            self.merge_results(outcome.results);
+        }
+    }
+
+    fn merge_results(&mut self, results: ProcResults<'o>) {
+        for (procname, kwarg_info) in results.used_kwargs {
+            match self.used_kwargs.entry(procname) {
+                std::collections::btree_map::Entry::Vacant(entry) => { entry.insert(kwarg_info); },
+                std::collections::btree_map::Entry::Occupied(mut entry) => {
+                    for (name, called_at) in kwarg_info.called_at {
+                        entry.get_mut().called_at.entry(name)
+                            .and_modify(|ca| ca.others += called_at.others + 1)
+                            .or_insert(called_at);
+                    }
+                },
+            }
+        }
+        for (proc, calls) in results.call_tree {
+            self.call_tree.entry(proc).or_default().extend(calls);
+        }
+        self.sleeping_procs.merge(results.sleeping_procs);
+        self.impure_procs.merge(results.impure_procs);
+        self.waitfor_procs.extend(results.waitfor_procs);
+        for (location, path) in results.resources {
+            self.resources.check(self.context, location, &path);
+        }
+        self.href_links.extend(results.href_links);
+        self.href_reads.extend(results.href_reads);
     }
 
     #[inline]
     fn add_directive_or_error(&mut self, proc: ProcRef<'o>, directive: &str, expr: &Expression, location: Location) {
         let procdirective = match directive {
-            "SpacemanDMM_should_not_override" => &mut self.must_not_override,
-            "SpacemanDMM_should_call_parent" => &mut self.must_call_parent,
-            "SpacemanDMM_private_proc" => &mut self.private,
-            "SpacemanDMM_protected_proc" => &mut self.protected,
-            "SpacemanDMM_should_not_sleep" => &mut self.must_not_sleep,
-            "SpacemanDMM_allowed_to_sleep" => &mut self.sleep_exempt,
-            "SpacemanDMM_should_be_pure" => &mut self.must_be_pure,
-            "SpacemanDMM_can_be_redefined" => &mut self.can_be_redefined,
+            "SpacemanDMM_should_not_override" => &mut self.settings.must_not_override,
+            "SpacemanDMM_should_call_parent" => &mut self.settings.must_call_parent,
+            "SpacemanDMM_private_proc" => &mut self.settings.private,
+            "SpacemanDMM_protected_proc" => &mut self.settings.protected,
+            "SpacemanDMM_should_not_sleep" => &mut self.settings.must_not_sleep,
+            "SpacemanDMM_allowed_to_sleep" => &mut self.settings.sleep_exempt,
+            "SpacemanDMM_should_be_pure" => &mut self.settings.must_be_pure,
+            "SpacemanDMM_must_use_result" => &mut self.settings.must_use_result,
+            "SpacemanDMM_can_be_redefined" => &mut self.settings.can_be_redefined,
             other => {
                 error(location, format!("unknown linter setting {:?}", directive))
                     .with_errortype("unknown_linter_setting")
@@ -637,6 +1042,7 @@ impl<'o> AnalyzeObjectTree<'o> {
             if let Some(procdef) = &mut proc.get_declaration() {
                 if procdef.location != proc.get().location {
                     error(location, format!("Can't define procs {} outside their initial definition", directive))
+                        .with_errortype("directive_outside_definition")
                         .set_severity(Severity::Warning)
                         .register(self.context);
                     return
@@ -650,12 +1056,12 @@ impl<'o> AnalyzeObjectTree<'o> {
                     self.context.register_error(error);
                 }
             },
-            Err(error) => self.context.register_error(error.with_errortype("invalid_lint_directive_value")),
+            Err(error) => self.context.register_error(error),
         }
     }
 
     pub fn check_proc_call_tree(&mut self) {
-        for (procref, &(_, location)) in self.must_not_sleep.directive.iter() {
+        for (procref, &(_, location)) in self.settings.must_not_sleep.in_order() {
             if let Some(sleepvec) = self.sleeping_procs.get_violators(*procref) {
                 error(procref.get().location, format!("{} sets SpacemanDMM_should_not_sleep but calls blocking built-in(s)", procref))
                     .with_note(location, "SpacemanDMM_should_not_sleep set here")
@@ -679,7 +1085,7 @@ impl<'o> AnalyzeObjectTree<'o> {
                 if let Some(_) = self.waitfor_procs.get(&nextproc) {
                     continue
                 }
-                if let Some(_) = self.sleep_exempt.get(nextproc) {
+                if let Some(_) = self.settings.sleep_exempt.get(nextproc) {
                     continue
                 }
                 if new_context {
@@ -714,7 +1120,7 @@ impl<'o> AnalyzeObjectTree<'o> {
             }
         }
 
-        for (procref, (_, location)) in self.must_be_pure.directive.iter() {
+        for (procref, (_, location)) in self.settings.must_be_pure.in_order() {
             if let Some(impurevec) = self.impure_procs.get_violators(*procref) {
                 error(procref.get().location, format!("{} does impure operations", procref))
                     .with_errortype("must_be_pure")
@@ -767,16 +1173,28 @@ impl<'o> AnalyzeObjectTree<'o> {
 
     /// Gather and store set directives for the given proc using the provided code body
     pub fn gather_settings(&mut self, proc: ProcRef<'o>, code: &'o [Spanned<Statement>]) {
+        let mut verb_like = verbs::is_declared_verb(proc);
+        let mut verb_name = None;
+        let mut verb_category = None;
         for statement in code.iter() {
-            if let Statement::Setting { ref name, ref value, .. } = statement.elem {
+            if let Statement::Setting { ref name, mode, ref value } = statement.elem {
+                if verbs::VERB_SETTINGS.contains(&name.as_str()) {
+                    verb_like = true;
+                }
+                if mode == SettingMode::In && name != "src" {
+                    error(statement.location, format!("set {} must use = rather than in", name))
+                        .set_severity(Severity::Warning)
+                        .with_errortype("invalid_set_value")
+                        .register(self.context);
+                }
                 if name == "SpacemanDMM_return_type" {
                     if let Some(Term::Prefab(fab)) = value.as_term() {
                         let bits: Vec<_> = fab.path.iter().map(|(_, name)| name.to_owned()).collect();
                         let ty = self.static_type(statement.location, &bits);
-                        self.return_type.insert(proc, TypeExpr::from(ty));
+                        self.settings.return_type.insert(proc, TypeExpr::from(ty));
                     } else {
                         match TypeExpr::compile(proc, statement.location, value) {
-                            Ok(expr) => { self.return_type.insert(proc, expr); },
+                            Ok(expr) => { self.settings.return_type.insert(proc, expr); },
                             Err(error) => error
                                 .with_component(dm::Component::DreamChecker)
                                 .register(self.context),
@@ -786,6 +1204,7 @@ impl<'o> AnalyzeObjectTree<'o> {
                     self.add_directive_or_error(proc, &name.as_str(), value, statement.location);
                 } else if !KNOWN_SETTING_NAMES.contains(&name.as_str()) {
                     error(statement.location, format!("unknown setting {:?}", name))
+                        .with_errortype("unknown_setting")
                         .set_severity(Severity::Warning)
                         .register(self.context);
                 } else {
@@ -801,8 +1220,12 @@ impl<'o> AnalyzeObjectTree<'o> {
                         "name" | "category" | "desc" => {
                             if let Some(term) = value.as_term() {
                                 match term {
-                                    // TODO: detect procs-as-verbs here
-                                    Term::String(_) | Term::InterpString(_, _) => {},
+                                    Term::String(text) => match name.as_str() {
+                                        "name" => verb_name = Some(text.as_str()),
+                                        "category" => verb_category = Some(text.as_str()),
+                                        _ => {},
+                                    },
+                                    Term::InterpString(_, _) => {},
                                     // category can be set null to hide it
                                     Term::Null if name.as_str() == "category" => {},
                                     other => {
@@ -814,6 +1237,14 @@ impl<'o> AnalyzeObjectTree<'o> {
                                 }
                             }
                         },
+                        "src" => {
+                            if !verbs::is_valid_src(value) {
+                                error(statement.location, format!("set src {} must be usr, usr.contents, usr.loc, usr.group, world, or a view(), oview(), range(), or orange() call", mode.name()))
+                                    .set_severity(Severity::Warning)
+                                    .with_errortype("invalid_set_value")
+                                    .register(self.context);
+                            }
+                        },
                         "invisibility" => {
                             if let Some(Term::Int(i)) = value.as_term() {
                                 if *i >= 0 && *i <= 100 {
@@ -832,6 +1263,16 @@ impl<'o> AnalyzeObjectTree<'o> {
                 break;
             }
         }
+
+        if verb_like {
+            if !verbs::is_declared_verb(proc) {
+                self.settings.verb_like.insert(proc);
+            }
+            verbs::check_input_types(self.context, self.objtree, proc);
+            if proc.parent_proc().is_none() {
+                self.verb_names.push(VerbName::new(proc, verb_name, verb_category));
+            }
+        }
     }
 
     /// Propagate violations make up the inheritence graph
@@ -924,12 +1365,16 @@ impl<'o> AnalyzeObjectTree<'o> {
     }
 
     fn static_type(&mut self, location: Location, of: &[String]) -> StaticType<'o> {
-        match static_type(self.objtree, location, of) {
-            Ok(s) => s,
-            Err(e) => {
-                e.register(self.context);
-                StaticType::None
-            }
+        static_type_or_error(self.objtree, self.context, location, of)
+    }
+}
+
+fn static_type_or_error<'o>(objtree: &'o ObjectTree, context: &Context, location: Location, of: &[String]) -> StaticType<'o> {
+    match static_type(objtree, location, of) {
+        Ok(s) => s,
+        Err(e) => {
+            e.register(context);
+            StaticType::None
         }
     }
 }
@@ -950,7 +1395,7 @@ fn static_type<'o>(objtree: &'o ObjectTree, location: Location, mut of: &[String
     } else if let Some(ty) = objtree.type_by_path(of) {
         Ok(StaticType::Type(ty))
     } else {
-        Err(error(location, format!("undefined type: {}", FormatTreePath(of))))
+        Err(error(location, format!("undefined type: {}", FormatTreePath(of))).with_errortype("undefined_type"))
     }
 }
 
@@ -994,6 +1439,7 @@ pub fn check_var_defs(objtree: &ObjectTree, context: &Context) {
                         continue;
                     }
                     DMError::new(mydecl.location, format!("{} redeclares var {:?}", path, varname))
+                        .with_errortype("redeclared_var")
                         .with_note(decl.location, format!("declared on {} here", parent.path))
                         .register(context);
                 }
@@ -1117,27 +1563,35 @@ impl<'o> From<Analysis<'o>> for LocalVar<'o> {
 }
 
 struct AnalyzeProc<'o, 's> {
-    env: &'s mut AnalyzeObjectTree<'o>,
-    context: &'o Context,
+    settings: &'s ProcSettings<'o>,
+    results: &'s mut ProcResults<'o>,
+    context: &'s Context,
     objtree: &'o ObjectTree,
     ty: TypeRef<'o>,
     proc_ref: ProcRef<'o>,
     calls_parent: bool,
     inside_newcontext: u32,
+    /// The proc whose call was most recently finished being visited.
+    last_call: Option<ProcRef<'o>>,
+    /// The type var most recently read through the `::` operator.
+    last_static_field: Option<(TypeRef<'o>, &'o str)>,
 }
 
 impl<'o, 's> AnalyzeProc<'o, 's> {
-    fn new(env: &'s mut AnalyzeObjectTree<'o>, context: &'o Context, objtree: &'o ObjectTree, proc_ref: ProcRef<'o>) -> Self {
+    fn new(settings: &'s ProcSettings<'o>, results: &'s mut ProcResults<'o>, context: &'s Context, objtree: &'o ObjectTree, proc_ref: ProcRef<'o>) -> Self {
         let ty = proc_ref.ty();
 
         AnalyzeProc {
-            env,
+            settings,
+            results,
             context,
             objtree,
             ty,
             proc_ref,
             calls_parent: false,
             inside_newcontext: 0,
+            last_call: None,
+            last_static_field: None,
         }
     }
 
@@ -1172,7 +1626,7 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
         //println!("purity {}", self.is_pure);
 
         if let Some(parent) = self.proc_ref.parent_proc() {
-            if let Some((proc, true, location)) = self.env.private.get_self_or_parent(self.proc_ref) {
+            if let Some((proc, true, location)) = self.settings.private.get_self_or_parent(self.proc_ref) {
                 if proc != self.proc_ref {
                     error(self.proc_ref.location, format!("proc overrides private parent, prohibited by {}", proc))
                     .with_note(location, "prohibited by this private_proc annotation")
@@ -1180,7 +1634,7 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
                     .register(self.context);
                 }
             }
-            if let Some((proc, true, location)) = self.env.must_not_override.get_self_or_parent(self.proc_ref) {
+            if let Some((proc, true, location)) = self.settings.must_not_override.get_self_or_parent(self.proc_ref) {
                 if proc != self.proc_ref {
                     error(self.proc_ref.location, format!("proc overrides parent, prohibited by {}", proc))
                         .with_note(location, "prohibited by this must_not_override annotation")
@@ -1189,15 +1643,21 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
                 }
             }
             if !self.calls_parent {
-                if let Some((proc, true, location)) = self.env.must_call_parent.get_self_or_parent(self.proc_ref) {
-                    error(self.proc_ref.location, format!("proc never calls parent, required by {}", proc))
+                if let Some((proc, true, location)) = self.settings.must_call_parent.get_self_or_parent(self.proc_ref) {
+                    let mut error = error(self.proc_ref.location, format!("proc never calls parent, required by {}", proc))
                         .with_note(location, "required by this must_call_parent annotation")
-                        .with_errortype("must_call_parent")
-                        .register(self.context);
+                        .with_errortype("must_call_parent");
+                    // Call the parent first thing, on its own line.
+                    if let Some(first) = block.first() {
+                        if first.location.line > self.proc_ref.location.line {
+                            error = error.with_edit(first.location, "", "..()\n");
+                        }
+                    }
+                    error.register(self.context);
                 }
             }
             if !parent.is_builtin() && self.proc_ref.ty() == parent.ty()
-                && self.env.can_be_redefined.get_self_or_parent(self.proc_ref).is_none() {
+                && self.settings.can_be_redefined.get_self_or_parent(self.proc_ref).is_none() {
                 error(self.proc_ref.location, format!("redefining proc {}/{}", self.ty, self.proc_ref.name()))
                     .with_errortype("redefined_proc")
                     .with_note(parent.location, "previous definition is here")
@@ -1256,15 +1716,18 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
     fn visit_statement(&mut self, location: Location, statement: &'o Statement, local_vars: &mut HashMap<String, LocalVar<'o>>) -> ControlFlow {
         match statement {
             Statement::Expr(expr) => {
+                let mut reported_pure = false;
                 match expr {
                     Expression::Base { unary, term, follow } => {
                         if let Term::Call(call, vec) = &term.elem {
                             if !follow.iter().any(|f| match f.elem { Follow::Call(..) => true, _ => false }) {
                                 if let Some(proc) = self.ty.get_proc(call) {
-                                    if let Some((_, _, loc)) = self.env.must_be_pure.get_self_or_parent(proc) {
+                                    if let Some((_, _, loc)) = self.settings.must_be_pure.get_self_or_parent(proc) {
                                         error(location, format!("call to pure proc {} discards return value", call))
+                                            .with_errortype("discarded_pure_result")
                                             .with_note(loc, "prohibited by this must_be_pure annotation")
                                             .register(self.context);
+                                        reported_pure = true;
                                     }
                                 }
                             }
@@ -1274,18 +1737,23 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
                         let lhsanalysis = self.visit_expression(location, lhs, None, local_vars);
                         if let Some(impurity) = lhsanalysis.is_impure {
                             if impurity {
-                                self.env.impure_procs.insert_violator(self.proc_ref, "purity breaking << on expression", location);
+                                self.results.impure_procs.insert_violator(self.proc_ref, "purity breaking << on expression", location);
                             }
                         }
                     },
                     _ => {},
                 }
+                self.last_call = None;
                 self.visit_expression(location, expr, None, local_vars);
+                if !reported_pure {
+                    self.check_discarded_result(location, expr);
+                }
             },
             Statement::Return(Some(expr)) => {
                 // TODO: factor in the previous return type if there was one
                 if self.inside_newcontext > 0 {
                     error(location, "returning a value in a spawn has no effect")
+                        .with_errortype("return_in_spawn")
                         .set_severity(Severity::Warning)
                         .register(self.context);
                 }
@@ -1314,6 +1782,7 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
                 let mut state = self.visit_block(block, &mut scoped_locals);
                 if state.terminates_loop() {
                     error(location,"do while terminates without ever reaching condition")
+                        .with_errortype("unreachable_code")
                         .register(self.context);
                     return state
                 }
@@ -1411,11 +1880,13 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
                                     // component system, where we loop over `var/datum/parent` that is known to be an
                                     // atom in a way that's hard for Dreamchecker to capture.
                                     error(location, "iterating over a /datum which might not be an /atom")
+                                        .with_errortype("bad_iteration")
                                         .set_severity(Severity::Hint)
                                         .register(self.context);
                                 } else {
                                     // The type is a /datum/foo subtype that definitely can't be looped over.
                                     error(location, format!("iterating over a {} which cannot be iterated", ty.path))
+                                        .with_errortype("bad_iteration")
                                         .register(self.context);
                                 }
                             }
@@ -1444,6 +1915,7 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
                         if let Some(validity) = startterm.valid_for_range(endterm, step.as_deref()) {
                             if !validity {
                                 error(location,"for range loop body is never reached due to invalid range")
+                                    .with_errortype("empty_for_range")
                                     .register(self.context);
                             } else {
                                 return state
@@ -1469,7 +1941,7 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
                     Some(Term::Ident(i)) if i == "FALSE" => Some(true),
                     _ => None,
                 } {
-                    Some(_) => { self.env.waitfor_procs.insert(self.proc_ref); },
+                    Some(_) => { self.results.waitfor_procs.insert(self.proc_ref); },
                     None => (),
                 }
             },
@@ -1515,6 +1987,7 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
                 self.visit_block(try_block, &mut local_vars.clone());
                 if catch_params.len() > 1 {
                     error(location, format!("Expected 0 or 1 catch parameters, got {}", catch_params.len()))
+                        .with_errortype("bad_catch_parameters")
                         .set_severity(Severity::Warning)
                         .register(self.context);
                 }
@@ -1548,7 +2021,7 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
 
     fn visit_var(&mut self, location: Location, var_type: &VarType, name: &str, value: Option<&'o Expression>, local_vars: &mut HashMap<String, LocalVar<'o>>) {
         // Calculate type hint
-        let static_type = self.env.static_type(location, &var_type.type_path);
+        let static_type = static_type_or_error(self.objtree, self.context, location, &var_type.type_path);
         // Visit the expression if it's there
         let mut analysis = match value {
             Some(ref expr) => self.visit_expression(location, expr, static_type.basic_type(), local_vars),
@@ -1563,17 +2036,48 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
     fn visit_expression(&mut self, location: Location, expression: &'o Expression, type_hint: Option<TypeRef<'o>>, local_vars: &mut HashMap<String, LocalVar<'o>>) -> Analysis<'o> {
         match expression {
             Expression::Base { unary, term, follow } => {
+                if let Some(href_list) = self.href_list_param() {
+                    if let Some(key) = hrefs::href_list_key(expression, href_list) {
+                        self.results.href_reads.push(HrefRead { location: term.location, ty: self.ty, key });
+                    }
+                }
                 let base_type_hint = if follow.is_empty() && unary.is_empty() {
                     type_hint
                 } else {
                     None
                 };
-                let mut ty = self.visit_term(term.location, &term.elem, base_type_hint, local_vars);
+                if let Some((last, rest)) = follow.split_last() {
+                    if let Follow::Call(_, name, args) = &last.elem {
+                        if name == "Add" && unary.is_empty() && verbs::is_verbs_list(&term.elem, rest) {
+                            self.check_added_verbs(location, args);
+                        }
+                    }
+                }
+                let mut ty = match (&term.elem, follow.first().map(|each| &each.elem)) {
+                    // `type::x` and `parent_type::x` look on the type this
+                    // proc is defined on, or its parent.
+                    (Term::Ident(name), Some(Follow::StaticField(_)))
+                    | (Term::Ident(name), Some(Follow::ProcReference(..)))
+                    | (Term::Ident(name), Some(Follow::Call(PropertyAccessKind::Scope, ..)))
+                        if (name == "type" || name == "parent_type") && !local_vars.contains_key(name) =>
+                    {
+                        let scope = if name == "type" {
+                            Some(self.ty)
+                        } else {
+                            self.ty.parent_type_without_root()
+                        };
+                        scope.map_or_else(Analysis::empty, Analysis::from_type_path)
+                    },
+                    _ => self.visit_term(term.location, &term.elem, base_type_hint, local_vars),
+                };
                 for each in follow.iter() {
                     ty = self.visit_follow(each.location, ty, &each.elem, local_vars);
                 }
+                if unary.iter().any(|op| matches!(op.elem, UnaryOp::PreIncr | UnaryOp::PostIncr | UnaryOp::PreDecr | UnaryOp::PostDecr)) {
+                    self.check_static_field_assign(location, follow);
+                }
                 for each in unary.iter().rev() {
-                    ty = self.visit_unary(ty, each, location, local_vars);
+                    ty = self.visit_unary(ty, &each.elem, location, local_vars);
                 }
                 ty
             },
@@ -1581,11 +2085,11 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
                 let lty = self.visit_expression(location, lhs, None, local_vars);
 
                 if lty.static_ty == StaticType::Type(self.objtree.expect("/mob")) {
-                    self.env.impure_procs.insert_violator(self.proc_ref, "LShift onto mob", location);
+                    self.results.impure_procs.insert_violator(self.proc_ref, "LShift onto mob", location);
                 } else if lty.static_ty == StaticType::Type(self.objtree.expect("/savefile")) {
-                    self.env.impure_procs.insert_violator(self.proc_ref, "LShift onto savefile", location);
+                    self.results.impure_procs.insert_violator(self.proc_ref, "LShift onto savefile", location);
                 } else if lty.static_ty == StaticType::Type(self.objtree.expect("/list")) {
-                    self.env.impure_procs.insert_violator(self.proc_ref, "LShift onto list", location);
+                    self.results.impure_procs.insert_violator(self.proc_ref, "LShift onto list", location);
                 }
 
                 let rty = self.visit_expression(location, rhs, None, local_vars);
@@ -1596,11 +2100,11 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
                 match &**lhs {
                     Expression::Base { unary, term, follow } => {
                         if unary.len() > 0 {
-                            error(location, format!("ambiguous `{}` on left side of an `in`", unary[0].name()))
+                            error(location, format!("ambiguous `{}` on left side of an `in`", unary[0].elem.name()))
                                 .set_severity(Severity::Warning)
                                 .with_errortype("ambiguous_in_lhs")
-                                .with_note(location, format!("add parentheses to fix: `{}`", unary[0].around("(a in b)")))
-                                .with_note(location, format!("add parentheses to disambiguate: `({}) in b`", unary[0].around("a")))
+                                .with_note(location, format!("add parentheses to fix: `{}`", unary[0].elem.around("(a in b)")))
+                                .with_note(location, format!("add parentheses to disambiguate: `({}) in b`", unary[0].elem.around("a")))
                                 .register(self.context);
                         }
                     },
@@ -1652,12 +2156,29 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
                 }
                 self.visit_binary(lty, rty, *op)
             },
-            Expression::AssignOp { lhs, rhs, .. } => {
-                let lhs = self.visit_expression(location, lhs, None, local_vars);
+            Expression::AssignOp { op, lhs: lhs_expr, rhs: rhs_expr } => {
+                let lhs = self.visit_expression(location, lhs_expr, None, local_vars);
+                if let Expression::Base { unary, follow, .. } = &**lhs_expr {
+                    if unary.is_empty() {
+                        self.check_static_field_assign(location, follow);
+                    }
+                }
                 if let Some(true) = lhs.is_impure {
-                    self.env.impure_procs.insert_violator(self.proc_ref, "Assignment on purity breaking expression", location);
+                    self.results.impure_procs.insert_violator(self.proc_ref, "Assignment on purity breaking expression", location);
                 }
-                self.visit_expression(location, rhs, lhs.static_ty.basic_type(), local_vars)
+                let rhs = self.visit_expression(location, rhs_expr, lhs.static_ty.basic_type(), local_vars);
+                if *op == AssignOp::Assign && self.settings.custom_lints.has_assign_rules() {
+                    if let Some(var) = assigned_var(lhs_expr, local_vars) {
+                        self.settings.custom_lints.check_assign(self.context, self.proc_ref, location, var, &rhs);
+                    }
+                }
+                if let Expression::Base { unary, term, follow } = &**lhs_expr {
+                    let adds = matches!(op, AssignOp::Assign | AssignOp::AddAssign | AssignOp::BitOrAssign);
+                    if adds && unary.is_empty() && verbs::is_verbs_list(&term.elem, follow) {
+                        self.check_added_verbs(location, std::slice::from_ref(rhs_expr));
+                    }
+                }
+                rhs
             },
             Expression::TernaryOp { cond, if_, else_ } => {
                 // TODO: be sensible
@@ -1674,10 +2195,35 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
             Term::Null => Analysis::null(),
             Term::Int(number) => Analysis::from_value(self.objtree, Constant::from(*number), type_hint),
             Term::Float(number) => Analysis::from_value(self.objtree, Constant::from(*number), type_hint),
-            Term::String(text) => Analysis::from_value(self.objtree, Constant::String(text.to_owned()), type_hint),
-            Term::Resource(text) => Analysis::from_value(self.objtree, Constant::Resource(text.to_owned()), type_hint),
+            Term::String(text) => {
+                text_macros::check_text_macros(self.context, self.objtree, location, text, &[]);
+                Analysis::from_value(self.objtree, Constant::String(text.to_owned()), type_hint)
+            },
+            Term::Resource(text) => {
+                self.results.resources.push((location, text.to_owned()));
+                Analysis::from_value(self.objtree, Constant::Resource(text.to_owned()), type_hint)
+            },
             Term::As(_) => assumption_set![Assumption::IsNum(true)].into(),
 
+            Term::Ident(name) if name == "__TYPE__" && !local_vars.contains_key(name) => {
+                self.context.check_target_version(location, 515, name);
+                Analysis::from_type_path(self.ty)
+            },
+            Term::Ident(name) if name == "__PROC__" && !local_vars.contains_key(name) => {
+                self.context.check_target_version(location, 515, name);
+                let kind = self.proc_ref.get_declaration().map_or(ProcDeclKind::Proc, |decl| decl.kind);
+                let mut path: Vec<String> = self.ty.path.split('/').skip(1).map(ToOwned::to_owned).collect();
+                path.push(kind.name().to_owned());
+                path.push(self.proc_ref.name().to_owned());
+                Analysis::from_value(self.objtree, Constant::Prefab(dm::constants::Pop::from(path)), None)
+            },
+            Term::Ident(name) if name == "__IMPLIED_TYPE__" && !local_vars.contains_key(name) => {
+                self.context.check_target_version(location, 515, name);
+                match type_hint {
+                    Some(ty) => Analysis::from_type_path(ty),
+                    None => Analysis::empty(),
+                }
+            },
             Term::Ident(unscoped_name) => {
                 if let Some(var) = local_vars.get(unscoped_name) {
                     return var.analysis.clone()
@@ -1685,12 +2231,33 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
                 }
                 if let Some(decl) = self.ty.get_var_declaration(unscoped_name) {
                     //println!("found type var");
+                    if let Some(since) = var_since(self.ty, unscoped_name) {
+                        self.context.check_target_version(location, since, unscoped_name);
+                    }
                     let mut ana = self.static_type(location, &decl.var_type.type_path)
                         .with_fix_hint(decl.location, "add additional type info here");
                     ana.is_impure = Some(true);
                     return ana
                 } else {
                     error(location, format!("undefined var: {:?}", unscoped_name))
+                        .with_errortype("undefined_var")
+                        .register(self.context);
+                    Analysis::empty()
+                }
+            },
+
+            Term::GlobalIdent(name) => {
+                if let Some(decl) = self.objtree.root().get_var_declaration(name) {
+                    if let Some(since) = var_since(self.objtree.root(), name) {
+                        self.context.check_target_version(location, since, name);
+                    }
+                    let mut ana = self.static_type(location, &decl.var_type.type_path)
+                        .with_fix_hint(decl.location, "add additional type info here");
+                    ana.is_impure = Some(true);
+                    ana
+                } else {
+                    error(location, format!("undefined global var: {:?}", name))
+                        .with_errortype("undefined_var")
                         .register(self.context);
                     Analysis::empty()
                 }
@@ -1699,30 +2266,55 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
             Term::Expr(expr) => self.visit_expression(location, expr, type_hint, local_vars),
             Term::Prefab(prefab) => {
                 if let Some(nav) = self.ty.navigate_path(&prefab.path) {
-                    let ty = nav.ty();  // TODO: handle proc/verb paths here
-                    let pop = dm::constants::Pop::from(ty.path.split("/").skip(1).map(ToOwned::to_owned).collect::<Vec<_>>());
-                    Analysis {
-                        static_ty: StaticType::None,
-                        aset: assumption_set![Assumption::IsPath(true, nav.ty())].into(),
-                        value: Some(Constant::Prefab(pop)),
-                        fix_hint: None,
-                        is_impure: None,
-                    }
+                    // TODO: handle proc/verb paths here
+                    self.check_type_version(location, nav.ty());
+                    Analysis::from_type_path(nav.ty())
                 } else {
                     error(location, format!("failed to resolve path {}", FormatTypePath(&prefab.path)))
+                        .with_errortype("undefined_type")
                         .register(self.context);
                     Analysis::empty()
                 }
             },
-            Term::InterpString(_, parts) => {
-                for (ref expr, _) in parts.iter() {
-                    if let Some(expr) = expr {
-                        self.visit_expression(location, expr, None, local_vars);
-                    }
-                }
+            Term::InterpString(first, parts) => {
+                let analyses: Vec<Option<Analysis<'o>>> = parts.iter()
+                    .map(|(expr, _)| expr.as_ref().map(|expr| self.visit_expression(location, expr, None, local_vars)))
+                    .collect();
+                for link in hrefs::parse_links(first, parts) {
+                    let target = match (link.target_is_part, link.target) {
+                        (Some(i), _) => analyses[i].as_ref().and_then(|analysis| analysis.static_ty.basic_type()),
+                        (None, Some(target)) => self.href_target(target, local_vars),
+                        (None, None) => None,
+                    };
+                    self.results.href_links.push(HrefLink {
+                        location,
+                        target: target.filter(|ty| !ty.is_root()),
+                        keys: link.keys,
+                        dynamic: link.dynamic,
+                    });
+                }
+                let parts: Vec<_> = analyses.into_iter()
+                    .zip(parts.iter().map(|(_, text)| text.as_str()))
+                    .collect();
+                text_macros::check_text_macros(self.context, self.objtree, location, first, &parts);
                 assumption_set![Assumption::IsText(true)].into()
             },
 
+            Term::Call(unscoped_name, args) if unscoped_name == "nameof" && args.len() == 1 => {
+                if let Some(proc) = self.ty.get_proc(unscoped_name) {
+                    self.check_proc_version(location, proc);
+                }
+                self.visit_expression(location, &args[0], None, local_vars);
+                match args[0].nameof() {
+                    Some(name) => Analysis::from_value(self.objtree, Constant::String(name.to_owned()), type_hint),
+                    None => {
+                        error(location, "nameof() argument has no name")
+                            .with_errortype("malformed_builtin_call")
+                            .register(self.context);
+                        Analysis::empty()
+                    },
+                }
+            },
             Term::Call(unscoped_name, args) => {
                 if self.inside_newcontext == 0 && matches!(unscoped_name.as_str(),
                     "sleep"
@@ -1730,7 +2322,7 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
                     | "shell"
                     | "winexists"
                     | "winget") {
-                        self.env.sleeping_procs.insert_violator(self.proc_ref, unscoped_name, location);
+                        self.results.sleeping_procs.insert_violator(self.proc_ref, unscoped_name, location);
                 }
                 self.check_type_sleepers(self.ty, location, unscoped_name);
                 let src = self.ty;
@@ -1748,6 +2340,18 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
                     Analysis::empty()
                 } else {
                     error(location, format!("undefined proc: {:?} on {}", unscoped_name, self.ty))
+                        .with_errortype("undefined_proc")
+                        .register(self.context);
+                    Analysis::empty()
+                }
+            },
+            Term::GlobalCall(name, args) => {
+                let root = self.objtree.root();
+                if let Some(proc) = root.get_proc(name) {
+                    self.visit_call(location, root, proc, args, false, local_vars)
+                } else {
+                    error(location, format!("undefined global proc: {:?}", name))
+                        .with_errortype("undefined_proc")
                         .register(self.context);
                     Analysis::empty()
                 }
@@ -1787,9 +2391,11 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
                     NewType::Prefab(prefab) => {
                         if let Some(nav) = self.ty.navigate_path(&prefab.path) {
                             // TODO: handle proc/verb paths here
+                            self.check_type_version(location, nav.ty());
                             Some(nav.ty())
                         } else {
                             error(location, format!("failed to resolve path {}", FormatTypePath(&prefab.path)))
+                                .with_errortype("undefined_type")
                                 .register(self.context);
                             None
                         }
@@ -1811,6 +2417,7 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
                             local_vars);
                     } else if typepath.path != "/list" {
                         error(location, format!("couldn't find {}/proc/New", typepath.path))
+                            .with_errortype("undefined_proc")
                             .register(self.context);
                     }
                     assumption_set![Assumption::IsType(true, typepath)].into()
@@ -1824,7 +2431,7 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
             },
             Term::Input { args, input_type, in_list } => {
                 if self.inside_newcontext == 0 {
-                    self.env.sleeping_procs.insert_violator(self.proc_ref, "input", location);
+                    self.results.sleeping_procs.insert_violator(self.proc_ref, "input", location);
                 }
                 // TODO: deal with in_list
                 self.visit_arguments(location, args, local_vars);
@@ -1865,6 +2472,12 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
 
                 if args.len() == 3 {  // X,Y,Z - it's gotta be a turf
                     assumption_set![Assumption::IsType(true, self.objtree.expect("/turf"))].into()
+                } else if let Some(text) = args.first().and_then(|arg| fold_string(arg, location)).filter(|text| text.starts_with('/')) {
+                    // Other strings are tags or refs, but these are typepaths.
+                    match self.check_string_path(location, &text, "locate") {
+                        Some(ty) => assumption_set![Assumption::IsType(true, ty)].into(),
+                        None => Analysis::empty(),
+                    }
                 } else {
                     Analysis::empty()
                 }
@@ -1893,12 +2506,12 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
             "/client" => if self.inside_newcontext == 0 && matches!(unscoped_name.as_str(),
                 "SoundQuery"
                 | "MeasureText") {
-                    self.env.sleeping_procs.insert_violator(self.proc_ref, format!("client.{}", unscoped_name).as_str(), location);
+                    self.results.sleeping_procs.insert_violator(self.proc_ref, format!("client.{}", unscoped_name).as_str(), location);
             },
             "/world" => if self.inside_newcontext == 0 && matches!(unscoped_name.as_str(),
                 "Import"
                 | "Export") {
-                    self.env.sleeping_procs.insert_violator(self.proc_ref, format!("world.{}", unscoped_name).as_str(), location);
+                    self.results.sleeping_procs.insert_violator(self.proc_ref, format!("world.{}", unscoped_name).as_str(), location);
             },
             _ => {},
         }
@@ -1942,9 +2555,84 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
                     _ => lhs.clone()  // carry through fix_hint
                 }
             },
+            Follow::StaticField(name) => {
+                self.last_static_field = None;
+                if let Some(ty) = lhs.scope_type() {
+                    if let Some(decl) = ty.get_var_declaration(name) {
+                        if let Some(since) = var_since(ty, name) {
+                            self.context.check_target_version(location, since, name);
+                        }
+                        self.last_static_field = Some((ty, name));
+                        self.static_type(location, &decl.var_type.type_path)
+                            .with_fix_hint(decl.location, "add additional type info here")
+                    } else {
+                        error(location, format!("undefined var: {:?} on {}", name, ty))
+                            .with_errortype("undefined_var")
+                            .register(self.context);
+                        Analysis::empty()
+                    }
+                } else {
+                    error(location, format!("scope operator requires a type path or static type: {:?}", name))
+                        .set_severity(Severity::Warning)
+                        .with_errortype("field_access_static_type")
+                        .with_fix_hint(&lhs)
+                        .register(self.context);
+                    Analysis::empty()
+                }
+            },
+            Follow::ProcReference(kind, name) => {
+                if let Some(ty) = lhs.scope_type() {
+                    if let Some(proc) = ty.get_proc(name) {
+                        if *kind == ProcDeclKind::Verb && !verbs::is_declared_verb(proc) {
+                            error(location, format!("{} is not declared as a verb", proc))
+                                .set_severity(Severity::Warning)
+                                .with_errortype("undefined_proc")
+                                .register(self.context);
+                        }
+                        Analysis::from_type_path(ty)
+                    } else {
+                        error(location, format!("undefined proc: {:?} on {}", name, ty))
+                            .with_errortype("undefined_proc")
+                            .register(self.context);
+                        Analysis::empty()
+                    }
+                } else {
+                    error(location, format!("scope operator requires a type path or static type: {:?}", name))
+                        .set_severity(Severity::Warning)
+                        .with_errortype("proc_call_static_type")
+                        .with_fix_hint(&lhs)
+                        .register(self.context);
+                    Analysis::empty()
+                }
+            },
+            Follow::Call(PropertyAccessKind::Scope, name, arguments) => {
+                if let Some(ty) = lhs.scope_type() {
+                    if let Some(proc) = ty.get_proc(name) {
+                        // The proc as defined on that type is called with the
+                        // current src, and never an override.
+                        let src = self.ty;
+                        self.visit_call(location, src, proc, arguments, true, local_vars)
+                    } else {
+                        error(location, format!("undefined proc: {:?} on {}", name, ty))
+                            .with_errortype("undefined_proc")
+                            .register(self.context);
+                        Analysis::empty()
+                    }
+                } else {
+                    error(location, format!("scope operator requires a type path or static type: {:?}", name))
+                        .set_severity(Severity::Warning)
+                        .with_errortype("proc_call_static_type")
+                        .with_fix_hint(&lhs)
+                        .register(self.context);
+                    Analysis::empty()
+                }
+            },
             Follow::Field(kind, name) => {
                 if let Some(ty) = lhs.static_ty.basic_type() {
                     if let Some(decl) = ty.get_var_declaration(name) {
+                        if let Some(since) = var_since(ty, name) {
+                            self.context.check_target_version(location, since, name);
+                        }
                         if ty != self.ty && decl.var_type.flags.is_private() {
                             error(location, format!("field {:?} on {} is declared as private", name, ty))
                                 .with_errortype("private_var")
@@ -1962,6 +2650,7 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
                             .with_fix_hint(decl.location, "add additional type info here")
                     } else {
                         error(location, format!("undefined field: {:?} on {}", name, ty))
+                            .with_errortype("undefined_var")
                             .register(self.context);
                         Analysis::empty()
                     }
@@ -1978,7 +2667,7 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
                 if let Some(ty) = lhs.static_ty.basic_type() {
                     self.check_type_sleepers(ty, location, name);
                     if let Some(proc) = ty.get_proc(name) {
-                        if let Some((privateproc, true, decllocation)) = self.env.private.get_self_or_parent(proc) {
+                        if let Some((privateproc, true, decllocation)) = self.settings.private.get_self_or_parent(proc) {
                             if ty != privateproc.ty() {
                                 error(location, format!("{} attempting to call private proc {}, types do not match", self.proc_ref, privateproc))
                                     .with_errortype("private_proc")
@@ -1987,7 +2676,7 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
                                 return Analysis::empty() // dont double up with visit_call()
                             }
                         }
-                        if let Some((protectedproc, true, decllocation)) = self.env.protected.get_self_or_parent(proc) {
+                        if let Some((protectedproc, true, decllocation)) = self.settings.protected.get_self_or_parent(proc) {
                             if !self.ty.is_subtype_of(protectedproc.ty().get()) {
                                 error(location, format!("{} attempting to call protected proc {}", self.proc_ref, protectedproc))
                                     .with_errortype("protected_proc")
@@ -1998,6 +2687,7 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
                         self.visit_call(location, ty, proc, arguments, false, local_vars)
                     } else {
                         error(location, format!("undefined proc: {:?} on {}", name, ty))
+                            .with_errortype("undefined_proc")
                             .register(self.context);
                         Analysis::empty()
                     }
@@ -2017,7 +2707,7 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
     fn check_operator_overload(&mut self, rhs: Analysis<'o>, location: Location, operator: &str, local_vars: &mut HashMap<String, LocalVar<'o>>) -> Analysis<'o> {
         if let Some(impurity) = rhs.is_impure {
             if impurity {
-                self.env.impure_procs.insert_violator(self.proc_ref, &format!("{} done on non-local var", operator), location);
+                self.results.impure_procs.insert_violator(self.proc_ref, &format!("{} done on non-local var", operator), location);
             }
         }
         let typeerror;
@@ -2037,6 +2727,7 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
             },
         };
         error(location, format!("Attempting {} on a {} which does not overload {}", operator, typeerror, operator))
+            .with_errortype("no_operator_overload")
             .register(self.context);
         return Analysis::empty()
     }
@@ -2061,14 +2752,26 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
 
     // checks for bitwise operations on a negated LHS
     fn check_negated_bitwise(&mut self, lhs: &dm::ast::Expression, location: Location, bit_op: BinaryOp, bool_op: BinaryOp) {
-        if matches!(lhs, Expression::Base { unary, .. } if unary.contains(&UnaryOp::Not)) {
-            error(location, format!("Ambiguous `!` on left side of bitwise `{}` operator", bit_op))
+        if let Expression::Base { unary, term, follow } = lhs {
+            if !unary.iter().any(|op| op.elem == UnaryOp::Not) {
+                return;
+            }
+            let mut error = error(location, format!("Ambiguous `!` on left side of bitwise `{}` operator", bit_op))
                 .with_errortype("ambiguous_not_bitwise")
                 .set_severity(Severity::Warning)
                 .with_note(location, format!("Did you mean `!(x {} y)`?", bit_op))
                 .with_note(location, format!("Did you mean `!x {} y`?", bool_op))
-                .with_note(location, format!("Did you mean `~x {} y`?", bit_op))
-                .register(self.context);
+                .with_note(location, format!("Did you mean `~x {} y`?", bit_op));
+            // The fix keeps the current meaning, `(!x) & y`, but spells it out.
+            // The `!` may be separated from the term, so each is edited alone.
+            if let ([not], Term::Ident(name), true) = (&unary[..], &term.elem, follow.is_empty()) {
+                if not.elem == UnaryOp::Not {
+                    error = error
+                        .with_edit(not.location, "!", "(!")
+                        .with_edit(term.location, name, format!("{})", name));
+                }
+            }
+            error.register(self.context);
         }
     }
 
@@ -2093,6 +2796,7 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
             Expression::BinaryOp{ op: BinaryOp::BitOr, lhs, rhs } => {
                 if exclusive {
                     error(location, format!("filter(type=\"{}\") '{}' parameter must have one value, found bitwise OR", typevalue, flagfieldname))
+                        .with_errortype("invalid_filter")
                         .with_filter_args(location, typevalue)
                         .register(self.context);
                     return
@@ -2104,6 +2808,7 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
             Expression::Base{ unary, term, follow: _ } => {
                 if unary.len() > 0 {
                     error(location, "filter() flag fields cannot have unary ops")
+                        .with_errortype("invalid_filter")
                         .register(self.context);
                     return
                 }
@@ -2111,6 +2816,7 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
                     Term::Ident(flagname) => {
                         if valid_flags.iter().position(|&x| x == flagname).is_none() {
                             error(location, format!("filter(type=\"{}\") called with invalid '{}' flag '{}'", typevalue, flagfieldname, flagname))
+                                .with_errortype("invalid_filter")
                                 .with_filter_args(location, typevalue)
                                 .register(self.context);
                         }
@@ -2118,6 +2824,7 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
                     Term::Int(0) if can_be_zero => {},
                     other => {
                         error(location, format!("filter(type=\"{}\") called with invalid '{}' value '{:?}'", typevalue, flagfieldname, other))
+                            .with_errortype("invalid_filter")
                             .with_filter_args(location, typevalue)
                             .register(self.context);
                     },
@@ -2125,15 +2832,111 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
             },
             _ => {
                 error(location, format!("filter(type=\"{}\"), extremely invalid value passed to '{}' field", typevalue, flagfieldname))
+                    .with_errortype("invalid_filter")
                     .with_filter_args(location, typevalue)
                     .register(self.context);
             }
         }
     }
 
+    /// Check a typepath given as a string, as to `text2path()`, returning the
+    /// type if it resolves to one.
+    fn check_string_path(&mut self, location: Location, text: &str, call: &str) -> Option<TypeRef<'o>> {
+        let segments: Vec<&str> = text.split('/').filter(|s| !s.is_empty()).collect();
+        // Paths like "/mob/proc/Login" name a proc rather than a type.
+        let (type_segments, proc_name) = match segments.iter().position(|&s| s == "proc" || s == "verb") {
+            Some(idx) if idx + 2 == segments.len() => (&segments[..idx], Some(segments[idx + 1])),
+            _ => (&segments[..], None),
+        };
+
+        let resolved = if text.starts_with('/') {
+            self.objtree.type_by_path(type_segments.iter())
+        } else {
+            None
+        };
+        match (resolved, proc_name) {
+            (Some(ty), None) => return Some(ty),
+            (Some(ty), Some(name)) if ty.get_proc(name).is_some() => return None,
+            _ => {},
+        }
+        error(location, format!("{}() of unresolvable path {:?}", call, text))
+            .with_errortype("unresolvable_string_path")
+            .set_severity(Severity::Warning)
+            .register(self.context);
+        None
+    }
+
     fn visit_call(&mut self, location: Location, src: TypeRef<'o>, proc: ProcRef<'o>, args: &'o [Expression], is_exact: bool, local_vars: &mut HashMap<String, LocalVar<'o>>) -> Analysis<'o> {
-        self.env.call_tree.entry(self.proc_ref).or_default().push((proc, location, self.inside_newcontext != 0));
-        if let Some((privateproc, true, decllocation)) = self.env.private.get_self_or_parent(proc) {
+        let analysis = self.visit_call_inner(location, src, proc, args, is_exact, local_vars);
+        // Set after the arguments are visited, so that it is the outermost
+        // call of an expression statement once that has been visited.
+        self.last_call = Some(proc);
+        analysis
+    }
+
+    /// Report a call to a builtin proc newer than the `target_version`.
+    fn check_proc_version(&self, location: Location, proc: ProcRef<'o>) {
+        if let Some(since) = proc_since(proc) {
+            self.context.check_target_version(location, since, format_args!("{}()", proc.name()));
+        }
+    }
+
+    /// Report a use of a builtin type newer than the `target_version`.
+    fn check_type_version(&self, location: Location, ty: TypeRef<'o>) {
+        if let Some(since) = type_since(ty) {
+            self.context.check_target_version(location, since, &ty.path);
+        }
+    }
+
+    /// Report an assignment to a non-static var through the `::` operator,
+    /// which only reads its initial value.
+    fn check_static_field_assign(&self, location: Location, follow: &'o [Spanned<Follow>]) {
+        let name = match follow.last().map(|each| &each.elem) {
+            Some(Follow::StaticField(name)) => name,
+            _ => return,
+        };
+        guard!(let Some((ty, _)) = self.last_static_field.filter(|&(_, field)| field == name) else { return });
+        guard!(let Some(decl) = ty.get_var_declaration(name) else { return });
+        if !decl.var_type.flags.is_static() {
+            error(location, format!("cannot assign to {}::{}, which is not static", ty, name))
+                .with_errortype("initial_value_assign")
+                .with_note(decl.location, "declared here")
+                .register(self.context);
+        }
+    }
+
+    /// Report a discarded result of a call to a `SpacemanDMM_must_use_result`
+    /// proc, if the expression statement is such a call.
+    fn check_discarded_result(&mut self, location: Location, expr: &'o Expression) {
+        let name = match expr {
+            Expression::Base { unary, term, follow } if unary.is_empty() => match follow.last() {
+                Some(each) => match &each.elem {
+                    Follow::Call(_, name, _) => name,
+                    _ => return,
+                },
+                None => match &term.elem {
+                    Term::Call(name, _) => name,
+                    _ => return,
+                },
+            },
+            _ => return,
+        };
+        guard!(let Some(proc) = self.last_call.filter(|proc| proc.name() == name) else { return });
+        if let Some((_, true, loc)) = self.settings.must_use_result.get_self_or_parent(proc) {
+            let mut error = error(location, format!("return value of {} is discarded", proc))
+                .with_errortype("must_use_result")
+                .set_severity(Severity::Warning);
+            if !loc.is_builtins() {
+                error.add_note(loc, "required by this must_use_result annotation");
+            }
+            error.register(self.context);
+        }
+    }
+
+    fn visit_call_inner(&mut self, location: Location, src: TypeRef<'o>, proc: ProcRef<'o>, args: &'o [Expression], is_exact: bool, local_vars: &mut HashMap<String, LocalVar<'o>>) -> Analysis<'o> {
+        self.check_proc_version(location, proc);
+        self.results.call_tree.entry(self.proc_ref).or_default().push((proc, location, self.inside_newcontext != 0));
+        if let Some((privateproc, true, decllocation)) = self.settings.private.get_self_or_parent(proc) {
             if self.ty != privateproc.ty() {
                 error(location, format!("{} attempting to call private proc {}, types do not match", self.proc_ref, privateproc))
                     .with_errortype("private_proc")
@@ -2168,7 +2971,7 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
                             if !proc.parameters.iter().any(|p| p.name == *name) {
                                 // Search for a child proc that does have this keyword argument.
                                 let mut error = error(location,
-                                    format!("bad keyword argument {:?} to {}", name, proc));
+                                    format!("bad keyword argument {:?} to {}", name, proc)).with_errortype("bad_keyword_argument");
                                 proc.recurse_children(&mut |child_proc| {
                                     if child_proc.ty() == proc.ty() { return }
                                     if child_proc.parameters.iter().any(|p| p.name == *name) {
@@ -2182,7 +2985,7 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
                                 // type the proc actually appears on, so that
                                 // calling /datum/foo() on a /datum/A won't
                                 // complain about /datum/B/foo().
-                                self.env.used_kwargs.entry(format!("{}/proc/{}", src, proc.name()))
+                                self.results.used_kwargs.entry(format!("{}/proc/{}", src, proc.name()))
                                     .or_insert_with(|| KwargInfo {
                                         location: proc.location,
                                         .. Default::default()
@@ -2213,6 +3016,7 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
             if any_kwargs_yet && this_kwarg.is_none() && !(proc.ty().is_root() && proc.name() == "animate") {
                 // TODO: don't hardcode the animate() exception
                 error(location, format!("proc called with non-kwargs after kwargs: {}()", proc.name()))
+                    .with_errortype("bad_keyword_argument")
                     .register(self.context);
             }
 
@@ -2226,6 +3030,14 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
             }
         }
 
+        if !arglist_used {
+            self.settings.custom_lints.check_call(self.context, self.proc_ref, location, proc, |i| {
+                param_idx_map.get(&i).or_else(|| {
+                    param_name_map.get(proc.parameters.get(i)?.name.as_str())
+                })
+            });
+        }
+
         // filter call checking
         // TODO: some filters have limits for their numerical params
         //  eg "rays" type "threshold" param defaults to 0.5, can be 0 to 1
@@ -2233,23 +3045,27 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
             guard!(let Some(typename) = param_name_map.get("type") else {
                 if !arglist_used {
                     error(location, "filter() called without mandatory keyword parameter 'type'")
+                        .with_errortype("invalid_filter")
                         .register(self.context);
                 } // regardless, we're done here
                 return Analysis::empty()
             });
             guard!(let Some(Constant::String(typevalue)) = &typename.value else {
                 error(location, format!("filter() called with non-string type keyword parameter value '{:?}'", typename.value))
+                    .with_errortype("invalid_filter")
                     .register(self.context);
                 return Analysis::empty()
             });
             guard!(let Some(arglist) = VALID_FILTER_TYPES.get(typevalue.as_str()) else {
                 error(location, format!("filter() called with invalid type keyword parameter value '{}'", typevalue))
+                    .with_errortype("invalid_filter")
                     .register(self.context);
                 return Analysis::empty()
             });
             for arg in param_name_map.keys() {
                 if *arg != "type" && arglist.iter().position(|&x| x == *arg).is_none() {
                     error(location, format!("filter(type=\"{}\") called with invalid keyword parameter '{}'", typevalue, arg))
+                        .with_errortype("invalid_filter")
                         .with_filter_args(location, typevalue)
                         .register(self.context);
                 }
@@ -2261,9 +3077,33 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
             }
         }
 
+        if proc.ty().is_root() && proc.is_builtin() && matches!(proc.name(), "file" | "icon" | "sound") {
+            if let Some(text) = args.first().and_then(|arg| fold_string(arg, location)) {
+                self.results.resources.push((location, text));
+            }
+        }
+
+        if proc.ty().is_root() && proc.is_builtin() {
+            if let Some(&(_, first)) = STRING_PATH_ARGS.iter().find(|&&(name, _)| name == proc.name()) {
+                for arg in args.iter().skip(first) {
+                    if let Some(text) = fold_string(arg, location).filter(|text| text.starts_with('/')) {
+                        self.check_string_path(location, &text, proc.name());
+                    }
+                }
+            }
+        }
+
+        if proc.ty().is_root() && proc.is_builtin() && proc.name() == "text2path" {
+            if let Some(text) = args.first().and_then(|arg| fold_string(arg, location)) {
+                if let Some(ty) = self.check_string_path(location, &text, "text2path") {
+                    return Analysis::from_type_path(ty);
+                }
+            }
+        }
+
         if proc.ty().is_root() && proc.is_builtin() {
             Analysis::from(self.global_builtin_returntype(proc))
-        } else if let Some(return_type) = self.env.return_type.get(&proc) {
+        } else if let Some(return_type) = self.settings.return_type.get(&proc) {
             let ec = type_expr::TypeExprContext {
                 objtree: self.objtree,
                 param_name_map,
@@ -2276,6 +3116,7 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
                 },
                 Err(err) => {
                     err.with_component(dm::Component::DreamChecker)
+                        .with_errortype("invalid_return_type")
                         .register(self.context);
                     Analysis::empty()
                 }
@@ -2304,8 +3145,34 @@ impl<'o, 's> AnalyzeProc<'o, 's> {
         }
     }
 
+    /// Report procs added to a `verbs` list which are not verbs.
+    fn check_added_verbs(&mut self, location: Location, added: &'o [Expression]) {
+        for prefab in added.iter().flat_map(verbs::added_procs) {
+            if let Some(NavigatePathResult::ProcPath(proc, _)) = self.ty.navigate_path(&prefab.path) {
+                verbs::check_added_proc(self.context, location, proc, &self.settings.verb_like);
+            }
+        }
+    }
+
+    /// The name of the `href_list` parameter, if this proc is a `Topic()`.
+    fn href_list_param(&self) -> Option<&'o str> {
+        if self.proc_ref.name() != "Topic" {
+            return None;
+        }
+        self.proc_ref.get().parameters.get(1).map(|param| param.name.as_str())
+    }
+
+    /// The type of the `src` a link is sent to, from the expression inside
+    /// its `REF()` or `\ref`.
+    fn href_target(&self, target: &Expression, local_vars: &HashMap<String, LocalVar<'o>>) -> Option<TypeRef<'o>> {
+        match target.as_term() {
+            Some(Term::Ident(name)) => local_vars.get(name)?.analysis.static_ty.basic_type(),
+            _ => None,
+        }
+    }
+
     fn static_type(&mut self, location: Location, of: &[String]) -> Analysis<'o> {
-        Analysis::from(self.env.static_type(location, of))
+        Analysis::from(static_type_or_error(self.objtree, self.context, location, of))
     }
 
     fn global_builtin_returntype(&mut self, proc: ProcRef) -> StaticType<'o> {
@@ -15,6 +15,12 @@ fn main() {
     let mut config_file = None;
     let mut json = false;
     let mut parse_only = false;
+    let mut metrics_file = None;
+    let mut jobs = 0;
+    let mut use_cache = true;
+    let mut preprocess_cache = false;
+    let mut fix = false;
+    let mut fix_dry_run = false;
 
     let mut args = std::env::args();
     let _ = args.next();  // skip executable name
@@ -29,6 +35,19 @@ fn main() {
             println!("and you are welcome to redistribute it under the conditions of the GNU");
             println!("General Public License version 3.");
             return;
+        } else if arg == "--explain" {
+            let key = args.next().expect("must specify a code for --explain");
+            match dm::diagnostics::lookup(&key) {
+                Some(diagnostic) => {
+                    println!("{} ({}): {}\n", diagnostic.code, diagnostic.errortype, diagnostic.summary);
+                    println!("{}", diagnostic.explanation);
+                    return;
+                }
+                None => {
+                    eprintln!("unknown diagnostic code: {}", key);
+                    std::process::exit(1);
+                }
+            }
         } else if arg == "-e" {
             environment = Some(args.next().expect("must specify a value for -e"));
         } else if arg == "-c" {
@@ -37,12 +56,30 @@ fn main() {
             json = true;
         } else if arg == "--parse-only" {
             parse_only = true;
+        } else if arg == "--jobs" || arg == "-j" {
+            jobs = args.next().expect("must specify a number for --jobs")
+                .parse().expect("--jobs must be a number");
+        } else if arg == "--no-cache" {
+            use_cache = false;
+        } else if arg == "--preprocess-cache" {
+            preprocess_cache = true;
+        } else if arg == "--fix" {
+            fix = true;
+        } else if arg == "--fix-dry-run" {
+            fix_dry_run = true;
+        } else if arg == "--metrics" {
+            metrics_file = Some(std::path::PathBuf::from(args.next().expect("must specify a file for --metrics")));
         } else {
             eprintln!("unknown argument: {}", arg);
             return;
         }
     }
 
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(jobs)
+        .build_global()
+        .expect("failed to initialize thread pool");
+
     let dme = environment
         .map(std::path::PathBuf::from)
         .unwrap_or_else(|| dm::detect_environment_default()
@@ -59,15 +96,68 @@ fn main() {
 
     println!("============================================================");
     println!("Parsing {}...\n", dme.display());
-    let pp = dm::preprocessor::Preprocessor::new(&context, dme)
+    let mut pp = dm::preprocessor::Preprocessor::new(&context, dme.clone())
         .expect("i/o error opening .dme");
-    let indents = dm::indents::IndentProcessor::new(&context, pp);
-    let mut parser = dm::parser::Parser::new(&context, indents);
-    parser.enable_procs();
-    let (fatal_errored, tree) = parser.parse_object_tree_2();
+    let preprocess_cache_file = dm::preprocess_cache::PreprocessCache::path_for(&dme);
+    if preprocess_cache {
+        pp.use_cache(dm::preprocess_cache::PreprocessCache::read(&preprocess_cache_file));
+    }
+    let (fatal_errored, tree) = {
+        let parse_cache = pp.parse_cache();
+        let indents = dm::indents::IndentProcessor::new(&context, &mut pp);
+        let mut parser = dm::parser::Parser::new(&context, indents);
+        parser.enable_procs();
+        parser.use_cache(parse_cache);
+        parser.parse_object_tree_2()
+    };
+    if let Some(cache) = pp.take_cache().filter(|cache| cache.is_changed()) {
+        if let Err(e) = cache.write(&preprocess_cache_file) {
+            eprintln!("i/o error writing {}: {}", preprocess_cache_file.display(), e);
+        }
+    }
 
     if !parse_only && !fatal_errored {
-        dreamchecker::run_cli(&context, &tree);
+        let cache_file = dme.with_file_name(".dreamchecker-cache");
+        let mut cache = if use_cache {
+            Some(dreamchecker::cache::AnalysisCache::read(&cache_file))
+        } else {
+            None
+        };
+        let metrics = dreamchecker::run_cli(&context, &tree, &dreamchecker::Environment::from_preprocessor(&pp), cache.as_mut());
+        if let Some(cache) = cache {
+            if let Err(e) = cache.write(&cache_file) {
+                eprintln!("i/o error writing {}: {}", cache_file.display(), e);
+            }
+        }
+
+        if let Some(path) = metrics_file {
+            println!("Writing metrics to {}", path.display());
+            let file = std::io::BufWriter::new(std::fs::File::create(&path).expect("i/o error creating metrics file"));
+            let result = if path.extension().map_or(false, |ext| ext == "csv") {
+                dreamchecker::metrics::write_csv(&context, &metrics, file)
+            } else {
+                dreamchecker::metrics::write_json(&context, &metrics, file)
+            };
+            result.expect("i/o error writing metrics file");
+        }
+    }
+
+    if fix || fix_dry_run {
+        let root = pp.env_file().parent().map(ToOwned::to_owned).unwrap_or_default();
+        let files = dreamchecker::fix::plan(&context, &root).expect("i/o error reading files to fix");
+        println!("============================================================");
+        for file in files.iter() {
+            if fix_dry_run {
+                print!("{}", dreamchecker::fix::unified_diff(&file.path, &file.original, &file.fixed));
+            } else {
+                println!("Fixed {} diagnostics in {}", file.fixes, file.path.display());
+                if let Err(e) = std::fs::write(root.join(&file.path), &file.fixed) {
+                    eprintln!("i/o error writing {}: {}", file.path.display(), e);
+                }
+            }
+        }
+        let fixes: usize = files.iter().map(|file| file.fixes).sum();
+        println!("{} {} diagnostics in {} files", if fix_dry_run { "Would fix" } else { "Fixed" }, fixes, files.len());
     }
 
     println!("============================================================");
@@ -80,6 +170,15 @@ fn main() {
             "info": context.errors().iter().filter(|each| each.severity() == dm::Severity::Info).count(),
             "warning": context.errors().iter().filter(|each| each.severity() == dm::Severity::Warning).count(),
             "error": context.errors().iter().filter(|each| each.severity() == dm::Severity::Error).count(),
+            "diagnostics": context.errors().iter().map(|each| json! {{
+                "file": context.file_path(each.location().file),
+                "line": each.location().line,
+                "column": each.location().column,
+                "severity": each.severity().to_string(),
+                "code": each.code(),
+                "errortype": each.errortype(),
+                "description": each.description(),
+            }}).collect::<Vec<_>>(),
         }}).unwrap();
     }
 
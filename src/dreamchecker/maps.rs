@@ -0,0 +1,137 @@
+//! Reading var edits out of the dictionaries of included `.dmm` files.
+
+use std::path::{Path, PathBuf};
+
+use dm::{Context, DMError, Location};
+use dm::constants::Constant;
+use dm::lexer::{LocationTracker, buffer_file, from_utf8_or_latin1};
+
+/// A var edited on an instance in a map's dictionary, as in
+/// `/obj/item{name = "thing"}`.
+#[derive(Debug, Clone)]
+pub struct MapVarEdit {
+    pub location: Location,
+    /// Distinguishes edits belonging to different instances.
+    pub instance: usize,
+    pub type_path: String,
+    pub name: String,
+    pub value: Constant,
+}
+
+/// Read every var edit from each of the given map files.
+///
+/// Map files which fail to read or parse register an error and are skipped.
+pub fn read_var_edits(context: &Context, maps: &[PathBuf]) -> Vec<MapVarEdit> {
+    let mut edits = Vec::new();
+    let mut instance = 0;
+    for path in maps {
+        match read_map(context, path, &mut edits, &mut instance) {
+            Ok(()) => {},
+            Err(e) => context.register_error(e),
+        }
+    }
+    edits
+}
+
+fn read_map(context: &Context, path: &Path, edits: &mut Vec<MapVarEdit>, instance: &mut usize) -> Result<(), DMError> {
+    let file = context.register_file(path);
+    let mut chars = LocationTracker::new(file, buffer_file(file, path)?.into());
+
+    let mut in_key_block = false;
+    let mut in_data_block = false;
+    let mut in_varedit_block = false;
+    let mut after_data_block = false;
+    let mut in_quote_block = false;
+    let mut escaping = false;
+
+    let mut curr_path = String::new();
+    let mut curr_datum: Vec<u8> = Vec::new();
+    let mut curr_var: Option<(Location, String)> = None;
+    let mut datum_start = Location::default();
+
+    while let Some(ch) = chars.next() {
+        if in_varedit_block {
+            if in_quote_block {
+                curr_datum.push(ch);
+                if escaping {
+                    escaping = false;
+                } else if ch == b'\\' {
+                    escaping = true;
+                } else if ch == b'"' {
+                    in_quote_block = false;
+                }
+            } else if ch == b'"' {
+                curr_datum.push(ch);
+                in_quote_block = true;
+            } else if ch == b'=' && curr_var.is_none() {
+                let name = from_utf8_or_latin1(std::mem::take(&mut curr_datum));
+                curr_var = Some((datum_start, name.trim().to_owned()));
+            } else if ch == b';' || ch == b'}' {
+                if let Some((location, name)) = curr_var.take() {
+                    let value = dm::constants::evaluate_str(location, trim(&curr_datum))?;
+                    edits.push(MapVarEdit {
+                        location,
+                        instance: *instance,
+                        type_path: curr_path.clone(),
+                        name,
+                        value,
+                    });
+                }
+                curr_datum.clear();
+                if ch == b'}' {
+                    in_varedit_block = false;
+                }
+            } else {
+                if curr_var.is_none() && curr_datum.iter().all(u8::is_ascii_whitespace) && !ch.is_ascii_whitespace() {
+                    datum_start = chars.location();
+                }
+                curr_datum.push(ch);
+            }
+        } else if in_data_block {
+            if ch == b'{' {
+                curr_path = from_utf8_or_latin1(std::mem::take(&mut curr_datum)).trim().to_owned();
+                curr_datum.clear();
+                in_varedit_block = true;
+                *instance += 1;
+            } else if ch == b',' || ch == b')' {
+                curr_datum.clear();
+                if ch == b')' {
+                    in_data_block = false;
+                    after_data_block = true;
+                }
+            } else {
+                curr_datum.push(ch);
+            }
+        } else if in_key_block {
+            if ch == b'"' {
+                in_key_block = false;
+            }
+        } else if ch == b'"' {
+            in_key_block = true;
+            after_data_block = false;
+        } else if ch == b'(' {
+            if after_data_block {
+                // the grid follows the dictionary, and has no var edits
+                break;
+            }
+            in_data_block = true;
+        }
+    }
+    Ok(())
+}
+
+fn trim(mut bytes: &[u8]) -> &[u8] {
+    while let Some((first, rest)) = bytes.split_first() {
+        if !first.is_ascii_whitespace() {
+            break;
+        }
+        bytes = rest;
+    }
+    while let Some((last, rest)) = bytes.split_last() {
+        if !last.is_ascii_whitespace() {
+            break;
+        }
+        bytes = rest;
+    }
+    bytes
+}
@@ -0,0 +1,390 @@
+//! Per-proc code metrics: complexity, length, nesting, and call tree fan.
+
+use std::collections::{HashMap, HashSet};
+use std::io::{self, Write};
+
+use dm::{Context, DMError, Location, Severity};
+use dm::ast::*;
+use dm::objtree::ProcRef;
+
+/// Code metrics gathered for a single proc body.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProcMetrics {
+    /// The display path of the proc, as in `/mob/proc/Login`.
+    pub proc_path: String,
+    /// The location of the proc definition.
+    pub location: Location,
+    /// McCabe cyclomatic complexity: one plus the number of decision points.
+    pub complexity: u32,
+    /// The total number of statements, including nested statements.
+    pub statements: u32,
+    /// The deepest level of block nesting, where the body itself is level 0.
+    pub max_nesting: u32,
+    /// The number of local variable declarations.
+    pub locals: u32,
+    /// The number of distinct procs which call this one.
+    pub fan_in: u32,
+    /// The number of distinct procs which this one calls.
+    pub fan_out: u32,
+}
+
+impl ProcMetrics {
+    /// Measure the structural metrics of a proc body.
+    ///
+    /// The call tree fields are left at zero, to be filled in by
+    /// `fill_call_tree` once every proc has been analyzed.
+    pub fn measure(proc: ProcRef, code: &[Spanned<Statement>]) -> ProcMetrics {
+        let mut walker = MetricsWalker::default();
+        walker.visit_block(code, 0);
+        ProcMetrics {
+            proc_path: proc.to_string(),
+            location: proc.location,
+            complexity: 1 + walker.decisions,
+            statements: walker.statements,
+            max_nesting: walker.max_nesting,
+            locals: walker.locals,
+            fan_in: 0,
+            fan_out: 0,
+        }
+    }
+}
+
+/// Fill in `fan_in` and `fan_out` from the analyzed call tree.
+pub(crate) fn fill_call_tree<'o>(
+    metrics: &mut [(ProcRef<'o>, ProcMetrics)],
+    call_tree: &HashMap<ProcRef<'o>, Vec<(ProcRef<'o>, Location, bool)>>,
+) {
+    let mut callers: HashMap<ProcRef<'o>, HashSet<ProcRef<'o>>> = HashMap::new();
+    for (caller, calls) in call_tree.iter() {
+        for &(callee, _, _) in calls.iter() {
+            callers.entry(callee).or_default().insert(*caller);
+        }
+    }
+
+    for (proc, metrics) in metrics.iter_mut() {
+        metrics.fan_out = call_tree.get(proc).map_or(0, |calls| {
+            calls.iter().map(|&(callee, _, _)| callee).collect::<HashSet<_>>().len() as u32
+        });
+        metrics.fan_in = callers.get(proc).map_or(0, |set| set.len() as u32);
+    }
+}
+
+/// Raise diagnostics for procs exceeding the `[code_standards]` thresholds.
+pub(crate) fn check_thresholds(context: &Context, metrics: &[(ProcRef, ProcMetrics)]) {
+    let (max_complexity, max_statements, max_nesting, max_locals) = {
+        let config = context.config();
+        let standards = &config.code_standards;
+        (
+            standards.max_proc_complexity,
+            standards.max_proc_statements,
+            standards.max_proc_nesting,
+            standards.max_proc_locals,
+        )
+    };
+
+    for (_, each) in metrics.iter() {
+        let check = |value: u32, max: Option<u32>, what: &str, errortype: &'static str| {
+            if let Some(max) = max {
+                if value > max {
+                    DMError::new(each.location, format!("{} has {} {}, more than the maximum of {}", each.proc_path, what, value, max))
+                        .with_component(dm::Component::DreamChecker)
+                        .with_errortype(errortype)
+                        .set_severity(Severity::Warning)
+                        .register(context);
+                }
+            }
+        };
+        check(each.complexity, max_complexity, "cyclomatic complexity", "proc_complexity");
+        check(each.statements, max_statements, "statement count", "proc_statements");
+        check(each.max_nesting, max_nesting, "nesting depth", "proc_nesting");
+        check(each.locals, max_locals, "local var count", "proc_locals");
+    }
+}
+
+// ----------------------------------------------------------------------------
+// Output formats
+
+/// Write the metrics as a JSON array of objects.
+pub fn write_json<W: Write>(context: &Context, metrics: &[ProcMetrics], w: W) -> io::Result<()> {
+    let array: Vec<_> = metrics.iter().map(|each| serde_json::json!({
+        "proc": each.proc_path,
+        "file": context.file_path(each.location.file),
+        "line": each.location.line,
+        "column": each.location.column,
+        "complexity": each.complexity,
+        "statements": each.statements,
+        "max_nesting": each.max_nesting,
+        "locals": each.locals,
+        "fan_in": each.fan_in,
+        "fan_out": each.fan_out,
+    })).collect();
+    serde_json::to_writer_pretty(w, &array)?;
+    Ok(())
+}
+
+/// Write the metrics as CSV with a header row.
+pub fn write_csv<W: Write>(context: &Context, metrics: &[ProcMetrics], mut w: W) -> io::Result<()> {
+    writeln!(w, "proc,file,line,column,complexity,statements,max_nesting,locals,fan_in,fan_out")?;
+    for each in metrics.iter() {
+        writeln!(
+            w,
+            "{},{},{},{},{},{},{},{},{},{}",
+            csv_escape(&each.proc_path),
+            csv_escape(&context.file_path(each.location.file).display().to_string()),
+            each.location.line,
+            each.location.column,
+            each.complexity,
+            each.statements,
+            each.max_nesting,
+            each.locals,
+            each.fan_in,
+            each.fan_out,
+        )?;
+    }
+    Ok(())
+}
+
+fn csv_escape(text: &str) -> std::borrow::Cow<str> {
+    if text.contains(|c| c == ',' || c == '"' || c == '\n') {
+        format!("\"{}\"", text.replace('"', "\"\"")).into()
+    } else {
+        text.into()
+    }
+}
+
+// ----------------------------------------------------------------------------
+// AST walker
+
+#[derive(Default)]
+struct MetricsWalker {
+    decisions: u32,
+    statements: u32,
+    max_nesting: u32,
+    locals: u32,
+}
+
+impl MetricsWalker {
+    fn visit_block(&mut self, block: &[Spanned<Statement>], depth: u32) {
+        self.max_nesting = self.max_nesting.max(depth);
+        for stmt in block.iter() {
+            self.statements += 1;
+            self.visit_statement(&stmt.elem, depth);
+        }
+    }
+
+    fn visit_statement(&mut self, statement: &Statement, depth: u32) {
+        match statement {
+            Statement::Expr(expr) |
+            Statement::Throw(expr) |
+            Statement::Del(expr) => self.visit_expression(expr),
+            Statement::Return(expr) |
+            Statement::Crash(expr) => {
+                if let Some(expr) = expr {
+                    self.visit_expression(expr);
+                }
+            },
+            Statement::While { condition, block } => {
+                self.decisions += 1;
+                self.visit_expression(condition);
+                self.visit_block(block, depth + 1);
+            },
+            Statement::DoWhile { block, condition } => {
+                self.decisions += 1;
+                self.visit_block(block, depth + 1);
+                self.visit_expression(&condition.elem);
+            },
+            Statement::If { arms, else_arm } => {
+                for (condition, block) in arms.iter() {
+                    self.decisions += 1;
+                    self.visit_expression(&condition.elem);
+                    self.visit_block(block, depth + 1);
+                }
+                if let Some(else_arm) = else_arm {
+                    self.visit_block(else_arm, depth + 1);
+                }
+            },
+            Statement::ForInfinite { block } => {
+                self.decisions += 1;
+                self.visit_block(block, depth + 1);
+            },
+            Statement::ForLoop { init, test, inc, block } => {
+                self.decisions += 1;
+                if let Some(init) = init {
+                    self.visit_statement(init, depth);
+                }
+                if let Some(test) = test {
+                    self.visit_expression(test);
+                }
+                if let Some(inc) = inc {
+                    self.visit_statement(inc, depth);
+                }
+                self.visit_block(block, depth + 1);
+            },
+            Statement::ForList { var_type, in_list, block, .. } => {
+                self.decisions += 1;
+                if var_type.is_some() {
+                    self.locals += 1;
+                }
+                if let Some(in_list) = in_list {
+                    self.visit_expression(in_list);
+                }
+                self.visit_block(block, depth + 1);
+            },
+            Statement::ForRange { var_type, start, end, step, block, .. } => {
+                self.decisions += 1;
+                if var_type.is_some() {
+                    self.locals += 1;
+                }
+                self.visit_expression(start);
+                self.visit_expression(end);
+                if let Some(step) = step {
+                    self.visit_expression(step);
+                }
+                self.visit_block(block, depth + 1);
+            },
+            Statement::Var(var) => self.visit_var(var),
+            Statement::Vars(vars) => {
+                for var in vars.iter() {
+                    self.visit_var(var);
+                }
+            },
+            Statement::Setting { .. } => {},
+            Statement::Spawn { delay, block } => {
+                if let Some(delay) = delay {
+                    self.visit_expression(delay);
+                }
+                self.visit_block(block, depth + 1);
+            },
+            Statement::Switch { input, cases, default } => {
+                self.visit_expression(input);
+                for (case, block) in cases.iter() {
+                    self.decisions += 1;
+                    for each in case.elem.iter() {
+                        match each {
+                            Case::Exact(expr) => self.visit_expression(expr),
+                            Case::Range(start, end) => {
+                                self.visit_expression(start);
+                                self.visit_expression(end);
+                            },
+                        }
+                    }
+                    self.visit_block(block, depth + 1);
+                }
+                if let Some(default) = default {
+                    self.visit_block(default, depth + 1);
+                }
+            },
+            Statement::TryCatch { try_block, catch_params, catch_block } => {
+                self.decisions += 1;
+                self.locals += catch_params.len() as u32;
+                self.visit_block(try_block, depth + 1);
+                self.visit_block(catch_block, depth + 1);
+            },
+            Statement::Continue(_) |
+            Statement::Break(_) |
+            Statement::Goto(_) => {},
+            Statement::Label { block, .. } => self.visit_block(block, depth + 1),
+        }
+    }
+
+    fn visit_var(&mut self, var: &VarStatement) {
+        self.locals += 1;
+        if let Some(value) = &var.value {
+            self.visit_expression(value);
+        }
+    }
+
+    fn visit_expression(&mut self, expression: &Expression) {
+        match expression {
+            Expression::Base { term, follow, .. } => {
+                self.visit_term(&term.elem);
+                for each in follow.iter() {
+                    match &each.elem {
+                        Follow::Index(_, expr) => self.visit_expression(expr),
+                        Follow::Field(..) |
+                        Follow::StaticField(_) |
+                        Follow::ProcReference(..) => {},
+                        Follow::Call(_, _, args) => self.visit_expressions(args),
+                    }
+                }
+            },
+            Expression::BinaryOp { op, lhs, rhs } => {
+                if let BinaryOp::And | BinaryOp::Or = op {
+                    self.decisions += 1;
+                }
+                self.visit_expression(lhs);
+                self.visit_expression(rhs);
+            },
+            Expression::AssignOp { op, lhs, rhs } => {
+                if let AssignOp::AndAssign | AssignOp::OrAssign = op {
+                    self.decisions += 1;
+                }
+                self.visit_expression(lhs);
+                self.visit_expression(rhs);
+            },
+            Expression::TernaryOp { cond, if_, else_ } => {
+                self.decisions += 1;
+                self.visit_expression(cond);
+                self.visit_expression(if_);
+                self.visit_expression(else_);
+            },
+        }
+    }
+
+    fn visit_expressions(&mut self, expressions: &[Expression]) {
+        for each in expressions.iter() {
+            self.visit_expression(each);
+        }
+    }
+
+    fn visit_term(&mut self, term: &Term) {
+        match term {
+            Term::Expr(expr) => self.visit_expression(expr),
+            Term::InterpString(_, parts) => {
+                for (expr, _) in parts.iter() {
+                    if let Some(expr) = expr {
+                        self.visit_expression(expr);
+                    }
+                }
+            },
+            Term::Call(_, args) |
+            Term::GlobalCall(_, args) |
+            Term::SelfCall(args) |
+            Term::ParentCall(args) |
+            Term::List(args) => self.visit_expressions(args),
+            Term::New { args, .. } => {
+                if let Some(args) = args {
+                    self.visit_expressions(args);
+                }
+            },
+            Term::Input { args, in_list, .. } |
+            Term::Locate { args, in_list } => {
+                self.visit_expressions(args);
+                if let Some(in_list) = in_list {
+                    self.visit_expression(in_list);
+                }
+            },
+            Term::Pick(choices) => {
+                for (weight, choice) in choices.iter() {
+                    if let Some(weight) = weight {
+                        self.visit_expression(weight);
+                    }
+                    self.visit_expression(choice);
+                }
+            },
+            Term::DynamicCall(lhs_args, rhs_args) => {
+                self.visit_expressions(lhs_args);
+                self.visit_expressions(rhs_args);
+            },
+            Term::Null |
+            Term::Int(_) |
+            Term::Float(_) |
+            Term::Ident(_) |
+            Term::GlobalIdent(_) |
+            Term::String(_) |
+            Term::Resource(_) |
+            Term::As(_) |
+            Term::Prefab(_) => {},
+        }
+    }
+}
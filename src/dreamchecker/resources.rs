@@ -0,0 +1,147 @@
+//! Checking that resource files referenced by the code exist on disk.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use dm::{Context, DMError, Location, Severity};
+use dm::constants::{Constant, ConstFn};
+use dm::objtree::ObjectTree;
+
+/// The result of searching for a resource file.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Lookup {
+    Found(PathBuf),
+    /// Found, but only by ignoring case, which fails on case-sensitive
+    /// filesystems.
+    WrongCase(PathBuf),
+    Missing,
+}
+
+/// Looks up resource files following DM's search rules, caching the results.
+///
+/// Resources are searched for relative to the `.dme` directory, then the
+/// directory of the file containing the reference, then each `FILE_DIR`.
+pub(crate) struct ResourceSearch<'o> {
+    dirs: &'o [PathBuf],
+    cache: HashMap<(PathBuf, String), Lookup>,
+}
+
+impl<'o> ResourceSearch<'o> {
+    pub fn new(dirs: &'o [PathBuf]) -> ResourceSearch<'o> {
+        ResourceSearch {
+            dirs,
+            cache: Default::default(),
+        }
+    }
+
+    /// Whether there is anywhere to search, i.e. whether checking is enabled.
+    pub fn is_enabled(&self) -> bool {
+        !self.dirs.is_empty()
+    }
+
+    pub fn lookup(&mut self, context: &Context, location: Location, path: &str) -> Lookup {
+        let source_dir = context.file_path(location.file).parent().map(Path::to_owned).unwrap_or_default();
+        let key = (source_dir, path.to_owned());
+        if let Some(lookup) = self.cache.get(&key) {
+            return lookup.clone();
+        }
+
+        let path = path.replace('\\', "/");
+        let candidates = self.dirs.first().into_iter()
+            .chain(std::iter::once(&key.0))
+            .chain(self.dirs.iter().skip(1));
+        let mut lookup = Lookup::Missing;
+        for dir in candidates {
+            let candidate = dir.join(&path);
+            if candidate.is_file() {
+                lookup = Lookup::Found(candidate);
+                break;
+            }
+            if lookup == Lookup::Missing {
+                let fixed = dm::fix_case(&candidate);
+                if fixed.as_ref() != candidate.as_path() && fixed.is_file() {
+                    lookup = Lookup::WrongCase(fixed.into_owned());
+                }
+            }
+        }
+
+        self.cache.insert(key, lookup.clone());
+        lookup
+    }
+
+    /// Look up a resource, registering a `missing_resource` diagnostic if it
+    /// is not found exactly. Returns `None` if checking is not enabled.
+    pub fn check(&mut self, context: &Context, location: Location, path: &str) -> Option<Lookup> {
+        if !self.is_enabled() {
+            return None;
+        }
+        let lookup = self.lookup(context, location, path);
+        match lookup {
+            Lookup::Found(_) => {},
+            Lookup::WrongCase(ref fixed) => {
+                let actual = fixed.file_name().map_or_else(String::new, |name| name.to_string_lossy().into_owned());
+                DMError::new(location, format!("resource {:?} has the wrong case, file on disk is {:?}", path, actual))
+                    .with_component(dm::Component::DreamChecker)
+                    .with_errortype("missing_resource")
+                    .set_severity(Severity::Warning)
+                    .register(context);
+            },
+            Lookup::Missing => {
+                DMError::new(location, format!("resource not found: {:?}", path))
+                    .with_component(dm::Component::DreamChecker)
+                    .with_errortype("missing_resource")
+                    .register(context);
+            },
+        }
+        Some(lookup)
+    }
+
+    /// Check the resources referenced by every type var's initial value.
+    pub fn check_var_values(&mut self, context: &Context, objtree: &ObjectTree) {
+        if !self.is_enabled() {
+            return;
+        }
+        objtree.root().recurse(&mut |ty| {
+            for var in ty.get().vars.values() {
+                if var.value.location.is_builtins() {
+                    continue;
+                }
+                if let Some(constant) = var.value.constant.as_ref() {
+                    self.check_constant(context, var.value.location, constant);
+                }
+            }
+        });
+    }
+
+    fn check_constant(&mut self, context: &Context, location: Location, constant: &Constant) {
+        match constant {
+            Constant::Resource(path) => {
+                self.check(context, location, path);
+            },
+            Constant::Call(func, args) => {
+                if let (ConstFn::Icon, Some((Constant::String(path), _))) |
+                    (ConstFn::Sound, Some((Constant::String(path), _))) |
+                    (ConstFn::File, Some((Constant::String(path), _))) = (func, args.first())
+                {
+                    self.check(context, location, path);
+                }
+                for (key, value) in args.iter() {
+                    self.check_constant(context, location, key);
+                    if let Some(value) = value {
+                        self.check_constant(context, location, value);
+                    }
+                }
+            },
+            Constant::New { args: Some(elements), .. } |
+            Constant::List(elements) => {
+                for (key, value) in elements.iter() {
+                    self.check_constant(context, location, key);
+                    if let Some(value) = value {
+                        self.check_constant(context, location, value);
+                    }
+                }
+            },
+            _ => {},
+        }
+    }
+}
@@ -1,12 +1,25 @@
 use dm::Context;
+use dm::config::Config;
 use std::borrow::Cow;
+use std::path::Path;
 
-use crate::{run_inner};
+use crate::{run_inner, Environment};
+use crate::cache::AnalysisCache;
+use crate::metrics::ProcMetrics;
 
 pub const NO_ERRORS: &[(u32, u16, &str)] = &[];
 
 pub fn parse_a_file_for_test<S: Into<Cow<'static, str>>>(buffer: S) -> Context {
+    parse_and_measure(buffer, Config::default(), &Environment::default()).0
+}
+
+pub fn metrics_for_test<S: Into<Cow<'static, str>>>(buffer: S) -> Vec<ProcMetrics> {
+    parse_and_measure(buffer, Config::default(), &Environment::default()).1
+}
+
+fn parse_and_measure<S: Into<Cow<'static, str>>>(buffer: S, config: Config, environment: &Environment) -> (Context, Vec<ProcMetrics>) {
     let context = Context::default();
+    context.set_config(config);
 
     let pp = dm::preprocessor::Preprocessor::from_buffer(&context, "unit_tests.rs".into(), buffer.into());
 
@@ -16,13 +29,36 @@ pub fn parse_a_file_for_test<S: Into<Cow<'static, str>>>(buffer: S) -> Context {
     parser.enable_procs();
     let tree = parser.parse_object_tree();
 
-    run_inner(&context, &tree, false);
+    let metrics = run_inner(&context, &tree, environment, None, false);
+
+    (context, metrics)
+}
 
+/// Check a `.dme` on disk, reusing and updating the results in `cache`.
+pub fn check_environment_with_cache(dme: &Path, cache: &mut AnalysisCache) -> Context {
+    let context = Context::default();
+    let mut pp = dm::preprocessor::Preprocessor::new(&context, dme.to_owned())
+        .expect("i/o error opening .dme");
+    let tree = {
+        let indents = dm::indents::IndentProcessor::new(&context, &mut pp);
+        let mut parser = dm::parser::Parser::new(&context, indents);
+        parser.enable_procs();
+        parser.parse_object_tree()
+    };
+    run_inner(&context, &tree, &Environment::from_preprocessor(&pp), Some(cache), false);
     context
 }
 
 pub fn check_errors_match<S: Into<Cow<'static, str>>>(buffer: S, errorlist: &[(u32, u16, &str)]) {
-    let context = parse_a_file_for_test(buffer);
+    check_errors_match_with_config(buffer, Config::default(), errorlist)
+}
+
+pub fn check_errors_match_with_config<S: Into<Cow<'static, str>>>(buffer: S, config: Config, errorlist: &[(u32, u16, &str)]) {
+    check_errors_match_in_environment(buffer, config, &Environment::default(), errorlist)
+}
+
+pub fn check_errors_match_in_environment<S: Into<Cow<'static, str>>>(buffer: S, config: Config, environment: &Environment, errorlist: &[(u32, u16, &str)]) {
+    let context = parse_and_measure(buffer, config, environment).0;
     let errors = context.errors();
     let mut iter = errors.iter();
     for (line, column, desc) in errorlist {
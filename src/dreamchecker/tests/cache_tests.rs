@@ -0,0 +1,117 @@
+extern crate dreamchecker as dc;
+extern crate dreammaker as dm;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use dc::cache::AnalysisCache;
+use dc::test_helpers::*;
+
+const DME: &str = r#"
+#include "a.dm"
+#include "b.dm"
+"#;
+
+const A: &str = r#"
+/mob/proc/sleeper()
+    sleep(1)
+
+/mob/proc/first()
+    set SpacemanDMM_should_not_sleep = TRUE
+    sleeper()
+    world << undefined_a
+"#;
+
+const B: &str = r#"
+/mob/proc/second()
+    var/undefined/x
+    world << x
+
+/mob/proc/third()
+    first()
+"#;
+
+fn write_environment(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("dreamchecker-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("test.dme"), DME).unwrap();
+    fs::write(dir.join("a.dm"), A).unwrap();
+    fs::write(dir.join("b.dm"), B).unwrap();
+    dir
+}
+
+fn diagnostics(dme: &Path, cache: &mut AnalysisCache) -> Vec<String> {
+    let context = check_environment_with_cache(dme, cache);
+    let errors = context.errors();
+    errors.iter()
+        .map(|error| {
+            let mut text = format!("{}, {}", context.file_path(error.location().file).display(), error);
+            for note in error.notes() {
+                text.push_str(&format!("\n- {}:{}: {}", note.location().line, note.location().column, note.description()));
+            }
+            text
+        })
+        .collect()
+}
+
+#[test]
+fn cached_results_are_reused() {
+    let dir = write_environment("reuse");
+    let dme = dir.join("test.dme");
+    let cache_file = dir.join(".dreamchecker-cache");
+
+    let mut cache = AnalysisCache::read(&cache_file);
+    let fresh = diagnostics(&dme, &mut cache);
+    assert_eq!(cache.hits(), 0);
+    assert!(!fresh.is_empty());
+    cache.write(&cache_file).unwrap();
+
+    let mut cache = AnalysisCache::read(&cache_file);
+    assert_eq!(diagnostics(&dme, &mut cache), fresh);
+    assert_eq!(cache.hits(), 4);
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn edited_files_are_reanalyzed() {
+    let dir = write_environment("edit");
+    let dme = dir.join("test.dme");
+    let cache_file = dir.join(".dreamchecker-cache");
+
+    let mut cache = AnalysisCache::read(&cache_file);
+    diagnostics(&dme, &mut cache);
+    cache.write(&cache_file).unwrap();
+
+    // Editing only a proc body keeps the other file's results.
+    fs::write(dir.join("b.dm"), B.replace("world << x", "world << x\n    world << undefined_b")).unwrap();
+    let mut cache = AnalysisCache::read(&cache_file);
+    let incremental = diagnostics(&dme, &mut cache);
+    assert_eq!(cache.hits(), 2);
+
+    let mut uncached = AnalysisCache::default();
+    assert_eq!(incremental, diagnostics(&dme, &mut uncached));
+    assert!(incremental.iter().any(|each| each.contains("undefined_b")));
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn changed_declarations_discard_the_cache() {
+    let dir = write_environment("declarations");
+    let dme = dir.join("test.dme");
+    let cache_file = dir.join(".dreamchecker-cache");
+
+    let mut cache = AnalysisCache::read(&cache_file);
+    diagnostics(&dme, &mut cache);
+    cache.write(&cache_file).unwrap();
+
+    fs::write(dir.join("b.dm"), format!("{}\n/mob/var/undefined_a\n", B)).unwrap();
+    let mut cache = AnalysisCache::read(&cache_file);
+    let incremental = diagnostics(&dme, &mut cache);
+    assert_eq!(cache.hits(), 0);
+    assert!(!incremental.iter().any(|each| each.contains("undefined_a")));
+
+    let _ = fs::remove_dir_all(&dir);
+}
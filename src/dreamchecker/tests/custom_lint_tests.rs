@@ -0,0 +1,137 @@
+
+extern crate dreamchecker as dc;
+extern crate dreammaker as dm;
+
+use dc::test_helpers::*;
+use dm::config::Config;
+use dm::lint_pattern::{LintPattern, ProcPattern, ValuePattern};
+
+fn config(lints: &str) -> Config {
+    Config::from_toml(lints).unwrap()
+}
+
+#[test]
+fn pattern_syntax() {
+    assert_eq!("/proc/to_chat(_, !text | null)".parse(), Ok(LintPattern::Call {
+        proc: ProcPattern::Path { ty: String::new(), name: "to_chat".to_owned() },
+        args: vec![
+            ValuePattern::Any,
+            ValuePattern::Or(vec![ValuePattern::Not(Box::new(ValuePattern::Text)), ValuePattern::Null]),
+        ],
+    }));
+    assert_eq!("density = !(0 | \"no\")".parse(), Ok(LintPattern::Assign {
+        var: "density".to_owned(),
+        value: ValuePattern::Not(Box::new(ValuePattern::Or(vec![
+            ValuePattern::Number(0.),
+            ValuePattern::String("no".to_owned()),
+        ]))),
+    }));
+    assert!("/mob/Move()".parse::<LintPattern>().is_err());
+    assert!("to_chat(_, words)".parse::<LintPattern>().is_err());
+    assert!(Config::from_toml("[[lint]]\nname = \"x\"\npattern = \"foo(\"\nmessage = \"x\"\n").is_err());
+}
+
+const TO_CHAT: &str = r#"
+[[lint]]
+name = "to_chat_text"
+pattern = "/proc/to_chat(_, !text)"
+message = "to_chat() must be passed a string"
+"#;
+
+#[test]
+fn call_with_typed_argument() {
+    let code = r#"
+/proc/to_chat(target, message)
+
+/obj/proc/test(mob/M, text_var, obj/O)
+    to_chat(M, "hello [M]")
+    to_chat(M, text_var)
+    to_chat(M, 5)
+    to_chat(M, O)
+    to_chat(target = M, message = null)
+"#.trim();
+    check_errors_match_with_config(code, config(TO_CHAT), &[
+        (6, 5, "to_chat_text: to_chat() must be passed a string"),
+        (7, 5, "to_chat_text: to_chat() must be passed a string"),
+        (8, 5, "to_chat_text: to_chat() must be passed a string"),
+    ]);
+}
+
+#[test]
+fn call_within_proc() {
+    let code = r#"
+/datum/proc/process()
+    sleep(1)
+
+/datum/thing/process()
+    sleep(1)
+
+/datum/proc/other()
+    sleep(1)
+"#.trim();
+    let lint = r#"
+[[lint]]
+name = "sleep_in_process"
+pattern = "sleep()"
+within = ["/datum/proc/process"]
+message = "process() must not sleep"
+severity = "error"
+"#;
+    check_errors_match_with_config(code, config(lint), &[
+        (2, 5, "sleep_in_process: process() must not sleep"),
+        (5, 5, "sleep_in_process: process() must not sleep"),
+    ]);
+}
+
+#[test]
+fn assignment_outside_type() {
+    let code = r#"
+/atom/proc/test()
+    density = 1
+
+/datum/proc/test(atom/A)
+    var/density = 0
+    density = 1
+    A.density = density
+"#.trim();
+    let lint = r#"
+[[lint]]
+name = "density_outside_atom"
+pattern = "density = _"
+outside = ["/atom"]
+message = "set density through the atom's own procs"
+"#;
+    check_errors_match_with_config(code, config(lint), &[
+        (7, 5, "density_outside_atom: set density through the atom's own procs"),
+    ]);
+}
+
+#[test]
+fn file_filters() {
+    let code = r#"
+/proc/to_chat(target, message)
+
+/proc/test()
+    to_chat(world, 5)
+"#.trim();
+    let lint = format!("{}files = [\"code/**/*.dm\"]\n", TO_CHAT);
+    check_errors_match_with_config(code, config(&lint), NO_ERRORS);
+    let lint = format!("{}exclude_files = [\"code/\"]\n", TO_CHAT);
+    check_errors_match_with_config(code, config(&lint), &[
+        (4, 5, "to_chat_text: to_chat() must be passed a string"),
+    ]);
+}
+
+#[test]
+fn unknown_paths() {
+    let lint = r#"
+[[lint]]
+name = "typo"
+pattern = "sleep()"
+within = ["/datum/proc/proccess"]
+message = "typo"
+"#;
+    check_errors_match_with_config("/datum/proc/process()\n    sleep(1)\n", config(lint), &[
+        (0, 0, "custom lint \"typo\" names unknown paths: /datum/proc/proccess"),
+    ]);
+}
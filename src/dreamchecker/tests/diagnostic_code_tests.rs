@@ -0,0 +1,171 @@
+
+extern crate dreamchecker as dc;
+extern crate dreammaker as dm;
+
+use std::collections::HashSet;
+use std::path::Path;
+
+use dc::test_helpers::*;
+use dm::config::Config;
+use dm::diagnostics::{self, DIAGNOSTICS};
+
+#[test]
+fn codes_are_unique() {
+    let mut codes = HashSet::new();
+    let mut errortypes = HashSet::new();
+    for diagnostic in DIAGNOSTICS {
+        assert!(
+            diagnostic.code.len() == 6
+                && diagnostic.code.starts_with("DM")
+                && diagnostic.code[2..].bytes().all(|b| b.is_ascii_digit()),
+            "malformed code {}", diagnostic.code
+        );
+        assert!(codes.insert(diagnostic.code), "duplicate code {}", diagnostic.code);
+        assert!(errortypes.insert(diagnostic.errortype), "duplicate errortype {}", diagnostic.errortype);
+        assert!(!diagnostic.explanation.is_empty());
+    }
+}
+
+fn check_sources(dir: &Path, missing: &mut Vec<String>) {
+    for entry in std::fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            check_sources(&path, missing);
+        } else if path.extension().map_or(false, |ext| ext == "rs") {
+            let source = std::fs::read_to_string(&path).unwrap();
+            for piece in source.split("with_errortype(\"").skip(1) {
+                let errortype = &piece[..piece.find('"').unwrap()];
+                if diagnostics::code_of(errortype).is_none() {
+                    missing.push(format!("{}: {}", path.display(), errortype));
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn every_errortype_has_a_code() {
+    let src = Path::new(env!("CARGO_MANIFEST_DIR")).parent().unwrap();
+    let mut missing = Vec::new();
+    for each in &["dreammaker", "dreamchecker", "tools"] {
+        check_sources(&src.join(each), &mut missing);
+    }
+    assert!(missing.is_empty(), "errortypes without codes: {:#?}", missing);
+}
+
+/// Constructions of diagnostics which legitimately have no errortype of
+/// their own, as a file name and the start of the construction.
+const UNTYPED_ALLOWED: &[(&str, &str)] = &[
+    // helpers whose callers attach the errortype
+    ("error.rs", "DMError::new(self.location(), message)"),
+    ("lib.rs", "DMError::new(location, desc)"),
+    // cached diagnostics, whose errortype is restored just after
+    ("cache.rs", "DMError::new(location(cached_error.location)?"),
+    ("preprocess_cache.rs", "DMError::new(from_cached(cached.location)?"),
+];
+
+/// The text of a call starting at `start`, and any method chain after it.
+fn call_chain(source: &str, start: usize) -> &str {
+    let mut depth = 0i32;
+    for (i, ch) in source[start..].char_indices() {
+        match ch {
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => {
+                depth -= 1;
+                if depth < 0 {
+                    return &source[start..start + i];
+                }
+            }
+            ';' | ',' if depth == 0 => return &source[start..start + i],
+            _ => {}
+        }
+    }
+    &source[start..]
+}
+
+fn check_untyped(dir: &Path, untyped: &mut Vec<String>) {
+    for entry in std::fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            if path.file_name() != Some("tests".as_ref()) {
+                check_untyped(&path, untyped);
+            }
+            continue;
+        } else if path.extension() != Some("rs".as_ref()) {
+            continue;
+        }
+        let file_name = path.file_name().unwrap().to_str().unwrap();
+        let source = std::fs::read_to_string(&path).unwrap();
+        let mut starts = Vec::new();
+        for (i, _) in source.match_indices("DMError::new(") {
+            starts.push(i);
+        }
+        for (i, _) in source.match_indices("error(") {
+            // `error(...)` and `x.error(...)`, but not `register_error(...)`
+            // or the definition of an `error` helper.
+            let before = &source[..i];
+            let prev = before.chars().next_back();
+            if matches!(prev, Some(ch) if ch.is_alphanumeric() || ch == '_') || before.ends_with("fn ") {
+                continue;
+            }
+            starts.push(before.rfind(|ch: char| ch.is_whitespace() || ch == '(').map_or(0, |j| j + 1));
+        }
+        for start in starts {
+            let line_start = source[..start].rfind('\n').map_or(0, |j| j + 1);
+            if source[line_start..].trim_start().starts_with("//") {
+                continue;
+            }
+            let chain = call_chain(&source, start);
+            if chain.contains(".with_errortype") {
+                continue;
+            }
+            if UNTYPED_ALLOWED.iter().any(|&(file, text)| file == file_name && chain.starts_with(text)) {
+                continue;
+            }
+            let line = source[..start].matches('\n').count() + 1;
+            untyped.push(format!("{}:{}: {}", path.display(), line, chain.lines().next().unwrap_or("")));
+        }
+    }
+}
+
+#[test]
+fn every_error_has_an_errortype() {
+    let src = Path::new(env!("CARGO_MANIFEST_DIR")).parent().unwrap();
+    let mut untyped = Vec::new();
+    for each in &["dreammaker", "dreamchecker", "tools"] {
+        check_untyped(&src.join(each), &mut untyped);
+    }
+    assert!(untyped.is_empty(), "diagnostics without an errortype: {:#?}", untyped);
+}
+
+#[test]
+fn errors_carry_codes() {
+    let context = parse_a_file_for_test(r#"
+/proc/test()
+    world << undefined
+"#.trim());
+    let errors = context.errors();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].errortype(), Some("undefined_var"));
+    assert_eq!(errors[0].code(), Some("DM0305"));
+}
+
+#[test]
+fn configure_by_code() {
+    let code = r#"
+/proc/test()
+    world << undefined
+"#.trim();
+    let config = Config::from_toml("[diagnostics]\nDM0305 = \"off\"\n").unwrap();
+    check_errors_match_with_config(code, config, NO_ERRORS);
+    let config = Config::from_toml("[diagnostics]\nundefined_var = \"off\"\n").unwrap();
+    check_errors_match_with_config(code, config, NO_ERRORS);
+}
+
+#[test]
+fn explain_lookup() {
+    let diagnostic = diagnostics::lookup("dm0305").unwrap();
+    assert_eq!(diagnostic.errortype, "undefined_var");
+    assert_eq!(diagnostics::lookup("undefined_var").unwrap().code, "DM0305");
+    assert!(diagnostics::lookup("DM9999").is_none());
+}
@@ -108,3 +108,34 @@ fn no_can_be_redefined() {
 "##.trim();
     check_errors_match(code, NO_CAN_BE_REDEFINED_ERRORS);
 }
+
+pub const MUST_USE_RESULT_ERRORS: &[(u32, u16, &str)] = &[
+    (10, 5, "return value of /mob/subtype/proc/try_thing is discarded"),
+    (11, 5, "return value of /mob/subtype/proc/try_thing is discarded"),
+    (12, 5, "return value of /proc/copytext is discarded"),
+    (13, 5, "return value of /list/proc/Copy is discarded"),
+];
+
+#[test]
+fn must_use_result() {
+    let code = r##"
+/mob/proc/try_thing()
+    set SpacemanDMM_must_use_result = TRUE
+    world << "impure"
+    return TRUE
+
+/mob/subtype/try_thing()
+    return FALSE
+
+/mob/subtype/proc/test(mob/subtype/other, list/L)
+    try_thing()
+    other.try_thing()
+    copytext("abc", 2)
+    L.Copy()
+    if(try_thing())
+        world << copytext("abc", 2)
+    var/list/copy = L.Copy()
+    world << copy.len
+"##.trim();
+    check_errors_match(code, MUST_USE_RESULT_ERRORS);
+}
@@ -0,0 +1,92 @@
+
+extern crate dreamchecker as dc;
+extern crate dreammaker as dm;
+
+use dc::test_helpers::*;
+use dm::config::{Config, DuplicateNormalization};
+
+fn config(min_statements: u32, normalize: DuplicateNormalization) -> Config {
+    let mut config = Config::default();
+    config.code_standards.duplicate_code_min_statements = Some(min_statements);
+    config.code_standards.duplicate_code_normalize = normalize;
+    config
+}
+
+const DUPLICATE_CODE: &str = r##"
+/mob/proc/a()
+    var/x = 1
+    if(x)
+        x++
+        world << x
+    return x
+
+/mob/proc/b()
+    var/x = 1
+    if(x)
+        x++
+        world << x
+    return x
+
+/mob/proc/c()
+    var/y = 2
+    if(y)
+        y++
+        world << y
+    return y
+"##;
+
+pub const EXACT_ERRORS: &[(u32, u16, &str)] = &[
+    (2, 5, "duplicate code: 5 statements appear in 2 places"),
+];
+
+#[test]
+fn exact_duplicates() {
+    check_errors_match_with_config(DUPLICATE_CODE.trim(), config(2, DuplicateNormalization::Exact), EXACT_ERRORS);
+}
+
+#[test]
+fn below_threshold() {
+    check_errors_match_with_config(DUPLICATE_CODE.trim(), config(6, DuplicateNormalization::Identifiers), NO_ERRORS);
+}
+
+pub const IDENTIFIERS_ERRORS: &[(u32, u16, &str)] = &[
+    (2, 5, "duplicate code: 5 statements appear in 3 places"),
+];
+
+#[test]
+fn normalized_identifiers() {
+    check_errors_match_with_config(DUPLICATE_CODE.trim(), config(2, DuplicateNormalization::Identifiers), IDENTIFIERS_ERRORS);
+}
+
+#[test]
+fn disabled_by_default() {
+    check_errors_match(DUPLICATE_CODE.trim(), NO_ERRORS);
+}
+
+const GLOBAL_DUPLICATE_CODE: &str = r##"
+var/first = 1
+var/second = 2
+
+/proc/first_proc()
+/proc/second_proc()
+
+/mob/proc/a()
+    ::first++
+    ::first_proc()
+    world << ::first
+
+/mob/proc/b()
+    ::second++
+    ::second_proc()
+    world << ::second
+"##;
+
+pub const GLOBAL_IDENTIFIERS_ERRORS: &[(u32, u16, &str)] = &[
+    (8, 5, "duplicate code: 3 statements appear in 2 places"),
+];
+
+#[test]
+fn normalized_global_identifiers() {
+    check_errors_match_with_config(GLOBAL_DUPLICATE_CODE.trim(), config(2, DuplicateNormalization::Exact), NO_ERRORS);
+    check_errors_match_with_config(GLOBAL_DUPLICATE_CODE.trim(), config(2, DuplicateNormalization::Identifiers), GLOBAL_IDENTIFIERS_ERRORS);
+}
@@ -0,0 +1,109 @@
+
+extern crate dreamchecker as dc;
+extern crate dreammaker as dm;
+
+use std::path::Path;
+
+use dc::fix::{apply, unified_diff};
+use dc::test_helpers::*;
+use dm::{Location, SuggestedEdit};
+
+fn fixed(code: &'static str) -> String {
+    let context = parse_a_file_for_test(code);
+    let errors = context.errors();
+    let fixes: Vec<&[SuggestedEdit]> = errors.iter()
+        .map(|error| error.suggested_edits())
+        .filter(|edits| !edits.is_empty())
+        .collect();
+    apply(code, &fixes).0
+}
+
+#[test]
+fn var_in_proc_parameter() {
+    assert_eq!(
+        fixed("/proc/test(var/x, var/obj/y)\n\treturn x || y\n"),
+        "/proc/test(x, obj/y)\n\treturn x || y\n",
+    );
+}
+
+#[test]
+fn tmp_no_effect() {
+    assert_eq!(
+        fixed("/proc/test()\n\tvar/tmp/x = 1\n\treturn x\n"),
+        "/proc/test()\n\tvar/x = 1\n\treturn x\n",
+    );
+}
+
+#[test]
+fn in_precedes_as() {
+    assert_eq!(
+        fixed("/proc/test(x in list(1, 2) as num)\n\treturn x\n"),
+        "/proc/test(x as num in list(1, 2))\n\treturn x\n",
+    );
+}
+
+#[test]
+fn ambiguous_not_bitwise() {
+    let code = "/proc/test(x, y)\n\treturn !x & y\n";
+    let fixed_code = fixed(code);
+    assert_eq!(fixed_code, "/proc/test(x, y)\n\treturn (!x) & y\n");
+    check_errors_match(fixed_code, NO_ERRORS);
+}
+
+#[test]
+fn ambiguous_not_bitwise_spaced() {
+    assert_eq!(
+        fixed("/proc/test(x, y)\n\treturn ! x & y\n"),
+        "/proc/test(x, y)\n\treturn (! x) & y\n",
+    );
+    assert_eq!(
+        fixed("/proc/test(x, y)\n\treturn !/* why */x | y\n"),
+        "/proc/test(x, y)\n\treturn (!/* why */x) | y\n",
+    );
+}
+
+#[test]
+fn must_call_parent() {
+    let code = r#"
+/datum/proc/test()
+    set SpacemanDMM_should_call_parent = 1
+    return
+
+/datum/child/test()
+    world << "hello"
+"#;
+    assert_eq!(fixed(code), code.replace("    world", "    ..()\n    world"));
+}
+
+#[test]
+fn stale_and_overlapping_edits_are_skipped() {
+    let start = Location { file: dm::FileId::default(), line: 1, column: 5 };
+    let first = [SuggestedEdit::new(start, "var/", "")];
+    let overlapping = [SuggestedEdit::new(start, "var/x", "y")];
+    let stale = [SuggestedEdit::new(Location { column: 1, ..start }, "proc", "verb")];
+    let (output, count) = apply("foo(var/x)", &[&first, &overlapping, &stale]);
+    assert_eq!(output, "foo(x)");
+    assert_eq!(count, 1);
+}
+
+#[test]
+fn diff_output() {
+    let old = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\n";
+    let new = "a\nB\nc\nd\ne\nf\ng\nh\ni\nj\nk\n";
+    assert_eq!(unified_diff(Path::new("code/test.dm"), old, new), "\
+--- a/code/test.dm
++++ b/code/test.dm
+@@ -1,5 +1,5 @@
+ a
+-b
++B
+ c
+ d
+ e
+@@ -8,3 +8,4 @@
+ h
+ i
+ j
++k
+");
+}
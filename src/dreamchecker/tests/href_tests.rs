@@ -0,0 +1,80 @@
+
+extern crate dreamchecker as dc;
+
+use dc::test_helpers::*;
+
+#[test]
+fn matching_keys() {
+    let code = r#"
+/proc/REF(thing)
+    return "\ref[thing]"
+
+/obj/machine/proc/interact(mob/user)
+    user << browse("<a href='?src=[REF(src)];action=eject'>Eject</a> <a href=\"?src=\ref[src]&power=1\">On</a>")
+
+/obj/machine/Topic(href, href_list)
+    if(href_list["action"] == "eject")
+        return
+    if(href_list["power"])
+        return
+    if(href_list["src"])
+        return
+"#.trim();
+    check_errors_match(code, NO_ERRORS);
+}
+
+#[test]
+fn mismatched_keys() {
+    let code = r#"
+/obj/machine/proc/interact(mob/user)
+    user << browse("<a href='?src=\ref[src];action=eject;mode=[1]'>Eject</a>")
+
+/obj/machine/Topic(href, href_list)
+    if(href_list["actoin"] == "eject")
+        return
+    if(href_list["mode"])
+        return
+"#.trim();
+    check_errors_match(code, &[
+        (5, 8, "href_list key \"actoin\" is read by /obj/machine/Topic() but no link produces it"),
+        (2, 20, "link key \"action\" is not read by the Topic() of /obj/machine"),
+    ]);
+}
+
+#[test]
+fn links_to_other_types() {
+    let code = r#"
+/obj/machine/Topic(href, href_list)
+    if(href_list["eject"])
+        return
+
+/obj/machine/vendor
+
+/mob/proc/interact(obj/machine/vendor/V)
+    src << browse("<a href='?src=\ref[V];eject=1'>Eject</a><a href='?src=\ref[V];vend=1'>Vend</a>")
+"#.trim();
+    check_errors_match(code, &[
+        (8, 19, "link key \"vend\" is not read by the Topic() of /obj/machine/vendor"),
+    ]);
+}
+
+#[test]
+fn dynamic_keys_and_escapes() {
+    let code = r#"
+/obj/machine/proc/interact(mob/user, key)
+    user << browse("<a href='?src=\ref[src];[key]=1'>Go</a>")
+
+/obj/machine/Topic(href, href_list)
+    if(href_list["anything"])
+        return
+
+/obj/console/proc/interact(mob/user)
+    user << browse("<a href='?src=\ref[src];choice=1'>Go</a>")
+
+/obj/console/Topic(href, href_list)
+    handle(href_list)
+
+/obj/console/proc/handle(list/params)
+"#.trim();
+    check_errors_match(code, NO_ERRORS);
+}
@@ -0,0 +1,62 @@
+
+extern crate dreamchecker as dc;
+extern crate dreammaker as dm;
+
+use std::path::PathBuf;
+
+use dc::test_helpers::*;
+use dc::Environment;
+use dm::config::Config;
+
+fn environment(maps: &[&str]) -> Environment {
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    Environment {
+        maps: maps.iter().map(|map| root.join(map)).collect(),
+        resource_dirs: vec![root],
+        .. Default::default()
+    }
+}
+
+const GUNS: &str = r##"
+/obj/item/gun
+    icon = 'tests/icons/guns.dmi'
+    icon_state = "laser"
+
+/obj/item/gun/taser
+    icon_state = "taser"
+
+/obj/item/gun/typo
+    icon_state = "lazer"
+
+/obj/item/gun/unknown
+    icon_state = "plasma"
+
+/obj/item/gun/unset
+    name = "gun"
+"##;
+
+pub const TYPE_ERRORS: &[(u32, u16, &str)] = &[
+    (9, 16, "icon_state \"lazer\" not found in 'tests/icons/guns.dmi', did you mean \"laser\"?"),
+    (12, 16, "icon_state \"plasma\" not found in 'tests/icons/guns.dmi'"),
+];
+
+#[test]
+fn type_icon_states() {
+    check_errors_match_in_environment(GUNS.trim(), Config::default(), &environment(&[]), TYPE_ERRORS);
+}
+
+pub const MAP_ERRORS: &[(u32, u16, &str)] = &[
+    (9, 16, "icon_state \"lazer\" not found in 'tests/icons/guns.dmi', did you mean \"laser\"?"),
+    (12, 16, "icon_state \"plasma\" not found in 'tests/icons/guns.dmi'"),
+    (1, 22, "icon_state \"tazer\" not found in 'tests/icons/guns.dmi', did you mean \"taser\"?"),
+];
+
+#[test]
+fn map_icon_states() {
+    check_errors_match_in_environment(GUNS.trim(), Config::default(), &environment(&["tests/maps/icon_states.dmm"]), MAP_ERRORS);
+}
+
+#[test]
+fn disabled_without_environment() {
+    check_errors_match(GUNS.trim(), NO_ERRORS);
+}
@@ -0,0 +1,54 @@
+
+extern crate dreamchecker as dc;
+
+use dc::test_helpers::*;
+
+#[test]
+fn complexity_and_nesting() {
+    let code = r##"
+/proc/test(a, b)
+    var/total = 0
+    for(var/i in 1 to 10)
+        if(a && b)
+            total++
+        else if(a || b)
+            total--
+    switch(total)
+        if(1)
+            return
+        if(2, 3)
+            return
+    return total > 0 ? 1 : 0
+"##.trim();
+    let metrics = metrics_for_test(code);
+    let test = metrics.iter().find(|each| each.proc_path == "/proc/test").unwrap();
+    // 1 + for + if + && + else if + || + 2 cases + ?:
+    assert_eq!(test.complexity, 9);
+    assert_eq!(test.statements, 9);
+    assert_eq!(test.max_nesting, 2);
+    assert_eq!(test.locals, 2);
+}
+
+#[test]
+fn fan_in_fan_out() {
+    let code = r##"
+/proc/leaf()
+    return
+
+/proc/middle()
+    leaf()
+    leaf()
+
+/proc/top()
+    middle()
+    leaf()
+"##.trim();
+    let metrics = metrics_for_test(code);
+    let get = |name: &str| metrics.iter().find(|each| each.proc_path == name).unwrap().clone();
+    let leaf = get("/proc/leaf");
+    assert_eq!((leaf.fan_in, leaf.fan_out), (2, 0));
+    let middle = get("/proc/middle");
+    assert_eq!((middle.fan_in, middle.fan_out), (1, 1));
+    let top = get("/proc/top");
+    assert_eq!((top.fan_in, top.fan_out), (0, 2));
+}
@@ -5,6 +5,9 @@ use dc::test_helpers::*;
 
 pub const NEW_DOT_ERRORS: &[(u32, u16, &str)] = &[
     (12, 14, "got '(', expected one of: operator, field access, ';'"),
+    (13, 16, "got '(', expected one of: operator, field access, ';'"),
+    (14, 9, "got '2', expected one of: '.', identifier, path separator, '(', operator, field access, ';'"),
+    (7, 5, "no type hint available on implicit new()"),
 ];
 
 #[test]
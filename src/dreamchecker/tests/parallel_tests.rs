@@ -0,0 +1,35 @@
+
+extern crate dreamchecker as dc;
+extern crate dreammaker as dm;
+extern crate rayon;
+
+use dc::test_helpers::*;
+
+fn diagnostics_with_threads(code: &str, threads: usize) -> Vec<(u32, u16, String)> {
+    let pool = rayon::ThreadPoolBuilder::new().num_threads(threads).build().unwrap();
+    let context = pool.install(|| parse_a_file_for_test(code.to_owned()));
+    let errors = context.errors();
+    errors.iter()
+        .map(|error| (error.location().line, error.location().column, error.description().to_owned()))
+        .collect()
+}
+
+#[test]
+fn deterministic_across_threads() {
+    let mut code = String::from("/mob/proc/sleeper()\n    sleep(1)\n\n");
+    for i in 0..50 {
+        code.push_str(&format!(r#"
+/mob/proc/test{i}()
+    set SpacemanDMM_should_not_sleep = TRUE
+    var/undefined/x
+    sleeper()
+    world << bad_var_{i}
+"#, i = i));
+    }
+
+    let sequential = diagnostics_with_threads(&code, 1);
+    assert!(sequential.len() >= 100);
+    for &threads in [2, 3, 8].iter() {
+        assert_eq!(sequential, diagnostics_with_threads(&code, threads));
+    }
+}
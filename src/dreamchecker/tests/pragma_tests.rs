@@ -0,0 +1,71 @@
+extern crate dreamchecker as dc;
+
+use dc::test_helpers::check_errors_match;
+
+pub const PRAGMA_PUSH_POP_ERRORS: &[(u32, u16, &str)] = &[
+    (9, 7, "ambiguous `!` on left side of an `in`"),
+];
+
+#[test]
+fn pragma_push_pop() {
+    let code = r##"
+#pragma push
+#pragma ambiguous_in_lhs ignore
+/proc/quiet()
+    if(!1 in list())
+        return
+#pragma pop
+
+/proc/loud()
+    if(!1 in list())
+        return
+"##.trim();
+    check_errors_match(code, PRAGMA_PUSH_POP_ERRORS);
+}
+
+#[test]
+fn pragma_by_code() {
+    let code = r##"
+#pragma DM1010 off
+/proc/quiet()
+    if(!1 in list())
+        return
+"##.trim();
+    check_errors_match(code, &[]);
+}
+
+pub const PRAGMA_UNKNOWN_ERRORS: &[(u32, u16, &str)] = &[
+    (1, 1, "unknown #pragma level \"loud\", expected error, warning, notice, or ignore"),
+    (2, 1, "unknown #pragma: multithreaded"),
+    (3, 1, "#pragma pop without a matching #pragma push"),
+];
+
+#[test]
+fn pragma_unknown() {
+    let code = r##"
+#pragma ambiguous_in_lhs loud
+#pragma multithreaded
+#pragma pop
+#pragma once
+"##.trim();
+    check_errors_match(code, PRAGMA_UNKNOWN_ERRORS);
+}
+
+pub const NAMEOF_ERRORS: &[(u32, u16, &str)] = &[
+    (8, 12, "nameof() argument has no name"),
+];
+
+#[test]
+fn nameof_and_special_idents() {
+    let code = r##"
+/obj/proc/test()
+    var/type_name = nameof(/obj)
+    var/proc_name = nameof(src.name)
+    var/self = __PROC__
+    var/obj/O = locate(__IMPLIED_TYPE__)
+    return list(type_name, proc_name, self, O, nameof(/obj::proc/test))
+/obj/proc/bad()
+    return nameof(1)
+"##.trim();
+    check_errors_match(code, NAMEOF_ERRORS);
+}
@@ -16,3 +16,38 @@ fn no_parent() {
 "##.trim();
     check_errors_match(code, NO_PARENT_ERRORS);
 }
+
+pub const RECOVERED_BODY_ERRORS: &[(u32, u16, &str)] = &[
+    (2, 16, "got ';', expected one of: operator, term"),
+    (4, 14, "got ')', expected one of: operator, term"),
+    (3, 5, "proc has no parent: /mob/proc/test"),
+];
+
+#[test]
+fn recovered_body() {
+    let code = r##"
+/mob/proc/test()
+    var/a = 1 +
+    ..()
+    world << )
+    return
+"##.trim();
+    check_errors_match(code, RECOVERED_BODY_ERRORS);
+}
+
+pub const RECOVERED_VAR_ERRORS: &[(u32, u16, &str)] = &[
+    (2, 16, "got ';', expected one of: operator, term"),
+    (3, 20, "got ')', expected one of: operator, term"),
+];
+
+#[test]
+fn recovered_var() {
+    let code = r##"
+/mob/proc/test()
+    var/a = 1 +
+    var/b = 2, c = )
+    world << a + b + c
+    return a
+"##.trim();
+    check_errors_match(code, RECOVERED_VAR_ERRORS);
+}
@@ -0,0 +1,79 @@
+
+extern crate dreamchecker as dc;
+extern crate dreammaker as dm;
+
+use std::path::PathBuf;
+
+use dc::test_helpers::*;
+use dc::Environment;
+use dm::config::Config;
+
+fn environment() -> Environment {
+    Environment {
+        maps: Vec::new(),
+        resource_dirs: vec![PathBuf::from(env!("CARGO_MANIFEST_DIR"))],
+        .. Default::default()
+    }
+}
+
+fn check_resources(code: &'static str, errorlist: &[(u32, u16, &str)]) {
+    check_errors_match_in_environment(code.trim(), Config::default(), &environment(), errorlist);
+}
+
+#[test]
+fn existing_resources() {
+    let code = r##"
+/obj
+    var/manifest = 'Cargo.toml'
+    var/sounds = list(sound("README.md"))
+
+/obj/proc/test()
+    world << 'README.md'
+    world << file("Cargo.toml")
+    world << icon("lib.rs")
+"##;
+    check_resources(code, NO_ERRORS);
+}
+
+pub const MISSING_ERRORS: &[(u32, u16, &str)] = &[
+    (5, 14, "resource not found: \"missing.dmi\""),
+    (6, 5, "resource not found: \"missing.ogg\""),
+    (2, 15, "resource not found: \"nothing.txt\""),
+];
+
+#[test]
+fn missing_resources() {
+    let code = r##"
+/obj
+    var/thing = 'nothing.txt'
+
+/obj/proc/test()
+    world << 'missing.dmi'
+    sound("missing.ogg")
+"##;
+    check_resources(code, MISSING_ERRORS);
+}
+
+#[cfg(not(windows))]
+pub const WRONG_CASE_ERRORS: &[(u32, u16, &str)] = &[
+    (2, 14, "resource \"readme.md\" has the wrong case, file on disk is \"README.md\""),
+];
+
+#[cfg(not(windows))]
+#[test]
+fn wrong_case_resources() {
+    let code = r##"
+/obj/proc/test()
+    world << 'readme.md'
+"##;
+    check_resources(code, WRONG_CASE_ERRORS);
+}
+
+#[test]
+fn disabled_without_environment() {
+    let code = r##"
+/obj/proc/test()
+    world << 'missing.dmi'
+"##;
+    check_errors_match(code.trim(), NO_ERRORS);
+}
@@ -50,3 +50,60 @@ fn return_type() {
 "##.trim();
     check_errors_match(code, RETURN_TYPE_ERRORS);
 }
+
+pub const SCOPE_OPERATOR_ERRORS: &[(u32, u16, &str)] = &[
+    (9, 5, "cannot assign to /obj::health, which is not static"),
+    (13, 22, "undefined var: \"nonexistent\" on /obj/item"),
+    (14, 22, "undefined proc: \"nonexistent\" on /obj/item"),
+    (15, 16, "undefined proc: \"nonexistent\" on /obj/item"),
+    (16, 13, "undefined global var: \"nonexistent\""),
+    (17, 18, "scope operator requires a type path or static type: \"health\""),
+];
+
+#[test]
+fn scope_operator() {
+    let code = r##"
+var/global_count = 0
+
+/obj/var/health = 10
+
+/obj/proc/use()
+
+/obj/item/use()
+    var/obj/O = new
+    O::health += /obj/item::health
+    return parent_type::use()
+
+/obj/item/proc/test(thing)
+    var/a = /obj/item::nonexistent
+    var/b = /obj/item::proc/nonexistent
+    var/c = src::nonexistent()
+    var/d = ::nonexistent
+    var/e = thing::health
+    var/f = /obj::proc/use
+    return ::global_count + src::health + type::health
+"##.trim();
+    check_errors_match(code, SCOPE_OPERATOR_ERRORS);
+}
+
+pub const SCOPE_OPERATOR_ASSIGN_ERRORS: &[(u32, u16, &str)] = &[
+    (7, 5, "cannot assign to /obj::health, which is not static"),
+    (9, 5, "cannot assign to /obj/item::health, which is not static"),
+];
+
+#[test]
+fn scope_operator_assign() {
+    let code = r##"
+/obj/var/health = 10
+/obj/var/static/count = 0
+/obj/item
+
+/obj/proc/test()
+    var/obj/O = new
+    O::health = 20
+    /obj::count = 1
+    /obj/item::health++
+    type::count += 1
+"##.trim();
+    check_errors_match(code, SCOPE_OPERATOR_ASSIGN_ERRORS);
+}
@@ -0,0 +1,50 @@
+
+extern crate dreamchecker as dc;
+
+use dc::test_helpers::*;
+
+pub const TEXT2PATH_ERRORS: &[(u32, u16, &str)] = &[
+    (6, 5, "text2path() of unresolvable path \"/obj/item/gun\""),
+    (8, 5, "text2path() of unresolvable path \"/obj/item/proc/missing\""),
+    (10, 5, "locate() of unresolvable path \"/mob/living\""),
+    (13, 5, "ispath() of unresolvable path \"/obj/item/gun\""),
+    (15, 5, "istype() of unresolvable path \"/mob/living\""),
+    (16, 5, "typesof() of unresolvable path \"/obj/machinery\""),
+];
+
+#[test]
+fn string_paths() {
+    let code = r##"
+/obj/item/proc/fire()
+
+/proc/test()
+    text2path("/obj/item")
+    text2path("[/obj]/item")
+    text2path("[/obj/item]/gun")
+    text2path("/obj/item/proc/fire")
+    text2path("/obj/item/proc/missing")
+    locate("/obj/item")
+    locate("/mob/living")
+    locate("tag_name")
+    ispath(/obj/item, "/obj/item")
+    ispath(/obj/item, "/obj/item/gun")
+    istype(usr, "/obj/item")
+    istype(usr, "/mob/living")
+    typesof("/obj/item", "/obj/machinery")
+"##.trim();
+    check_errors_match(code, TEXT2PATH_ERRORS);
+}
+
+#[test]
+fn string_path_type_inference() {
+    let code = r##"
+/obj/item/var/force = 1
+
+/proc/test()
+    var/path = text2path("/obj/item")
+    var/obj/item/I = new path
+    var/obj/item/J = locate("/obj/item")
+    return J.force + I.force
+"##.trim();
+    check_errors_match(code, NO_ERRORS);
+}
@@ -0,0 +1,81 @@
+extern crate dreamchecker as dc;
+extern crate dreammaker as dm;
+
+use dc::test_helpers::{check_errors_match_with_config, NO_ERRORS};
+use dm::config::Config;
+
+fn target(version: u32) -> Config {
+    Config::from_toml(&format!("target_version = {}", version)).unwrap()
+}
+
+pub const TARGET_513_ERRORS: &[(u32, u16, &str)] = &[
+    (6, 20, "the :: operator requires BYOND 515, but target_version is 513"),
+    (3, 6, "Splice() requires BYOND 514, but target_version is 513"),
+    (4, 13, "undefined var: \"TILE_MOVER\""),
+    (5, 13, "/particles requires BYOND 514, but target_version is 513"),
+    (6, 20, "timezone requires BYOND 514, but target_version is 513"),
+    (7, 13, "timezone requires BYOND 514, but target_version is 513"),
+];
+
+const TARGET_CODE: &str = r##"
+/client/proc/test()
+    var/list/L = list(1, 2, 3)
+    L.Splice(1, 2, 4)
+    var/x = TILE_MOVER
+    var/p = /particles
+    var/n = /client::timezone
+    var/t = timezone
+    var/c = clamp(x, 1, 2)
+    return list(p, n, t, c)
+"##;
+
+#[test]
+fn target_version_older() {
+    check_errors_match_with_config(TARGET_CODE.trim(), target(513), TARGET_513_ERRORS);
+}
+
+#[test]
+fn target_version_current() {
+    check_errors_match_with_config(TARGET_CODE.trim(), target(515), NO_ERRORS);
+}
+
+#[test]
+fn target_version_dm_version() {
+    let code = r##"
+#if DM_VERSION >= 514
+#error wrong DM_VERSION
+#endif
+/proc/test()
+    #pragma unused_var ignore
+    return nameof(/proc/test)
+"##.trim();
+    check_errors_match_with_config(code, target(513), &[
+        (5, 5, "#pragma requires BYOND 515, but target_version is 513"),
+        (6, 12, "nameof() requires BYOND 515, but target_version is 513"),
+    ]);
+}
+
+#[test]
+fn target_version_macros() {
+    let code = r##"
+#if DM_BUILD != 1542
+#error wrong DM_BUILD
+#endif
+#ifdef TILE_MOVER
+#error TILE_MOVER should not be defined
+#endif
+"##.trim();
+    check_errors_match_with_config(code, target(513), NO_ERRORS);
+}
+
+#[test]
+fn target_version_polyfill() {
+    let code = r##"
+/proc/Splice(list/L)
+    return L
+
+/client/proc/test()
+    return Splice(list())
+"##.trim();
+    check_errors_match_with_config(code, target(513), NO_ERRORS);
+}
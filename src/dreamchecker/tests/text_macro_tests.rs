@@ -0,0 +1,67 @@
+
+extern crate dreamchecker as dc;
+
+use dc::test_helpers::*;
+
+#[test]
+fn valid_macros() {
+    let code = r#"
+/obj/item
+    name = "\improper thing"
+
+/obj/item/proc/test(mob/user, count, list/L)
+    user << "[user] picks up \the [src]. \He drops \his [src]."
+    user << "You have [count] item\s and [L.len] list\s.\nThat's \a [name]! \ref[src] \icon[src]"
+    user << "\The [src] is the [count]\th \\the and \[not] [user]\..."
+"#.trim();
+    check_errors_match(code, NO_ERRORS);
+}
+
+#[test]
+fn missing_expressions() {
+    let code = r#"
+/obj/proc/test(mob/user, count)
+    user << "\the [src] breaks \his"
+    user << "\s [count] and \the"
+    user << "\the thing"
+"#.trim();
+    check_errors_match(code, &[
+        (2, 13, "text macro \\his has no embedded expression after it"),
+        (3, 13, "text macro \\s has no embedded expression before it"),
+        (3, 13, "text macro \\the has no embedded expression after it"),
+        (4, 13, "text macro \\the has no embedded expression after it"),
+    ]);
+}
+
+#[test]
+fn wrong_types() {
+    let code = r#"
+/datum/thing
+
+/obj/proc/test(mob/user, datum/thing/T, list/L)
+    user << "\the [T] and \he [L] and \his [5] and \roman[src] and \the ["text"]"
+    user << "[src]\s with \improper[src]"
+"#.trim();
+    check_errors_match(code, &[
+        (4, 13, "text macro \\the is applied to a datum which is not an atom"),
+        (4, 13, "text macro \\he is applied to a list"),
+        (4, 13, "text macro \\his is applied to a number"),
+        (4, 13, "text macro \\roman is applied to an atom"),
+        (5, 13, "text macro \\s is applied to an atom"),
+        (5, 13, "text macro \\improper has no effect except at the start of a string"),
+    ]);
+}
+
+#[test]
+fn unknown_escapes() {
+    let code = r#"
+/obj/proc/test(mob/user)
+    user << "[src] matches \d+ and \zap \\d"
+    user << "\d"
+"#.trim();
+    check_errors_match(code, &[
+        (2, 13, "unknown escape sequence \\d"),
+        (2, 13, "unknown escape sequence \\zap"),
+        (3, 13, "unknown escape sequence \\d"),
+    ]);
+}
@@ -0,0 +1,60 @@
+
+extern crate dreamchecker as dc;
+extern crate dreammaker as dm;
+
+use dc::test_helpers::*;
+use dm::config::Config;
+
+fn config(diagnostic: &str) -> Config {
+    Config::from_toml(&format!("[diagnostics]\n{} = \"hint\"\n", diagnostic)).unwrap()
+}
+
+pub const UNUSED_VAR_ERRORS: &[(u32, u16, &str)] = &[
+    (2, 15, "type var is never used: /obj/var/unused"),
+];
+
+#[test]
+fn unused_type_var() {
+    let code = r##"
+/obj
+    var/unused
+    var/used_by_field
+    var/used_by_ident
+    var/used_by_string
+    var/used_by_prefab
+    name = "overridden builtin"
+
+/obj/proc/test(obj/O)
+    O.used_by_field = used_by_ident
+    vars["used_by_string"] = 1
+    new /obj{used_by_prefab = 2}
+"##.trim();
+    check_errors_match_with_config(code, config("unused_type_var"), UNUSED_VAR_ERRORS);
+}
+
+#[test]
+fn unused_type_var_opt_in() {
+    let code = r##"
+/obj
+    var/unused
+"##.trim();
+    check_errors_match(code, NO_ERRORS);
+}
+
+pub const UNUSED_MACRO_ERRORS: &[(u32, u16, &str)] = &[
+    (1, 9, "macro is never used: UNUSED"),
+];
+
+#[test]
+fn unused_macro() {
+    let code = r##"
+#define UNUSED 1
+#define USED 2
+#define TESTED
+#ifdef TESTED
+/proc/test()
+    return USED
+#endif
+"##.trim();
+    check_errors_match_with_config(code, config("unused_macro"), UNUSED_MACRO_ERRORS);
+}
@@ -0,0 +1,102 @@
+
+extern crate dreamchecker as dc;
+
+use dc::test_helpers::*;
+
+#[test]
+fn set_src() {
+    let code = r#"
+/obj/verb/pick_up()
+    set src in oview(1)
+
+/obj/verb/use()
+    set src = usr.contents
+
+/obj/verb/examine()
+    set src in usr.client
+
+/obj/verb/wave()
+    set src in view(1, 2)
+
+/obj/verb/drop()
+    set name in "Drop"
+"#.trim();
+    check_errors_match(code, &[
+        (8, 5, "set src in must be usr, usr.contents, usr.loc, usr.group, world, or a view(), oview(), range(), or orange() call"),
+        (11, 5, "set src in must be usr, usr.contents, usr.loc, usr.group, world, or a view(), oview(), range(), or orange() call"),
+        (14, 5, "set name must use = rather than in"),
+    ]);
+}
+
+#[test]
+fn input_types() {
+    let code = r#"
+/mob/verb/give(obj/item/I as obj in usr, mob/living/M as mob|null, mob/N as obj, atom/movable/A as mob|obj)
+    return
+
+/mob/verb/say_to(mob/M as text, msg as text, obj/O as anything)
+    return
+
+/mob/proc/not_a_verb(mob/M as obj)
+    return
+
+/obj/item
+/mob/living
+"#.trim();
+    check_errors_match(code, &[
+        (1, 74, "verb argument N is declared as /mob but takes input as obj"),
+        (4, 24, "verb argument M is declared as /mob but takes input as text"),
+    ]);
+}
+
+#[test]
+fn duplicate_names() {
+    let code = r#"
+/mob/verb/look()
+    set category = "IC"
+
+/mob/living/verb/look_around()
+    set name = "Look"
+    set category = "IC"
+
+/mob/ghost/verb/look_around()
+    set name = "look"
+    set category = "IC"
+
+/mob/living/verb/look_ooc()
+    set name = "look"
+    set category = "OOC"
+
+/mob/living/look()
+    set name = "Look"
+"#.trim();
+    check_errors_match(code, &[
+        (4, 29, "verb /mob/living/proc/look_around has the same name \"Look\" in category \"IC\" as /mob/proc/look"),
+        (8, 28, "verb /mob/ghost/proc/look_around has the same name \"look\" in category \"IC\" as /mob/proc/look"),
+    ]);
+}
+
+#[test]
+fn procs_added_to_verbs() {
+    let code = r#"
+/client/proc/admin_tool()
+    set category = "Admin"
+
+/client/proc/helper()
+    return
+
+/mob/verb/emote()
+    return
+
+/client/proc/give_verbs(mob/M)
+    verbs += /client/proc/admin_tool
+    verbs += list(/client/proc/helper, /client/proc/admin_tool)
+    M.verbs |= /mob/verb/emote
+    verbs.Add(/client/proc/helper)
+    verbs -= /client/proc/helper
+"#.trim();
+    check_errors_match(code, &[
+        (12, 5, "/client/proc/helper is added to verbs but is not declared as a verb"),
+        (14, 5, "/client/proc/helper is added to verbs but is not declared as a verb"),
+    ]);
+}
@@ -0,0 +1,215 @@
+//! Validation of text macros like `\the` and `\s` in strings.
+//!
+//! Most text macros apply to the next embedded expression in the string, and
+//! `\s` and `\th` to the previous one. Each is checked against the static type
+//! or constant value of the expression it applies to.
+
+use dm::{Context, DMError, Location, Severity};
+use dm::objtree::ObjectTree;
+
+use crate::{Analysis, Assumption, StaticType};
+
+/// What a text macro expects of the expression it applies to.
+#[derive(Clone, Copy, PartialEq)]
+enum Expects {
+    /// Any value, as for `\ref`.
+    Anything,
+    /// An atom or text, as for `\the` and `\a`.
+    Name,
+    /// An atom, as for `\he` and `\his`.
+    Atom,
+    /// An atom or icon, as for `\icon`.
+    Icon,
+    /// A number, as for `\roman`.
+    Number,
+}
+
+/// Text macros which apply to the next embedded expression.
+const FOLLOWING: &[(&str, Expects)] = &[
+    ("the", Expects::Name),
+    ("a", Expects::Name),
+    ("an", Expects::Name),
+    ("he", Expects::Atom),
+    ("she", Expects::Atom),
+    ("his", Expects::Atom),
+    ("him", Expects::Atom),
+    ("himself", Expects::Atom),
+    ("herself", Expects::Atom),
+    ("hers", Expects::Atom),
+    ("icon", Expects::Icon),
+    ("ref", Expects::Anything),
+    ("roman", Expects::Number),
+];
+
+/// Text macros which apply to the previous embedded expression.
+const PRECEDING: &[&str] = &["s", "th"];
+
+/// Text macros which only have an effect at the start of a string.
+const LEADING: &[&str] = &["proper", "improper"];
+
+/// Escapes and formatting macros which apply to no expression.
+const OTHER: &[&str] = &[
+    "n", "t", "b", "i", "x",
+    "bold", "italic",
+    "red", "blue", "green", "black", "white", "yellow",
+];
+
+/// The kind of value an embedded expression has, as far as is known.
+#[derive(Clone, Copy, PartialEq)]
+enum Kind {
+    Unknown,
+    Text,
+    Number,
+    Atom,
+    Icon,
+    List,
+    Datum,
+}
+
+impl Kind {
+    fn of(objtree: &ObjectTree, analysis: &Analysis) -> Kind {
+        if analysis.aset.set.contains(&Assumption::IsText(true)) {
+            return Kind::Text;
+        }
+        if analysis.aset.set.contains(&Assumption::IsNum(true)) {
+            return Kind::Number;
+        }
+        match analysis.static_ty {
+            StaticType::None => Kind::Unknown,
+            StaticType::List { .. } => Kind::List,
+            StaticType::Type(ty) if ty.is_root() => Kind::Unknown,
+            StaticType::Type(ty) if ty.path == "/list" => Kind::List,
+            StaticType::Type(ty) if ty.path == "/icon" => Kind::Icon,
+            StaticType::Type(ty) if ty.is_subtype_of(&objtree.expect("/atom")) => Kind::Atom,
+            StaticType::Type(_) => Kind::Datum,
+        }
+    }
+
+    fn describe(self) -> &'static str {
+        match self {
+            Kind::Unknown => "unknown",
+            Kind::Text => "text",
+            Kind::Number => "a number",
+            Kind::Atom => "an atom",
+            Kind::Icon => "an icon",
+            Kind::List => "a list",
+            Kind::Datum => "a datum which is not an atom",
+        }
+    }
+}
+
+impl Expects {
+    fn accepts(self, kind: Kind) -> bool {
+        match (self, kind) {
+            (Expects::Anything, _) | (_, Kind::Unknown) => true,
+            (Expects::Name, kind) => kind == Kind::Atom || kind == Kind::Text,
+            (Expects::Atom, kind) => kind == Kind::Atom,
+            (Expects::Icon, kind) => kind == Kind::Atom || kind == Kind::Icon,
+            (Expects::Number, kind) => kind == Kind::Number,
+        }
+    }
+}
+
+/// A `\` sequence found in the text of a string.
+struct Escape<'a> {
+    /// The index of the text segment: 0 before the first embedded expression,
+    /// and `i + 1` after the `i`th.
+    segment: usize,
+    /// The byte offset within that segment.
+    offset: usize,
+    /// The letters after the `\`.
+    word: &'a str,
+}
+
+fn find_escapes<'a>(segments: &[&'a str]) -> Vec<Escape<'a>> {
+    let mut escapes = Vec::new();
+    for (segment, text) in segments.iter().enumerate() {
+        let mut chars = text.char_indices();
+        while let Some((offset, ch)) = chars.next() {
+            if ch != '\\' {
+                continue;
+            }
+            let rest = &text[offset + 1..];
+            let len = rest.find(|c: char| !c.is_ascii_alphabetic()).unwrap_or(rest.len());
+            if len == 0 {
+                // An escaped character, such as `\\` or `\[`.
+                chars.next();
+            } else {
+                escapes.push(Escape { segment, offset, word: &rest[..len] });
+            }
+        }
+    }
+    escapes
+}
+
+/// The known macro which a word after a `\` starts with, if any. DM ends a
+/// macro once it is complete, so `\nHello` is `\n` followed by `Hello`.
+fn known_macro(word: &str) -> Option<String> {
+    let word = word.to_lowercase();
+    FOLLOWING.iter().map(|&(name, _)| name)
+        .chain(PRECEDING.iter().copied())
+        .chain(LEADING.iter().copied())
+        .chain(OTHER.iter().copied())
+        .filter(|name| word.starts_with(name))
+        .max_by_key(|name| name.len())
+        .map(ToOwned::to_owned)
+}
+
+/// Check the text macros and escapes of a string, given the analysis of each
+/// embedded expression. Plain strings have no parts.
+pub(crate) fn check_text_macros(
+    context: &Context,
+    objtree: &ObjectTree,
+    location: Location,
+    first: &str,
+    parts: &[(Option<Analysis>, &str)],
+) {
+    let mut segments = vec![first];
+    segments.extend(parts.iter().map(|&(_, text)| text));
+
+    for escape in find_escapes(&segments) {
+        guard!(let Some(name) = known_macro(escape.word) else {
+            DMError::new(location, format!("unknown escape sequence \\{}", escape.word))
+                .with_component(dm::Component::DreamChecker)
+                .with_errortype("unknown_escape")
+                .set_severity(Severity::Warning)
+                .register(context);
+            continue;
+        });
+
+        if LEADING.contains(&name.as_str()) {
+            if escape.segment != 0 || escape.offset != 0 {
+                text_macro_error(context, location, format!("text macro \\{} has no effect except at the start of a string", name));
+            }
+            continue;
+        }
+
+        let (applies_to, expects, position) = if let Some(&(_, expects)) = FOLLOWING.iter().find(|&&(each, _)| each == name) {
+            (parts.get(escape.segment), expects, "after")
+        } else if PRECEDING.contains(&name.as_str()) {
+            (escape.segment.checked_sub(1).and_then(|i| parts.get(i)), Expects::Number, "before")
+        } else {
+            continue;
+        };
+
+        match applies_to {
+            None | Some((None, _)) => {
+                text_macro_error(context, location, format!("text macro \\{} has no embedded expression {} it", name, position));
+            }
+            Some((Some(analysis), _)) => {
+                let kind = Kind::of(objtree, analysis);
+                if !expects.accepts(kind) {
+                    text_macro_error(context, location, format!("text macro \\{} is applied to {}", name, kind.describe()));
+                }
+            }
+        }
+    }
+}
+
+fn text_macro_error(context: &Context, location: Location, message: String) {
+    DMError::new(location, message)
+        .with_component(dm::Component::DreamChecker)
+        .with_errortype("text_macro")
+        .set_severity(Severity::Warning)
+        .register(context);
+}
@@ -143,8 +143,8 @@ impl<'o> TypeExprCompiler<'o> {
                 if let Some(op) = unary.first() {
                     return Err(DMError::new(
                         location,
-                        format!("type expr: bad unary {}", op.name()),
-                    ));
+                        format!("type expr: bad unary {}", op.elem.name()),
+                    ).with_errortype("invalid_return_type"));
                 }
 
                 let mut ty = self.visit_term(term.location, &term.elem)?;
@@ -186,7 +186,7 @@ impl<'o> TypeExprCompiler<'o> {
                 if_: Box::new(self.visit_expression(location, if_)?),
                 else_: Box::new(self.visit_expression(location, else_)?),
             }),
-            _ => Err(DMError::new(location, "type expr: bad expression node")),
+            _ => Err(DMError::new(location, "type expr: bad expression node").with_errortype("invalid_return_type")),
         }
     }
 
@@ -207,7 +207,7 @@ impl<'o> TypeExprCompiler<'o> {
                 Err(DMError::new(
                     location,
                     format!("type expr: no such parameter {:?}", unscoped_name),
-                ))
+                ).with_errortype("invalid_return_type"))
             }
 
             Term::Expr(expr) => self.visit_expression(location, expr),
@@ -218,7 +218,7 @@ impl<'o> TypeExprCompiler<'o> {
                 Ok(TypeExpr::from(ty))
             }
 
-            _ => Err(DMError::new(location, "type expr: bad term node")),
+            _ => Err(DMError::new(location, "type expr: bad term node").with_errortype("invalid_return_type")),
         }
     }
 
@@ -244,12 +244,12 @@ impl<'o> TypeExprCompiler<'o> {
                     _ => Err(DMError::new(
                         location,
                         "type expr: cannot index non-parameters",
-                    )),
+                    ).with_errortype("invalid_return_type")),
                 },
                 _ => Err(DMError::new(
                     location,
                     "type expr: cannot index by anything but `_`",
-                )),
+                ).with_errortype("invalid_return_type")),
             },
 
             // X.type => static type of argument X
@@ -266,10 +266,10 @@ impl<'o> TypeExprCompiler<'o> {
                 _ => Err(DMError::new(
                     location,
                     "type expr: cannot take .type of non-parameters",
-                )),
+                ).with_errortype("invalid_return_type")),
             },
 
-            _ => Err(DMError::new(location, "type expr: bad follow node")),
+            _ => Err(DMError::new(location, "type expr: bad follow node").with_errortype("invalid_return_type")),
         }
     }
 }
@@ -0,0 +1,268 @@
+//! Whole-program detection of type vars which are never referenced.
+
+use std::collections::HashSet;
+
+use dm::{Context, DMError, Severity};
+use dm::ast::*;
+use dm::objtree::{Code, ObjectTree};
+
+use crate::maps::MapVarEdit;
+
+/// Report declared type vars whose names are never referenced by any proc
+/// body, var initializer, prefab, or map var edit.
+///
+/// References are matched by name only, so a var counts as used if any var
+/// of the same name is accessed anywhere.
+pub(crate) fn check_unused_vars(context: &Context, objtree: &ObjectTree, map_edits: &[MapVarEdit]) {
+    let mut names = NameCollector::default();
+    for edit in map_edits.iter() {
+        names.names.insert(edit.name.as_str());
+    }
+
+    objtree.root().recurse(&mut |ty| {
+        for var in ty.get().vars.values() {
+            if let Some(expr) = var.value.expression.as_ref() {
+                names.visit_expression(expr);
+            }
+        }
+        for proc in ty.iter_self_procs() {
+            let value = proc.get();
+            for param in value.parameters.iter() {
+                names.visit_opt_expression(param.default.as_ref());
+                names.visit_opt_expression(param.in_list.as_ref());
+            }
+            if let Code::Present(ref code) = value.code {
+                names.visit_block(code);
+            }
+        }
+    });
+
+    let mut unused = Vec::new();
+    objtree.root().recurse(&mut |ty| {
+        for (name, var) in ty.get().vars.iter() {
+            if let Some(decl) = var.declaration.as_ref() {
+                if !decl.location.is_builtins() && !names.names.contains(name.as_str()) {
+                    unused.push((decl.location, format!("{}/var/{}", ty.path, name)));
+                }
+            }
+        }
+    });
+    unused.sort();
+
+    for (location, path) in unused {
+        DMError::new(location, format!("type var is never used: {}", path))
+            .with_component(dm::Component::DreamChecker)
+            .with_errortype("unused_type_var")
+            .set_severity(Severity::Hint)
+            .register(context);
+    }
+}
+
+#[derive(Default)]
+struct NameCollector<'a> {
+    names: HashSet<&'a str>,
+}
+
+impl<'a> NameCollector<'a> {
+    fn visit_block(&mut self, block: &'a [Spanned<Statement>]) {
+        for stmt in block.iter() {
+            self.visit_statement(&stmt.elem);
+        }
+    }
+
+    fn visit_statement(&mut self, statement: &'a Statement) {
+        match statement {
+            Statement::Expr(expr) |
+            Statement::Throw(expr) |
+            Statement::Del(expr) => self.visit_expression(expr),
+            Statement::Return(expr) |
+            Statement::Crash(expr) => self.visit_opt_expression(expr.as_ref()),
+            Statement::While { condition, block } => {
+                self.visit_expression(condition);
+                self.visit_block(block);
+            },
+            Statement::DoWhile { block, condition } => {
+                self.visit_block(block);
+                self.visit_expression(&condition.elem);
+            },
+            Statement::If { arms, else_arm } => {
+                for (condition, block) in arms.iter() {
+                    self.visit_expression(&condition.elem);
+                    self.visit_block(block);
+                }
+                if let Some(else_arm) = else_arm {
+                    self.visit_block(else_arm);
+                }
+            },
+            Statement::ForInfinite { block } => self.visit_block(block),
+            Statement::ForLoop { init, test, inc, block } => {
+                if let Some(init) = init {
+                    self.visit_statement(init);
+                }
+                self.visit_opt_expression(test.as_deref());
+                if let Some(inc) = inc {
+                    self.visit_statement(inc);
+                }
+                self.visit_block(block);
+            },
+            Statement::ForList { name, in_list, block, .. } => {
+                // `for (x in ...)` without `var/` may loop over a type var
+                self.names.insert(name);
+                self.visit_opt_expression(in_list.as_deref());
+                self.visit_block(block);
+            },
+            Statement::ForRange { name, start, end, step, block, .. } => {
+                self.names.insert(name);
+                self.visit_expression(start);
+                self.visit_expression(end);
+                self.visit_opt_expression(step.as_deref());
+                self.visit_block(block);
+            },
+            Statement::Var(var) => self.visit_opt_expression(var.value.as_ref()),
+            Statement::Vars(vars) => {
+                for var in vars.iter() {
+                    self.visit_opt_expression(var.value.as_ref());
+                }
+            },
+            Statement::Setting { value, .. } => self.visit_expression(value),
+            Statement::Spawn { delay, block } => {
+                self.visit_opt_expression(delay.as_ref());
+                self.visit_block(block);
+            },
+            Statement::Switch { input, cases, default } => {
+                self.visit_expression(input);
+                for (case, block) in cases.iter() {
+                    for each in case.elem.iter() {
+                        match each {
+                            Case::Exact(expr) => self.visit_expression(expr),
+                            Case::Range(start, end) => {
+                                self.visit_expression(start);
+                                self.visit_expression(end);
+                            },
+                        }
+                    }
+                    self.visit_block(block);
+                }
+                if let Some(default) = default {
+                    self.visit_block(default);
+                }
+            },
+            Statement::TryCatch { try_block, catch_block, .. } => {
+                self.visit_block(try_block);
+                self.visit_block(catch_block);
+            },
+            Statement::Label { block, .. } => self.visit_block(block),
+            Statement::Continue(_) |
+            Statement::Break(_) |
+            Statement::Goto(_) => {},
+        }
+    }
+
+    fn visit_opt_expression(&mut self, expression: Option<&'a Expression>) {
+        if let Some(expression) = expression {
+            self.visit_expression(expression);
+        }
+    }
+
+    fn visit_expressions(&mut self, expressions: &'a [Expression]) {
+        for each in expressions.iter() {
+            self.visit_expression(each);
+        }
+    }
+
+    fn visit_expression(&mut self, expression: &'a Expression) {
+        match expression {
+            Expression::Base { term, follow, .. } => {
+                self.visit_term(&term.elem);
+                for each in follow.iter() {
+                    match &each.elem {
+                        Follow::Index(_, expr) => self.visit_expression(expr),
+                        Follow::Field(_, name) |
+                        Follow::StaticField(name) => {
+                            self.names.insert(name);
+                        },
+                        Follow::ProcReference(..) => {},
+                        Follow::Call(_, _, args) => self.visit_expressions(args),
+                    }
+                }
+            },
+            Expression::BinaryOp { lhs, rhs, .. } |
+            Expression::AssignOp { lhs, rhs, .. } => {
+                self.visit_expression(lhs);
+                self.visit_expression(rhs);
+            },
+            Expression::TernaryOp { cond, if_, else_ } => {
+                self.visit_expression(cond);
+                self.visit_expression(if_);
+                self.visit_expression(else_);
+            },
+        }
+    }
+
+    fn visit_term(&mut self, term: &'a Term) {
+        match term {
+            Term::Ident(name) |
+            Term::GlobalIdent(name) => {
+                self.names.insert(name);
+            },
+            // Strings may name vars for `vars[...]` and similar lookups.
+            Term::String(text) => {
+                self.names.insert(text);
+            },
+            Term::Expr(expr) => self.visit_expression(expr),
+            Term::Prefab(prefab) => self.visit_prefab(prefab),
+            Term::InterpString(_, parts) => {
+                for (expr, _) in parts.iter() {
+                    self.visit_opt_expression(expr.as_ref());
+                }
+            },
+            Term::Call(_, args) |
+            Term::GlobalCall(_, args) |
+            Term::SelfCall(args) |
+            Term::ParentCall(args) |
+            Term::List(args) => self.visit_expressions(args),
+            Term::New { type_, args } => {
+                match type_ {
+                    NewType::Implicit => {},
+                    NewType::Prefab(prefab) => self.visit_prefab(prefab),
+                    NewType::MiniExpr { ident, fields } => {
+                        self.names.insert(ident);
+                        for field in fields.iter() {
+                            self.names.insert(&field.ident);
+                        }
+                    },
+                }
+                if let Some(args) = args {
+                    self.visit_expressions(args);
+                }
+            },
+            Term::Input { args, in_list, .. } |
+            Term::Locate { args, in_list } => {
+                self.visit_expressions(args);
+                self.visit_opt_expression(in_list.as_deref());
+            },
+            Term::Pick(choices) => {
+                for (weight, choice) in choices.iter() {
+                    self.visit_opt_expression(weight.as_ref());
+                    self.visit_expression(choice);
+                }
+            },
+            Term::DynamicCall(lhs_args, rhs_args) => {
+                self.visit_expressions(lhs_args);
+                self.visit_expressions(rhs_args);
+            },
+            Term::Null |
+            Term::Int(_) |
+            Term::Float(_) |
+            Term::Resource(_) |
+            Term::As(_) => {},
+        }
+    }
+
+    fn visit_prefab(&mut self, prefab: &'a Prefab) {
+        for (name, value) in prefab.vars.iter() {
+            self.names.insert(name);
+            self.visit_expression(value);
+        }
+    }
+}
@@ -0,0 +1,185 @@
+//! Checks specific to verbs and the procs added to `verbs` lists.
+
+use std::collections::{BTreeMap, HashSet};
+
+use dm::{Context, DMError, Location, Severity};
+use dm::ast::*;
+use dm::objtree::{ObjectTree, ProcRef};
+
+/// Settings which only mean anything on verbs. A proc which sets one of them
+/// is taken to be used as a verb.
+pub(crate) const VERB_SETTINGS: &[&str] = &[
+    "name",
+    "desc",
+    "category",
+    "src",
+    "hidden",
+    "popup_menu",
+    "instant",
+    "invisibility",
+];
+
+/// Whether a proc is declared with `verb/`.
+pub(crate) fn is_declared_verb(proc: ProcRef) -> bool {
+    matches!(proc.get_declaration(), Some(decl) if decl.kind == ProcDeclKind::Verb)
+}
+
+/// Whether `set src` has one of the forms DM accepts, like `usr`,
+/// `usr.contents`, `world`, or `view(1)`.
+pub(crate) fn is_valid_src(expr: &Expression) -> bool {
+    match expr {
+        Expression::Base { unary, term, follow } if unary.is_empty() => match (&term.elem, &follow[..]) {
+            (Term::Expr(inner), []) => is_valid_src(inner),
+            (Term::Ident(name), []) => name == "usr" || name == "world",
+            (Term::Ident(name), [field]) if name == "usr" => match &field.elem {
+                Follow::Field(_, field) => matches!(field.as_str(), "contents" | "loc" | "group"),
+                _ => false,
+            },
+            (Term::Call(name, args), []) => {
+                matches!(name.as_str(), "view" | "oview" | "range" | "orange") && args.len() <= 1
+            },
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+/// Report verb parameters whose `as` input type can't produce a value of
+/// their declared type, like `mob/M as obj` or `obj/O as text`.
+pub(crate) fn check_input_types(context: &Context, objtree: &ObjectTree, proc: ProcRef) {
+    for param in proc.get().parameters.iter() {
+        guard!(let Some(input_type) = param.input_type else { continue });
+        if param.var_type.type_path.is_empty() || input_type.contains(InputType::ANYTHING) {
+            continue;
+        }
+        guard!(let Some(declared) = objtree.type_by_path(&param.var_type.type_path) else { continue });
+
+        let mut mismatched = InputType::empty();
+        for &(flag, path) in INPUT_TYPE_PATHS.iter() {
+            if !input_type.contains(flag) {
+                continue;
+            }
+            let matches = match path.and_then(|path| objtree.find(path)) {
+                Some(ty) => ty.is_subtype_of(&declared) || declared.is_subtype_of(&ty),
+                None => false,
+            };
+            if !matches {
+                mismatched |= flag;
+            }
+        }
+        if !mismatched.is_empty() {
+            DMError::new(param.location, format!("verb argument {} is declared as {} but takes input as {}", param.name, declared, mismatched))
+                .with_component(dm::Component::DreamChecker)
+                .with_errortype("verb_input_type")
+                .set_severity(Severity::Warning)
+                .register(context);
+        }
+    }
+}
+
+/// The type each input type produces, if any.
+const INPUT_TYPE_PATHS: &[(InputType, Option<&str>)] = &[
+    (InputType::MOB, Some("/mob")),
+    (InputType::OBJ, Some("/obj")),
+    (InputType::TURF, Some("/turf")),
+    (InputType::AREA, Some("/area")),
+    (InputType::ICON, Some("/icon")),
+    (InputType::SOUND, Some("/sound")),
+    (InputType::TEXT, None),
+    (InputType::NUM, None),
+    (InputType::FILE, None),
+    (InputType::KEY, None),
+    (InputType::MESSAGE, None),
+    (InputType::PASSWORD, None),
+    (InputType::COMMAND_TEXT, None),
+    (InputType::COLOR, None),
+];
+
+/// The name and category under which a verb is shown.
+pub(crate) struct VerbName<'o> {
+    pub proc: ProcRef<'o>,
+    pub name: String,
+    pub category: String,
+}
+
+impl<'o> VerbName<'o> {
+    /// The displayed name of a verb, from `set name` if it is constant and
+    /// otherwise from the proc's name with underscores as spaces.
+    pub fn new(proc: ProcRef<'o>, name: Option<&str>, category: Option<&str>) -> VerbName<'o> {
+        VerbName {
+            proc,
+            name: name.map_or_else(|| proc.name().replace('_', " "), ToOwned::to_owned),
+            category: category.unwrap_or_default().to_owned(),
+        }
+    }
+}
+
+/// Report verbs which share a name and category with another verb on the
+/// same type, its parents, or its subtypes, as only one of them can be used.
+pub(crate) fn check_duplicate_names(context: &Context, verbs: &[VerbName]) {
+    let mut groups: BTreeMap<(String, String), Vec<&VerbName>> = BTreeMap::new();
+    for verb in verbs.iter() {
+        groups.entry((verb.category.to_lowercase(), verb.name.to_lowercase())).or_default().push(verb);
+    }
+
+    for group in groups.values() {
+        let mut group = group.clone();
+        group.sort_by_key(|verb| verb.proc.get().location);
+        for (i, verb) in group.iter().enumerate() {
+            let ty = verb.proc.ty();
+            let earlier = group[..i].iter().find(|other| {
+                let other_ty = other.proc.ty();
+                ty.is_subtype_of(&other_ty) || other_ty.is_subtype_of(&ty)
+            });
+            if let Some(other) = earlier {
+                let in_category = if verb.category.is_empty() {
+                    String::new()
+                } else {
+                    format!(" in category {:?}", verb.category)
+                };
+                DMError::new(verb.proc.get().location, format!("verb {} has the same name {:?}{} as {}", verb.proc, verb.name, in_category, other.proc))
+                    .with_component(dm::Component::DreamChecker)
+                    .with_note(other.proc.get().location, format!("{} is defined here", other.proc))
+                    .with_errortype("duplicate_verb_name")
+                    .set_severity(Severity::Warning)
+                    .register(context);
+            }
+        }
+    }
+}
+
+/// The procs named by the right-hand side of an addition to a `verbs` list,
+/// as in `verbs += /mob/proc/x` or `verbs += list(/mob/proc/x, /mob/proc/y)`.
+pub(crate) fn added_procs(rhs: &Expression) -> Vec<&Prefab> {
+    match rhs.as_term() {
+        Some(Term::Prefab(prefab)) => vec![prefab],
+        Some(Term::List(args)) => args.iter().flat_map(added_procs).collect(),
+        Some(Term::Expr(inner)) => added_procs(inner),
+        _ => Vec::new(),
+    }
+}
+
+/// Whether an expression is a `verbs` var, as in `verbs` or `src.verbs`.
+pub(crate) fn is_verbs_list(term: &Term, follow: &[Spanned<Follow>]) -> bool {
+    match follow.last() {
+        None => matches!(term, Term::Ident(name) if name == "verbs"),
+        Some(each) => matches!(&each.elem, Follow::Field(_, name) if name == "verbs"),
+    }
+}
+
+/// Report a proc which is added to a `verbs` list, but neither it nor a
+/// parent is declared as a verb or sets any verb settings.
+pub(crate) fn check_added_proc<'o>(context: &Context, location: Location, proc: ProcRef<'o>, verb_like: &HashSet<ProcRef<'o>>) {
+    let mut current = Some(proc);
+    while let Some(each) = current {
+        if is_declared_verb(each) || verb_like.contains(&each) {
+            return;
+        }
+        current = each.parent_proc();
+    }
+    DMError::new(location, format!("{} is added to verbs but is not declared as a verb", proc))
+        .with_component(dm::Component::DreamChecker)
+        .with_errortype("proc_added_to_verbs")
+        .set_severity(Severity::Warning)
+        .register(context);
+}
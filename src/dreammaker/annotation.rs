@@ -23,6 +23,8 @@ pub enum Annotation {
     UnscopedVar(Ident),
     ScopedCall(Vec<Ident>, Ident),
     ScopedVar(Vec<Ident>, Ident),
+    StaticVar(TypePath, Ident),  // /type::var
+    StaticProc(TypePath, Ident),  // /type::proc/name or /type::name()
     ParentCall,  // ..
     ReturnVal,  // .
     InSequence(usize),  // where in TreePath or TypePath is this ident
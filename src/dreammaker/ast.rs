@@ -1,17 +1,22 @@
 //! The DM abstract syntax tree.
 //!
 //! Most AST types can be pretty-printed using the `Display` trait.
+//! Expressions and statements print as DM code which parses back to the same
+//! tree.
 use std::fmt;
 use std::iter::FromIterator;
 use phf::phf_map;
 
 use linked_hash_map::LinkedHashMap;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 use crate::error::Location;
+use crate::lexer::FormatFloat;
 
-#[derive(Copy, Clone, Eq, Debug)]
+#[derive(Copy, Clone, Eq, Debug, Serialize, Deserialize)]
 pub struct Spanned<T> {
     // TODO: add a Span type and use it here
+    #[serde(with = "crate::preprocess_cache::location")]
     pub location: Location,
     pub elem: T,
 }
@@ -30,7 +35,7 @@ impl<T> Spanned<T> {
 }
 
 /// The unary operators, both prefix and postfix.
-#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub enum UnaryOp {
     Neg,
     Not,
@@ -87,7 +92,7 @@ pub type Ident = String;
 /// The DM path operators.
 ///
 /// Which path operator is used typically only matters at the start of a path.
-#[derive(Copy, Clone, Hash, PartialEq, Eq, Debug)]
+#[derive(Copy, Clone, Hash, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub enum PathOp {
     /// `/` for absolute pathing.
     Slash,
@@ -142,7 +147,7 @@ impl<'a> fmt::Display for FormatTypePath<'a> {
 }
 
 /// The binary operators.
-#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub enum BinaryOp {
     Add,
     Sub,
@@ -201,7 +206,7 @@ impl fmt::Display for BinaryOp {
 }
 
 /// The assignment operators, including augmented assignment.
-#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub enum AssignOp {
     Assign,
     AddAssign,
@@ -242,7 +247,7 @@ impl fmt::Display for AssignOp {
 }
 
 /// The ternary operator, represented uniformly for convenience.
-#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub enum TernaryOp {
     Conditional,
 }
@@ -283,9 +288,10 @@ augmented! {
 }
 
 /// A typepath optionally followed by a set of variables.
-#[derive(Clone, PartialEq, Debug)]
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub struct Prefab {
     pub path: TypePath,
+    #[serde(with = "ordered_map")]
     pub vars: LinkedHashMap<Ident, Expression>,
 }
 
@@ -298,6 +304,12 @@ impl From<TypePath> for Prefab {
     }
 }
 
+impl fmt::Display for Prefab {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", FormatTypePath(&self.path), FormatVars(&self.vars))
+    }
+}
+
 /// Formatting helper for variable arrays.
 pub struct FormatVars<'a, E>(pub &'a LinkedHashMap<Ident, E>);
 
@@ -319,8 +331,21 @@ impl<'a, E: fmt::Display> fmt::Display for FormatVars<'a, E> {
     }
 }
 
+/// Serializes a `LinkedHashMap` as a list of pairs, keeping its order.
+mod ordered_map {
+    use super::*;
+
+    pub fn serialize<V: Serialize, S: Serializer>(map: &LinkedHashMap<Ident, V>, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(map.iter())
+    }
+
+    pub fn deserialize<'de, V: Deserialize<'de>, D: Deserializer<'de>>(deserializer: D) -> Result<LinkedHashMap<Ident, V>, D::Error> {
+        Ok(Vec::<(Ident, V)>::deserialize(deserializer)?.into_iter().collect())
+    }
+}
+
 /// The different forms of the `new` command.
-#[derive(Clone, PartialEq, Debug)]
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub enum NewType {
     /// Implicit type, taken from context.
     Implicit,
@@ -334,13 +359,13 @@ pub enum NewType {
 }
 
 /// The structure of an expression, a tree of terms and operators.
-#[derive(Clone, PartialEq, Debug)]
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub enum Expression {
     /// An expression containing a term directly. The term is evaluated first,
     /// then its follows, then its unary operators in reverse order.
     Base {
         /// The unary operations applied to this value, in reverse order.
-        unary: Vec<UnaryOp>,
+        unary: Vec<Spanned<UnaryOp>>,
         /// The term of the expression.
         term: Box<Spanned<Term>>,
         /// The follow operations applied to this value.
@@ -399,6 +424,31 @@ impl Expression {
         }
     }
 
+    /// The name `nameof()` produces for this expression: the last var or
+    /// proc name in an access like `src.health` or `/obj::proc/use`, or the
+    /// last part of a type path.
+    pub fn nameof(&self) -> Option<&str> {
+        match self {
+            Expression::Base { unary, term, follow } if unary.is_empty() => match follow.last() {
+                Some(each) => match &each.elem {
+                    Follow::Field(_, name) |
+                    Follow::StaticField(name) |
+                    Follow::ProcReference(_, name) => Some(name),
+                    _ => None,
+                },
+                None => match &term.elem {
+                    Term::Ident(name) | Term::GlobalIdent(name) => Some(name),
+                    Term::Prefab(prefab) if prefab.vars.is_empty() => {
+                        prefab.path.last().map(|(_, name)| name.as_str())
+                    },
+                    Term::Expr(expr) => expr.nameof(),
+                    _ => None,
+                },
+            },
+            _ => None,
+        }
+    }
+
     pub fn is_const_eval(&self) -> bool {
         match self {
             Expression::BinaryOp { op, lhs, rhs } => {
@@ -438,7 +488,7 @@ impl Expression {
                 });
                 let mut negation = false;
                 for u in unary {
-                    if let UnaryOp::Not = u {
+                    if let UnaryOp::Not = u.elem {
                         negation = !negation;
                     }
                 }
@@ -499,7 +549,7 @@ impl From<Term> for Expression {
 }
 
 /// The structure of a term, the basic building block of the AST.
-#[derive(Clone, PartialEq, Debug)]
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub enum Term {
     // Terms with no recursive contents ---------------------------------------
     /// The literal `null`.
@@ -507,9 +557,14 @@ pub enum Term {
     /// An integer literal.
     Int(i32),
     /// A floating-point literal.
-    Float(f32),
+    Float(
+        #[serde(serialize_with = "crate::json::serialize_float", deserialize_with = "crate::json::deserialize_float")]
+        f32
+    ),
     /// An identifier.
     Ident(Ident),
+    /// A global var, as in `::x`.
+    GlobalIdent(Ident),
     /// A string literal.
     String(String),
     /// A resource literal.
@@ -528,6 +583,8 @@ pub enum Term {
     // Function calls with recursive contents ---------------------------------
     /// An unscoped function call.
     Call(Ident, Vec<Expression>),
+    /// A global proc call, as in `::x()`.
+    GlobalCall(Ident, Vec<Expression>),
     /// A `.()` call.
     SelfCall(Vec<Expression>),
     /// A `..()` call. If arguments is empty, the proc's arguments are passed.
@@ -640,7 +697,7 @@ impl From<Expression> for Term {
 }
 
 /// The possible kinds of access operators for lists
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub enum ListAccessKind {
     /// `[]`
     Normal,
@@ -649,7 +706,7 @@ pub enum ListAccessKind {
 }
 
 /// The possible kinds of index operators, for both fields and methods.
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub enum PropertyAccessKind {
     /// `a.b`
     Dot,
@@ -659,6 +716,8 @@ pub enum PropertyAccessKind {
     SafeDot,
     /// `a?:b`
     SafeColon,
+    /// `a::b()`
+    Scope,
 }
 
 impl PropertyAccessKind {
@@ -668,6 +727,7 @@ impl PropertyAccessKind {
             PropertyAccessKind::Colon => ":",
             PropertyAccessKind::SafeDot => "?.",
             PropertyAccessKind::SafeColon => "?:",
+            PropertyAccessKind::Scope => "::",
         }
     }
 }
@@ -679,7 +739,7 @@ impl fmt::Display for PropertyAccessKind {
 }
 
 /// An expression part which is applied to a term or another follow.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Follow {
     /// Index the value by an expression.
     Index(ListAccessKind, Box<Expression>),
@@ -687,10 +747,15 @@ pub enum Follow {
     Field(PropertyAccessKind, Ident),
     /// Call a method of the value.
     Call(PropertyAccessKind, Ident, Vec<Expression>),
+    /// Read the initial or static value of a var on the value's type, as in
+    /// `/obj/item::name` or `src::health`.
+    StaticField(Ident),
+    /// Reference a proc on the value's type, as in `/datum::proc/foo`.
+    ProcReference(ProcDeclKind, Ident),
 }
 
 /// Like a `Follow` but only supports field accesses.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Field {
     pub kind: PropertyAccessKind,
     pub ident: Ident,
@@ -707,7 +772,7 @@ impl From<Field> for Follow {
 /// DM requires referencing proc paths to include whether the target is
 /// declared as a proc or verb, even though the two modes are functionally
 /// identical in many other respects.
-#[derive(Debug, Clone, PartialEq, Eq, Copy, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Copy, Hash, Serialize, Deserialize)]
 pub enum ProcDeclKind {
     Proc,
     Verb,
@@ -744,13 +809,14 @@ impl fmt::Display for ProcDeclKind {
 }
 
 /// A parameter declaration in the header of a proc.
-#[derive(Debug, Clone, PartialEq, Default)]
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
 pub struct Parameter {
     pub var_type: VarType,
     pub name: Ident,
     pub default: Option<Expression>,
     pub input_type: Option<InputType>,
     pub in_list: Option<Expression>,
+    #[serde(with = "crate::preprocess_cache::location")]
     pub location: Location,
 }
 
@@ -785,6 +851,18 @@ macro_rules! type_table {
             }
         }
 
+        impl Serialize for $name {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                self.bits().serialize(serializer)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                Ok($name::from_bits_truncate(u32::deserialize(deserializer)?))
+            }
+        }
+
         impl fmt::Display for $name {
             fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
                 let mut first = true;
@@ -841,6 +919,18 @@ bitflags! {
     }
 }
 
+impl Serialize for VarTypeFlags {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.bits().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for VarTypeFlags {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(VarTypeFlags::from_bits_truncate(u8::deserialize(deserializer)?))
+    }
+}
+
 impl VarTypeFlags {
     pub fn from_name(name: &str) -> Option<VarTypeFlags> {
         match name {
@@ -934,7 +1024,7 @@ impl fmt::Display for VarTypeFlags {
 }
 
 /// A type which may be ascribed to a `var`.
-#[derive(Debug, Clone, PartialEq, Default)]
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
 pub struct VarType {
     pub flags: VarTypeFlags,
     pub type_path: TreePath,
@@ -1020,7 +1110,7 @@ impl VarSuffix {
 pub type Block = Box<[Spanned<Statement>]>;
 
 /// A statement in a proc body.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Statement {
     Expr(Expression),
     Return(Option<Expression>),
@@ -1095,14 +1185,14 @@ pub enum Statement {
     Crash(Option<Expression>),
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct VarStatement {
     pub var_type: VarType,
     pub name: Ident,
     pub value: Option<Expression>,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum SettingMode {
     /// As in `set name = "Use"`.
     Assign,
@@ -1126,12 +1216,658 @@ impl fmt::Display for SettingMode {
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Case {
     Exact(Expression),
     Range(Expression, Expression),
 }
 
+// ----------------------------------------------------------------------------
+// Pretty-printing
+
+// Parentheses written in the source are kept in the tree as `Term::Expr`, so
+// the printer only adds them where a synthesized tree would otherwise parse
+// with a different shape.
+
+const CONDITIONAL_STRENGTH: u8 = 12;
+const ASSIGN_STRENGTH: u8 = 13;
+const IN_STRENGTH: u8 = 14;
+
+/// How loosely an expression's outermost operator binds, following the
+/// parser's precedence table. Terms bind tightest at zero.
+fn strength(expr: &Expression) -> u8 {
+    use self::BinaryOp::*;
+    match expr {
+        Expression::Base { .. } => 0,
+        Expression::BinaryOp { op, .. } => match op {
+            Pow => 1,
+            Mul | Div | Mod => 2,
+            Add | Sub => 3,
+            Less | Greater | LessEq | GreaterEq => 4,
+            LShift | RShift => 5,
+            Eq | NotEq | Equiv | NotEquiv => 6,
+            BitAnd => 7,
+            BitXor => 8,
+            BitOr => 9,
+            And => 10,
+            Or => 11,
+            In | To => IN_STRENGTH,
+        },
+        Expression::TernaryOp { .. } => CONDITIONAL_STRENGTH,
+        Expression::AssignOp { .. } => ASSIGN_STRENGTH,
+    }
+}
+
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt_expression(f, self, false)
+    }
+}
+
+/// Write an expression. `in_ternary` is set within the arms of a conditional,
+/// where the parser reads `:` differently.
+fn fmt_expression(f: &mut fmt::Formatter, expr: &Expression, in_ternary: bool) -> fmt::Result {
+    match expr {
+        Expression::Base { unary, term, follow } => fmt_base(f, unary, &term.elem, follow, in_ternary),
+        // `in` followed by `to` is a range, and `to` reads the rest of the
+        // expression as its right-hand side.
+        Expression::BinaryOp { op: BinaryOp::In, lhs, rhs } if matches!(**rhs, Expression::BinaryOp { op: BinaryOp::To, .. }) => {
+            fmt_operand(f, lhs, ends_with_range(lhs), in_ternary)?;
+            f.write_str(" in ")?;
+            fmt_expression(f, rhs, in_ternary)
+        }
+        Expression::BinaryOp { op: BinaryOp::To, lhs, rhs } => {
+            fmt_operand(f, lhs, strength(lhs) >= IN_STRENGTH || ends_with_range(lhs), in_ternary)?;
+            f.write_str(" to ")?;
+            fmt_expression(f, rhs, in_ternary)
+        }
+        Expression::BinaryOp { op, lhs, rhs } => {
+            let own = strength(expr);
+            let parens = strength(lhs) > own
+                || ends_with_range(lhs)
+                || (*op == BinaryOp::Div && ends_with_path(lhs));
+            fmt_operand(f, lhs, parens, in_ternary)?;
+            write!(f, " {} ", op)?;
+            fmt_operand(f, rhs, strength(rhs) >= own, in_ternary)
+        }
+        // Assignment is the only right-associative operator.
+        Expression::AssignOp { op, lhs, rhs } => {
+            fmt_operand(f, lhs, strength(lhs) >= ASSIGN_STRENGTH || ends_with_range(lhs), in_ternary)?;
+            write!(f, " {} ", op)?;
+            fmt_operand(f, rhs, strength(rhs) > ASSIGN_STRENGTH, in_ternary)
+        }
+        // A conditional in either arm needs no parentheses, but one in the
+        // condition does.
+        Expression::TernaryOp { cond, if_, else_ } => {
+            fmt_operand(f, cond, strength(cond) >= CONDITIONAL_STRENGTH || ends_with_range(cond), in_ternary)?;
+            f.write_str(" ? ")?;
+            fmt_operand(f, if_, strength(if_) > CONDITIONAL_STRENGTH || ends_with_range(if_), true)?;
+            f.write_str(" : ")?;
+            fmt_operand(f, else_, strength(else_) > CONDITIONAL_STRENGTH, true)
+        }
+    }
+}
+
+fn fmt_operand(f: &mut fmt::Formatter, expr: &Expression, parens: bool, in_ternary: bool) -> fmt::Result {
+    if parens {
+        write!(f, "({})", expr)
+    } else {
+        fmt_expression(f, expr, in_ternary)
+    }
+}
+
+fn fmt_base(
+    f: &mut fmt::Formatter,
+    unary: &[Spanned<UnaryOp>],
+    term: &Term,
+    follow: &[Spanned<Follow>],
+    in_ternary: bool,
+) -> fmt::Result {
+    if in_ternary && !colons_are_fields(term, follow) {
+        f.write_str("(")?;
+        fmt_base(f, unary, term, follow, false)?;
+        return f.write_str(")");
+    }
+
+    let is_postfix = |op: &&Spanned<UnaryOp>| matches!(op.elem, UnaryOp::PostIncr | UnaryOp::PostDecr);
+    let mut previous: Option<&str> = None;
+    for op in unary.iter().filter(|op| !is_postfix(op)).map(|op| op.elem) {
+        // Keep `- -x` from lexing as `--x`, and `~ !x` as `~!x`.
+        if let Some(previous) = previous {
+            if tokens_merge(previous, op.name()) {
+                f.write_str(" ")?;
+            }
+        }
+        f.write_str(op.name())?;
+        previous = Some(op.name());
+    }
+    let negative = match *term {
+        Term::Int(i) => i < 0,
+        Term::Float(n) => n.is_sign_negative(),
+        _ => false,
+    };
+    if negative && matches!(previous, Some(previous) if previous.ends_with('-')) {
+        f.write_str(" ")?;
+    }
+
+    let absorbs_follow = match follow.first().map(|each| &each.elem) {
+        Some(Follow::Field(kind, _)) | Some(Follow::Call(kind, _, _)) => match kind {
+            PropertyAccessKind::Dot => extends_path(term) || matches!(term, Term::Int(_) | Term::Float(_)),
+            // The lexer only reads `:` as property access directly after an
+            // identifier or closing bracket.
+            PropertyAccessKind::Colon => extends_path(term) || matches!(term,
+                Term::Int(_)
+                | Term::Float(_)
+                | Term::String(_)
+                | Term::Resource(_)
+                | Term::InterpString(..)
+                | Term::Prefab(_)
+                | Term::New { type_: NewType::Prefab(_), args: None }
+            ),
+            _ => false,
+        },
+        _ => false,
+    };
+    if absorbs_follow {
+        write!(f, "({})", term)?;
+    } else {
+        write!(f, "{}", term)?;
+    }
+
+    for each in follow.iter() {
+        write!(f, "{}", each.elem)?;
+    }
+    for op in unary.iter().filter(is_postfix) {
+        f.write_str(op.elem.name())?;
+    }
+    Ok(())
+}
+
+/// Whether a term would absorb a `.`, `:`, or `/` written directly after it,
+/// as the path of a prefab or `new` would.
+fn extends_path(term: &Term) -> bool {
+    match term {
+        Term::Prefab(prefab) => prefab.vars.is_empty(),
+        Term::New { type_: NewType::Prefab(prefab), args: None } => prefab.vars.is_empty(),
+        Term::New { args: None, .. } => true,
+        _ => false,
+    }
+}
+
+/// Whether an expression ends in a `to` range, which would read anything
+/// written after it as part of the range.
+fn ends_with_range(expr: &Expression) -> bool {
+    match expr {
+        Expression::Base { .. } => false,
+        Expression::BinaryOp { op: BinaryOp::To, .. } => true,
+        Expression::BinaryOp { rhs, .. } | Expression::AssignOp { rhs, .. } => ends_with_range(rhs),
+        Expression::TernaryOp { else_, .. } => ends_with_range(else_),
+    }
+}
+
+/// Whether an expression is written ending in a term which extends a path.
+fn ends_with_path(expr: &Expression) -> bool {
+    match expr {
+        Expression::Base { unary, term, follow } => {
+            follow.is_empty()
+                && !unary.iter().any(|op| matches!(op.elem, UnaryOp::PostIncr | UnaryOp::PostDecr))
+                && extends_path(&term.elem)
+        }
+        Expression::BinaryOp { rhs, .. } | Expression::AssignOp { rhs, .. } => ends_with_path(rhs),
+        Expression::TernaryOp { else_, .. } => ends_with_path(else_),
+    }
+}
+
+fn tokens_merge(first: &str, second: &str) -> bool {
+    matches!(
+        (first.as_bytes().last(), second.as_bytes().first()),
+        (Some(b'-'), Some(b'-')) | (Some(b'+'), Some(b'+')) | (Some(b'~'), Some(b'!'))
+    )
+}
+
+/// Whether every `:` access in a base expression would still be read as one
+/// in the arms of a conditional, where it must follow a chain of vars.
+fn colons_are_fields(term: &Term, follow: &[Spanned<Follow>]) -> bool {
+    let mut vars = match term {
+        Term::Ident(name) => name != ".",
+        Term::GlobalIdent(_) => true,
+        _ => false,
+    };
+    for each in follow.iter() {
+        match each.elem {
+            Follow::Field(kind, _) if kind == PropertyAccessKind::Colon && !vars => return false,
+            Follow::Call(kind, _, _) if kind == PropertyAccessKind::Colon && !vars => return false,
+            Follow::Field(..) | Follow::StaticField(_) => {}
+            _ => vars = false,
+        }
+    }
+    true
+}
+
+/// Formatting helper for comma-separated arguments.
+struct FormatArgs<'a>(&'a [Expression]);
+
+impl<'a> fmt::Display for FormatArgs<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, arg) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            arg.fmt(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Term {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Term::Null => f.write_str("null"),
+            Term::Int(i) => write!(f, "{}", i),
+            // Debug formatting round-trips exactly and always looks like a float.
+            Term::Float(n) if n.is_finite() => write!(f, "{:?}", n),
+            Term::Float(n) => FormatFloat(*n).fmt(f),
+            Term::Ident(name) => f.write_str(name),
+            Term::GlobalIdent(name) => write!(f, "::{}", name),
+            Term::String(string) => fmt_string(f, string),
+            Term::Resource(resource) => write!(f, "'{}'", resource),
+            Term::As(input_type) if input_type.is_empty() => f.write_str("as()"),
+            Term::As(input_type) => write!(f, "as({})", input_type),
+            Term::Expr(expr) => write!(f, "({})", expr),
+            Term::Prefab(prefab) => prefab.fmt(f),
+            Term::InterpString(begin, parts) => fmt_interp_string(f, begin, parts),
+            Term::Call(name, args) => write!(f, "{}({})", name, FormatArgs(args)),
+            Term::GlobalCall(name, args) => write!(f, "::{}({})", name, FormatArgs(args)),
+            Term::SelfCall(args) => write!(f, ".({})", FormatArgs(args)),
+            Term::ParentCall(args) => write!(f, "..({})", FormatArgs(args)),
+            Term::New { type_, args } => {
+                f.write_str("new")?;
+                match type_ {
+                    NewType::Implicit => {}
+                    NewType::Prefab(prefab) => write!(f, " {}", prefab)?,
+                    NewType::MiniExpr { ident, fields } => {
+                        write!(f, " {}", ident)?;
+                        for field in fields.iter() {
+                            write!(f, "{}{}", field.kind, field.ident)?;
+                        }
+                    }
+                }
+                if let Some(args) = args {
+                    write!(f, "({})", FormatArgs(args))?;
+                }
+                Ok(())
+            }
+            Term::List(args) => write!(f, "list({})", FormatArgs(args)),
+            Term::Input { args, input_type, in_list } => {
+                write!(f, "input({})", FormatArgs(args))?;
+                if let Some(input_type) = input_type {
+                    write!(f, " as {}", input_type)?;
+                }
+                if let Some(in_list) = in_list {
+                    write!(f, " in {}", in_list)?;
+                }
+                Ok(())
+            }
+            Term::Locate { args, in_list } => {
+                write!(f, "locate({})", FormatArgs(args))?;
+                if let Some(in_list) = in_list {
+                    write!(f, " in {}", in_list)?;
+                }
+                Ok(())
+            }
+            Term::Pick(args) => {
+                f.write_str("pick(")?;
+                for (i, (weight, value)) in args.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    if let Some(weight) = weight {
+                        write!(f, "{}; ", weight)?;
+                    }
+                    value.fmt(f)?;
+                }
+                f.write_str(")")
+            }
+            Term::DynamicCall(call_args, proc_args) => {
+                write!(f, "call({})({})", FormatArgs(call_args), FormatArgs(proc_args))
+            }
+        }
+    }
+}
+
+/// The ways a string's raw contents could fail to survive being quoted.
+#[derive(Default)]
+struct StringContents {
+    /// Contains a `"` which is not escaped.
+    quote: bool,
+    /// Contains a line break.
+    newline: bool,
+    /// Contains a `"` which is not escaped and is followed by `}`, another
+    /// `"`, or the end of the string, which a block string would misread.
+    block_end: bool,
+    /// Contains something a quoted string would treat specially: an
+    /// unescaped `[`, an escaped line break, or a trailing backslash.
+    special: bool,
+}
+
+impl StringContents {
+    fn scan(string: &str) -> StringContents {
+        let mut contents = StringContents::default();
+        let mut backslash = false;
+        let mut chars = string.chars().peekable();
+        while let Some(ch) = chars.next() {
+            if ch == '\n' || ch == '\r' {
+                contents.newline = true;
+            }
+            if backslash {
+                backslash = false;
+                contents.special |= ch == '\n' || ch == '\r';
+                continue;
+            }
+            match ch {
+                '\\' => backslash = true,
+                '[' => contents.special = true,
+                '"' => {
+                    contents.quote = true;
+                    contents.block_end |= matches!(chars.peek(), Some('}') | Some('"') | None);
+                }
+                _ => {}
+            }
+        }
+        contents.special |= backslash;
+        contents
+    }
+}
+
+/// Write a string, whose escapes have not been parsed, as a literal which
+/// lexes back to the same contents.
+fn fmt_string(f: &mut fmt::Formatter, string: &str) -> fmt::Result {
+    let contents = StringContents::scan(string);
+    if !contents.special {
+        if !contents.quote && !contents.newline {
+            return write!(f, "\"{}\"", string);
+        } else if !contents.block_end {
+            return write!(f, "{{\"{}\"}}", string);
+        }
+    }
+
+    // Raw strings take their contents verbatim.
+    if let Some(delimiter) = "\"'|#!^~`".chars().find(|&ch| !string.contains(ch)) {
+        write!(f, "@{}{}{}", delimiter, string, delimiter)
+    } else if !string.contains("\"}") {
+        write!(f, "@{{\"{}\"}}", string)
+    } else {
+        let mut terminator = String::from("END");
+        while string.contains(terminator.as_str()) {
+            terminator.push('_');
+        }
+        write!(f, "@({}){}{}", terminator, string, terminator)
+    }
+}
+
+fn fmt_interp_string(f: &mut fmt::Formatter, begin: &str, parts: &[(Option<Expression>, String)]) -> fmt::Result {
+    let block = std::iter::once(begin)
+        .chain(parts.iter().map(|(_, part)| part.as_str()))
+        .any(|part| {
+            let contents = StringContents::scan(part);
+            contents.quote || contents.newline
+        });
+    f.write_str(if block { "{\"" } else { "\"" })?;
+    f.write_str(begin)?;
+    for (expr, part) in parts.iter() {
+        f.write_str("[")?;
+        if let Some(expr) = expr {
+            write!(f, "{}", expr)?;
+        }
+        write!(f, "]{}", part)?;
+    }
+    f.write_str(if block { "\"}" } else { "\"" })
+}
+
+impl fmt::Display for Follow {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Follow::Index(ListAccessKind::Normal, expr) => write!(f, "[{}]", expr),
+            Follow::Index(ListAccessKind::Safe, expr) => write!(f, "?[{}]", expr),
+            Follow::Field(kind, name) => write!(f, "{}{}", kind, name),
+            Follow::Call(kind, name, args) => write!(f, "{}{}({})", kind, name, FormatArgs(args)),
+            Follow::StaticField(name) => write!(f, "::{}", name),
+            Follow::ProcReference(kind, name) => write!(f, "::{}/{}", kind, name),
+        }
+    }
+}
+
+impl fmt::Display for Case {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Case::Exact(expr) => expr.fmt(f),
+            Case::Range(start, end) => {
+                fmt_operand(f, start, strength(start) >= IN_STRENGTH || ends_with_range(start), false)?;
+                write!(f, " to {}", end)
+            }
+        }
+    }
+}
+
+/// Formatting helper for a block of statements, one per line, indented by
+/// the given number of tabs. Nested blocks are indented further.
+pub struct FormatBlock<'a>(pub &'a [Spanned<Statement>], pub usize);
+
+impl<'a> fmt::Display for FormatBlock<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for statement in self.0.iter() {
+            fmt_indent(f, self.1)?;
+            fmt_statement(f, &statement.elem, self.1)?;
+            f.write_str("\n")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Statement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt_statement(f, self, 0)
+    }
+}
+
+fn fmt_indent(f: &mut fmt::Formatter, indent: usize) -> fmt::Result {
+    for _ in 0..indent {
+        f.write_str("\t")?;
+    }
+    Ok(())
+}
+
+fn fmt_newline(f: &mut fmt::Formatter, indent: usize) -> fmt::Result {
+    f.write_str("\n")?;
+    fmt_indent(f, indent)
+}
+
+/// Write the statements of a nested block, each starting a new line. An
+/// empty block writes nothing.
+fn fmt_block(f: &mut fmt::Formatter, block: &[Spanned<Statement>], indent: usize) -> fmt::Result {
+    for statement in block.iter() {
+        fmt_newline(f, indent)?;
+        fmt_statement(f, &statement.elem, indent)?;
+    }
+    Ok(())
+}
+
+fn fmt_var_statement(f: &mut fmt::Formatter, var: &VarStatement) -> fmt::Result {
+    write!(f, "{}{}", var.var_type, var.name)?;
+    if let Some(value) = &var.value {
+        write!(f, " = {}", value)?;
+    }
+    Ok(())
+}
+
+fn fmt_loop_var(f: &mut fmt::Formatter, var_type: &Option<VarType>, name: &str) -> fmt::Result {
+    if let Some(var_type) = var_type {
+        write!(f, "var/{}", var_type)?;
+    }
+    f.write_str(name)
+}
+
+fn fmt_statement(f: &mut fmt::Formatter, statement: &Statement, indent: usize) -> fmt::Result {
+    match statement {
+        Statement::Expr(expr) => write!(f, "{}", expr),
+        Statement::Return(None) => f.write_str("return"),
+        Statement::Return(Some(expr)) => write!(f, "return {}", expr),
+        Statement::Throw(expr) => write!(f, "throw {}", expr),
+        Statement::While { condition, block } => {
+            write!(f, "while({})", condition)?;
+            fmt_block(f, block, indent + 1)
+        }
+        Statement::DoWhile { block, condition } => {
+            f.write_str("do")?;
+            fmt_block(f, block, indent + 1)?;
+            fmt_newline(f, indent)?;
+            write!(f, "while({})", condition.elem)
+        }
+        Statement::If { arms, else_arm } => {
+            for (i, (condition, block)) in arms.iter().enumerate() {
+                if i > 0 {
+                    fmt_newline(f, indent)?;
+                    f.write_str("else ")?;
+                }
+                write!(f, "if({})", condition.elem)?;
+                fmt_block(f, block, indent + 1)?;
+            }
+            if let Some(block) = else_arm {
+                fmt_newline(f, indent)?;
+                f.write_str("else")?;
+                fmt_block(f, block, indent + 1)?;
+            }
+            Ok(())
+        }
+        Statement::ForInfinite { block } => {
+            f.write_str("for()")?;
+            fmt_block(f, block, indent + 1)
+        }
+        Statement::ForLoop { init, test, inc, block } => {
+            f.write_str("for(")?;
+            if let Some(init) = init {
+                fmt_statement(f, init, indent)?;
+            }
+            f.write_str(";")?;
+            if let Some(test) = test {
+                write!(f, " {}", test)?;
+            }
+            f.write_str(";")?;
+            if let Some(inc) = inc {
+                f.write_str(" ")?;
+                fmt_statement(f, inc, indent)?;
+            }
+            f.write_str(")")?;
+            fmt_block(f, block, indent + 1)
+        }
+        Statement::ForList { var_type, name, input_type, in_list, block } => {
+            f.write_str("for(")?;
+            fmt_loop_var(f, var_type, name)?;
+            if let Some(input_type) = input_type {
+                write!(f, " as {}", input_type)?;
+            }
+            if let Some(in_list) = in_list {
+                // Without `var/` or `as`, the list is the right-hand side of
+                // an `in` expression.
+                let parens = var_type.is_none() && input_type.is_none() && strength(in_list) >= IN_STRENGTH;
+                f.write_str(" in ")?;
+                fmt_operand(f, in_list, parens, false)?;
+            }
+            f.write_str(")")?;
+            fmt_block(f, block, indent + 1)
+        }
+        Statement::ForRange { var_type, name, start, end, step, block } => {
+            f.write_str("for(")?;
+            fmt_loop_var(f, var_type, name)?;
+            f.write_str(" in ")?;
+            fmt_operand(f, start, strength(start) >= IN_STRENGTH || ends_with_range(start), false)?;
+            write!(f, " to {}", end)?;
+            if let Some(step) = step {
+                write!(f, " step {}", step)?;
+            }
+            f.write_str(")")?;
+            fmt_block(f, block, indent + 1)
+        }
+        Statement::Var(var) => {
+            f.write_str("var/")?;
+            fmt_var_statement(f, var)
+        }
+        Statement::Vars(vars) => {
+            f.write_str("var/")?;
+            for (i, var) in vars.iter().enumerate() {
+                if i > 0 {
+                    f.write_str(", ")?;
+                }
+                fmt_var_statement(f, var)?;
+            }
+            Ok(())
+        }
+        Statement::Setting { name, mode, value } => write!(f, "set {} {} {}", name, mode, value),
+        Statement::Spawn { delay, block } => {
+            f.write_str("spawn(")?;
+            if let Some(delay) = delay {
+                write!(f, "{}", delay)?;
+            }
+            f.write_str(")")?;
+            fmt_block(f, block, indent + 1)
+        }
+        Statement::Switch { input, cases, default } => {
+            write!(f, "switch({})", input)?;
+            if cases.is_empty() && default.is_none() {
+                // Without an indented line, nothing would open the body.
+                return f.write_str(" {}");
+            }
+            for (case, block) in cases.iter() {
+                fmt_newline(f, indent + 1)?;
+                f.write_str("if(")?;
+                for (i, each) in case.elem.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{}", each)?;
+                }
+                f.write_str(")")?;
+                fmt_block(f, block, indent + 2)?;
+            }
+            if let Some(block) = default {
+                fmt_newline(f, indent + 1)?;
+                f.write_str("else")?;
+                fmt_block(f, block, indent + 2)?;
+            }
+            Ok(())
+        }
+        Statement::TryCatch { try_block, catch_params, catch_block } => {
+            f.write_str("try")?;
+            fmt_block(f, try_block, indent + 1)?;
+            fmt_newline(f, indent)?;
+            f.write_str("catch")?;
+            if !catch_params.is_empty() {
+                f.write_str("(")?;
+                for (i, param) in catch_params.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    f.write_str(&param.join("/"))?;
+                }
+                f.write_str(")")?;
+            }
+            fmt_block(f, catch_block, indent + 1)
+        }
+        Statement::Continue(None) => f.write_str("continue"),
+        Statement::Continue(Some(label)) => write!(f, "continue {}", label),
+        Statement::Break(None) => f.write_str("break"),
+        Statement::Break(Some(label)) => write!(f, "break {}", label),
+        Statement::Goto(label) => write!(f, "goto {}", label),
+        Statement::Label { name, block } => {
+            write!(f, "{}:", name)?;
+            fmt_block(f, block, indent + 1)
+        }
+        Statement::Del(expr) => write!(f, "del {}", expr),
+        Statement::Crash(None) => f.write_str("CRASH()"),
+        Statement::Crash(Some(expr)) => write!(f, "CRASH({})", expr),
+    }
+}
+
 pub const KNOWN_SETTING_NAMES: &[&str] = &[
     "name",
     "desc",
@@ -8,11 +8,24 @@ use super::preprocessor::{DefineMap, Define};
 use super::constants::Constant;
 use super::docs::{BuiltinDocs, DocCollection};
 
-const DM_VERSION: i32 = 514;
-const DM_BUILD: i32 = 1554;
+const DM_VERSION: i32 = 515;
+const DM_BUILD: i32 = 1633;
+
+/// The last build of each older major version, used for `DM_BUILD` when
+/// targeting that version.
+fn last_build(version: u32) -> Option<i32> {
+    match version {
+        512 => Some(1488),
+        513 => Some(1542),
+        514 => Some(1589),
+        _ => None,
+    }
+}
 
-/// Register BYOND builtin macros to the given define map.
-pub fn default_defines(defines: &mut DefineMap) {
+/// Register BYOND builtin macros to the given define map. If there is a target
+/// version, `DM_VERSION` and `DM_BUILD` describe it and macros added in later
+/// versions are left undefined.
+pub fn default_defines(defines: &mut DefineMap, target_version: Option<u32>) {
     use super::lexer::*;
     use super::lexer::Token::*;
     let location = Location::builtins();
@@ -63,18 +76,25 @@ pub fn default_defines(defines: &mut DefineMap) {
 
     // constants
     macro_rules! c {
-        ($($i:ident = $($x:expr),*;)*) => {
-            for (name, value) in &[
-                $((stringify!($i), [$($x,)*]),)*
+        ($($(#[since($since:expr)])? $i:ident = $($x:expr),*;)*) => {
+            for (name, since, value) in &[
+                $((stringify!($i), None$(.or(Some($since)))?, [$($x,)*]),)*
             ] {
-                let previous = defines.insert(name.to_string(), (location, Define::Constant { subst: value.to_vec(), docs: Default::default() }));
+                if let (Some(since), Some(target)) = (*since, target_version) {
+                    if since > target {
+                        continue;
+                    }
+                }
+                let mut docs = DocCollection::default();
+                docs.since = *since;
+                let previous = defines.insert(name.to_string(), (location, Define::Constant { subst: value.to_vec(), docs }));
                 assert!(previous.is_none(), "redefined: {}", name);
             }
         }
     }
     c! {
-        DM_VERSION = Int(DM_VERSION);
-        DM_BUILD = Int(DM_BUILD);
+        DM_VERSION = Int(target_version.map_or(DM_VERSION, |version| version as i32));
+        DM_BUILD = Int(target_version.and_then(last_build).unwrap_or(DM_BUILD));
         SPACEMAN_DMM = Int(1);
 
         FALSE = Int(0);
@@ -104,7 +124,7 @@ pub fn default_defines(defines: &mut DefineMap) {
         TILE_BOUND = Int(256);
         PIXEL_SCALE = Int(512);
         PASS_MOUSE = Int(1024);
-        TILE_MOVER = Int(2048); // 514
+        #[since(514)] TILE_MOVER = Int(2048);
 
         CONTROL_FREAK_ALL = Int(1);
         CONTROL_FREAK_SKIN = Int(2);
@@ -140,7 +160,7 @@ pub fn default_defines(defines: &mut DefineMap) {
         ELASTIC_EASING = Int(5);
         BACK_EASING = Int(6);
         QUAD_EASING = Int(7);
-        JUMP_EASING = Int(8); // 513
+        #[since(513)] JUMP_EASING = Int(8);
         EASE_IN = Int(64);
         EASE_OUT = Int(128);
 
@@ -166,35 +186,35 @@ pub fn default_defines(defines: &mut DefineMap) {
         DATABASE_ROW_COLUMN_VALUE = Int(17);
         DATABASE_ROW_LIST = Int(18);
 
-        // vis_flags (513)
-        VIS_INHERIT_ICON = Int(1);
-        VIS_INHERIT_ICON_STATE = Int(2);
-        VIS_INHERIT_DIR = Int(4);
-        VIS_INHERIT_LAYER = Int(8);
-        VIS_INHERIT_PLANE = Int(16);
-        VIS_INHERIT_ID = Int(32);
-        VIS_UNDERLAY = Int(64);
-        VIS_HIDE = Int(128);
-
-        // color spaces (514)
-        COLORSPACE_RGB = Int(0);
-        COLORSPACE_HSV = Int(1);
-        COLORSPACE_HSL = Int(2);
-        COLORSPACE_HCY = Int(3);
-
-        // world.Profile() (513)
-        PROFILE_STOP = Int(1);
-        PROFILE_CLEAR = Int(2);
-        PROFILE_AVERAGE = Int(4);
-        PROFILE_START = Int(0);
-        PROFILE_REFRESH = Int(0);
-        PROFILE_RESTART = Int(2);
-
-        // generator functions (514)
-        UNIFORM_RAND = Int(0);
-        NORMAL_RAND = Int(1);
-        LINEAR_RAND = Int(2);
-        SQUARE_RAND = Int(3);
+        // vis_flags
+        #[since(513)] VIS_INHERIT_ICON = Int(1);
+        #[since(513)] VIS_INHERIT_ICON_STATE = Int(2);
+        #[since(513)] VIS_INHERIT_DIR = Int(4);
+        #[since(513)] VIS_INHERIT_LAYER = Int(8);
+        #[since(513)] VIS_INHERIT_PLANE = Int(16);
+        #[since(513)] VIS_INHERIT_ID = Int(32);
+        #[since(513)] VIS_UNDERLAY = Int(64);
+        #[since(513)] VIS_HIDE = Int(128);
+
+        // color spaces
+        #[since(514)] COLORSPACE_RGB = Int(0);
+        #[since(514)] COLORSPACE_HSV = Int(1);
+        #[since(514)] COLORSPACE_HSL = Int(2);
+        #[since(514)] COLORSPACE_HCY = Int(3);
+
+        // world.Profile()
+        #[since(513)] PROFILE_STOP = Int(1);
+        #[since(513)] PROFILE_CLEAR = Int(2);
+        #[since(513)] PROFILE_AVERAGE = Int(4);
+        #[since(513)] PROFILE_START = Int(0);
+        #[since(513)] PROFILE_REFRESH = Int(0);
+        #[since(513)] PROFILE_RESTART = Int(2);
+
+        // generator functions
+        #[since(514)] UNIFORM_RAND = Int(0);
+        #[since(514)] NORMAL_RAND = Int(1);
+        #[since(514)] LINEAR_RAND = Int(2);
+        #[since(514)] SQUARE_RAND = Int(3);
     }
 }
 
@@ -257,7 +277,8 @@ pub fn register_builtins(tree: &mut ObjectTree) {
             var/const/EDGE_PERSPECTIVE = int!(2);
         }
 
-        // enum /world/var/movement_mode (514)
+        // enum /world/var/movement_mode
+        #[since(514)]
         #[dm_ref("/world/var/movement_mode")] {
             var/const/LEGACY_MOVEMENT_MODE = int!(0);
             var/const/TILE_MOVEMENT_MODE = int!(1);
@@ -484,7 +505,8 @@ pub fn register_builtins(tree: &mut ObjectTree) {
         proc/link(url);
         proc/list(A,B,C/*,...*/);  // +1 form
         proc/list2params(List);
-        proc/load_resource(File, KeepTime); // 514 (special form?)
+        #[since(514)]
+        proc/load_resource(File, KeepTime); // special form?
         proc/locate(Type)/*in Container*/;  // +3 forms
         proc/log(X=2.718, Y);
         proc/lowertext(T);
@@ -493,6 +515,8 @@ pub fn register_builtins(tree: &mut ObjectTree) {
         proc/md5(T);
         proc/min(A,B,C/*,...*/);
         proc/missile(Type,Start,End);
+        #[since(515)]
+        proc/nameof(Thing);
         proc/newlist(A,B,C/*,...*/);
         proc/nonspantext(Haystack,Needles,Start=1);
         proc/num2text(N,SigFig=6, Radix); // +1 form, (N,SigFig) (N, Digits, Radix)
@@ -611,7 +635,8 @@ pub fn register_builtins(tree: &mut ObjectTree) {
         list/proc/Insert(Index, Item1, Item2/*,...*/);
         list/proc/Join(Glue, Start=1, End=0);
         list/proc/Remove(Item1, Item2/*,...*/);
-        list/proc/Splice(Start=1, End=0, Item1, Item2/*,...*/); // 514
+        #[since(514)]
+        list/proc/Splice(Start=1, End=0, Item1, Item2/*,...*/);
         list/proc/Swap(Index1, Index2);
         list/var/len;
 
@@ -768,7 +793,8 @@ pub fn register_builtins(tree: &mut ObjectTree) {
         world/var/turf/turf = path!(/turf);
         world/var/time;
         world/var/timeofday;
-        world/var/timezone; // 514
+        #[since(514)]
+        world/var/timezone;
         world/var/url;
         world/var/version = int!(0);
         world/var/view = int!(5);
@@ -845,7 +871,8 @@ pub fn register_builtins(tree: &mut ObjectTree) {
         client/var/statobj;
         client/var/statpanel;
         client/var/tick_lag = int!(0);
-        client/var/timezone; // 514
+        #[since(514)]
+        client/var/timezone;
         client/var/list/verbs;
         client/var/view;
         client/var/virtual_eye;
@@ -1061,70 +1088,74 @@ pub fn register_builtins(tree: &mut ObjectTree) {
         savefile/proc/Unlock();
 
         // 513 stuff
-        proc/arctan(A,B);
-        proc/clamp(NumberOrList,Low,High);
-        proc/islist(List);
-        proc/ismovable(Loc1, Loc2/*,...*/);
-        proc/sha1(StringOrFile);
-        proc/tan(A);
-
-        // text procs
-        proc/length_char(E);
-        proc/text2ascii_char(T,pos=1);
-        proc/copytext_char(T,Start/*=1*/,End/*=0*/);
-        proc/findtext_char(Haystack,Needle,Start=1,End=0);
-        proc/findtextEx_char(Haystack,Needle,Start=1,End=0);
-        proc/findlasttext_char(Haystack,Needle,Start=0,End=1);
-        proc/findlasttextEx_char(Haystack,Needle,Start=0,End=1);
-        proc/replacetext_char(Haystack,Needle,Replacement,Start=1,End=0);
-        proc/replacetextEx_char(Haystack,Needle,Replacement,Start=1,End=0);
-        proc/spantext_char(Haystack,Needles,Start=1);
-        proc/nonspantext_char(Haystack,Needles,Start=1);
-        proc/splittext_char(Text,Delimiter,Start=1,End=0,include_delimiters=0);
-        proc/splicetext_char(Text,Start=1,End=0,Insert=""); // 514
-
-        atom/var/render_target;
-        atom/var/render_source;
-        atom/var/vis_flags;
-
-        client/proc/MeasureText(text, style, width/*=0*/);
-        client/proc/SoundQuery();
-
-        regex/proc/Find_char(text, start, end);
-        regex/proc/Replace_char(text, rep, start, end);
+        #[since(513)] {
+            proc/arctan(A,B);
+            proc/clamp(NumberOrList,Low,High);
+            proc/islist(List);
+            proc/ismovable(Loc1, Loc2/*,...*/);
+            proc/sha1(StringOrFile);
+            proc/tan(A);
+
+            // text procs
+            proc/length_char(E);
+            proc/text2ascii_char(T,pos=1);
+            proc/copytext_char(T,Start/*=1*/,End/*=0*/);
+            proc/findtext_char(Haystack,Needle,Start=1,End=0);
+            proc/findtextEx_char(Haystack,Needle,Start=1,End=0);
+            proc/findlasttext_char(Haystack,Needle,Start=0,End=1);
+            proc/findlasttextEx_char(Haystack,Needle,Start=0,End=1);
+            proc/replacetext_char(Haystack,Needle,Replacement,Start=1,End=0);
+            proc/replacetextEx_char(Haystack,Needle,Replacement,Start=1,End=0);
+            proc/spantext_char(Haystack,Needles,Start=1);
+            proc/nonspantext_char(Haystack,Needles,Start=1);
+            proc/splittext_char(Text,Delimiter,Start=1,End=0,include_delimiters=0);
+            #[since(514)]
+            proc/splicetext_char(Text,Start=1,End=0,Insert="");
+
+            atom/var/render_target;
+            atom/var/render_source;
+            atom/var/vis_flags;
+
+            client/proc/MeasureText(text, style, width/*=0*/);
+            client/proc/SoundQuery();
+
+            regex/proc/Find_char(text, start, end);
+            regex/proc/Replace_char(text, rep, start, end);
+        }
 
         // 514 stuff
-
-        generator;
-        generator/proc/Rand();
-        generator/proc/Turn(a);
-
-        /proc/generator(type, A, B, rand);
-
-        particles;
-        particles/var/width;
-        particles/var/height;
-        particles/var/spawning;
-        particles/var/count;
-        particles/var/bound1;
-        particles/var/bound2;
-        particles/var/gravity;
-        particles/var/gradient;
-        particles/var/transform;
-        particles/var/lifespan;
-        particles/var/fade;
-        particles/var/position;
-        particles/var/velocity;
-        particles/var/color;
-        particles/var/color_change;
-        particles/var/friction;
-        particles/var/icon;
-        particles/var/icon_state;
-        particles/var/scale;
-        particles/var/grow;
-        particles/var/rotation;
-        particles/var/spin;
-        particles/var/drift;
+        #[since(514)] {
+            generator;
+            generator/proc/Rand();
+            generator/proc/Turn(a);
+
+            /proc/generator(type, A, B, rand);
+
+            particles;
+            particles/var/width;
+            particles/var/height;
+            particles/var/spawning;
+            particles/var/count;
+            particles/var/bound1;
+            particles/var/bound2;
+            particles/var/gravity;
+            particles/var/gradient;
+            particles/var/transform;
+            particles/var/lifespan;
+            particles/var/fade;
+            particles/var/position;
+            particles/var/velocity;
+            particles/var/color;
+            particles/var/color_change;
+            particles/var/friction;
+            particles/var/icon;
+            particles/var/icon_state;
+            particles/var/scale;
+            particles/var/grow;
+            particles/var/rotation;
+            particles/var/spin;
+            particles/var/drift;
+        }
     };
 }
 
@@ -1133,4 +1164,9 @@ impl DocCollection {
         self.builtin_docs = BuiltinDocs::ReferenceHash(hash);
         self
     }
+
+    fn since(&mut self, version: u32) -> &mut Self {
+        self.since = Some(version);
+        self
+    }
 }
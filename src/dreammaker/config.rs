@@ -3,29 +3,58 @@
 use std::fs::File;
 use std::io::Read;
 use std::path::{Path, PathBuf};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 use serde::Deserialize;
 
-use crate::error::Severity;
-use crate::DMError;
+use crate::error::{FileId, Severity};
+use crate::lint_pattern::LintPattern;
+use crate::{DMError, Location};
 
 /// Struct for deserializing from a config TOML
 #[derive(Deserialize, Default, Debug, Clone)]
 #[serde(default)]
 pub struct Config {
     pub environment: Option<PathBuf>,
+    /// The BYOND version the project must compile under, such as `514`.
+    /// Builtins and syntax newer than this are reported as errors.
+    pub target_version: Option<u32>,
 
     // diagnostic configuration
     display: WarningDisplay,
-    diagnostics: HashMap<String, WarningLevel>,
+    diagnostics: BTreeMap<String, WarningLevel>,
     pub code_standards: CodeStandards,
+    #[serde(rename = "lint")]
+    pub lints: Vec<CustomLint>,
 
     // tool-specific configuration
     pub langserver: Langserver,
     pub dmdoc: DMDoc,
+    pub dmfmt: DMFmt,
     pub debugger: Debugger,
     pub map_renderer: MapRenderer,
+
+    /// The file this configuration was read from, if any.
+    #[serde(skip)]
+    path: Option<PathBuf>,
+
+    /// The TOML this configuration was parsed from.
+    #[serde(skip)]
+    source: String,
+
+    /// Levels set by `#pragma` directives, which override `diagnostics` from
+    /// their line until the end of the file they are in.
+    #[serde(skip)]
+    pragmas: BTreeMap<FileId, Vec<Pragma>>,
+}
+
+/// A `#pragma` directive setting the level of a diagnostic.
+#[derive(Debug, Clone)]
+struct Pragma {
+    line: u32,
+    errortype: String,
+    /// `Unset` restores the level from `diagnostics`, as after `#pragma pop`.
+    level: WarningLevel,
 }
 
 /// General error display options
@@ -37,8 +66,10 @@ pub struct WarningDisplay {
 
 /// Langserver config options
 #[derive(Deserialize, Default, Debug, Clone)]
+#[serde(default)]
 pub struct Langserver {
     pub dreamchecker: bool,
+    pub preprocess_cache: bool,
 }
 
 /// Extremely opinionated linter config options
@@ -47,6 +78,65 @@ pub struct Langserver {
 pub struct CodeStandards {
     pub disallow_relative_proc_definitions: bool,
     pub disallow_relative_type_definitions: bool,
+    pub max_proc_complexity: Option<u32>,
+    pub max_proc_statements: Option<u32>,
+    pub max_proc_nesting: Option<u32>,
+    pub max_proc_locals: Option<u32>,
+    pub duplicate_code_min_statements: Option<u32>,
+    pub duplicate_code_normalize: DuplicateNormalization,
+}
+
+/// A project-specific lint, reported by DreamChecker wherever its pattern
+/// matches code within its filters.
+#[derive(Deserialize, Debug, Clone)]
+pub struct CustomLint {
+    /// A short name for the lint, shown with each diagnostic.
+    pub name: String,
+    /// The code to report, as described in [`lint_pattern`].
+    ///
+    /// [`lint_pattern`]: ../lint_pattern/index.html
+    pub pattern: LintPattern,
+    /// The diagnostic message.
+    pub message: String,
+    #[serde(default = "CustomLint::default_severity")]
+    pub severity: WarningLevel,
+    /// Types or procs, as in `/datum` or `/datum/proc/process`, which the
+    /// code must be inside. Empty means anywhere.
+    #[serde(default)]
+    pub within: Vec<String>,
+    /// Types or procs which the code must not be inside.
+    #[serde(default)]
+    pub outside: Vec<String>,
+    /// File paths or globs which the code must be in. Empty means any file.
+    #[serde(default)]
+    pub files: Vec<String>,
+    /// File paths or globs which the code must not be in.
+    #[serde(default)]
+    pub exclude_files: Vec<String>,
+}
+
+impl CustomLint {
+    fn default_severity() -> WarningLevel {
+        WarningLevel::Warning
+    }
+}
+
+/// How much of the code is ignored when comparing for duplicates.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all(deserialize = "lowercase"))]
+pub enum DuplicateNormalization {
+    /// Only exactly identical code is considered duplicate.
+    Exact,
+    /// Number, string, resource, and typepath literals are ignored.
+    Literals,
+    /// Literals and the names of vars and procs are ignored.
+    Identifiers,
+}
+
+impl Default for DuplicateNormalization {
+    fn default() -> Self {
+        DuplicateNormalization::Exact
+    }
 }
 
 /// DMDoc config options
@@ -58,6 +148,25 @@ pub struct DMDoc {
     pub module_directories: Vec<String>,
 }
 
+/// Formatter config options
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct DMFmt {
+    /// The most consecutive blank lines to keep.
+    pub max_blank_lines: usize,
+    /// Files and directories which are never formatted.
+    pub exclude: Vec<String>,
+}
+
+impl Default for DMFmt {
+    fn default() -> Self {
+        DMFmt {
+            max_blank_lines: 2,
+            exclude: Vec::new(),
+        }
+    }
+}
+
 // Debugger config options
 #[derive(Deserialize, Default, Debug, Clone)]
 pub struct Debugger {
@@ -112,14 +221,68 @@ impl Config {
         let mut file = File::open(path)?;
         let mut config_toml = String::new();
         file.read_to_string(&mut config_toml)?;
-        Ok(toml::from_str(&config_toml)?)
+        let mut config = Config::from_toml(&config_toml)?;
+        config.path = Some(path.to_owned());
+        Ok(config)
+    }
+
+    /// Get the path of the file this configuration was read from, if any.
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Get the TOML this configuration was parsed from, which is empty for
+    /// the default configuration.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Parse a config TOML from a string.
+    pub fn from_toml(config_toml: &str) -> Result<Config, Error> {
+        let mut config: Config = toml::from_str(config_toml)?;
+        config.source = config_toml.to_owned();
+        Ok(config)
     }
 
     fn config_warninglevel(&self, error: &DMError) -> Option<&WarningLevel> {
-        if let Some(errortype) = error.errortype() {
-            return self.diagnostics.get(errortype)
+        let errortype = error.errortype()?;
+        match self.pragma_level(error.location(), errortype) {
+            Some(WarningLevel::Unset) | None => self.diagnostic_level(errortype),
+            Some(level) => Some(level),
         }
-        None
+    }
+
+    /// Set the level of a diagnostic, by name or code, from a location until
+    /// the end of its file.
+    pub fn add_pragma(&mut self, location: Location, errortype: String, level: WarningLevel) {
+        self.pragmas.entry(location.file).or_default().push(Pragma {
+            line: location.line,
+            errortype,
+            level,
+        });
+    }
+
+    /// Find the level set for an errortype by the last `#pragma` before a
+    /// location in the same file.
+    fn pragma_level(&self, location: Location, errortype: &str) -> Option<&WarningLevel> {
+        let code = crate::diagnostics::code_of(errortype);
+        self.pragmas.get(&location.file)?
+            .iter()
+            .rev()
+            .filter(|pragma| pragma.line <= location.line)
+            .find(|pragma| pragma.errortype == errortype || matches!(code, Some(code) if pragma.errortype.eq_ignore_ascii_case(code)))
+            .map(|pragma| &pragma.level)
+    }
+
+    /// Find the configured level for an errortype, set either by name or by
+    /// its diagnostic code.
+    fn diagnostic_level(&self, errortype: &str) -> Option<&WarningLevel> {
+        self.diagnostics.get(errortype).or_else(|| {
+            let code = crate::diagnostics::code_of(errortype)?;
+            self.diagnostics.iter()
+                .find(|(key, _)| key.eq_ignore_ascii_case(code))
+                .map(|(_, level)| level)
+        })
     }
 
     /// Return a new [`DMError`] with the configured [`Severity`] or [`None`] if disabled
@@ -138,6 +301,15 @@ impl Config {
         })
     }
 
+    /// Test whether an opt-in diagnostic has been explicitly enabled with a
+    /// severity in the `[diagnostics]` section.
+    pub fn is_opted_in(&self, errortype: &str) -> bool {
+        match self.diagnostic_level(errortype) {
+            Some(WarningLevel::Disabled) | Some(WarningLevel::Unset) | None => false,
+            Some(_) => true,
+        }
+    }
+
     /// Test the error against the configured error level threshold
     pub fn registerable_error(&self, error: &DMError) -> bool {
         self.display.error_level.applies_to(error.severity())
@@ -402,7 +402,7 @@ pub fn evaluate_str(location: Location, input: &[u8]) -> Result<Constant, DMErro
     let expr = crate::parser::parse_expression(&ctx, location, &mut lexer)?;
     let leftover = lexer.remaining();
     if !leftover.is_empty() {
-        return Err(DMError::new(location, format!("leftover: {:?} {}", from_utf8_or_latin1_borrowed(&input), leftover.len())));
+        return Err(DMError::new(location, format!("leftover: {:?} {}", from_utf8_or_latin1_borrowed(&input), leftover.len())).with_errortype("syntax_error"));
     }
     expr.simple_evaluate(location)
 }
@@ -453,7 +453,7 @@ pub(crate) fn evaluate_all(context: &Context, tree: &mut ObjectTree) {
                             key,
                             tree[ty].path,
                         ),
-                    ));
+                    ).with_errortype("undefined_var"));
                 }
             }
         }
@@ -494,17 +494,17 @@ fn constant_ident_lookup(
                             return Err(DMError::new(
                                 var.value.location,
                                 format!("recursive constant reference: {}", ident),
-                            ));
+                            ).with_errortype("constant_evaluation"));
                         } else if !decl.var_type.is_const_evaluable() {
                             return Err(DMError::new(
                                 var.value.location,
                                 format!("non-const-evaluable variable: {}", ident),
-                            ));
+                            ).with_errortype("constant_evaluation"));
                         } else if !decl.var_type.flags.is_const() && must_be_const {
                             return Err(DMError::new(
                                 var.value.location,
                                 format!("non-const variable: {}", ident),
-                            ));
+                            ).with_errortype("constant_evaluation"));
                         }
                         var.value.being_evaluated = true;
                         (var.value.location, decl.var_type.type_path, expr)
@@ -554,12 +554,22 @@ impl<'a> ConstantFolder<'a> {
                 } else {
                     None
                 };
-                let mut term = self.term(term.elem, base_type_hint)?;
+                let mut term = match (term.elem, follow.first()) {
+                    // `src::name` reads the initial value of a var on the
+                    // type being evaluated.
+                    (Term::Ident(ref ident), Some(Spanned { elem: Follow::StaticField(_), .. })) if ident == "src" => {
+                        match self.tree.as_ref() {
+                            Some(tree) => Constant::Prefab(Pop::from(NavigatePathResult::Type(TypeRef::new(tree, self.ty)).to_path())),
+                            None => return Err(self.error("cannot reference src in this context").with_errortype("constant_evaluation")),
+                        }
+                    },
+                    (term, _) => self.term(term, base_type_hint)?,
+                };
                 for each in follow {
                     term = self.follow(term, each.elem)?;
                 }
                 for each in unary.into_iter().rev() {
-                    term = self.unary(term, each)?;
+                    term = self.unary(term, each.elem)?;
                 }
                 term
             },
@@ -574,12 +584,11 @@ impl<'a> ConstantFolder<'a> {
                     false => self.expr(*else_, type_hint)?,
                 }
             },
-            Expression::AssignOp { .. } => return Err(self.error("non-constant assignment")),
+            Expression::AssignOp { .. } => return Err(self.error("non-constant assignment").with_errortype("constant_evaluation")),
         })
     }
 
     /// list of expressions, keyword arguments disallowed
-    #[allow(dead_code)]
     fn expr_vec(&mut self, v: Vec<Expression>) -> Result<Vec<Constant>, DMError> {
         let mut out = Vec::new();
         for each in v {
@@ -620,17 +629,48 @@ impl<'a> ConstantFolder<'a> {
             // If it's a reference to a type-hinted value, look up the field in
             // its const variables (but not non-const variables).
             (Constant::Null(Some(type_hint)), Follow::Field(_, field_name)) => {
-                let mut full_path = String::new();
-                for each in type_hint {
-                    full_path.push('/');
-                    full_path.push_str(&each);
+                let idx = self.type_index(&type_hint)?;
+                self.recursive_lookup(idx, &field_name, true)
+            }
+            // `/obj/item::name` reads the initial value of a var, const or
+            // not, on the given type or the declared type of a var.
+            (Constant::Prefab(pop), Follow::StaticField(field_name)) => {
+                if let Some(value) = pop.vars.get(&field_name) {
+                    return Ok(value.clone());
                 }
-                match self.tree.as_mut().and_then(|t| t.find(&full_path)).map(|t| t.index()) {
-                    Some(idx) => self.recursive_lookup(idx, &field_name, true),
-                    None => Err(self.error(format!("unknown typepath {}", full_path))),
+                let idx = self.type_index(&pop.path)?;
+                self.recursive_lookup(idx, &field_name, false)
+            }
+            (Constant::Null(Some(type_hint)), Follow::StaticField(field_name)) => {
+                let idx = self.type_index(&type_hint)?;
+                self.recursive_lookup(idx, &field_name, false)
+            }
+            // `/datum::proc/foo` is the same as `/datum/proc/foo`.
+            (Constant::Prefab(pop), Follow::ProcReference(kind, proc_name)) if pop.vars.is_empty() => {
+                let idx = self.type_index(&pop.path)?;
+                let tree = self.tree.as_ref().unwrap();
+                if TypeRef::new(tree, idx).get_proc(&proc_name).is_none() {
+                    return Err(self.error(format!("undefined proc: {:?} on {}", proc_name, FormatTreePath(&pop.path)))
+                        .with_errortype("constant_evaluation"));
                 }
+                let mut path = pop.path;
+                path.push(kind.name().to_owned());
+                path.push(proc_name);
+                Ok(Constant::Prefab(Pop::from(path)))
             }
-            (term, follow) => Err(self.error(format!("non-constant expression follower: {} {:?}", term, follow))),
+            (term, follow) => Err(self.error(format!("non-constant expression follower: {} {:?}", term, follow)).with_errortype("constant_evaluation")),
+        }
+    }
+
+    fn type_index(&mut self, path: &[Ident]) -> Result<NodeIndex, DMError> {
+        let mut full_path = String::new();
+        for each in path {
+            full_path.push('/');
+            full_path.push_str(each);
+        }
+        match self.tree.as_mut().and_then(|t| t.find(&full_path)).map(|t| t.index()) {
+            Some(idx) => Ok(idx),
+            None => Err(self.error(format!("unknown typepath {}", full_path)).with_errortype("constant_evaluation")),
         }
     }
 
@@ -645,11 +685,12 @@ impl<'a> ConstantFolder<'a> {
             // float ops
             (UnaryOp::Neg, Float(i)) => Float(-i),
             // unsupported
-            (op, term) => return Err(self.error(format!("non-constant unary operation: {}", op.around(&term)))),
+            (op, term) => return Err(self.error(format!("non-constant unary operation: {}", op.around(&term))).with_errortype("constant_evaluation")),
         })
     }
 
     fn binary(&mut self, mut lhs: Constant, mut rhs: Constant, op: BinaryOp) -> Result<Constant, DMError> {
+        use std::convert::TryFrom;
         use self::Constant::*;
 
         macro_rules! numeric {
@@ -674,7 +715,6 @@ impl<'a> ConstantFolder<'a> {
         numeric!(GreaterEq >=);
         match (op, lhs, rhs) {
             (BinaryOp::Pow, Int(lhs), Int(rhs)) => {
-                use std::convert::TryFrom;
                 if let Ok(rhs2) = u32::try_from(rhs) {
                     if let Some(result) = lhs.checked_pow(rhs2) {
                         return Ok(Constant::from(result));
@@ -691,27 +731,66 @@ impl<'a> ConstantFolder<'a> {
             }
         }
 
+        // bitwise operators truncate their operands to integers
         macro_rules! integer {
-            ($name:ident $oper:tt) => {
+            ($name:ident |$l:ident, $r:ident| $result:expr) => {
                 match (op, lhs, rhs) {
-                    (BinaryOp::$name, Int(lhs), Int(rhs)) => return Ok(Int(lhs $oper rhs)),
+                    (BinaryOp::$name, lhs_ @ Int(_), rhs_ @ Int(_)) |
+                    (BinaryOp::$name, lhs_ @ Int(_), rhs_ @ Float(_)) |
+                    (BinaryOp::$name, lhs_ @ Float(_), rhs_ @ Int(_)) |
+                    (BinaryOp::$name, lhs_ @ Float(_), rhs_ @ Float(_)) => {
+                        let ($l, $r) = (lhs_.to_int().unwrap(), rhs_.to_int().unwrap());
+                        return Ok(Int($result));
+                    }
                     (_, lhs_, rhs_) => { lhs = lhs_; rhs = rhs_; }
                 }
             }
         }
-        integer!(BitOr |);
-        integer!(BitAnd &);
-        integer!(BitXor ^);
-        integer!(LShift <<);
-        integer!(RShift >>);
+        integer!(BitOr |l, r| l | r);
+        integer!(BitAnd |l, r| l & r);
+        integer!(BitXor |l, r| l ^ r);
+        // shifting by more than the width of the number leaves nothing
+        integer!(LShift |l, r| u32::try_from(r).ok().and_then(|r| l.checked_shl(r)).unwrap_or(0));
+        integer!(RShift |l, r| u32::try_from(r).ok().and_then(|r| l.checked_shr(r)).unwrap_or(0));
 
         match (op, lhs, rhs) {
             (BinaryOp::Add, String(lhs), String(rhs)) => Ok(String(lhs + &rhs)),
+            // list arithmetic, where a non-list operand acts as a single item
+            (BinaryOp::Add, List(mut lhs), rhs) => {
+                lhs.extend(list_items(rhs));
+                Ok(List(lhs))
+            }
+            (BinaryOp::Sub, List(mut lhs), rhs) => {
+                for (key, _) in list_items(rhs) {
+                    if let Some(pos) = lhs.iter().rposition(|(each, _)| *each == key) {
+                        lhs.remove(pos);
+                    }
+                }
+                Ok(List(lhs))
+            }
+            (BinaryOp::BitOr, List(mut lhs), rhs) => {
+                for item in list_items(rhs) {
+                    if !list_contains(&lhs, &item.0) {
+                        lhs.push(item);
+                    }
+                }
+                Ok(List(lhs))
+            }
+            (BinaryOp::BitAnd, List(lhs), rhs) => {
+                let rhs = list_items(rhs);
+                Ok(List(lhs.into_iter().filter(|(key, _)| list_contains(&rhs, key)).collect()))
+            }
+            (BinaryOp::BitXor, List(lhs), rhs) => {
+                let rhs = list_items(rhs);
+                let mut result: Vec<_> = lhs.iter().filter(|(key, _)| !list_contains(&rhs, key)).cloned().collect();
+                result.extend(rhs.into_iter().filter(|(key, _)| !list_contains(&lhs, key)));
+                Ok(List(result))
+            }
             (BinaryOp::Eq, lhs, rhs) => Ok(Constant::from(lhs == rhs)),
             (BinaryOp::NotEq, lhs, rhs) => Ok(Constant::from(lhs != rhs)),
             (BinaryOp::And, lhs, rhs) => Ok(if lhs.to_bool() { rhs } else { lhs }),
             (BinaryOp::Or, lhs, rhs) => Ok(if lhs.to_bool() { lhs } else { rhs }),
-            (op, lhs, rhs) => Err(self.error(format!("non-constant {:?}: {} {} {}", op, lhs, op, rhs))),
+            (op, lhs, rhs) => Err(self.error(format!("non-constant {:?}: {} {} {}", op, lhs, op, rhs)).with_errortype("constant_evaluation")),
         }
     }
 
@@ -722,7 +801,7 @@ impl<'a> ConstantFolder<'a> {
                 type_: match type_ {
                     NewType::Prefab(e) => Some(self.prefab(*e)?),
                     NewType::Implicit => None,
-                    NewType::MiniExpr { .. } => return Err(self.error("non-constant new expression")),
+                    NewType::MiniExpr { .. } => return Err(self.error("non-constant new expression").with_errortype("constant_evaluation")),
                 },
                 args: match args {
                     Some(args) => Some(self.arguments(args)?),
@@ -744,39 +823,252 @@ impl<'a> ConstantFolder<'a> {
                 "arcsin" => self.trig_op(args, f32::asin)?,
                 "arccos" => self.trig_op(args, f32::acos)?,
                 "rgb" => Constant::String(self.rgb(args)?),
+                "min" => self.min_max("min", args, false)?,
+                "max" => self.min_max("max", args, true)?,
+                "round" => self.round(args)?,
+                "floor" => integral(self.float_op("floor", args)?.floor()),
+                "ceil" => integral(self.float_op("ceil", args)?.ceil()),
+                "clamp" => self.clamp(args)?,
+                "abs" => match self.builtin_args("abs", args, 1, 1)?.remove(0) {
+                    Constant::Int(i) => Constant::Int(i.wrapping_abs()),
+                    Constant::Float(f) => Constant::Float(f.abs()),
+                    other => return Err(self.not_numeric("abs", &other)),
+                },
+                "sqrt" => {
+                    let value = self.float_op("sqrt", args)?;
+                    if value < 0. {
+                        return Err(self.error(format!("sqrt() of negative number: {}", Constant::Float(value))).with_errortype("constant_evaluation"));
+                    }
+                    integral(value.sqrt())
+                },
+                "text2num" => self.text2num(args)?,
+                "num2text" => Constant::String(self.num2text(args)?),
+                "length" => match self.builtin_args("length", args, 1, 1)?.remove(0) {
+                    Constant::Null(_) | Constant::Int(_) | Constant::Float(_) => Constant::Int(0),
+                    Constant::String(s) => Constant::Int(s.len() as i32),
+                    Constant::List(list) => Constant::Int(list.len() as i32),
+                    other => return Err(self.error(format!("non-constant length() of {}", other)).with_errortype("constant_evaluation")),
+                },
+                "uppertext" => Constant::String(self.text_op("uppertext", args)?.to_ascii_uppercase()),
+                "lowertext" => Constant::String(self.text_op("lowertext", args)?.to_ascii_lowercase()),
+                "ascii2text" => {
+                    let code = self.float_op("ascii2text", args)? as u32;
+                    match std::char::from_u32(code) {
+                        Some(ch) => Constant::String(ch.to_string()),
+                        None => return Err(self.error(format!("malformed ascii2text() call, {} is not a valid character", code)).with_errortype("malformed_builtin_call")),
+                    }
+                },
+                "nameof" => {
+                    if args.len() != 1 {
+                        return Err(self.error(format!("malformed nameof() call, must have 1 argument and instead has {}", args.len())).with_errortype("malformed_builtin_call"));
+                    }
+                    match args[0].nameof() {
+                        Some(name) => Constant::String(name.to_owned()),
+                        None => return Err(self.error("malformed nameof() call, argument given has no name").with_errortype("malformed_builtin_call")),
+                    }
+                },
                 "defined" if self.defines.is_some() => {
                     let defines = self.defines.unwrap();  // annoying, but keeps the match clean
                     if args.len() != 1 {
-                        return Err(self.error(format!("malformed defined() call, must have 1 argument and instead has {}", args.len())));
+                        return Err(self.error(format!("malformed defined() call, must have 1 argument and instead has {}", args.len())).with_errortype("malformed_builtin_call"));
                     }
                     match args[0].as_term() {
                         Some(Term::Ident(ref ident)) => {
                             Constant::Int(if defines.contains_key(ident) { 1 } else { 0 })
                         },
-                        _ => return Err(self.error("malformed defined() call, argument given isn't an Ident.")),
+                        _ => return Err(self.error("malformed defined() call, argument given isn't an Ident.").with_errortype("malformed_builtin_call")),
                     }
                 }
                 // other functions are no-goes
-                _ => return Err(self.error(format!("non-constant function call: {}", ident))),
+                _ => return Err(self.error(format!("non-constant function call: {}", ident)).with_errortype("constant_evaluation")),
             },
             Term::Prefab(prefab) => Constant::Prefab(self.prefab(*prefab)?),
+            Term::Ident(ref ident) if ident == "__TYPE__" => match self.tree.as_ref() {
+                Some(tree) => Constant::Prefab(Pop::from(NavigatePathResult::Type(TypeRef::new(tree, self.ty)).to_path())),
+                None => return Err(self.error("cannot reference __TYPE__ in this context").with_errortype("constant_evaluation")),
+            },
             Term::Ident(ident) => self.ident(ident, false)?,
+            Term::GlobalIdent(ident) => self.recursive_lookup(NodeIndex::new(0), &ident, false)?,
             Term::String(v) => Constant::String(v),
             Term::Resource(v) => Constant::Resource(v),
             Term::Int(v) => Constant::Int(v),
             Term::Float(v) => Constant::from(v),
             Term::Expr(expr) => self.expr(*expr, type_hint)?,
-            _ => return Err(self.error("non-constant expression".to_owned())),
+            _ => return Err(self.error("non-constant expression".to_owned()).with_errortype("constant_evaluation")),
         })
     }
 
     fn trig_op(&mut self, mut args: Vec<Expression>, op: fn(f32) -> f32) -> Result<Constant, DMError> {
         if args.len() != 1 {
-            Err(self.error(format!("trig function requires exactly 1 argument, instead found {}", args.len())))
+            Err(self.error(format!("trig function requires exactly 1 argument, instead found {}", args.len())).with_errortype("malformed_builtin_call"))
         } else if let Some(f) = self.expr(args.remove(0), None)?.to_float() {
             Ok(Constant::Float(op(f)))
         } else {
-            Err(self.error("trig function requires numeric argument"))
+            Err(self.error("trig function requires numeric argument").with_errortype("malformed_builtin_call"))
+        }
+    }
+
+    /// Evaluate the positional arguments of a builtin, checking their count.
+    fn builtin_args(&mut self, name: &str, args: Vec<Expression>, min: usize, max: usize) -> Result<Vec<Constant>, DMError> {
+        if args.len() < min || args.len() > max {
+            let expected = if min == max {
+                format!("{} argument{}", min, if min == 1 { "" } else { "s" })
+            } else {
+                format!("{} to {} arguments", min, max)
+            };
+            return Err(self.error(format!("malformed {}() call, must have {} and instead has {}", name, expected, args.len()))
+                .with_errortype("malformed_builtin_call"));
+        }
+        self.expr_vec(args)
+    }
+
+    fn not_numeric(&self, name: &str, value: &Constant) -> DMError {
+        self.error(format!("malformed {}() call, {} is not a number", name, value)).with_errortype("malformed_builtin_call")
+    }
+
+    fn to_float(&self, name: &str, value: &Constant) -> Result<f32, DMError> {
+        value.to_float().ok_or_else(|| self.not_numeric(name, value))
+    }
+
+    /// A builtin taking exactly one numeric argument.
+    fn float_op(&mut self, name: &str, args: Vec<Expression>) -> Result<f32, DMError> {
+        let value = self.builtin_args(name, args, 1, 1)?.remove(0);
+        self.to_float(name, &value)
+    }
+
+    /// A builtin taking exactly one text argument.
+    fn text_op(&mut self, name: &str, args: Vec<Expression>) -> Result<String, DMError> {
+        match self.builtin_args(name, args, 1, 1)?.remove(0) {
+            Constant::String(s) => Ok(s),
+            other => Err(self.error(format!("malformed {}() call, {} is not text", name, other)).with_errortype("malformed_builtin_call")),
+        }
+    }
+
+    fn min_max(&mut self, name: &str, args: Vec<Expression>, max: bool) -> Result<Constant, DMError> {
+        let mut values = self.expr_vec(args)?;
+        // min(list) and max(list) operate on the list's items
+        if values.len() == 1 {
+            if let Constant::List(_) = values[0] {
+                if let Constant::List(list) = values.remove(0) {
+                    values = list.into_iter().map(|(key, _)| key).collect();
+                }
+            }
+        }
+
+        let mut best: Option<Constant> = None;
+        for value in values {
+            let number = self.to_float(name, &value)?;
+            let replace = match best {
+                None => true,
+                Some(ref current) => {
+                    let current = current.to_float().unwrap();
+                    if max { number > current } else { number < current }
+                }
+            };
+            if replace {
+                best = Some(value);
+            }
+        }
+        match best {
+            Some(best) => Ok(best),
+            None => Err(self.error(format!("malformed {}() call, must have at least 1 argument", name)).with_errortype("malformed_builtin_call")),
+        }
+    }
+
+    fn round(&mut self, args: Vec<Expression>) -> Result<Constant, DMError> {
+        let args = self.builtin_args("round", args, 1, 2)?;
+        let value = self.to_float("round", &args[0])?;
+        match args.get(1) {
+            // round(A) rounds down, like floor()
+            None => Ok(integral(value.floor())),
+            Some(multiple) => {
+                let multiple = self.to_float("round", multiple)?;
+                if multiple == 0. {
+                    return Err(self.error("round() to a multiple of zero").with_errortype("constant_evaluation"));
+                }
+                Ok(integral((value / multiple + 0.5).floor() * multiple))
+            }
+        }
+    }
+
+    fn clamp(&mut self, args: Vec<Expression>) -> Result<Constant, DMError> {
+        let mut args = self.builtin_args("clamp", args, 3, 3)?;
+        let low = self.to_float("clamp", &args[1])?;
+        let high = self.to_float("clamp", &args[2])?;
+        let value = self.to_float("clamp", &args[0])?;
+        Ok(if value < low {
+            args.remove(1)
+        } else if value > high {
+            args.remove(2)
+        } else {
+            args.remove(0)
+        })
+    }
+
+    fn text2num(&mut self, args: Vec<Expression>) -> Result<Constant, DMError> {
+        let mut args = self.builtin_args("text2num", args, 1, 2)?;
+        let radix = match args.get(1) {
+            Some(radix) => match radix.to_int() {
+                Some(radix) if (2..=36).contains(&radix) => radix as u32,
+                _ => return Err(self.error(format!("malformed text2num() call, bad radix: {}", radix)).with_errortype("malformed_builtin_call")),
+            },
+            None => 10,
+        };
+        let text = match args.swap_remove(0) {
+            number @ Constant::Int(_) | number @ Constant::Float(_) => return Ok(number),
+            Constant::String(text) => text,
+            _ => return Ok(Constant::Null(None)),
+        };
+        Ok(parse_number(&text, radix).unwrap_or(Constant::Null(None)))
+    }
+
+    fn num2text(&mut self, args: Vec<Expression>) -> Result<String, DMError> {
+        let args = self.builtin_args("num2text", args, 1, 3)?;
+        let value = self.to_float("num2text", &args[0])?;
+        let digits = match args.get(1) {
+            Some(digits) => Some(self.to_float("num2text", digits)? as usize),
+            None => None,
+        };
+        let radix = match args.get(2) {
+            Some(radix) => match radix.to_int() {
+                Some(radix) if (2..=36).contains(&radix) => radix as u32,
+                _ => return Err(self.error(format!("malformed num2text() call, bad radix: {}", radix)).with_errortype("malformed_builtin_call")),
+            },
+            None => 10,
+        };
+
+        if args.len() == 3 {
+            // with a radix, the number is truncated and padded with zeroes
+            if matches!(digits, Some(digits) if digits > MAX_NUM2TEXT_DIGITS) {
+                return Err(self.error(format!("non-constant num2text() call, padding to more than {} digits", MAX_NUM2TEXT_DIGITS))
+                    .with_errortype("constant_evaluation"));
+            }
+            let magnitude = value.trunc().abs();
+            // u32::MAX rounds up to 2^32 as a float
+            if !magnitude.is_finite() || magnitude >= u32::MAX as f32 {
+                return Err(self.error(format!("non-constant num2text() call, {} is too large to format", Constant::Float(value)))
+                    .with_errortype("constant_evaluation"));
+            }
+            let mut magnitude = magnitude as u32;
+            let mut text = Vec::new();
+            loop {
+                text.push(std::char::from_digit(magnitude % radix, radix).unwrap());
+                magnitude /= radix;
+                if magnitude == 0 {
+                    break;
+                }
+            }
+            while text.len() < digits.unwrap_or(0) {
+                text.push('0');
+            }
+            if value <= -1. {
+                text.push('-');
+            }
+            Ok(text.into_iter().rev().collect())
+        } else if matches!(digits, None | Some(6)) {
+            Ok(crate::lexer::FormatFloat(value).to_string())
+        } else {
+            Err(self.error("non-constant num2text() call, only the default precision is supported").with_errortype("constant_evaluation"))
         }
     }
 
@@ -795,14 +1087,14 @@ impl<'a> ConstantFolder<'a> {
             Some(tree) => tree,
             None => return Err(self.error(format!(
                 "cannot resolve relative type path without an object tree: {}",
-                FormatTypePath(&prefab.path)))),
+                FormatTypePath(&prefab.path))).with_errortype("constant_evaluation")),
         };
 
         let relative_to = TypeRef::new(tree, self.ty);
         let found = match relative_to.navigate_path(&prefab.path) {
             Some(found) => found,
             None => return Err(self.error(format!("could not resolve {} relative to {}",
-                FormatTypePath(&prefab.path), relative_to))),
+                FormatTypePath(&prefab.path), relative_to)).with_errortype("constant_evaluation")),
         };
 
         let path = found.to_path();
@@ -829,7 +1121,7 @@ impl<'a> ConstantFolder<'a> {
         while let Some(ty) = idx {
             let location = self.location;
             if self.tree.is_none() {
-                return Err(self.error(format!("cannot reference variable {:?} in this context", ident)));
+                return Err(self.error(format!("cannot reference variable {:?} in this context", ident)).with_errortype("constant_evaluation"));
             }
             let tree = self.tree.as_mut().unwrap();
             match constant_ident_lookup(tree, ty, &ident, must_be_const)
@@ -839,7 +1131,7 @@ impl<'a> ConstantFolder<'a> {
                 ConstLookup::Continue(i) => idx = i,
             }
         }
-        Err(self.error(format!("unknown variable: {}", ident)))
+        Err(self.error(format!("unknown variable: {}", ident)).with_errortype("constant_evaluation"))
     }
 
     fn rgb(&mut self, args: Vec<Expression>) -> Result<String, DMError> {
@@ -865,7 +1157,7 @@ impl<'a> ConstantFolder<'a> {
         }
 
         if args.len() != 3 && args.len() != 4 && args.len() != 5 {
-            return Err(self.error(format!("malformed rgb() call, must have 3, 4, or 5 arguments and instead has {}", args.len())));
+            return Err(self.error(format!("malformed rgb() call, must have 3, 4, or 5 arguments and instead has {}", args.len())).with_errortype("malformed_builtin_call"));
         }
 
         let arguments = self.arguments(args)?;
@@ -894,15 +1186,15 @@ impl<'a> ConstantFolder<'a> {
                             Some(2) => space = Some(ColorSpace::Hsl),
                             Some(3) => space = Some(ColorSpace::Hcy),
                             _ => {
-                                return Err(self.error(format!("malformed rgb() call, bad color space: {}", kwarg_value)))
+                                return Err(self.error(format!("malformed rgb() call, bad color space: {}", kwarg_value)).with_errortype("malformed_builtin_call"))
                             }
                         }
                         _ => {
-                            return Err(self.error(format!("malformed rgb() call, bad kwarg passed: {}", kwarg)))
+                            return Err(self.error(format!("malformed rgb() call, bad kwarg passed: {}", kwarg)).with_errortype("malformed_builtin_call"))
                         }
                     }
                 } else {
-                    return Err(self.error(format!("malformed rgb() call, kwarg is not string: {}", value)));
+                    return Err(self.error(format!("malformed rgb() call, kwarg is not string: {}", value)).with_errortype("malformed_builtin_call"));
                 }
             }
         }
@@ -923,10 +1215,10 @@ impl<'a> ConstantFolder<'a> {
                     } else if color_args.l {
                         ColorSpace::Hsl
                     } else {
-                        return Err(self.error("malformed rgb() call, could not determine space: only h & s specified"));
+                        return Err(self.error("malformed rgb() call, could not determine space: only h & s specified").with_errortype("malformed_builtin_call"));
                     }
                 } else {
-                    return Err(self.error("malformed rgb() call, could not determine space: only h specified"));
+                    return Err(self.error("malformed rgb() call, could not determine space: only h specified").with_errortype("malformed_builtin_call"));
                 }
             } else {
                 ColorSpace::Rgb  // Default
@@ -976,17 +1268,18 @@ impl<'a> ConstantFolder<'a> {
                         "a" | "alpha" => 0..=255,
                         "space" => continue, // Don't range-check the value of the space
                         _ => {
-                            return Err(self.error(format!("malformed rgb() call, bad kwarg passed: {}", kwarg)))
+                            return Err(self.error(format!("malformed rgb() call, bad kwarg passed: {}", kwarg)).with_errortype("malformed_builtin_call"))
                         }
                     };
                 } else {
-                    return Err(self.error(format!("malformed rgb() call, kwarg is not string: {}", value)));
+                    return Err(self.error(format!("malformed rgb() call, kwarg is not string: {}", value)).with_errortype("malformed_builtin_call"));
                 }
             }
 
             if let Some(i) = to_check.to_int() {
                 if !range.contains(&i) {
                     return Err(self.error(format!("malformed rgb() call, {} is not within the valid range ({}..{})", i, range.start(), range.end()))
+                        .with_errortype("malformed_builtin_call")
                         .set_severity(Severity::Warning)
                         .with_location(self.location)
                     );
@@ -994,7 +1287,7 @@ impl<'a> ConstantFolder<'a> {
                 let clamped = std::cmp::max(::std::cmp::min(i, *range.end()), *range.start());
                 value_vec.push(clamped.into());
             } else {
-                return Err(self.error("malformed rgb() call, value wasn't an int"));
+                return Err(self.error("malformed rgb() call, value wasn't an int").with_errortype("malformed_builtin_call"));
             }
         }
 
@@ -1019,3 +1312,50 @@ impl<'a> ConstantFolder<'a> {
         }
     }
 }
+
+/// The most digits `num2text()` will pad to, enough for any 32-bit number in
+/// binary.
+const MAX_NUM2TEXT_DIGITS: usize = 32;
+
+/// Represent a float as an integer where it is exactly one.
+fn integral(value: f32) -> Constant {
+    if value.fract() == 0. && value >= i32::MIN as f32 && value < i32::MAX as f32 {
+        Constant::Int(value as i32)
+    } else {
+        Constant::Float(value)
+    }
+}
+
+/// Parse the number at the start of `text` as `text2num()` does.
+fn parse_number(text: &str, radix: u32) -> Option<Constant> {
+    let text = text.trim_start();
+    if radix == 10 {
+        let end = text
+            .find(|ch: char| !(ch.is_ascii_digit() || "+-.eE".contains(ch)))
+            .unwrap_or(text.len());
+        // take the longest prefix which forms a valid number
+        (1..=end).rev()
+            .find_map(|len| text[..len].parse::<f32>().ok())
+            .map(integral)
+    } else {
+        let (negative, digits) = match text.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, text),
+        };
+        let end = digits.find(|ch: char| !ch.is_digit(radix)).unwrap_or(digits.len());
+        let value = i64::from_str_radix(&digits[..end], radix).ok()? as f32;
+        Some(integral(if negative { -value } else { value }))
+    }
+}
+
+/// The items of a list operand, or a non-list operand as a single item.
+fn list_items(value: Constant) -> Vec<(Constant, Option<Constant>)> {
+    match value {
+        Constant::List(items) => items,
+        other => vec![(other, None)],
+    }
+}
+
+fn list_contains(list: &[(Constant, Option<Constant>)], key: &Constant) -> bool {
+    list.iter().any(|(each, _)| each == key)
+}
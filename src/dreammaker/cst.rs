@@ -0,0 +1,233 @@
+//! A lossless concrete syntax tree, which keeps the comments and whitespace
+//! that the lexer discards.
+//!
+//! Every byte of the source belongs to exactly one token or piece of trivia,
+//! so a file can be reproduced byte-for-byte. Tokens are those of the
+//! [`Lexer`](../lexer/struct.Lexer.html), including newlines and doc
+//! comments, with the exception of indentation, which becomes trivia. The
+//! `location` of a `Spanned` AST node is the location of its first token, so
+//! [`ConcreteSyntaxTree::index_of`] maps nodes parsed from the same file back
+//! to their tokens.
+use std::borrow::Cow;
+use std::io::{self, Write};
+use std::ops::Range;
+use std::path::Path;
+
+use super::lexer::{self, Lexer, Punctuation, Token};
+use super::{Context, DMError, FileId, Location};
+
+/// The kind of a piece of trivia.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TriviaKind {
+    /// Spaces, tabs, and carriage returns between tokens.
+    Whitespace,
+    /// Spaces and tabs at the start of a line.
+    Indent,
+    /// A `//` comment which is not a doc comment.
+    LineComment,
+    /// A `/* */` comment which is not a doc comment.
+    BlockComment,
+    /// A backslash joining a line to the next, up to and including the newline.
+    LineContinuation,
+    /// Bytes which the lexer rejected as illegal.
+    Skipped,
+}
+
+/// Source text which is not part of any token.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Trivia {
+    pub kind: TriviaKind,
+    /// The bytes of source this trivia covers.
+    pub span: Range<usize>,
+}
+
+/// A token along with the trivia surrounding it.
+///
+/// Trivia between a token and the end of its line trails that token; all
+/// other trivia leads the token which follows it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CstToken {
+    pub location: Location,
+    pub token: Token,
+    /// The bytes of source this token was lexed from. Empty for the newline
+    /// the lexer adds at the end of the file.
+    pub span: Range<usize>,
+    pub leading: Vec<Trivia>,
+    pub trailing: Vec<Trivia>,
+}
+
+impl CstToken {
+    /// The bytes of source covered by this token and its trivia.
+    pub fn full_span(&self) -> Range<usize> {
+        let start = self.leading.first().map_or(self.span.start, |t| t.span.start);
+        let end = self.trailing.last().map_or(self.span.end, |t| t.span.end);
+        start..end
+    }
+}
+
+/// The tokens and trivia of a single file.
+#[derive(Clone, Debug)]
+pub struct ConcreteSyntaxTree<'a> {
+    source: Cow<'a, [u8]>,
+    tokens: Vec<CstToken>,
+}
+
+impl<'a> ConcreteSyntaxTree<'a> {
+    /// Lex a file's contents, registering any lexer errors to the context.
+    pub fn new<I: Into<Cow<'a, [u8]>>>(context: &Context, file: FileId, source: I) -> Self {
+        let source = source.into();
+        let mut tokens: Vec<CstToken> = Vec::new();
+        let mut pending = Vec::new();
+        let mut last_end = 0;
+
+        let mut lexer = Lexer::new(context, file, &*source);
+        while let Some((located, span)) = lexer.next_spanned() {
+            split_trivia(&source, last_end..span.start, &mut pending);
+            last_end = span.end;
+
+            // Indentation is only meaningful to the indent processor, and
+            // formatting tools treat it like any other whitespace.
+            if let Token::Punct(Punctuation::Space) | Token::Punct(Punctuation::Tab) = located.token {
+                match pending.last_mut() {
+                    Some(Trivia { kind: TriviaKind::Indent, span: previous }) if previous.end == span.start => {
+                        previous.end = span.end;
+                    }
+                    _ => pending.push(Trivia { kind: TriviaKind::Indent, span }),
+                }
+                continue;
+            }
+
+            let is_newline = located.token == Token::Punct(Punctuation::Newline);
+            let mut leading = std::mem::take(&mut pending);
+            if is_newline {
+                if let Some(previous) = tokens.last_mut() {
+                    if previous.token != Token::Punct(Punctuation::Newline) {
+                        previous.trailing = std::mem::take(&mut leading);
+                    }
+                }
+            }
+            tokens.push(CstToken {
+                location: located.location,
+                token: located.token,
+                span,
+                leading,
+                trailing: Vec::new(),
+            });
+        }
+
+        ConcreteSyntaxTree { source, tokens }
+    }
+
+    /// Read and lex a file from disk.
+    pub fn from_file(context: &Context, file: FileId, path: &Path) -> Result<ConcreteSyntaxTree<'static>, DMError> {
+        Ok(ConcreteSyntaxTree::new(context, file, lexer::buffer_file(file, path)?))
+    }
+
+    /// The source this tree was lexed from.
+    pub fn source(&self) -> &[u8] {
+        &self.source
+    }
+
+    /// All tokens, in source order.
+    pub fn tokens(&self) -> &[CstToken] {
+        &self.tokens
+    }
+
+    /// The source bytes covered by a token or trivia span.
+    pub fn text(&self, span: &Range<usize>) -> &[u8] {
+        &self.source[span.clone()]
+    }
+
+    /// Find the index of the token starting at a location, such as that of a
+    /// `Spanned` AST node.
+    pub fn index_of(&self, location: Location) -> Option<usize> {
+        self.tokens.binary_search_by_key(&location, |token| token.location).ok()
+    }
+
+    /// The tokens starting at or after `start` and before `end`, such as
+    /// those of an AST node followed by its next sibling.
+    pub fn tokens_between(&self, start: Location, end: Location) -> &[CstToken] {
+        let first = self.tokens.partition_point(|token| token.location < start);
+        let last = self.tokens.partition_point(|token| token.location < end);
+        &self.tokens[first..last.max(first)]
+    }
+
+    /// Write the tokens and trivia back out, reproducing the source.
+    pub fn write_to<W: Write>(&self, mut output: W) -> io::Result<()> {
+        for token in self.tokens.iter() {
+            for trivia in token.leading.iter() {
+                output.write_all(self.text(&trivia.span))?;
+            }
+            output.write_all(self.text(&token.span))?;
+            for trivia in token.trailing.iter() {
+                output.write_all(self.text(&trivia.span))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Reproduce the source from the tokens and trivia.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut output = Vec::with_capacity(self.source.len());
+        self.write_to(&mut output).expect("writing to a Vec cannot fail");
+        output
+    }
+}
+
+/// Divide the source between two tokens into trivia.
+fn split_trivia(source: &[u8], range: Range<usize>, output: &mut Vec<Trivia>) {
+    let mut start = range.start;
+    while start < range.end {
+        let rest = &source[start..range.end];
+        let (kind, len) = match rest {
+            [b' ', ..] | [b'\t', ..] | [b'\r', ..] => {
+                (TriviaKind::Whitespace, rest.iter().take_while(|&&b| b == b' ' || b == b'\t' || b == b'\r').count())
+            }
+            // A line comment runs until the newline token which ends it,
+            // except for any carriage return before that newline.
+            [b'/', b'/', ..] => {
+                let trailing_cr = rest.iter().rev().take_while(|&&b| b == b'\r').count();
+                (TriviaKind::LineComment, rest.len() - trailing_cr)
+            }
+            [b'/', b'*', ..] => (TriviaKind::BlockComment, block_comment_len(rest)),
+            [b'\\', ..] => {
+                let blank = rest[1..].iter().take_while(|&&b| b == b' ' || b == b'\t' || b == b'\r').count();
+                if rest.get(1 + blank) == Some(&b'\n') {
+                    (TriviaKind::LineContinuation, blank + 2)
+                } else {
+                    (TriviaKind::LineContinuation, 1)
+                }
+            }
+            _ => (TriviaKind::Skipped, 1),
+        };
+        let span = start..start + len;
+        start += len;
+        match output.last_mut() {
+            Some(previous) if previous.kind == kind && kind == TriviaKind::Skipped => previous.span.end = span.end,
+            _ => output.push(Trivia { kind, span }),
+        }
+    }
+}
+
+/// The length of a possibly-nested block comment at the start of `rest`.
+fn block_comment_len(rest: &[u8]) -> usize {
+    let mut depth = 0;
+    let mut i = 0;
+    while i + 1 < rest.len() {
+        match &rest[i..i + 2] {
+            b"/*" => {
+                depth += 1;
+                i += 2;
+            }
+            b"*/" => {
+                depth -= 1;
+                i += 2;
+                if depth == 0 {
+                    return i;
+                }
+            }
+            _ => i += 1,
+        }
+    }
+    rest.len()
+}
@@ -0,0 +1,809 @@
+//! The registry of stable diagnostic codes.
+//!
+//! Every diagnostic raised by the suite carries an errortype, and every
+//! errortype is assigned a code such as `DM0201` which never changes meaning.
+//! Either may be used as a key in the `[diagnostics]` configuration section.
+
+/// Information about one kind of diagnostic.
+#[derive(Debug, Clone, Copy)]
+pub struct Diagnostic {
+    /// The stable code, such as `DM0201`.
+    pub code: &'static str,
+    /// The errortype attached to diagnostics of this kind.
+    pub errortype: &'static str,
+    /// A one-line summary.
+    pub summary: &'static str,
+    /// A long-form explanation, with examples.
+    pub explanation: &'static str,
+}
+
+/// Look up a diagnostic by its code or errortype.
+pub fn lookup(key: &str) -> Option<&'static Diagnostic> {
+    DIAGNOSTICS.iter().find(|each| each.code.eq_ignore_ascii_case(key) || each.errortype == key)
+}
+
+/// Look up the registered copy of an errortype, such as one read from a cache.
+pub fn errortype(errortype: &str) -> Option<&'static str> {
+    DIAGNOSTICS.iter().find(|each| each.errortype == errortype).map(|each| each.errortype)
+}
+
+/// Look up the code for an errortype.
+pub fn code_of(errortype: &str) -> Option<&'static str> {
+    DIAGNOSTICS.iter().find(|each| each.errortype == errortype).map(|each| each.code)
+}
+
+macro_rules! diagnostics {
+    ($($code:ident $errortype:ident: $summary:expr, $explanation:expr;)*) => {
+        /// Every diagnostic, in order of code.
+        pub static DIAGNOSTICS: &[Diagnostic] = &[$(
+            Diagnostic {
+                code: stringify!($code),
+                errortype: stringify!($errortype),
+                summary: $summary,
+                explanation: $explanation,
+            },
+        )*];
+    }
+}
+
+diagnostics! {
+    // ------------------------------------------------------------------------
+    // DM00xx: reading files and lexing
+
+    DM0001 io_error: "a file could not be read",
+r#"A file included by the environment, or the environment itself, could not be
+opened or read. The attached note describes the underlying error.
+
+Check that the file exists and is readable, and that its name matches the
+`#include` exactly."#;
+
+    DM0002 unterminated_comment: "a block comment is never closed",
+r#"A `/*` block comment continues to the end of the file. Block comments nest,
+so every `/*` inside a comment also needs its own `*/`.
+
+    /* outer /* inner */
+    /proc/never_parsed()"#;
+
+    DM0003 backslash_in_line_comment: "a line comment ends with a backslash",
+r#"A `//` comment which ends with `\` continues onto the following line, so
+that line is commented out too.
+
+    // see C:\dir\
+    var/x = 1  // this line is part of the comment above"#;
+
+    DM0004 integer_precision_loss: "an integer literal cannot be represented exactly",
+r#"Numbers in DM are 32-bit floats, which represent integers exactly only up to
+16777216. Larger integer literals are rounded.
+
+    var/x = 16777217  // becomes 16777216"#;
+
+    DM0005 bad_number: "a number literal is malformed",
+r#"A number literal could not be read, for example because it has digits which
+are not valid in its base.
+
+    var/x = 0xZZ"#;
+
+    DM0006 unterminated_string: "a string or resource literal is never closed",
+r#"A string, raw string, or resource literal continues to the end of the line
+or file without its closing delimiter.
+
+    var/x = "missing the quote
+    var/y = @{"raw string with no terminator"#;
+
+    DM0007 illegal_character: "a character is not valid DM syntax",
+r#"The file contains a byte which cannot begin any token, outside of strings and
+comments. Only the first such byte in a run is reported.
+
+    var/x = 1 ` 2"#;
+
+    DM0010 invalid_config: "the configuration file could not be read",
+r#"The `SpacemanDMM.toml` configuration file is not valid TOML, or one of its
+options has the wrong type. The default configuration is used instead."#;
+
+    // ------------------------------------------------------------------------
+    // DM002x: indentation
+
+    DM0020 inconsistent_indentation: "indentation does not match the surrounding blocks",
+r#"A line is indented by an amount which is not a multiple of the file's
+indentation, or by more than one level deeper than the line before it.
+
+    /mob
+        var/x = 1
+           var/y = 2  // three extra spaces"#;
+
+    DM0021 unmatched_brace: "a closing brace has no opening brace",
+r#"A `}` appears where there is no open `{` block to close.
+
+    /proc/test()
+        return
+    }"#;
+
+    // ------------------------------------------------------------------------
+    // DM01xx: preprocessor
+
+    DM0101 malformed_directive: "a preprocessor directive is malformed",
+r#"A preprocessor directive, such as a `#define` with parameters, is missing a
+required part or has an unexpected token.
+
+    #define CALL(a, ...rest, b)  // only the last parameter may be variadic
+    #ifdef"#;
+
+    DM0102 unknown_directive: "a preprocessor directive is not recognized",
+r#"A line begins with `#` followed by a name which is not a directive. A
+suggestion is given if the name begins with a known directive.
+
+    #defined FOO"#;
+
+    DM0103 unmatched_conditional: "`#if` and `#endif` directives are unbalanced",
+r#"An `#else`, `#elif`, or `#endif` has no matching `#if`, `#ifdef`, or
+`#ifndef`, or one of those is still open at the end of the environment.
+
+    #ifdef DEBUG
+    /proc/debug_only()
+    // missing #endif"#;
+
+    DM0104 missing_include: "an `#include` names a file which was not found",
+r#"The file named by an `#include` does not exist relative to the including
+file, the environment, or any `FILE_DIR`.
+
+    #include "code/missing.dm""#;
+
+    DM0105 bad_include: "an `#include` names a file of an unknown type",
+r#"Included files must have one of the extensions `.dm`, `.dme`, `.dmm`, `.dmf`,
+or `.dms`.
+
+    #include "notes.txt""#;
+
+    DM0106 duplicate_include: "a file is included more than once",
+r#"All DM source files behave as if they had `#pragma once`, so including a file
+a second time has no effect.
+
+    #include "code/mob.dm"
+    #include "code/mob.dm""#;
+
+    DM0107 macro_redefined: "a macro is defined again without being undefined",
+r#"A `#define` replaces an existing macro of the same name. Use `#undef` first
+if the redefinition is intended.
+
+    #define MAX_HEALTH 100
+    #define MAX_HEALTH 200"#;
+
+    DM0108 macro_undefined_no_definition: "`#undef` names a macro which is not defined",
+r#"An `#undef` names a macro which is not currently defined, which usually
+means the name is misspelled.
+
+    #define MAX_HEALTH 100
+    #undef MAX_HAELTH"#;
+
+    DM0109 macro_used_before_change: "a macro is used on the line before it is redefined",
+r#"The DM compiler expands some macros lazily, so a macro used immediately
+before an `#undef` or `#define` of the same name may see the new definition.
+See https://secure.byond.com/forum/?post=2072419"#;
+
+    DM0110 macro_recursion: "macro expansion is nested too deeply",
+r#"Expanding a macro would exceed the maximum depth of nested expansions,
+which usually means macros refer to each other in a cycle.
+
+    #define A B
+    #define B A"#;
+
+    DM0111 macro_arguments: "a macro is called with the wrong number of arguments",
+r#"A function-like macro must be called with as many arguments as it has
+parameters, unless its last parameter is variadic.
+
+    #define ADD(a, b) ((a) + (b))
+    var/x = ADD(1)"#;
+
+    DM0112 bad_stringify: "`#` in a macro is not followed by a parameter",
+r#"In a macro body, `#` converts the following parameter to a string. It
+cannot be applied to anything else.
+
+    #define NAME(x) #y"#;
+
+    DM0113 warn_directive: "a `#warn` directive was reached",
+r#"The environment contains a `#warn` directive which was not disabled by a
+preprocessor conditional. The message is the directive's text."#;
+
+    DM0114 error_directive: "an `#error` directive was reached",
+r#"The environment contains an `#error` directive which was not disabled by a
+preprocessor conditional. The message is the directive's text."#;
+
+    DM0115 unused_macro: "a macro is never used",
+r#"Opt-in. A macro defined in the environment is never expanded, nor tested
+by `#ifdef`, `#ifndef`, or `defined()`.
+
+    #define UNUSED_FLAG 1"#;
+    DM0116 unknown_pragma: "a #pragma is not understood",
+r#"A `#pragma` directive names an unknown form or warning level. Only
+`once`, `push`, `pop`, and `#pragma <diagnostic> <level>` are understood,
+where the level is one of `error`, `warning`, `notice`, or `ignore`.
+
+    #pragma unused_var loud"#;
+
+    // ------------------------------------------------------------------------
+    // DM02xx: parser
+
+    DM0201 syntax_error: "the code could not be parsed",
+r#"The parser found a token it did not expect. The message lists what would
+have been accepted at that point. A note is added when the real problem is
+likely extra indentation on the following line.
+
+    /proc/test()
+        if (x
+            return"#;
+
+    DM0202 path_has_no_effect: "a path ends with a trailing slash",
+r#"A tree path ends in `/` with nothing after it, so it declares nothing.
+
+    /mob/"#;
+
+    DM0203 path_separator: "a path uses `.` or `:` instead of `/`",
+r#"Paths in declarations should begin unprefixed or with `/`, and separate
+their parts with `/`. The DM compiler accepts `.` and `:` here, but they are
+easily confused with field access.
+
+    /mob.living
+        var/x"#;
+
+    DM0204 nested_absolute_path: "an absolute path is nested inside a block",
+r#"A path beginning with `/` inside a type block refers to the root rather than
+to a child of the enclosing type.
+
+    /mob
+        /obj/item  // declares /obj/item, not /mob/obj/item"#;
+
+    DM0205 relative_type_definition: "a type is defined by a relative path",
+r#"Raised when `disallow_relative_type_definitions` is set in
+`[code_standards]`, on types defined inside another type's block.
+
+    /mob
+        living  // prefer /mob/living"#;
+
+    DM0206 relative_proc_definition: "a proc is defined by a relative path",
+r#"Raised when `disallow_relative_proc_definitions` is set in
+`[code_standards]`, on procs defined inside a type block.
+
+    /mob
+        proc/test()  // prefer /mob/proc/test()"#;
+
+    DM0207 item_has_no_effect: "a `var` or `proc` item declares nothing",
+r#"A `var`, `var/flag`, or `proc` item without a name after it has no effect.
+
+    /mob
+        var
+        proc"#;
+
+    DM0208 var_in_proc_paramater: "a proc parameter starts with `var/`",
+r#"Proc parameters are always vars, so `var/` in a parameter list has no effect.
+
+    /proc/test(var/x)  // prefer /proc/test(x)"#;
+
+    DM0209 static_in_proc_parameter: "a proc parameter is declared `static`",
+r#"`static/` has no effect on proc parameters.
+
+    /proc/test(static/x)"#;
+
+    DM0210 semicolon_in_proc_parameter: "a proc parameter list contains `;`",
+r#"The DM compiler accepts `;` between proc parameters, but it has no effect.
+
+    /proc/test(a; b)"#;
+
+    DM0211 bad_input_type: "an `as` clause names an unknown input type",
+r#"The types in an `as` clause of a parameter or `input()` must be input types
+such as `num`, `text`, `mob`, or `anything`.
+
+    /proc/test(x as number)"#;
+
+    DM0212 in_precedes_as: "an `input()` has `as` after `in`",
+r#"In `input()`, an `as` clause which follows the `in` clause is silently
+ignored by the DM compiler.
+
+    input("Pick one") in choices as text  // prefer as text in choices"#;
+
+    DM0213 tmp_no_effect: "a local var is declared `tmp`",
+r#"`var/tmp` only affects savefiles, so has no effect on local vars.
+
+    /proc/test()
+        var/tmp/x = 1"#;
+
+    DM0214 final_no_effect: "a local var is declared `SpacemanDMM_final`",
+r#"`SpacemanDMM_final` only prevents overriding type vars, so has no effect on
+local vars."#;
+
+    DM0215 as_local_var: "a local var has an `as` clause",
+r#"An `as` clause on a local var declaration has no effect.
+
+    /proc/test()
+        var/x as num"#;
+
+    DM0216 conditional_missing_else: "a `? :` operator has no else arm",
+r#"The DM compiler treats a missing else arm of the conditional operator as
+`null`. Write `null` explicitly.
+
+    var/x = y ? 1 :"#;
+
+    DM0217 bad_locate: "`locate()` with an `in` clause is malformed",
+r#"`locate(X in Y)` should be written `locate(X) in Y`, and the coordinate form
+`locate(x, y, z)` cannot take an `in` clause.
+
+    var/mob/M = locate(/mob in world)"#;
+
+    DM0218 empty_parentheses: "`()` is used as a value",
+r#"Empty parentheses evaluate to `null`. Write `null` explicitly.
+
+    var/x = ()"#;
+
+    // ------------------------------------------------------------------------
+    // DM03xx: object tree and constants
+
+    DM0301 bad_parent_type: "`parent_type` is invalid",
+r#"A type's `parent_type` does not name an existing type, is not a string or
+typepath, or is set on a type whose parent cannot be changed.
+
+    /mob/special
+        parent_type = /nonexistent"#;
+
+    DM0302 bad_declaration: "a declaration is malformed",
+r#"A declaration cannot be interpreted as either a var or a proc, for example
+because a proc declaration has no name or a var declaration has a parameter
+list."#;
+
+    DM0303 duplicate_definition: "a proc is declared twice on the same type",
+r#"A type declares a proc with `proc/` or `verb/` which it or a parent already
+declared. Overrides should omit the `proc/` or `verb/`.
+
+    /mob/proc/test()
+    /mob/proc/test()"#;
+
+    DM0304 override_precedes_definition: "a proc override comes before its definition",
+r#"An override of a proc appears earlier in the include order than the proc's
+definition. The DM compiler reorders them only in some situations.
+See http://www.byond.com/forum/post/2441385"#;
+
+    DM0305 undefined_var: "a var is not defined",
+r#"An identifier or field access names a var which does not exist on the type
+being accessed, or in any enclosing scope.
+
+    /mob/proc/test()
+        world << helth"#;
+
+    DM0306 constant_evaluation: "a value cannot be evaluated at compile time",
+r#"A type var's initial value, or a preprocessor condition, must be a constant
+expression. This includes references to vars which are not `const` or which
+refer to themselves.
+
+    /mob
+        var/x = y
+        var/y = x"#;
+
+    DM0307 malformed_builtin_call: "a builtin is called with invalid arguments in a constant",
+r#"A call to `rgb()`, `defined()`, or a trigonometric function in a constant
+expression has the wrong number or kind of arguments.
+
+    var/color = rgb(255, 0)"#;
+    DM0308 newer_than_target: "a builtin or syntax is newer than `target_version`",
+r#"Code uses a builtin proc, var, or type, or a syntax feature, which
+was added in a newer version of BYOND than the `target_version` set in
+`SpacemanDMM.toml`. The project will not compile with that version.
+
+    // with target_version = 513
+    var/list/L = list(1, 2, 3)
+    L.Splice(1, 2, 4)"#;
+
+    // ------------------------------------------------------------------------
+    // DM04xx: maps
+
+    DM0401 malformed_map: "a `.dmm` map file is malformed",
+r#"A map file could not be read, for example because of a bad coordinate or a
+tile with no value."#;
+
+    // ------------------------------------------------------------------------
+    // DM10xx: DreamChecker analysis
+
+    DM1001 undefined_type: "a typepath does not name a type",
+r#"A typepath in code or a type hint names a type which does not exist.
+
+    /proc/test()
+        new /obj/nonexistent"#;
+
+    DM1002 undefined_proc: "a proc is not defined",
+r#"A call names a proc which does not exist on the type being called, or
+globally.
+
+    /mob/proc/test()
+        src.nonexistent()"#;
+
+    DM1003 bad_keyword_argument: "a keyword argument does not match a parameter",
+r#"A call passes a keyword argument which is not a parameter of the proc, or
+passes positional arguments after keyword arguments. A note is added if an
+override of the proc has the parameter.
+
+    /proc/test(a)
+    /proc/caller()
+        test(b = 1)"#;
+
+    DM1004 override_missing_keyword_arg: "an override lacks a parameter used by keyword",
+r#"A proc is called elsewhere with a keyword argument which one of its
+overrides does not have as a parameter.
+
+    /mob/proc/test(a)
+    /mob/living/test()  // missing a
+    /proc/caller(mob/M)
+        M.test(a = 1)"#;
+
+    DM1005 proc_has_no_parent: "`..()` is called in a proc with no parent",
+r#"`..()` calls the parent proc, but this proc is not an override.
+
+    /mob/proc/test()
+        ..()"#;
+
+    DM1006 no_typehint_implicit_new: "`new` has no type and no type hint",
+r#"`new()` without a type creates an instance of the var's declared type, but
+the var being assigned has none.
+
+    var/x = new()"#;
+
+    DM1007 field_access_static_type: "a field is accessed on a value without a type hint",
+r#"`.field` access needs the static type of the value to check the field. Use
+`:field` to access fields without checking, or declare the var's type.
+
+    /proc/test(x)
+        world << x.name  // prefer /proc/test(atom/x)"#;
+
+    DM1008 proc_call_static_type: "a proc is called on a value without a type hint",
+r#"`.proc()` calls need the static type of the value to check the proc. Use
+`:proc()` to call without checking, or declare the var's type.
+
+    /proc/test(x)
+        x.Move()  // prefer /proc/test(atom/movable/x)"#;
+
+    DM1009 no_operator_overload: "an operator is used on a type which does not overload it",
+r#"An operator is applied to a datum whose type does not define the matching
+`operator` proc.
+
+    /proc/test(mob/M)
+        M++"#;
+
+    DM1010 ambiguous_in_lhs: "the left side of `in` is ambiguous",
+r#"`in` binds more loosely than most operators, so `!x in y` means
+`(!x) in y`. Add parentheses to make the intent clear.
+
+    if (!x in list)  // prefer !(x in list)"#;
+
+    DM1011 ambiguous_not_bitwise: "`!` on the left of a bitwise operator is ambiguous",
+r#"`!x & y` means `(!x) & y`, which is rarely intended. Add parentheses.
+
+    if (!flags & FLAG)  // prefer !(flags & FLAG)"#;
+
+    DM1012 unreachable_code: "code can never be executed",
+r#"Code follows a statement which always returns, or is in a branch whose
+condition can never be reached.
+
+    /proc/test()
+        return
+        world << "never""#;
+
+    DM1013 control_condition_static: "a control flow condition is a constant",
+r#"An `if` or loop condition is a literal or constant expression.
+
+    if ("text")
+        world << "always""#;
+
+    DM1014 if_condition_determinate: "an `if` condition is always true or always false",
+r#"Analysis shows the condition of an `if` always has the same truthiness.
+
+    /proc/test()
+        var/x = 1
+        if (x)
+            world << "always""#;
+
+    DM1015 loop_condition_determinate: "a loop condition is always true or always false",
+r#"Analysis shows the condition of a loop always has the same truthiness.
+
+    /proc/test()
+        var/x = 0
+        while (x)
+            world << "never""#;
+
+    DM1016 empty_for_range: "a `for` range loop never runs",
+r#"The range of a `for (var/i in a to b)` loop is empty.
+
+    for (var/i in 10 to 1)
+        world << i"#;
+
+    DM1017 bad_iteration: "a loop iterates over a value which cannot be iterated",
+r#"A `for (var/x in y)` loop's list is a datum which is not a list or atom.
+
+    /proc/test(datum/D)
+        for (var/x in D)
+            world << x"#;
+
+    DM1018 bad_catch_parameters: "a `catch` has too many parameters",
+r#"A `catch` block may have at most one parameter, the exception.
+
+    catch (e, f)"#;
+
+    DM1019 return_in_spawn: "a value is returned inside `spawn`",
+r#"The return value of a `spawn` block is discarded.
+
+    spawn(10)
+        return 1"#;
+
+    DM1020 invalid_filter: "a `filter()` call is invalid",
+r#"A `filter()` call is missing its `type`, names an unknown filter type, or
+passes a parameter or flag which that filter type does not accept.
+
+    filter(type = "blur", radius = 2)  // blur takes size"#;
+
+    DM1021 redeclared_var: "a var is declared again on a subtype",
+r#"A subtype uses `var/` to declare a var which a parent type already declared.
+Overrides should omit `var/`.
+
+    /mob/var/x = 1
+    /mob/living/var/x = 2  // prefer /mob/living/x = 2"#;
+
+    DM1022 unknown_setting: "a `set` statement names an unknown setting",
+r#"`set` may only be used with proc settings such as `name`, `category`, or
+`background`, or with `SpacemanDMM_*` directives.
+
+    set nonexistent = 1"#;
+
+    DM1023 invalid_set_value: "a `set` statement has an invalid value",
+r#"The value of a builtin `set` statement has the wrong type or range.
+
+    set background = "yes"
+    set invisibility = 200"#;
+
+    DM1024 invalid_return_type: "a `SpacemanDMM_return_type` is invalid",
+r#"The value of `set SpacemanDMM_return_type` could not be understood as a type
+expression, or could not be evaluated at a call site."#;
+
+    DM1025 unresolvable_string_path: "a string passed as a typepath does not name one",
+r#"A constant string passed to `text2path()`, `locate()`, `ispath()`,
+`istype()` or `typesof()` does not name an existing type or proc.
+
+    var/path = text2path("/obj/nonexistent")"#;
+
+    DM1026 text_macro: "a text macro is misused",
+r#"A text macro in a string has no embedded expression to apply to, or is
+applied to the wrong kind of value. `\the`, `\he`, `\icon`, and similar apply
+to the next embedded expression, and `\s` and `\th` to the previous one.
+`\proper` and `\improper` only work at the start of a string.
+
+    usr << "[src] drops \his"
+    usr << "\the [5] and \he [list()]""#;
+
+    DM1027 unknown_escape: "an escape sequence in a string is not known",
+r#"A `\` in a string is followed by letters which are not a text macro or
+escape known to DreamChecker. To include a backslash, write `\\`.
+
+    usr << "[src] matches \d+""#;
+
+    DM1028 initial_value_assign: "a non-static var is assigned through `::`",
+r#"`type::var` reads the initial value of `var` on that type, which cannot be
+changed at runtime. Only `static` (or `global`) vars may be assigned through
+the scope operator.
+
+    /obj/var/health = 10
+    /proc/test()
+        /obj::health = 20"#;
+
+    // ------------------------------------------------------------------------
+    // DM11xx: SpacemanDMM directives
+
+    DM1101 unknown_linter_setting: "a `SpacemanDMM_*` directive is not recognized",
+r#"A `set SpacemanDMM_*` statement names a directive which DreamChecker does
+not implement.
+
+    set SpacemanDMM_should_not_slep = TRUE"#;
+
+    DM1102 invalid_lint_directive_value: "a directive is set to a non-boolean value",
+r#"`SpacemanDMM_*` directives must be set to `1`, `0`, `TRUE`, or `FALSE`.
+
+    set SpacemanDMM_should_call_parent = "yes""#;
+
+    DM1103 disabled_directive: "a directive which cannot be disabled is set false",
+r#"Some directives are inherited by overrides and cannot be turned off again.
+
+    set SpacemanDMM_should_not_sleep = FALSE"#;
+
+    DM1104 sets_directive_twice: "a directive is set twice in one proc",
+r#"The same `SpacemanDMM_*` directive is set more than once in a proc."#;
+
+    DM1105 incompatible_directive: "a directive cannot be set on global procs",
+r#"Some directives only make sense on procs of a type, such as those about
+overriding."#;
+
+    DM1106 directive_outside_definition: "a directive is set on an override",
+r#"Some directives may only be set where the proc is first declared, not on
+its overrides."#;
+
+    DM1107 must_call_parent: "a proc does not call `..()` as its parent requires",
+r#"A parent proc sets `SpacemanDMM_should_call_parent`, but this override
+never calls `..()`.
+
+    /mob/proc/test()
+        set SpacemanDMM_should_call_parent = TRUE
+    /mob/living/test()
+        return"#;
+
+    DM1108 must_not_override: "a proc overrides one which must not be overridden",
+r#"A parent proc sets `SpacemanDMM_should_not_override`, but this type
+overrides it."#;
+
+    DM1109 must_not_sleep: "a proc which must not sleep may sleep",
+r#"A proc sets `SpacemanDMM_should_not_sleep`, but it calls a builtin that
+sleeps, such as `sleep()` or `input()`, or a proc which does. Notes show the
+chain of calls.
+
+    /mob/proc/test()
+        set SpacemanDMM_should_not_sleep = TRUE
+        sleep(1)"#;
+
+    DM1110 must_be_pure: "a proc which must be pure has side effects",
+r#"A proc sets `SpacemanDMM_should_be_pure`, but it modifies state outside
+itself or calls a proc which does. Notes show where."#;
+
+    DM1111 discarded_pure_result: "the result of a pure proc is discarded",
+r#"A proc which sets `SpacemanDMM_should_be_pure` has no effect other than its
+return value, so calling it as a statement does nothing.
+
+    /proc/double(x)
+        set SpacemanDMM_should_be_pure = TRUE
+        return x * 2
+    /proc/test()
+        double(2)"#;
+
+    DM1112 private_proc: "a private proc is called or overridden outside its type",
+r#"A proc which sets `SpacemanDMM_private_proc` may only be called by, and
+overridden on, the type which declares it."#;
+
+    DM1113 protected_proc: "a protected proc is called from outside its type",
+r#"A proc which sets `SpacemanDMM_protected_proc` may only be called by the
+type which declares it and its subtypes."#;
+
+    DM1114 private_var: "a private var is used outside its type",
+r#"A var declared `var/SpacemanDMM_private` may only be accessed and
+overridden by the type which declares it."#;
+
+    DM1115 protected_var: "a protected var is used from outside its type",
+r#"A var declared `var/SpacemanDMM_protected` may only be accessed by the type
+which declares it and its subtypes."#;
+
+    DM1116 final_var: "a final var is overridden",
+r#"A var declared `var/SpacemanDMM_final` may not have its value changed by
+subtypes."#;
+
+    DM1117 redefined_proc: "a proc is defined twice on the same type",
+r#"A type has two bodies for the same proc, so the second replaces the first.
+Set `SpacemanDMM_can_be_redefined` on the first to allow this.
+
+    /mob/test()
+        return 1
+    /mob/test()
+        return 2"#;
+
+    DM1118 must_use_result: "the result of a proc which must be used is discarded",
+r#"A proc which sets `SpacemanDMM_must_use_result`, or whose parent does, is
+called as a statement, so its return value is lost. Builtins which only
+compute a value, such as `copytext()` and `list.Copy()`, are included.
+
+    /proc/try_insert(obj/item/I)
+        set SpacemanDMM_must_use_result = TRUE
+        return I.forceMove(src)
+    /proc/test(obj/item/I)
+        try_insert(I)"#;
+
+    // ------------------------------------------------------------------------
+    // DM12xx: code standards
+
+    DM1201 proc_complexity: "a proc's cyclomatic complexity is over the maximum",
+r#"Raised when `max_proc_complexity` is set in `[code_standards]`."#;
+
+    DM1202 proc_statements: "a proc has more statements than the maximum",
+r#"Raised when `max_proc_statements` is set in `[code_standards]`."#;
+
+    DM1203 proc_nesting: "a proc's blocks nest deeper than the maximum",
+r#"Raised when `max_proc_nesting` is set in `[code_standards]`."#;
+
+    DM1204 proc_locals: "a proc declares more local vars than the maximum",
+r#"Raised when `max_proc_locals` is set in `[code_standards]`."#;
+
+    DM1205 duplicate_code: "a block of statements is duplicated",
+r#"Raised when `duplicate_code_min_statements` is set in `[code_standards]`,
+on blocks of at least that many statements which appear in more than one
+place. Notes show every copy."#;
+
+    DM1206 unused_type_var: "a type var is never used",
+r#"Opt-in. A type var's name is never referenced by code, prefabs, or map var
+edits.
+
+    /mob/var/never_read = 1"#;
+
+    // ------------------------------------------------------------------------
+    // DM13xx: resources
+
+    DM1301 missing_resource: "a resource file does not exist",
+r#"A resource literal, or a constant path passed to `file()`, `icon()`, or
+`sound()`, does not exist relative to the environment or any `FILE_DIR`, or
+exists only with different capitalization.
+
+    icon = 'icons/mob/missing.dmi'"#;
+
+    DM1302 missing_icon_state: "an `icon_state` is not in its icon",
+r#"A constant `icon_state` on a type or map instance is not one of the states
+in the `.dmi` file it uses. A close match is suggested if there is one.
+
+    /obj/gun
+        icon = 'icons/guns.dmi'
+        icon_state = "lazer""#;
+
+    // ------------------------------------------------------------------------
+    // DM14xx: project-specific lints
+
+    DM1401 custom_lint: "code matches a `[[lint]]` from the configuration",
+r#"A proc call or assignment matches the pattern of a project-specific lint
+defined in a `[[lint]]` section of `SpacemanDMM.toml`. The message and
+severity come from that section.
+
+    [[lint]]
+    name = "to_chat_text"
+    pattern = "to_chat(_, !text)"
+    message = "to_chat() must be passed a string""#;
+
+    DM1402 invalid_custom_lint: "a `[[lint]]` names a type or proc which does not exist",
+r#"A `[[lint]]` section in `SpacemanDMM.toml` names a typepath, in its pattern or
+in `within` or `outside`, which is not in the object tree. The lint is skipped.
+
+    [[lint]]
+    name = "no_sleep_in_process"
+    pattern = "sleep()"
+    within = ["/datum/proc/proccess"]"#;
+
+    // DM15xx: Topic() links
+
+    DM1501 href_key_not_produced: "a `Topic()` reads an `href_list` key no link produces",
+r#"A `Topic()` override reads a constant key from `href_list`, but no link
+built for that type, by a string such as `"?src=[REF(src)];action=eject"`,
+has a parameter with that key. This is often a typo in either place. It is
+not reported for types with links whose keys are not all constant.
+
+    /obj/machine/Topic(href, href_list)
+        if(href_list["actoin"] == "eject")
+            eject()"#;
+
+    DM1502 href_key_not_handled: "a link has a key its target's `Topic()` never reads",
+r#"A link built by a string such as `"?src=[REF(src)];action=eject"` has a
+parameter whose key is not read from `href_list` by the `Topic()` of the
+type it is sent to, its parents, or its subtypes. It is not reported if a
+`Topic()` uses `href_list` other than by indexing it with a constant.
+
+    usr << browse("<a href='?src=[REF(src)];actoin=eject'>Eject</a>")"#;
+
+    // ------------------------------------------------------------------------
+    // DM16xx: verbs
+
+    DM1601 verb_input_type: "a verb argument's input type can't produce its declared type",
+r#"A verb argument's `as` clause allows input which can never be a value of
+the argument's declared type, such as an object argument taking text.
+
+    /mob/verb/give(obj/item/I as mob)"#;
+
+    DM1602 duplicate_verb_name: "two verbs on related types share a name and category",
+r#"Two verbs, on the same type or on a type and one of its subtypes, are shown
+with the same name in the same category, so players can only use one of them.
+The name is from `set name`, or the proc's name with underscores as spaces.
+
+    /mob/verb/look()
+    /mob/living/verb/look_around()
+        set name = "look""#;
+
+    DM1603 proc_added_to_verbs: "a proc which is not a verb is added to `verbs`",
+r#"A proc is added to a `verbs` list, but is declared with `proc/` rather than
+`verb/` and sets none of the settings which only apply to verbs, such as
+`set name` or `set category`.
+
+    /client/proc/helper()
+    /client/New()
+        verbs += /client/proc/helper"#;
+}
@@ -2,11 +2,16 @@
 
 use std::fmt;
 
+use serde::{Deserialize, Serialize};
+
 /// A collection of documentation comments targeting the same item.
 #[derive(Default, Clone, Debug, PartialEq)]
 pub struct DocCollection {
     elems: Vec<DocComment>,
     pub builtin_docs: BuiltinDocs,
+    /// For builtins, the BYOND version which introduced this item, if it is
+    /// newer than 512.
+    pub since: Option<u32>,
 }
 
 impl DocCollection {
@@ -20,6 +25,11 @@ impl DocCollection {
         self.elems.extend(collection.elems);
     }
 
+    /// Access the doc comments in this collection.
+    pub fn comments(&self) -> &[DocComment] {
+        &self.elems
+    }
+
     /// Check whether this collection is empty.
     pub fn is_empty(&self) -> bool {
         self.elems.iter().all(|c| c.is_empty())
@@ -60,7 +70,7 @@ impl DocCollection {
 }
 
 /// A documentation comment.
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct DocComment {
     pub kind: CommentKind,
     pub target: DocTarget,
@@ -95,7 +105,7 @@ impl fmt::Display for DocComment {
 }
 
 /// The possible documentation comment kinds.
-#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub enum CommentKind {
     /// A block `/** */` comment.
     Block,
@@ -197,7 +207,7 @@ fn is_empty(text: &str, ignore_char: char) -> bool {
 }
 
 /// The possible items that a documentation comment may target.
-#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub enum DocTarget {
     /// Starting with `*` or `/`, referring to the following item.
     FollowingItem,
@@ -1,13 +1,14 @@
 //! Error, warning, and other diagnostics handling.
 
 use std::{fmt, error, io};
+use std::ops::Range;
 use std::path::{PathBuf, Path};
 use std::cell::{RefCell, Ref, RefMut};
 use std::collections::HashMap;
 
 use termcolor::{ColorSpec, Color};
 
-use crate::config::Config;
+use crate::config::{Config, WarningLevel};
 
 /// An identifier referring to a loaded file.
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
@@ -127,6 +128,7 @@ impl Context {
                 let file = self.register_file(toml);
                 let (line, column) = io_error.line_col().unwrap_or((1, 1));
                 DMError::new(Location { file, line, column }, "Error reading configuration file")
+                    .with_errortype("invalid_config")
                     .with_boxed_cause(io_error.into_boxed_error())
                     .register(self);
             }
@@ -144,6 +146,28 @@ impl Context {
         self.config.borrow()
     }
 
+    /// Replace the configuration, as when it was not read from a file.
+    pub fn set_config(&self, config: Config) {
+        *self.config.borrow_mut() = config;
+    }
+
+    /// Set the level of a diagnostic from a `#pragma` directive, until the end
+    /// of the file it is in.
+    pub fn add_pragma(&self, location: Location, errortype: String, level: WarningLevel) {
+        self.config.borrow_mut().add_pragma(location, errortype, level);
+    }
+
+    /// Report a builtin or syntax feature introduced in a BYOND version newer
+    /// than the configured `target_version`.
+    pub fn check_target_version(&self, location: Location, since: u32, what: impl fmt::Display) {
+        let target = self.config().target_version;
+        if let Some(target) = target.filter(|&target| target < since) {
+            DMError::new(location, format!("{} requires BYOND {}, but target_version is {}", what, since, target))
+                .with_errortype("newer_than_target")
+                .register(self);
+        }
+    }
+
     /// Set a severity at and above which errors will be printed immediately.
     pub fn set_print_severity(&mut self, print_severity: Option<Severity>) {
         self.print_severity = print_severity;
@@ -191,6 +215,26 @@ impl Context {
         Ref::map(self.errors.borrow(), |x| &**x)
     }
 
+    /// Remove and return all diagnostics generated so far.
+    pub fn take_errors(&self) -> Vec<DMError> {
+        self.errors.take()
+    }
+
+    /// Create a context with the same files and configuration but no
+    /// diagnostics, which can be sent to another thread.
+    ///
+    /// Diagnostics registered to the fork can be moved back with
+    /// `take_errors`.
+    pub fn fork(&self) -> Context {
+        Context {
+            files: self.files.clone(),
+            errors: Default::default(),
+            config: RefCell::new(self.config.borrow().clone()),
+            print_severity: None,
+            io_time: Default::default(),
+        }
+    }
+
     /// Mutably access the diagnostics list. Dangerous.
     #[doc(hidden)]
     pub fn errors_mut(&self) -> RefMut<Vec<DMError>> {
@@ -209,6 +253,9 @@ impl Context {
 
         w.set_color(&error.severity.style())?;
         write!(w, "{}", error.severity())?;
+        if let Some(code) = error.code() {
+            write!(w, "[{}]", code)?;
+        }
         w.reset()?;
         writeln!(w, ": {}", error.description())?;
 
@@ -415,6 +462,7 @@ pub struct DMError {
     notes: Vec<DiagnosticNote>,
     cause: Option<Box<dyn error::Error + Send + Sync>>,
     errortype: Option<&'static str>,
+    edits: Vec<SuggestedEdit>,
 }
 
 /// An additional note attached to an error, at some other location.
@@ -424,6 +472,19 @@ pub struct DiagnosticNote {
     description: String,
 }
 
+/// A suggested replacement of some source text, as part of an automatic fix.
+///
+/// The edit records the text it expects to replace, so that it can be skipped
+/// if the source has changed or the span came from a macro expansion. Lines
+/// after a newline in the replacement should be indented to match the line on
+/// which the edit begins.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SuggestedEdit {
+    span: Range<Location>,
+    original: String,
+    replacement: String,
+}
+
 #[allow(unused_variables)]
 impl DMError {
     pub fn new<S: Into<String>>(location: Location, desc: S) -> DMError {
@@ -435,6 +496,7 @@ impl DMError {
             notes: Vec::new(),
             cause: None,
             errortype: None,
+            edits: Vec::new(),
         }
     }
 
@@ -475,6 +537,14 @@ impl DMError {
         self
     }
 
+    /// Suggest replacing `original`, starting at `start`, with `replacement`.
+    ///
+    /// All the edits suggested on one error make up a single fix.
+    pub fn with_edit<S: Into<String>>(mut self, start: Location, original: &str, replacement: S) -> DMError {
+        self.edits.push(SuggestedEdit::new(start, original, replacement));
+        self
+    }
+
     pub fn with_location(mut self, location: Location) -> DMError {
         self.location = location;
         self
@@ -510,10 +580,20 @@ impl DMError {
         self.errortype
     }
 
+    /// Get the stable diagnostic code associated with this error's errortype.
+    pub fn code(&self) -> Option<&'static str> {
+        crate::diagnostics::code_of(self.errortype?)
+    }
+
     /// Get the additional notes associated with this error.
     pub fn notes(&self) -> &[DiagnosticNote] {
         &self.notes
     }
+
+    /// Get the suggested edits which together fix this error.
+    pub fn suggested_edits(&self) -> &[SuggestedEdit] {
+        &self.edits
+    }
 }
 
 impl fmt::Display for DMError {
@@ -542,8 +622,42 @@ impl Clone for DMError {
             notes: self.notes.clone(),
             cause: None,  // not trivially cloneable
             errortype: self.errortype,
+            edits: self.edits.clone(),
+        }
+    }
+}
+
+impl SuggestedEdit {
+    pub fn new<S: Into<String>>(start: Location, original: &str, replacement: S) -> SuggestedEdit {
+        let mut end = start;
+        match original.rfind('\n') {
+            Some(idx) => {
+                end.line += original.matches('\n').count() as u32;
+                end.column = (original.len() - idx) as u16;
+            }
+            None => end.column += original.len() as u16,
+        }
+        SuggestedEdit {
+            span: start..end,
+            original: original.to_owned(),
+            replacement: replacement.into(),
         }
     }
+
+    /// Get the span of source text to be replaced.
+    pub fn span(&self) -> Range<Location> {
+        self.span.clone()
+    }
+
+    /// Get the source text which the span is expected to contain.
+    pub fn original(&self) -> &str {
+        &self.original
+    }
+
+    /// Get the text to replace the span with.
+    pub fn replacement(&self) -> &str {
+        &self.replacement
+    }
 }
 
 impl DiagnosticNote {
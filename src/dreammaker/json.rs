@@ -0,0 +1,417 @@
+//! A stable JSON representation of the object tree for external tools.
+//!
+//! The schema is versioned by `SCHEMA_VERSION`, which is bumped whenever a
+//! field is removed or changes meaning. Adding fields does not bump it, so
+//! consumers should ignore fields they do not recognize.
+//!
+//! Expressions are written as DM source text, and evaluated constants are
+//! written as tagged `Value` objects.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::ast::{self, FormatTreePath};
+use crate::constants::{Constant, Pop};
+use crate::docs::DocCollection;
+use crate::objtree::{self, ObjectTree, TypeRef};
+use crate::Context;
+
+/// The current version of the schema.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Which parts of the object tree to serialize.
+#[derive(Debug, Clone, Default)]
+pub struct Filter {
+    /// Only include these types and their subtypes. Empty means all types.
+    pub paths: Vec<String>,
+    /// Include vars and procs which are only defined by BYOND itself.
+    pub builtins: bool,
+}
+
+impl Filter {
+    fn includes(&self, path: &str) -> bool {
+        self.paths.is_empty() || self.paths.iter().any(|filter| {
+            let filter = filter.trim_end_matches('/');
+            path == filter || (path.starts_with(filter) && path[filter.len()..].starts_with('/'))
+        })
+    }
+}
+
+/// The root of the document.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Tree {
+    pub version: u32,
+    /// Every included type, sorted by path.
+    pub types: Vec<Type>,
+}
+
+impl Tree {
+    /// Describe the types of an object tree which pass the given filter.
+    pub fn new(context: &Context, objtree: &ObjectTree, filter: &Filter) -> Tree {
+        let mut types: Vec<Type> = objtree.iter_types()
+            .filter(|ty| filter.includes(&ty.path))
+            .map(|ty| Type::new(context, ty, filter))
+            .collect();
+        types.sort_by(|a, b| a.path.cmp(&b.path));
+        Tree {
+            version: SCHEMA_VERSION,
+            types,
+        }
+    }
+
+    /// Find a type by its path.
+    pub fn find(&self, path: &str) -> Option<&Type> {
+        self.types
+            .binary_search_by(|ty| ty.path.as_str().cmp(path))
+            .ok()
+            .map(|idx| &self.types[idx])
+    }
+}
+
+/// A source location.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+    /// The path of the file, or `(builtins)` for things defined by BYOND.
+    pub file: String,
+    pub line: u32,
+    pub column: u16,
+}
+
+impl Location {
+    fn new(context: &Context, location: crate::Location) -> Location {
+        Location {
+            file: context.file_path(location.file).display().to_string(),
+            line: location.line,
+            column: location.column,
+        }
+    }
+}
+
+/// A type, with the vars and procs it declares or overrides.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Type {
+    /// The path of the type. The root, where globals live, is empty.
+    pub path: String,
+    /// The path of the parent type, taking `parent_type` into account.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent: Option<String>,
+    pub location: Location,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub docs: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub vars: BTreeMap<String, Var>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub procs: BTreeMap<String, Proc>,
+}
+
+impl Type {
+    fn new(context: &Context, ty: TypeRef, filter: &Filter) -> Type {
+        let mut vars = BTreeMap::new();
+        for (name, var) in ty.vars.iter() {
+            let builtin = var.value.location.is_builtins()
+                && var.declaration.iter().all(|decl| decl.location.is_builtins());
+            if filter.builtins || !builtin {
+                vars.insert(name.clone(), Var::new(context, var));
+            }
+        }
+
+        let mut procs = BTreeMap::new();
+        for (name, proc) in ty.procs.iter() {
+            let definitions: Vec<ProcDefinition> = proc.value.iter()
+                .filter(|value| filter.builtins || !value.location.is_builtins())
+                .map(|value| ProcDefinition::new(context, value))
+                .collect();
+            let declared = proc.declaration.iter()
+                .any(|decl| filter.builtins || !decl.location.is_builtins());
+            if definitions.is_empty() && !declared {
+                continue;
+            }
+            procs.insert(name.clone(), Proc {
+                declaration: proc.declaration.as_ref().map(|decl| ProcDeclaration {
+                    kind: decl.kind.name().to_owned(),
+                    location: Location::new(context, decl.location),
+                    private: decl.is_private,
+                    protected: decl.is_protected,
+                }),
+                definitions,
+            });
+        }
+
+        Type {
+            path: ty.path.clone(),
+            parent: ty.parent_type().map(|parent| parent.path.clone()),
+            location: Location::new(context, ty.location),
+            docs: docs_text(&ty.docs),
+            vars,
+            procs,
+        }
+    }
+}
+
+/// A var declared or overridden on a type.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Var {
+    /// Present if this type declares the var rather than overriding it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub declaration: Option<VarDeclaration>,
+    pub location: Location,
+    /// The value as written in the source.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expression: Option<String>,
+    /// The evaluated value, if it could be evaluated.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub docs: Option<String>,
+}
+
+impl Var {
+    fn new(context: &Context, var: &objtree::TypeVar) -> Var {
+        Var {
+            declaration: var.declaration.as_ref().map(|decl| VarDeclaration {
+                type_path: type_path(&decl.var_type),
+                flags: flags(&decl.var_type),
+                location: Location::new(context, decl.location),
+            }),
+            location: Location::new(context, var.value.location),
+            expression: var.value.expression.as_ref().map(ToString::to_string),
+            value: var.value.constant.as_ref().map(Value::new),
+            docs: docs_text(&var.value.docs),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct VarDeclaration {
+    /// The declared type, such as `/obj` in `var/obj/thing`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub type_path: Option<String>,
+    /// Modifiers such as `static` and `tmp`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub flags: Vec<String>,
+    pub location: Location,
+}
+
+/// A proc or verb declared or overridden on a type.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Proc {
+    /// Present if this type declares the proc rather than overriding it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub declaration: Option<ProcDeclaration>,
+    /// Each definition of the proc on this type, in source order.
+    pub definitions: Vec<ProcDefinition>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ProcDeclaration {
+    /// Either `proc` or `verb`.
+    pub kind: String,
+    pub location: Location,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub private: bool,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub protected: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ProcDefinition {
+    pub location: Location,
+    pub parameters: Vec<Parameter>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub docs: Option<String>,
+}
+
+impl ProcDefinition {
+    fn new(context: &Context, value: &objtree::ProcValue) -> ProcDefinition {
+        ProcDefinition {
+            location: Location::new(context, value.location),
+            parameters: value.parameters.iter().map(Parameter::new).collect(),
+            docs: docs_text(&value.docs),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Parameter {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub type_path: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub flags: Vec<String>,
+    /// The default value as written in the source.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<String>,
+    /// The `as` input type, such as `mob|obj`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input_type: Option<String>,
+    /// The `in` list as written in the source.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub in_list: Option<String>,
+}
+
+impl Parameter {
+    fn new(param: &ast::Parameter) -> Parameter {
+        Parameter {
+            name: param.name.clone(),
+            type_path: type_path(&param.var_type),
+            flags: flags(&param.var_type),
+            default: param.default.as_ref().map(ToString::to_string),
+            input_type: param.input_type.map(|input_type| input_type.to_string()),
+            in_list: param.in_list.as_ref().map(ToString::to_string),
+        }
+    }
+}
+
+/// An evaluated constant.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Value {
+    Null {
+        /// The declared type of a typed `null`.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        type_path: Option<String>,
+    },
+    Int {
+        value: i32,
+    },
+    /// Floats which JSON cannot represent are written as the strings `inf`,
+    /// `-inf`, or `nan`.
+    Float {
+        #[serde(serialize_with = "serialize_float", deserialize_with = "deserialize_float")]
+        value: f32,
+    },
+    String {
+        value: String,
+    },
+    Resource {
+        value: String,
+    },
+    Prefab(Prefab),
+    List {
+        items: Vec<ListItem>,
+    },
+    New {
+        #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+        type_: Option<Prefab>,
+        /// Absent for `new` without an argument list.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        args: Option<Vec<ListItem>>,
+    },
+    /// A call to a constant constructor such as `icon()` or `matrix()`.
+    Call {
+        function: String,
+        args: Vec<ListItem>,
+    },
+}
+
+impl Value {
+    pub fn new(constant: &Constant) -> Value {
+        match constant {
+            Constant::Null(path) => Value::Null {
+                type_path: path.as_ref().map(|path| FormatTreePath(path).to_string()),
+            },
+            Constant::Int(value) => Value::Int { value: *value },
+            Constant::Float(value) => Value::Float { value: *value },
+            Constant::String(value) => Value::String { value: value.clone() },
+            Constant::Resource(value) => Value::Resource { value: value.clone() },
+            Constant::Prefab(pop) => Value::Prefab(Prefab::new(pop)),
+            Constant::List(items) => Value::List { items: list_items(items) },
+            Constant::New { type_, args } => Value::New {
+                type_: type_.as_ref().map(Prefab::new),
+                args: args.as_ref().map(|args| list_items(args)),
+            },
+            Constant::Call(function, args) => Value::Call {
+                function: function.to_string(),
+                args: list_items(args),
+            },
+        }
+    }
+}
+
+/// A type path with optional var overrides.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Prefab {
+    pub path: String,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub vars: BTreeMap<String, Value>,
+}
+
+impl Prefab {
+    fn new(pop: &Pop) -> Prefab {
+        Prefab {
+            path: FormatTreePath(&pop.path).to_string(),
+            vars: pop.vars.iter().map(|(name, value)| (name.clone(), Value::new(value))).collect(),
+        }
+    }
+}
+
+/// An element of a list or argument list, with its association if any.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ListItem {
+    pub key: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<Value>,
+}
+
+fn list_items(items: &[(Constant, Option<Constant>)]) -> Vec<ListItem> {
+    items.iter().map(|(key, value)| ListItem {
+        key: Value::new(key),
+        value: value.as_ref().map(Value::new),
+    }).collect()
+}
+
+fn type_path(var_type: &ast::VarType) -> Option<String> {
+    if var_type.type_path.is_empty() {
+        None
+    } else {
+        Some(FormatTreePath(&var_type.type_path).to_string())
+    }
+}
+
+fn flags(var_type: &ast::VarType) -> Vec<String> {
+    var_type.flags.to_vec().into_iter().map(ToOwned::to_owned).collect()
+}
+
+fn docs_text(docs: &DocCollection) -> Option<String> {
+    if docs.is_empty() {
+        None
+    } else {
+        Some(docs.text())
+    }
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+pub(crate) fn serialize_float<S: Serializer>(value: &f32, serializer: S) -> Result<S::Ok, S::Error> {
+    if value.is_finite() {
+        serializer.serialize_f32(*value)
+    } else if value.is_nan() {
+        serializer.serialize_str("nan")
+    } else if *value > 0. {
+        serializer.serialize_str("inf")
+    } else {
+        serializer.serialize_str("-inf")
+    }
+}
+
+pub(crate) fn deserialize_float<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f32, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Float {
+        Number(f32),
+        Text(String),
+    }
+
+    match Float::deserialize(deserializer)? {
+        Float::Number(value) => Ok(value),
+        Float::Text(text) => match text.as_str() {
+            "nan" => Ok(f32::NAN),
+            "inf" => Ok(f32::INFINITY),
+            "-inf" => Ok(f32::NEG_INFINITY),
+            _ => Err(serde::de::Error::custom(format!("invalid float: {:?}", text))),
+        },
+    }
+}
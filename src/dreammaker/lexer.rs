@@ -3,6 +3,9 @@ use std::io::Read;
 use std::str::FromStr;
 use std::fmt;
 use std::borrow::Cow;
+use std::ops::Range;
+
+use serde::{Deserialize, Serialize};
 
 use super::{DMError, Location, HasLocation, FileId, Context, Severity};
 use super::docs::*;
@@ -14,7 +17,7 @@ macro_rules! table {
         $($literal:expr, $name:ident $(-> $close:ident)*;)*
     ) => {
         $(#[$attr])*
-        #[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
+        #[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize)]
         pub enum $enum_ {
             $(
                 $name,
@@ -100,6 +103,7 @@ table! {
     "//",  LineComment;
     "/=",  DivAssign;
     ":",   Colon -> CloseColon;
+    "::",  Scope;
     ":=",  AssignInto;
     ";",   Semicolon;
     "<",   Less;
@@ -152,15 +156,15 @@ static SPEEDY_TABLE: [(usize, usize); 127] = [
     (2, 3), (3, 5), (5, 6), (6, 8), (0, 0), (8, 10), (10, 14), (14, 15),
     (15, 16), (16, 17), (17, 20), (20, 23), (23, 24), (24, 27), (27, 30), (30, 34),
     (0, 0), (0, 0), (0, 0), (0, 0), (0, 0), (0, 0), (0, 0), (0, 0),
-    (0, 0), (0, 0), (34, 36), (36, 37), (37, 42), (42, 44), (44, 48), (48, 52),
+    (0, 0), (0, 0), (34, 37), (37, 38), (38, 43), (43, 45), (45, 49), (49, 53),
     (0, 0), (0, 0), (0, 0), (0, 0), (0, 0), (0, 0), (0, 0), (0, 0),
     (0, 0), (0, 0), (0, 0), (0, 0), (0, 0), (0, 0), (0, 0), (0, 0),
     (0, 0), (0, 0), (0, 0), (0, 0), (0, 0), (0, 0), (0, 0), (0, 0),
-    (0, 0), (0, 0), (0, 0), (52, 53), (0, 0), (53, 54), (54, 56), (0, 0),
+    (0, 0), (0, 0), (0, 0), (53, 54), (0, 0), (54, 55), (55, 57), (0, 0),
     (0, 0), (0, 0), (0, 0), (0, 0), (0, 0), (0, 0), (0, 0), (0, 0),
     (0, 0), (0, 0), (0, 0), (0, 0), (0, 0), (0, 0), (0, 0), (0, 0),
     (0, 0), (0, 0), (0, 0), (0, 0), (0, 0), (0, 0), (0, 0), (0, 0),
-    (0, 0), (0, 0), (0, 0), (56, 58), (58, 62), (62, 63), (63, 66)];
+    (0, 0), (0, 0), (0, 0), (57, 59), (59, 63), (63, 64), (64, 67)];
 
 #[test]
 fn make_speedy_table() {
@@ -235,7 +239,7 @@ fn filter_punct<'a>(input: &'a [(&'static str, Punctuation)], filter: &[u8]) ->
 }
 
 /// A single DM token.
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum Token {
     /// An end-of-file. Exists as a convenience and is not emitted by the lexer.
     Eof,
@@ -256,7 +260,10 @@ pub enum Token {
     /// An integer literal.
     Int(i32),
     /// A floating-point literal.
-    Float(f32),
+    Float(
+        #[serde(serialize_with = "crate::json::serialize_float", deserialize_with = "crate::json::deserialize_float")]
+        f32
+    ),
     /// A documentation comment.
     DocComment(DocComment),
 }
@@ -482,13 +489,13 @@ enum Directive {
     Stringy,
 }
 
-fn buffer_read<R: Read>(file: FileId, mut read: R) -> Result<Vec<u8>, DMError> {
+pub(crate) fn buffer_read<R: Read>(file: FileId, mut read: R) -> Result<Vec<u8>, DMError> {
     let mut buffer = Vec::new();
 
     if let Err(error) = read.read_to_end(&mut buffer) {
         let mut tracker = LocationTracker::new(file, buffer.as_slice().into());
         tracker.by_ref().count();
-        return Err(DMError::new(tracker.location(), "i/o error reading file").with_cause(error));
+        return Err(DMError::new(tracker.location(), "i/o error reading file").with_errortype("io_error").with_cause(error));
     }
 
     Ok(buffer)
@@ -504,13 +511,13 @@ pub fn buffer_file(file: FileId, path: &std::path::Path) -> Result<Vec<u8>, DMEr
 
     let mut read = match std::fs::File::open(path) {
         Ok(read) => read,
-        Err(error) => return Err(DMError::new(Location { file, line: 1, column: 1 }, "i/o error opening file").with_cause(error)),
+        Err(error) => return Err(DMError::new(Location { file, line: 1, column: 1 }, "i/o error opening file").with_errortype("io_error").with_cause(error)),
     };
 
     if let Err(error) = read.read_to_end(&mut buffer) {
         let mut tracker = LocationTracker::new(file, buffer.as_slice().into());
         tracker.by_ref().count();
-        return Err(DMError::new(tracker.location(), "i/o error reading file").with_cause(error));
+        return Err(DMError::new(tracker.location(), "i/o error reading file").with_errortype("io_error").with_cause(error));
     }
 
     Ok(buffer)
@@ -604,6 +611,8 @@ pub struct Lexer<'ctx> {
     close_allowed: bool,
     directive: Directive,
     interp_stack: Vec<Interpolation>,
+    /// The byte offset of the first byte of the last token returned.
+    token_start: usize,
 }
 
 impl<'ctx> fmt::Debug for Lexer<'ctx> {
@@ -639,6 +648,7 @@ impl<'ctx> Lexer<'ctx> {
             close_allowed: true,
             directive: Directive::None,
             interp_stack: Vec::new(),
+            token_start: 0,
         }
     }
 
@@ -662,6 +672,14 @@ impl<'ctx> Lexer<'ctx> {
         self.input.remaining()
     }
 
+    /// Lex the next token along with the range of input bytes it covers. The
+    /// whitespace and comments skipped before it are not included.
+    pub fn next_spanned(&mut self) -> Option<(LocatedToken, Range<usize>)> {
+        let token = Iterator::next(self)?;
+        let end = self.input.offset - self.next.is_some() as usize;
+        Some((token, self.token_start..end))
+    }
+
     fn next(&mut self) -> Option<u8> {
         if let Some(next) = self.next.take() {
             return Some(next);
@@ -717,7 +735,7 @@ impl<'ctx> Lexer<'ctx> {
             match self.next() {
                 Some(val) => buffer[1] = val,
                 None => {
-                    self.context.register_error(self.error("still skipping comments at end of file"));
+                    self.context.register_error(self.error("still skipping comments at end of file").with_errortype("unterminated_comment"));
                     break;
                 }
             }
@@ -769,6 +787,7 @@ impl<'ctx> Lexer<'ctx> {
             } else if backslash {
                 if ch == b'\n' {
                     self.error("backslash in line comment may be commenting out the following line")
+                        .with_errortype("backslash_in_line_comment")
                         .set_severity(Severity::Warning)
                         .register(self.context);
                 }
@@ -872,7 +891,7 @@ impl<'ctx> Lexer<'ctx> {
                 }
             }
             self.context.register_error(self.error(
-                format!("bad base-{} integer \"{}\": {}", radix, buf, original_error)));
+                format!("bad base-{} integer \"{}\": {}", radix, buf, original_error)).with_errortype("bad_number"));
             Token::Int(0)  // fallback
         } else {
             // ignore radix
@@ -880,7 +899,7 @@ impl<'ctx> Lexer<'ctx> {
                 Ok(val) => Token::Float(val),
                 Err(e) => {
                     self.context.register_error(self.error(
-                        format!("bad float \"{}\": {}", buf, e)));
+                        format!("bad float \"{}\": {}", buf, e)).with_errortype("bad_number"));
                     Token::Float(0.0)  // fallback
                 }
             }
@@ -917,7 +936,7 @@ impl<'ctx> Lexer<'ctx> {
                 Some(b'\'') => break,
                 Some(ch) => buf.push(ch),
                 None => {
-                    self.context.register_error(DMError::new(start_loc, "unterminated resource literal"));
+                    self.context.register_error(DMError::new(start_loc, "unterminated resource literal").with_errortype("unterminated_string"));
                     break;
                 }
             }
@@ -936,7 +955,7 @@ impl<'ctx> Lexer<'ctx> {
             let ch = match self.next() {
                 Some(ch) => ch,
                 None => {
-                    self.context.register_error(DMError::new(start_loc, "unterminated string literal"));
+                    self.context.register_error(DMError::new(start_loc, "unterminated string literal").with_errortype("unterminated_string"));
                     break;
                 }
             };
@@ -1001,6 +1020,7 @@ impl<'ctx> Lexer<'ctx> {
                 Some(ch) => buf.push(ch),
                 None => {
                     DMError::new(start_loc, "unterminated raw string")
+                        .with_errortype("unterminated_string")
                         .register(self.context);
                     break;
                 }
@@ -1020,7 +1040,7 @@ impl<'ctx> Lexer<'ctx> {
             // @<LF> - error
             Some(b'\n') |
             None => {
-                self.error("unterminated raw string").register(self.context);
+                self.error("unterminated raw string").with_errortype("unterminated_string").register(self.context);
                 Token::String(String::new())
             },
             // @(<terminator string>)<string><terminator string> - no LF in contents
@@ -1032,13 +1052,13 @@ impl<'ctx> Lexer<'ctx> {
                         Some(b')') => break,
                         Some(ch) => terminator.push(ch),
                         None => {
-                            self.error("unterminated raw string terminator").register(self.context);
+                            self.error("unterminated raw string terminator").with_errortype("unterminated_string").register(self.context);
                             return Token::String(String::new())
                         }
                     }
                 }
                 if terminator.is_empty() {
-                    self.error("empty raw string terminator").register(self.context);
+                    self.error("empty raw string terminator").with_errortype("unterminated_string").register(self.context);
                     return Token::String(String::new())
                 }
                 self.read_raw_string_inner(&terminator)
@@ -1131,6 +1151,7 @@ impl<'ctx> Iterator for Lexer<'ctx> {
                     // always end with a newline
                     if !self.final_newline {
                         self.final_newline = true;
+                        self.token_start = self.input.offset;
                         let mut location = self.location();
                         location.column += 1;
                         return Some(LocatedToken {
@@ -1144,6 +1165,7 @@ impl<'ctx> Iterator for Lexer<'ctx> {
             };
             skip_newlines = false;
 
+            self.token_start = self.input.offset - 1;
             let loc = self.location();
             let locate = |token| LocatedToken::new(loc, token);
 
@@ -1177,11 +1199,11 @@ impl<'ctx> Iterator for Lexer<'ctx> {
                 Some(SingleQuote) => Some(locate(Resource(self.read_resource()))),
                 Some(DoubleQuote) => Some(locate(self.read_string(b"\"", false))),
                 Some(BlockString) => Some(locate(self.read_string(b"\"}", false))),
-                Some(LBracket) => {
+                Some(v @ LBracket) | Some(v @ SafeLBracket) => {
                     if let Some(interp) = self.interp_stack.last_mut() {
                         interp.bracket_depth += 1;
                     }
-                    Some(locate(Punct(LBracket)))
+                    Some(locate(Punct(v)))
                 }
                 Some(RBracket) => {
                     if let Some(mut interp) = self.interp_stack.pop() {
@@ -1235,7 +1257,7 @@ impl<'ctx> Iterator for Lexer<'ctx> {
                                 use std::fmt::Write;
                                 let _ = write!(msg, " ({:?})", first as char);
                             }
-                            self.context.register_error(self.error(msg));
+                            self.context.register_error(self.error(msg).with_errortype("illegal_character"));
                             found_illegal = true;
                         }
                         continue;
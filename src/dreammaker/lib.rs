@@ -27,20 +27,25 @@ macro_rules! try_iter {
 
 mod error;
 pub use error::*;
+pub mod diagnostics;
 
 // roughly in order of stage
 pub mod docs;
 pub mod lexer;
+pub mod cst;
 pub mod preprocessor;
+pub mod preprocess_cache;
 pub mod indents;
 pub mod parser;
 pub mod annotation;
 pub mod ast;
 pub mod objtree;
+pub mod json;
 mod builtins;
 pub mod constants;
 pub mod dmi;
 pub mod config;
+pub mod lint_pattern;
 
 impl Context {
     /// Run the parsing suite on a given `.dme` file, producing an object tree.
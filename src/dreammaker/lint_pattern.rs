@@ -0,0 +1,236 @@
+//! Structural patterns for project-specific lints, configured with `[[lint]]`
+//! sections in `SpacemanDMM.toml`.
+//!
+//! A pattern describes either a proc call or an assignment:
+//!
+//! ```text
+//! to_chat(_, !text)           a call to any proc named to_chat
+//! /proc/to_chat(_, !text)     a call to the global proc to_chat
+//! /mob/proc/Move(/turf)       a call to /mob/proc/Move or its overrides
+//! density = _                 an assignment to any var named density
+//! ```
+//!
+//! Each argument, and the assigned value, is matched by a value pattern:
+//!
+//! * `_` matches anything.
+//! * `text`, `num`, `null`, and `list` match values of those kinds.
+//! * A typepath such as `/obj/item` matches instances of that type.
+//! * A number or string literal matches that constant value.
+//! * `!p` matches values which do not match `p`.
+//! * `p | q` matches values which match either.
+//!
+//! Whether a value matches is decided by its static type and any constant
+//! value. A value which can't be decided either way is not matched.
+
+use std::str::FromStr;
+
+use serde::de::{Deserialize, Deserializer, Error};
+
+/// A structural pattern over proc calls or assignments.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LintPattern {
+    /// A call to a matching proc, with arguments matching positionally.
+    /// Arguments beyond those listed are not constrained.
+    Call {
+        proc: ProcPattern,
+        args: Vec<ValuePattern>,
+    },
+    /// A `=` assignment to a var with the given name.
+    Assign {
+        var: String,
+        value: ValuePattern,
+    },
+}
+
+/// The proc which a call pattern refers to.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProcPattern {
+    /// Any proc with this name, as in `to_chat`.
+    Name(String),
+    /// A proc on a type and its subtypes, as in `/mob/proc/Move`. The type
+    /// is empty for global procs, as in `/proc/to_chat`.
+    Path { ty: String, name: String },
+}
+
+/// A pattern matched against a single value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValuePattern {
+    Any,
+    Text,
+    Num,
+    Null,
+    List,
+    Type(String),
+    Number(f32),
+    String(String),
+    Not(Box<ValuePattern>),
+    Or(Vec<ValuePattern>),
+}
+
+impl FromStr for LintPattern {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<LintPattern, String> {
+        let mut parser = PatternParser { input: s, pos: 0 };
+        let pattern = parser.pattern()?;
+        parser.skip_whitespace();
+        if parser.pos < s.len() {
+            return Err(parser.unexpected());
+        }
+        Ok(pattern)
+    }
+}
+
+impl<'de> Deserialize<'de> for LintPattern {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<LintPattern, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        text.parse().map_err(|e| D::Error::custom(format!("invalid lint pattern {:?}: {}", text, e)))
+    }
+}
+
+struct PatternParser<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl<'a> PatternParser<'a> {
+    fn rest(&self) -> &'a str {
+        &self.input[self.pos..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn eat(&mut self, token: char) -> bool {
+        self.skip_whitespace();
+        if self.rest().starts_with(token) {
+            self.pos += token.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, token: char) -> Result<(), String> {
+        if self.eat(token) {
+            Ok(())
+        } else {
+            Err(format!("expected '{}', {}", token, self.unexpected()))
+        }
+    }
+
+    fn unexpected(&self) -> String {
+        match self.rest().chars().next() {
+            Some(ch) => format!("found '{}' at position {}", ch, self.pos + 1),
+            None => "found end of pattern".to_owned(),
+        }
+    }
+
+    fn ident(&mut self) -> Option<&'a str> {
+        self.skip_whitespace();
+        let rest = self.rest();
+        let len = rest.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_')).unwrap_or(rest.len());
+        if len == 0 || rest.as_bytes()[0].is_ascii_digit() {
+            return None;
+        }
+        self.pos += len;
+        Some(&rest[..len])
+    }
+
+    fn typepath(&mut self) -> Result<Vec<&'a str>, String> {
+        let mut parts = Vec::new();
+        while self.rest().starts_with('/') {
+            self.pos += 1;
+            match self.ident() {
+                Some(part) => parts.push(part),
+                None => return Err(format!("expected a path segment, {}", self.unexpected())),
+            }
+        }
+        Ok(parts)
+    }
+
+    fn pattern(&mut self) -> Result<LintPattern, String> {
+        self.skip_whitespace();
+        let proc = if self.rest().starts_with('/') {
+            let mut parts = self.typepath()?;
+            let name = parts.pop().unwrap_or_default();
+            match parts.pop() {
+                Some("proc") | Some("verb") => {}
+                _ => return Err("a call path must look like /type/proc/name".to_owned()),
+            }
+            let ty = parts.iter().map(|part| format!("/{}", part)).collect();
+            ProcPattern::Path { ty, name: name.to_owned() }
+        } else {
+            let name = self.ident().ok_or_else(|| format!("expected a proc or var name, {}", self.unexpected()))?;
+            if self.eat('=') {
+                let value = self.value()?;
+                return Ok(LintPattern::Assign { var: name.to_owned(), value });
+            }
+            ProcPattern::Name(name.to_owned())
+        };
+
+        self.expect('(')?;
+        let mut args = Vec::new();
+        if !self.eat(')') {
+            loop {
+                args.push(self.value()?);
+                if self.eat(')') {
+                    break;
+                }
+                self.expect(',')?;
+            }
+        }
+        Ok(LintPattern::Call { proc, args })
+    }
+
+    fn value(&mut self) -> Result<ValuePattern, String> {
+        let mut options = vec![self.value_term()?];
+        while self.eat('|') {
+            options.push(self.value_term()?);
+        }
+        if options.len() == 1 {
+            Ok(options.remove(0))
+        } else {
+            Ok(ValuePattern::Or(options))
+        }
+    }
+
+    fn value_term(&mut self) -> Result<ValuePattern, String> {
+        if self.eat('!') {
+            return Ok(ValuePattern::Not(Box::new(self.value_term()?)));
+        }
+        if self.eat('(') {
+            let inner = self.value()?;
+            self.expect(')')?;
+            return Ok(inner);
+        }
+        self.skip_whitespace();
+        let rest = self.rest();
+        if rest.starts_with('/') {
+            let parts = self.typepath()?;
+            return Ok(ValuePattern::Type(parts.iter().map(|part| format!("/{}", part)).collect()));
+        }
+        if rest.starts_with('"') {
+            let end = rest[1..].find('"').ok_or("unterminated string")?;
+            self.pos += end + 2;
+            return Ok(ValuePattern::String(rest[1..end + 1].to_owned()));
+        }
+        if rest.starts_with(|c: char| c.is_ascii_digit() || c == '-' || c == '.') {
+            let len = rest[1..].find(|c: char| !(c.is_ascii_digit() || c == '.')).map_or(rest.len(), |len| len + 1);
+            let value = rest[..len].parse().map_err(|_| format!("invalid number {:?}", &rest[..len]))?;
+            self.pos += len;
+            return Ok(ValuePattern::Number(value));
+        }
+        match self.ident() {
+            Some("_") => Ok(ValuePattern::Any),
+            Some("text") => Ok(ValuePattern::Text),
+            Some("num") => Ok(ValuePattern::Num),
+            Some("null") => Ok(ValuePattern::Null),
+            Some("list") => Ok(ValuePattern::List),
+            Some(other) => Err(format!("unknown value pattern '{}'", other)),
+            None => Err(format!("expected a value pattern, {}", self.unexpected())),
+        }
+    }
+}
@@ -772,7 +772,7 @@ impl ObjectTree {
                         context.register_error(DMError::new(
                             var.value.location,
                             format!("not allowed to change {}/parent_type", path),
-                        ));
+                        ).with_errortype("bad_parent_type"));
                     }
                 }
 
@@ -811,7 +811,7 @@ impl ObjectTree {
                             Ok(Constant::EMPTY_STRING)
                         } else {
                             // A weird situation which should not happen.
-                            Err(DMError::new(location, format!("missing {}/parent_type", path)))
+                            Err(DMError::new(location, format!("missing {}/parent_type", path)).with_errortype("bad_parent_type"))
                         };
 
                         match constant {
@@ -827,7 +827,7 @@ impl ObjectTree {
                                 parent_type = &parent_type_buf;
                             }
                             Ok(other) => {
-                                context.register_error(DMError::new(location, format!("value of {}/parent_type must be a string or typepath, got {}", path, other)));
+                                context.register_error(DMError::new(location, format!("value of {}/parent_type must be a string or typepath, got {}", path, other)).with_errortype("bad_parent_type"));
                             }
                             Err(e) => {
                                 context.register_error(e);
@@ -846,7 +846,7 @@ impl ObjectTree {
                     context.register_error(DMError::new(
                         location,
                         format!("bad parent type for {}: {}", path, parent_type),
-                    ));
+                    ).with_errortype("bad_parent_type"));
                     NodeIndex::new(0)  // on bad parent_type, fall back to the root
                 }
             };
@@ -961,7 +961,7 @@ impl ObjectTree {
         let mut current = NodeIndex::new(0);
         let mut last = match path.next() {
             Some(name) => name,
-            None => return Err(DMError::new(location, "cannot register root path")),
+            None => return Err(DMError::new(location, "cannot register root path").with_errortype("bad_declaration")),
         };
         if is_decl(last) {
             return Ok((current, last));
@@ -1008,7 +1008,7 @@ impl ObjectTree {
                 }
             }
         } else if is_proc_decl(prev) {
-            return Err(DMError::new(location, "proc looks like a var"));
+            return Err(DMError::new(location, "proc looks like a var").with_errortype("bad_declaration"));
         }
 
         let mut type_path = Vec::new();
@@ -1060,6 +1060,7 @@ impl ObjectTree {
         if let Some(kind) = declaration {
             if let Some(ref decl) = proc.declaration {
                 DMError::new(location, format!("duplicate definition of {}/{}", kind, name))
+                    .with_errortype("duplicate_definition")
                     .with_note(decl.location, "previous definition")
                     .register(context);
             } else {
@@ -1184,16 +1185,16 @@ impl ObjectTree {
             declaration = Some(kind);
             proc_name = match path.next() {
                 Some(name) => name,
-                None => return Err(DMError::new(location, "proc must have a name")),
+                None => return Err(DMError::new(location, "proc must have a name").with_errortype("bad_declaration")),
             };
         } else if is_var_decl(proc_name) {
-            return Err(DMError::new(location, "var looks like a proc"));
+            return Err(DMError::new(location, "var looks like a proc").with_errortype("bad_declaration"));
         }
         if let Some(other) = path.next() {
             return Err(DMError::new(
                 location,
                 format!("proc name must be a single identifier (spurious {:?})", other),
-            ));
+            ).with_errortype("bad_declaration"));
         }
 
         self.register_proc(context, location, parent, proc_name, declaration, parameters, code)
@@ -9,6 +9,7 @@ use linked_hash_map::LinkedHashMap;
 use super::{DMError, Location, HasLocation, Context, Severity, FileId};
 use super::lexer::{LocatedToken, Token, Punctuation};
 use super::objtree::{ObjectTree, NodeIndex};
+use super::preprocess_cache::{self, CachedCode, ParseCache, TreeOp, TreeRecorder};
 use super::annotation::*;
 use super::ast::*;
 use super::docs::*;
@@ -301,6 +302,13 @@ pub struct Parser<'ctx, 'an, 'inp> {
     procs: bool,
     procs_bad: u64,
     procs_good: u64,
+    recovered_errors: Vec<DMError>,
+    /// Local vars declared so far by the statements being parsed, so that a
+    /// statement which fails to parse still declares them.
+    partial_vars: Vec<(VarType, Ident)>,
+
+    cache: ParseCache,
+    recorder: Option<TreeRecorder>,
 }
 
 impl<'ctx, 'an, 'inp> HasLocation for Parser<'ctx, 'an, 'inp> {
@@ -333,6 +341,11 @@ impl<'ctx, 'an, 'inp> Parser<'ctx, 'an, 'inp> {
             procs: false,
             procs_bad: 0,
             procs_good: 0,
+            recovered_errors: Vec::new(),
+            partial_vars: Vec::new(),
+
+            cache: Default::default(),
+            recorder: None,
         }
     }
 
@@ -345,6 +358,14 @@ impl<'ctx, 'an, 'inp> Parser<'ctx, 'an, 'inp> {
         self.procs = true;
     }
 
+    /// Add the declarations of files replayed from the preprocessor's cache
+    /// without parsing them, and record those of the files parsed for it.
+    ///
+    /// The cache is not used while annotating.
+    pub fn use_cache(&mut self, cache: ParseCache) {
+        self.cache = cache;
+    }
+
     pub fn parse_object_tree(mut self) -> ObjectTree {
         self.tree.register_builtins();
         self.run();
@@ -376,6 +397,9 @@ impl<'ctx, 'an, 'inp> Parser<'ctx, 'an, 'inp> {
     }
 
     fn run(&mut self) {
+        if self.annotations.is_none() {
+            self.recorder = TreeRecorder::start(&self.cache, self.procs);
+        }
         let root = self.root();
         if let Err(mut e) = self.require(root) {
             let loc = e.location();
@@ -385,6 +409,11 @@ impl<'ctx, 'an, 'inp> Parser<'ctx, 'an, 'inp> {
             self.fatal_errored = true;
             self.context.register_error(e);
         }
+        if let Some(recorder) = self.recorder.take() {
+            if !self.fatal_errored {
+                recorder.finish(self.context, &self.module_docs);
+            }
+        }
     }
 
     fn finalize_object_tree(mut self) -> ObjectTree {
@@ -409,13 +438,13 @@ impl<'ctx, 'an, 'inp> Parser<'ctx, 'an, 'inp> {
     fn describe_parse_error(&mut self) -> DMError {
         let expected = self.expected.join(", ");
         if self.eof {
-            return self.error(format!("got EOF, expected one of: {}", expected));
+            return self.error(format!("got EOF, expected one of: {}", expected)).with_errortype("syntax_error");
         }
         match self.next("") {
             Ok(got) => {
                 let message = format!("got '{}', expected one of: {}", got, expected);
                 self.put_back(got);
-                let mut e = self.error(message);
+                let mut e = self.error(message).with_errortype("syntax_error");
                 if self.possible_indentation_error {
                     let mut loc = e.location();
                     loc.line += 1;
@@ -427,6 +456,7 @@ impl<'ctx, 'an, 'inp> Parser<'ctx, 'an, 'inp> {
             }
             Err(err) => self
                 .error(format!("i/o error, expected one of: {}", expected))
+                .with_errortype("io_error")
                 .with_cause(err),
         }
     }
@@ -448,7 +478,11 @@ impl<'ctx, 'an, 'inp> Parser<'ctx, 'an, 'inp> {
             if let Some(next) = self.next.take() {
                 break Ok(next);
             }
-            match self.input.next() {
+            let token = self.input.next();
+            if let (Some(token), Some(recorder)) = (token.as_ref(), self.recorder.as_mut()) {
+                recorder.token(token.location, &token.token);
+            }
+            match token {
                 Some(LocatedToken {
                     location,
                     token: Token::DocComment(dc),
@@ -459,8 +493,15 @@ impl<'ctx, 'an, 'inp> Parser<'ctx, 'an, 'inp> {
                             .or_default()
                             .push((location.line, dc));
                     }
-                    DocTarget::EnclosingItem => self.docs_enclosing.push(dc),
-                    DocTarget::FollowingItem => self.docs_following.push(dc),
+                    target => {
+                        if let Some(recorder) = self.recorder.as_mut() {
+                            recorder.doc(location.file);
+                        }
+                        match target {
+                            DocTarget::EnclosingItem => self.docs_enclosing.push(dc),
+                            DocTarget::FollowingItem => self.docs_following.push(dc),
+                        }
+                    }
                 },
                 Some(token) => {
                     self.expected.clear();
@@ -595,23 +636,96 @@ impl<'ctx, 'an, 'inp> Parser<'ctx, 'an, 'inp> {
     }
 
     fn tree_entries(&mut self, current: NodeIndex, proc_kind: Option<ProcDeclKind>, var_type: Option<VarType>, terminator: Token) -> Status<()> {
+        // files replayed from the cache only ever hold whole top-level entries
+        let top_level = terminator == Token::Eof;
         loop {
             let message: Cow<'static, str> = match terminator {
                 Token::Eof => "newline".into(),
                 ref other => format!("newline, '{}'", other).into(),
             };
             let next = self.next(message)?;
+            if top_level {
+                self.apply_replayed();
+            }
             if next == terminator || next == Token::Eof {
                 break;
             } else if next == Token::Punct(Punctuation::Semicolon) {
                 continue;
             }
             self.put_back(next);
+            if let (true, Some(recorder)) = (top_level, self.recorder.as_mut()) {
+                recorder.begin_entry(self.context, self.location.file);
+            }
             require!(self.tree_entry(current, proc_kind, var_type.clone()));
+            if let (true, Some(recorder)) = (top_level, self.recorder.as_mut()) {
+                let docs_pending = !self.docs_following.comments().is_empty() || !self.docs_enclosing.comments().is_empty();
+                recorder.end_entry(self.context, docs_pending);
+            }
         }
         SUCCESS
     }
 
+    /// Add the declarations of the files which the preprocessor replayed from
+    /// its cache without passing on their tokens.
+    fn apply_replayed(&mut self) {
+        while let Some((file, parsed)) = self.cache.pop_replayed() {
+            if let Some(recorder) = self.recorder.as_mut() {
+                recorder.collect_errors(self.context);
+            }
+            for op in parsed.ops {
+                self.apply_cached(op);
+            }
+            if !parsed.module_docs.is_empty() {
+                self.module_docs.entry(file).or_default().extend(parsed.module_docs);
+            }
+            if let Some(recorder) = self.recorder.as_mut() {
+                recorder.skip_errors(self.context);
+            }
+        }
+    }
+
+    fn apply_cached(&mut self, op: TreeOp) {
+        let find = |tree: &ObjectTree, path: &str| tree.find(path).map(|ty| ty.index());
+        match op {
+            TreeOp::Subtype { location, parent, name, len } => {
+                if let Some(parent) = find(&self.tree, &parent) {
+                    self.tree.subtype_or_add(location, parent, &name, len);
+                }
+            }
+            TreeOp::TypeDocs { path, docs } => {
+                if let Some(ty) = find(&self.tree, &path) {
+                    self.tree[ty].docs.extend(preprocess_cache::doc_collection(docs));
+                }
+            }
+            TreeOp::DeclareVar { path, name, location, docs, var_type, expression } => {
+                if let Some(ty) = find(&self.tree, &path) {
+                    self.tree.declare_var(ty, &name, location, preprocess_cache::doc_collection(docs), var_type, expression);
+                }
+            }
+            TreeOp::OverrideVar { path, name, location, docs, expression } => {
+                if let Some(ty) = find(&self.tree, &path) {
+                    self.tree.override_var(ty, &name, location, preprocess_cache::doc_collection(docs), expression);
+                }
+            }
+            TreeOp::Proc { path, name, location, kind, parameters, code, good, docs } => {
+                if let Some(ty) = find(&self.tree, &path) {
+                    if self.procs {
+                        if good {
+                            self.procs_good += 1;
+                        } else {
+                            self.procs_bad += 1;
+                        }
+                    }
+                    match self.tree.register_proc(self.context, location, ty, &name, kind, parameters, code.into_code()) {
+                        Ok((_, proc)) => proc.docs.extend(preprocess_cache::doc_collection(docs)),
+                        Err(e) => self.context.register_error(e),
+                    }
+                }
+            }
+            TreeOp::Error(error) => self.context.register_error(error),
+        }
+    }
+
     // ------------------------------------------------------------------------
     // Object tree - types
 
@@ -634,7 +748,7 @@ impl<'ctx, 'an, 'inp> Parser<'ctx, 'an, 'inp> {
                 self.annotate_precise(slash_loc..slash_loc, || {
                     Annotation::IncompleteTreePath(absolute, parts.clone())
                 });
-                self.context.register_error(self.error("path has no effect"));
+                self.context.register_error(self.error("path has no effect").with_errortype("path_has_no_effect"));
                 return success((absolute, Vec::new()));
             }
         }
@@ -665,6 +779,7 @@ impl<'ctx, 'an, 'inp> Parser<'ctx, 'an, 'inp> {
             Token::Punct(p @ Punctuation::CloseColon) |
             Token::Punct(p @ Punctuation::Colon) => {
                 self.error(format!("path started by '{}', should be unprefixed", p))
+                    .with_errortype("path_separator")
                     .set_severity(Severity::Warning)
                     .register(self.context);
                 Ok((false, true))
@@ -684,6 +799,7 @@ impl<'ctx, 'an, 'inp> Parser<'ctx, 'an, 'inp> {
             Token::Punct(p @ Punctuation::CloseColon) |
             Token::Punct(p @ Punctuation::Colon) => {
                 self.error(format!("path separated by '{}', should be '/'", p))
+                    .with_errortype("path_separator")
                     .set_severity(Severity::Warning)
                     .register(self.context);
                 SUCCESS
@@ -709,6 +825,7 @@ impl<'ctx, 'an, 'inp> Parser<'ctx, 'an, 'inp> {
 
         if absolute && current != self.tree.root().index() {
             DMError::new(entry_start, format!("nested absolute path inside {}", &self.tree[current].path))
+                .with_errortype("nested_absolute_path")
                 .set_severity(Severity::Warning)
                 .register(self.context);
             current = self.tree.root().index();
@@ -719,6 +836,7 @@ impl<'ctx, 'an, 'inp> Parser<'ctx, 'an, 'inp> {
             Some(x) => x,
             None => {
                 self.error("what?")
+                    .with_errortype("syntax_error")
                     .register(self.context);
                 return SUCCESS;
             }
@@ -740,10 +858,11 @@ impl<'ctx, 'an, 'inp> Parser<'ctx, 'an, 'inp> {
                     proc_kind = Some(kind);
                 } else if proc_kind.is_some() {
                     self.error("cannot have sub-blocks of `proc/` block")
+                        .with_errortype("syntax_error")
                         .register(self.context);
                 } else {
                     let len = self.tree[current].path.chars().filter(|&c| c == '/').count() + path_len;
-                    current = self.tree.subtype_or_add(self.location, current, each, len);
+                    current = self.subtype_or_add(current, each, len);
 
                     if !absolute && self.context.config().code_standards.disallow_relative_type_definitions {
                         relative_type_location = Some(self.location);
@@ -755,6 +874,7 @@ impl<'ctx, 'an, 'inp> Parser<'ctx, 'an, 'inp> {
             () => {
                 if let Some(loc) = relative_type_location {
                     DMError::new(loc, "relatively pathed type defined here")
+                        .with_errortype("relative_type_definition")
                         .set_severity(Severity::Warning)
                         .register(self.context);
                 }
@@ -790,7 +910,7 @@ impl<'ctx, 'an, 'inp> Parser<'ctx, 'an, 'inp> {
                     require!(self.tree_block(current, proc_kind, var_type.clone()));
                 } else {
                     let (comment, ()) = require!(self.doc_comment(|this| this.tree_block(current, proc_kind, var_type.clone())));
-                    self.tree[current].docs.extend(comment);
+                    self.type_docs(current, comment);
                 }
 
                 let node = self.tree[current].path.to_owned();
@@ -819,9 +939,9 @@ impl<'ctx, 'an, 'inp> Parser<'ctx, 'an, 'inp> {
 
                 if let Some(mut var_type) = var_type {
                     var_type.suffix(&var_suffix);
-                    self.tree.declare_var(current, last_part, location, docs, var_type, Some(expression));
+                    self.declare_var(current, last_part, location, docs, var_type, Some(expression));
                 } else {
-                    self.tree.override_var(current, last_part, location, docs, expression);
+                    self.override_var(current, last_part, location, docs, expression);
                 }
 
                 SUCCESS
@@ -839,11 +959,13 @@ impl<'ctx, 'an, 'inp> Parser<'ctx, 'an, 'inp> {
 
                 if last_part == "var" {
                     self.error("`var;` item has no effect")
+                        .with_errortype("item_has_no_effect")
                         .set_severity(Severity::Warning)
                         .register(self.context);
                 } else if let Some(mut var_type) = var_type.take() {
                     if VarTypeFlags::from_name(last_part).is_some() {
                         self.error(format!("`var/{};` item has no effect", last_part))
+                            .with_errortype("item_has_no_effect")
                             .set_severity(Severity::Warning)
                             .register(self.context);
                     } else {
@@ -851,21 +973,23 @@ impl<'ctx, 'an, 'inp> Parser<'ctx, 'an, 'inp> {
                         var_type.suffix(&var_suffix);
                         let node = self.tree[current].path.to_owned();
                         self.annotate(entry_start, || Annotation::Variable(reconstruct_path(&node, proc_kind, Some(&var_type), last_part)));
-                        self.tree.declare_var(current, last_part, self.location, docs, var_type, var_suffix.into_initializer());
+                        self.declare_var(current, last_part, self.location, docs, var_type, var_suffix.into_initializer());
                     }
                 } else if ProcDeclKind::from_name(last_part).is_some() {
                     self.error("`proc;` item has no effect")
+                        .with_errortype("item_has_no_effect")
                         .set_severity(Severity::Warning)
                         .register(self.context);
                 } else if proc_kind.is_some() {
                     self.error("child of `proc/` without body")
+                        .with_errortype("syntax_error")
                         .register(self.context);
                 } else {
                     handle_relative_type_error!();
                     let docs = std::mem::take(&mut self.docs_following);
                     let len = self.tree[current].path.chars().filter(|&c| c == '/').count() + path_len;
-                    current = self.tree.subtype_or_add(self.location, current, last_part, len);
-                    self.tree[current].docs.extend(docs);
+                    current = self.subtype_or_add(current, last_part, len);
+                    self.type_docs(current, docs);
                 }
 
                 SUCCESS
@@ -873,6 +997,55 @@ impl<'ctx, 'an, 'inp> Parser<'ctx, 'an, 'inp> {
         }
     }
 
+    // ------------------------------------------------------------------------
+    // Object tree - changes, recorded for the cache
+
+    fn subtype_or_add(&mut self, parent: NodeIndex, name: &str, len: usize) -> NodeIndex {
+        let location = self.location;
+        if let Some(recorder) = self.recorder.as_mut() {
+            let parent = self.tree[parent].path.clone();
+            recorder.op(self.context, || TreeOp::Subtype { location, parent, name: name.to_owned(), len });
+        }
+        self.tree.subtype_or_add(location, parent, name, len)
+    }
+
+    fn type_docs(&mut self, ty: NodeIndex, docs: DocCollection) {
+        if let (false, Some(recorder)) = (docs.comments().is_empty(), self.recorder.as_mut()) {
+            let path = self.tree[ty].path.clone();
+            recorder.op(self.context, || TreeOp::TypeDocs { path, docs: docs.comments().to_vec() });
+        }
+        self.tree[ty].docs.extend(docs);
+    }
+
+    fn declare_var(&mut self, ty: NodeIndex, name: &str, location: Location, docs: DocCollection, var_type: VarType, expression: Option<Expression>) {
+        if let Some(recorder) = self.recorder.as_mut() {
+            let path = self.tree[ty].path.clone();
+            recorder.op(self.context, || TreeOp::DeclareVar {
+                path,
+                name: name.to_owned(),
+                location,
+                docs: docs.comments().to_vec(),
+                var_type: var_type.clone(),
+                expression: expression.clone(),
+            });
+        }
+        self.tree.declare_var(ty, name, location, docs, var_type, expression);
+    }
+
+    fn override_var(&mut self, ty: NodeIndex, name: &str, location: Location, docs: DocCollection, expression: Expression) {
+        if let Some(recorder) = self.recorder.as_mut() {
+            let path = self.tree[ty].path.clone();
+            recorder.op(self.context, || TreeOp::OverrideVar {
+                path,
+                name: name.to_owned(),
+                location,
+                docs: docs.comments().to_vec(),
+                expression: expression.clone(),
+            });
+        }
+        self.tree.override_var(ty, name, location, docs, expression);
+    }
+
     // ------------------------------------------------------------------------
     // Object tree - Vars
 
@@ -981,20 +1154,27 @@ impl<'ctx, 'an, 'inp> Parser<'ctx, 'an, 'inp> {
             SUCCESS
         }));
 
+        let mut good = true;
         let code = if self.procs {
-            let result = {
+            let (result, recovered) = {
                 let mut subparser: Parser<'ctx, '_, '_> = Parser::new(self.context, body_tt);
                 if let Some(a) = self.annotations.as_mut() {
                     subparser.annotations = Some(&mut *a);
                 }
                 let block = subparser.block(&LoopContext::None);
-                subparser.require(block)
+                (subparser.require(block), std::mem::take(&mut subparser.recovered_errors))
             };
-            if result.is_ok() {
+            good = result.is_ok() && recovered.is_empty();
+            if good {
                 self.procs_good += 1;
             } else {
                 self.procs_bad += 1;
             }
+            // statements which failed to parse were skipped, but the rest of
+            // the body is kept so it can still be checked
+            for err in recovered {
+                self.context.register_error(err);
+            }
             match result {
                 Err(err) => {
                     let err2 = err.clone();
@@ -1009,7 +1189,25 @@ impl<'ctx, 'an, 'inp> Parser<'ctx, 'an, 'inp> {
             Code::Disabled
         };
 
-        match self.tree.register_proc(self.context, location, current, name, proc_kind, parameters, code) {
+        if let Some(recorder) = self.recorder.as_mut() {
+            let path = self.tree[current].path.clone();
+            recorder.op(self.context, || TreeOp::Proc {
+                path,
+                name: name.to_owned(),
+                location,
+                kind: proc_kind,
+                parameters: parameters.clone(),
+                code: CachedCode::new(&code),
+                good,
+                docs: comment.comments().to_vec(),
+            });
+        }
+        let registered = self.tree.register_proc(self.context, location, current, name, proc_kind, parameters, code);
+        // duplicate definitions are found again when the tree is rebuilt
+        if let Some(recorder) = self.recorder.as_mut() {
+            recorder.skip_errors(self.context);
+        }
+        match registered {
             Ok((idx, proc)) => {
                 proc.docs.extend(comment);
                 // manually performed for borrowck reasons
@@ -1020,6 +1218,7 @@ impl<'ctx, 'an, 'inp> Parser<'ctx, 'an, 'inp> {
                 }
                 if !absolute && self.context.config().code_standards.disallow_relative_proc_definitions {
                     DMError::new(location, "relatively pathed proc defined here")
+                        .with_errortype("relative_proc_definition")
                         .set_severity(Severity::Warning)
                         .register(self.context);
                 }
@@ -1044,7 +1243,7 @@ impl<'ctx, 'an, 'inp> Parser<'ctx, 'an, 'inp> {
 
         // `name` or `obj/name` or `var/obj/name` or ...
         let leading_loc = self.updated_location();
-        let (_absolute, mut path) = leading!(self.tree_path(true));
+        let (absolute, mut path) = leading!(self.tree_path(true));
         let name = match path.pop() {
             Some(name) => name,
             None => {
@@ -1057,6 +1256,7 @@ impl<'ctx, 'an, 'inp> Parser<'ctx, 'an, 'inp> {
             DMError::new(leading_loc, "'var/' is unnecessary here")
                 .set_severity(Severity::Hint)
                 .with_errortype("var_in_proc_paramater")
+                .with_edit(leading_loc, if absolute { "/var/" } else { "var/" }, "")
                 .register(self.context);
         }
         let mut var_type: VarType = path.into_iter().collect();
@@ -1122,14 +1322,22 @@ impl<'ctx, 'an, 'inp> Parser<'ctx, 'an, 'inp> {
         // `in view(7)` or `in list("a", "b")` or ...
         let in_list;
         if let Some(()) = self.exact(Token::Punct(Punctuation::In))? {
+            let in_loc = self.location;
             in_list = Some(require!(self.expression()));
             // in case it is out of order
             if let Some(()) = self.exact_ident("as")? {
-                self.error("'as' clause should precede 'in' clause, and is being ignored")
+                let mut error = self.error("'as' clause should precede 'in' clause, and is being ignored")
                     .with_errortype("in_precedes_as")
-                    .set_severity(Severity::Warning)
-                    .register(self.context);
-                let _ = require!(self.input_type());
+                    .set_severity(Severity::Warning);
+                let mut as_loc = self.location;
+                let as_what = require!(self.input_type());
+                if input_type.is_none() && as_loc.column > 1 {
+                    as_loc.column -= 1;
+                    error = error
+                        .with_edit(in_loc, "in", format!("as {} in", as_what))
+                        .with_edit(as_loc, &format!(" as {}", as_what), "");
+                }
+                error.register(self.context);
             }
         } else {
             in_list = None;
@@ -1149,7 +1357,7 @@ impl<'ctx, 'an, 'inp> Parser<'ctx, 'an, 'inp> {
         let mut as_what = match InputType::from_str(&ident) {
             Some(what) => what,
             None => {
-                self.context.register_error(self.error(format!("bad input type: '{}'", ident)));
+                self.context.register_error(self.error(format!("bad input type: '{}'", ident)).with_errortype("bad_input_type"));
                 InputType::empty()
             }
         };
@@ -1158,7 +1366,7 @@ impl<'ctx, 'an, 'inp> Parser<'ctx, 'an, 'inp> {
             match InputType::from_str(&ident) {
                 Some(what) => as_what |= what,
                 None => {
-                    self.context.register_error(self.error(format!("bad input type: '{}'", ident)));
+                    self.context.register_error(self.error(format!("bad input type: '{}'", ident)).with_errortype("bad_input_type"));
                 }
             }
         }
@@ -1175,9 +1383,32 @@ impl<'ctx, 'an, 'inp> Parser<'ctx, 'an, 'inp> {
                     break;
                 } else if let Some(()) = self.exact(Token::Punct(Punctuation::Semicolon))? {
                     continue;
-                } else {
-                    statements.push(require!(self.statement(loop_ctx, &mut vars)));
                 }
+                let start = self.location();
+                let partial_start = self.partial_vars.len();
+                let statement = self.statement(loop_ctx, &mut vars);
+                match self.require(statement) {
+                    Ok(statement) => statements.push(statement),
+                    Err(err) => {
+                        // skip to the next statement so later errors are
+                        // reported too
+                        if !self.recover_statement()? {
+                            return Err(err);
+                        }
+                        self.recovered_errors.push(err);
+                        // keep the vars it declared, without their values,
+                        // so later uses of them are not reported
+                        let mut declared: Vec<VarStatement> = self.partial_vars.drain(partial_start..)
+                            .map(|(var_type, name)| VarStatement { var_type, name, value: None })
+                            .collect();
+                        match declared.len() {
+                            0 => {}
+                            1 => statements.push(Spanned::new(start, Statement::Var(Box::new(declared.remove(0))))),
+                            _ => statements.push(Spanned::new(start, Statement::Vars(declared))),
+                        }
+                    }
+                }
+                self.partial_vars.truncate(partial_start);
             }
             statements
         } else if let Some(()) = self.statement_terminator()? {
@@ -1284,7 +1515,7 @@ impl<'ctx, 'an, 'inp> Parser<'ctx, 'an, 'inp> {
                         // for(a = 1 to
                         let name = match lhs.into_term() {
                             Some(Term::Ident(name)) => name,
-                            _ => return Err(self.error("for-list must start with variable")),
+                            _ => return Err(self.error("for-list must start with variable").with_errortype("syntax_error")),
                         };
                         require!(self.exact_ident("to"));
                         let to_rhs = require!(self.expression());
@@ -1297,7 +1528,7 @@ impl<'ctx, 'an, 'inp> Parser<'ctx, 'an, 'inp> {
                     }) => {
                         let name = match lhs.into_term() {
                             Some(Term::Ident(name)) => name,
-                            _ => return Err(self.error("for-list must start with variable")),
+                            _ => return Err(self.error("for-list must start with variable").with_errortype("syntax_error")),
                         };
                         // Explicit move is necessary because rustc becomes
                         // confused when matching on the *rhs lvalue, thinking
@@ -1323,9 +1554,9 @@ impl<'ctx, 'an, 'inp> Parser<'ctx, 'an, 'inp> {
                     },
                     Statement::Expr(expr) => match expr.into_term() {
                         Some(Term::Ident(name)) => (None, name),
-                        _ => return Err(self.error("for-list must start with variable")),
+                        _ => return Err(self.error("for-list must start with variable").with_errortype("syntax_error")),
                     },
-                    _ => return Err(self.error("for-list must start with variable")),
+                    _ => return Err(self.error("for-list must start with variable").with_errortype("syntax_error")),
                 };
 
                 let input_type = if let Some(()) = self.exact_ident("as")? {
@@ -1382,7 +1613,7 @@ impl<'ctx, 'an, 'inp> Parser<'ctx, 'an, 'inp> {
                 require!(self.exact(Token::Punct(Punctuation::LParen)));
                 let what = require!(self.separated(Punctuation::Comma, Punctuation::RParen, None, Parser::case));
                 if what.is_empty() {
-                    self.context.register_error(self.error("switch case cannot be empty"));
+                    self.context.register_error(self.error("switch case cannot be empty").with_errortype("syntax_error"));
                 }
                 let block = require!(self.block(loop_ctx));
                 cases.push((Spanned::new(self.location(), what), block));
@@ -1481,6 +1712,55 @@ impl<'ctx, 'an, 'inp> Parser<'ctx, 'an, 'inp> {
         }
     }
 
+    /// Skip the rest of a statement which failed to parse, stopping after
+    /// the next `;` or dedent, or before the `}` which closes the current
+    /// block.
+    ///
+    /// Returns `false` if the input ran out before any of these were found.
+    fn recover_statement(&mut self) -> Result<bool, DMError> {
+        let mut braces = 0usize;
+        let mut parens = 0usize;
+        loop {
+            match self.next("")? {
+                Token::Eof => return Ok(false),
+                Token::Punct(Punctuation::Semicolon) if braces == 0 && parens == 0 => {}
+                Token::Punct(Punctuation::LBrace) => {
+                    braces += 1;
+                    continue;
+                }
+                Token::Punct(Punctuation::RBrace) if braces == 0 => {
+                    self.put_back(Token::Punct(Punctuation::RBrace));
+                    return Ok(true);
+                }
+                Token::Punct(Punctuation::RBrace) => {
+                    braces -= 1;
+                    if braces > 0 {
+                        continue;
+                    }
+                    parens = 0;
+                }
+                Token::Punct(Punctuation::LParen) |
+                Token::Punct(Punctuation::LBracket) |
+                Token::Punct(Punctuation::SafeLBracket) => {
+                    parens += 1;
+                    continue;
+                }
+                Token::Punct(Punctuation::RParen) |
+                Token::Punct(Punctuation::RBracket) => {
+                    parens = parens.saturating_sub(1);
+                    continue;
+                }
+                _ => continue,
+            }
+            // the `else` and `catch` arms of a broken `if` or `try` belong
+            // to it, so skip them too
+            self.skip_phantom_semicolons()?;
+            if self.exact_ident("else")?.is_none() && self.exact_ident("catch")?.is_none() {
+                return Ok(true);
+            }
+        }
+    }
+
     fn skip_phantom_semicolons(&mut self) -> Result<(), DMError> {
         // Indent processor inserts these semicolons which should be ignored:
         //   if(cond){block}  ;
@@ -1498,13 +1778,14 @@ impl<'ctx, 'an, 'inp> Parser<'ctx, 'an, 'inp> {
     fn simple_statement(&mut self, in_for: bool, vars: &mut Vec<(Location, VarType, Ident)>) -> Status<Statement> {
         if let Some(()) = self.exact_ident("var")? {
             // statement :: 'var' type_path name ('=' value)
+            let partial_start = self.partial_vars.len();
             let mut var_stmts = Vec::new();
             loop {
                 let type_path_start = self.location();
                 let (_, mut tree_path) = require!(self.tree_path(true));
                 let name = match tree_path.pop() {
                     Some(name) => name,
-                    None => return Err(self.error("'var' must be followed by a name")),
+                    None => return Err(self.error("'var' must be followed by a name").with_errortype("syntax_error")),
                 };
 
                 let mut var_type = tree_path.into_iter().collect::<VarType>();
@@ -1512,6 +1793,7 @@ impl<'ctx, 'an, 'inp> Parser<'ctx, 'an, 'inp> {
                     DMError::new(type_path_start, "var/tmp has no effect here")
                         .set_severity(Severity::Warning)
                         .with_errortype("tmp_no_effect")
+                        .with_edit(type_path_start, "var/tmp/", "var/")
                         .register(self.context);
                 }
                 if var_type.flags.is_final() {
@@ -1534,6 +1816,7 @@ impl<'ctx, 'an, 'inp> Parser<'ctx, 'an, 'inp> {
                 }
                 let var_suffix = require!(self.var_suffix());
                 var_type.suffix(&var_suffix);
+                self.partial_vars.push((var_type.clone(), name.clone()));
 
                 if self.annotations.is_some() {
                     vars.push((self.location, var_type.clone(), name.clone()));
@@ -1561,6 +1844,7 @@ impl<'ctx, 'an, 'inp> Parser<'ctx, 'an, 'inp> {
                     break;
                 }
             }
+            self.partial_vars.truncate(partial_start);
             if var_stmts.len() == 1 {
                 success(Statement::Var(Box::new(var_stmts.remove(0))))
             } else {
@@ -1815,6 +2099,7 @@ impl<'ctx, 'an, 'inp> Parser<'ctx, 'an, 'inp> {
                     Some(else_) => else_,
                     None => {
                         self.error("missing else arm of conditional operator should be replaced with 'null'")
+                            .with_errortype("conditional_missing_else")
                             .set_severity(Severity::Warning)
                             .register(self.context);
                         Expression::from(Term::Null)
@@ -1853,17 +2138,19 @@ impl<'ctx, 'an, 'inp> Parser<'ctx, 'an, 'inp> {
         // read unary ops
         let mut unary_ops = Vec::new();
         loop {
-            match self.next("operator")? {
-                Token::Punct(Punctuation::Sub) => unary_ops.push(UnaryOp::Neg),
-                Token::Punct(Punctuation::Not) => unary_ops.push(UnaryOp::Not),
-                Token::Punct(Punctuation::BitNot) => unary_ops.push(UnaryOp::BitNot),
-                Token::Punct(Punctuation::PlusPlus) => unary_ops.push(UnaryOp::PreIncr),
-                Token::Punct(Punctuation::MinusMinus) => unary_ops.push(UnaryOp::PreDecr),
+            let start = self.updated_location();
+            let op = match self.next("operator")? {
+                Token::Punct(Punctuation::Sub) => UnaryOp::Neg,
+                Token::Punct(Punctuation::Not) => UnaryOp::Not,
+                Token::Punct(Punctuation::BitNot) => UnaryOp::BitNot,
+                Token::Punct(Punctuation::PlusPlus) => UnaryOp::PreIncr,
+                Token::Punct(Punctuation::MinusMinus) => UnaryOp::PreDecr,
                 other => {
                     self.put_back(other);
                     break;
                 }
-            }
+            };
+            unary_ops.push(Spanned::new(start, op));
         }
 
         let mut belongs_to = Vec::new();
@@ -1876,13 +2163,19 @@ impl<'ctx, 'an, 'inp> Parser<'ctx, 'an, 'inp> {
         // Read follows
         let mut follow = Vec::new();
         loop {
+            let start = self.updated_location();
             match self.next("operator")? {
-                Token::Punct(Punctuation::PlusPlus) => unary_ops.push(UnaryOp::PostIncr),
-                Token::Punct(Punctuation::MinusMinus) => unary_ops.push(UnaryOp::PostDecr),
+                Token::Punct(Punctuation::PlusPlus) => unary_ops.push(Spanned::new(start, UnaryOp::PostIncr)),
+                Token::Punct(Punctuation::MinusMinus) => unary_ops.push(Spanned::new(start, UnaryOp::PostDecr)),
                 other => {
                     self.put_back(other);
                     match self.follow(&mut belongs_to, in_ternary)? {
-                        Some(f) => follow.push(f),
+                        Some(f) => {
+                            if let (Term::Prefab(prefab), true) = (&term.elem, follow.is_empty()) {
+                                self.annotate_scope(&prefab.path, &f);
+                            }
+                            follow.push(f)
+                        },
                         None => break,
                     }
                 }
@@ -1993,6 +2286,7 @@ impl<'ctx, 'an, 'inp> Parser<'ctx, 'an, 'inp> {
                     // warn against this mistake
                     if let Some(&Expression::BinaryOp { op: BinaryOp::In, .. } ) = args.get(0) {
                         self.error("bad `locate(X in Y)`, should be `locate(X) in Y`")
+                            .with_errortype("bad_locate")
                             .set_severity(Severity::Warning)
                             .register(self.context);
                     }
@@ -2001,6 +2295,7 @@ impl<'ctx, 'an, 'inp> Parser<'ctx, 'an, 'inp> {
                     let in_list = if let Some(()) = self.exact(Token::Punct(Punctuation::In))? {
                         if args.len() > 1 {
                             DMError::new(start, "bad 'locate(x, y, z) in'")
+                                .with_errortype("bad_locate")
                                 .set_severity(Severity::Warning)
                                 .register(self.context);
                         }
@@ -2045,6 +2340,27 @@ impl<'ctx, 'an, 'inp> Parser<'ctx, 'an, 'inp> {
                 }
             },
 
+            // term :: '::' ident arglist?
+            Token::Punct(Punctuation::Scope) => {
+                self.context.check_target_version(start, 515, "the :: operator");
+                let ident_start = self.updated_location();
+                let ident = require!(self.ident());
+                let end = self.updated_location();
+                let global = vec!["global".to_owned()];
+                match self.arguments(&global, &ident)? {
+                    Some(args) => {
+                        self.annotate_precise(ident_start..end, || Annotation::ScopedCall(global, ident.clone()));
+                        Term::GlobalCall(ident, args)
+                    },
+                    None => {
+                        self.annotate_precise(ident_start..end, || Annotation::ScopedVar(global.clone(), ident.clone()));
+                        belongs_to.extend(global);
+                        belongs_to.push(ident.clone());
+                        Term::GlobalIdent(ident)
+                    },
+                }
+            },
+
             // term :: '..' arglist
             Token::Punct(Punctuation::Super) => {
                 self.annotate(start, || Annotation::ParentCall);
@@ -2091,6 +2407,7 @@ impl<'ctx, 'an, 'inp> Parser<'ctx, 'an, 'inp> {
             Token::Punct(LParen) => {
                 if let Some(()) = self.exact(Token::Punct(Punctuation::RParen))? {
                     self.error("'()' should be replaced with 'null'")
+                        .with_errortype("empty_parentheses")
                         .set_severity(Severity::Warning)
                         .register(self.context);
                     Term::Null
@@ -2154,11 +2471,15 @@ impl<'ctx, 'an, 'inp> Parser<'ctx, 'an, 'inp> {
             Token::Punct(Punctuation::CloseColon) if !belongs_to.is_empty() || !in_ternary => PropertyAccessKind::Colon,
             Token::Punct(Punctuation::SafeDot) => PropertyAccessKind::SafeDot,
             Token::Punct(Punctuation::SafeColon) => PropertyAccessKind::SafeColon,
+            Token::Punct(Punctuation::Scope) => PropertyAccessKind::Scope,
 
             other => return self.try_another(other),
         };
 
         let mut index_op_loc = self.location;
+        if kind == PropertyAccessKind::Scope {
+            self.context.check_target_version(index_op_loc, 515, "the :: operator");
+        }
         let start = self.updated_location();
         let ident = match self.ident()? {
             Some(ident) => ident,
@@ -2174,6 +2495,21 @@ impl<'ctx, 'an, 'inp> Parser<'ctx, 'an, 'inp> {
         };
         let end = self.updated_location();
 
+        // follow :: '::' ('proc' | 'verb') '/' ident
+        if kind == PropertyAccessKind::Scope {
+            if let Some(proc_kind) = ProcDeclKind::from_name(&ident) {
+                if let Some(()) = self.exact(Token::Punct(Punctuation::Slash))? {
+                    let proc_start = self.updated_location();
+                    let name = require!(self.ident());
+                    if !belongs_to.is_empty() {
+                        let past = std::mem::replace(belongs_to, Vec::new());
+                        self.annotate(proc_start, || Annotation::ScopedCall(past, name.clone()));
+                    }
+                    return success(Spanned::new(first_location, Follow::ProcReference(proc_kind, name)));
+                }
+            }
+        }
+
         let follow = match self.arguments(belongs_to, &ident)? {
             Some(args) => {
                 if !belongs_to.is_empty() {
@@ -2187,12 +2523,28 @@ impl<'ctx, 'an, 'inp> Parser<'ctx, 'an, 'inp> {
                     self.annotate_precise(start..end, || Annotation::ScopedVar(belongs_to.clone(), ident.clone()));
                     belongs_to.push(ident.clone());
                 }
-                Follow::Field(kind, ident)
+                if kind == PropertyAccessKind::Scope {
+                    Follow::StaticField(ident)
+                } else {
+                    Follow::Field(kind, ident)
+                }
             },
         };
         success(Spanned::new(first_location, follow))
     }
 
+    /// Annotate a `::` follow applied directly to a type path, as in
+    /// `/obj/item::name` or `/datum::proc/foo`.
+    fn annotate_scope(&mut self, path: &TypePath, follow: &Spanned<Follow>) {
+        let annotation = match &follow.elem {
+            Follow::StaticField(name) => Annotation::StaticVar(path.clone(), name.clone()),
+            Follow::ProcReference(_, name) |
+            Follow::Call(PropertyAccessKind::Scope, name, _) => Annotation::StaticProc(path.clone(), name.clone()),
+            _ => return,
+        };
+        self.annotate(follow.location, || annotation);
+    }
+
     // TODO: somehow fix the fact that this is basically copy-pasted from
     // follow() above.
     fn field(&mut self, belongs_to: &mut Vec<Ident>, in_ternary: bool) -> Status<Field> {
@@ -0,0 +1,872 @@
+//! An on-disk cache of preprocessed and parsed files, reused across runs.
+//!
+//! Everything the preprocessor does while reading a file is recorded: the
+//! tokens it produces, the macros it defines and undefines, its `#pragma`s,
+//! and its diagnostics. When a parser reads those tokens, what the file adds
+//! to the object tree is recorded too: the types, vars and procs it declares,
+//! their parsed proc bodies, and the parser's diagnostics.
+//!
+//! On the next run, a file with the same contents and the same incoming
+//! define state is replayed from the cache instead of being lexed and
+//! preprocessed again. If its parse results were recorded, its tokens are
+//! dropped and the parser adds its declarations to the object tree directly,
+//! so the file is not parsed again either. Diagnostics which depend on other
+//! files, such as duplicate definitions, are still worked out as the
+//! declarations are added.
+//!
+//! Files which `#include` other files are always read and parsed normally,
+//! since what they produce depends on the files they include. In a real
+//! environment, that means the `.dme` and any file which gathers others
+//! together with `#include`s are never cached, while the files they include
+//! are. Parse results are not kept for a file whose declarations run into
+//! the file before or after it, as with a block left open at its end.
+//! Changing the configuration, the environment, or the version discards the
+//! whole cache.
+
+use std::cell::RefCell;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fs::File;
+use std::hash::{Hash, Hasher};
+use std::io;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::ast::{Block, Expression, Parameter, ProcDeclKind, VarType};
+use crate::config::WarningLevel;
+use crate::docs::{DocCollection, DocComment};
+use crate::lexer::{Punctuation, Token};
+use crate::objtree::Code;
+use crate::preprocessor::Define;
+use crate::{Component, Context, DMError, FileId, Location, Severity};
+
+/// Changed whenever the layout of the cache file changes.
+const FORMAT: u32 = 3;
+
+/// The name of the cache file, which is kept next to the `.dme`.
+pub const FILE_NAME: &str = ".spacemandmm-preprocess-cache";
+
+/// Preprocessed files from a previous run, and those of this run.
+#[derive(Debug, Default)]
+pub struct PreprocessCache {
+    previous: CacheFile,
+    previous_files: HashMap<PathBuf, usize>,
+
+    current: CacheFile,
+    hits: usize,
+    parse_hits: usize,
+    misses: usize,
+
+    parse: ParseCache,
+    /// This run's files which were read or parsed again, by index into
+    /// `current.files`.
+    unparsed: Vec<usize>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default)]
+struct CacheFile {
+    format: u32,
+    version: String,
+    environment: u64,
+    files: Vec<CachedFile>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct CachedFile {
+    path: PathBuf,
+    contents: u64,
+    defines: u64,
+    /// The files the events' locations point into, this file first.
+    files: Vec<PathBuf>,
+    events: Vec<CachedEvent>,
+    /// What the file added to the object tree, as a `ParsedFile`.
+    parsed: Option<serde_json::Value>,
+}
+
+/// A location, with `file` indexing the entry's file list or `None` for the
+/// builtins.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+struct CachedLocation {
+    file: Option<usize>,
+    line: u32,
+    column: u16,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+enum CachedEvent {
+    Token(CachedLocation, Token),
+    Define(CachedLocation, String, CachedDefine),
+    Undef(CachedLocation, String),
+    UseDefine(CachedLocation),
+    Pragma(CachedLocation, String, u8),
+    Ifdef(CachedLocation, CachedLocation, bool),
+    Error(CachedError),
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct CachedDefine {
+    params: Option<Vec<String>>,
+    subst: Vec<Token>,
+    variadic: bool,
+    docs: Vec<DocComment>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct CachedError {
+    location: CachedLocation,
+    severity: u8,
+    dreamchecker: bool,
+    errortype: Option<String>,
+    description: String,
+    notes: Vec<(CachedLocation, String)>,
+    edits: Vec<(CachedLocation, String, String)>,
+}
+
+/// Something the preprocessor did while reading a file.
+#[derive(Debug)]
+pub(crate) enum Event {
+    /// A token was produced.
+    Token(Location, Token),
+    /// A macro was defined at the given location.
+    Define(Location, String, Define),
+    /// A macro was undefined at the given location.
+    Undef(Location, String),
+    /// The macro defined at the given location was used.
+    UseDefine(Location),
+    /// A `#pragma` set the level of a diagnostic.
+    Pragma(Location, String, WarningLevel),
+    /// An `#if` block ended.
+    Ifdef(Location, Location, bool),
+    /// A diagnostic was registered.
+    Error(DMError),
+}
+
+/// The events of one file being read, to be stored in the cache.
+#[derive(Debug)]
+pub(crate) struct Recording {
+    pub file: FileId,
+    pub contents: u64,
+    pub defines: u64,
+    /// The depth of the `#if` stack when the file started.
+    pub ifdef_depth: usize,
+    pub events: Vec<Event>,
+    /// Cleared if replaying the events would not reproduce the file.
+    pub cacheable: bool,
+}
+
+/// What one file added to the object tree.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub(crate) struct ParsedFile {
+    /// Whether proc bodies were parsed.
+    pub procs: bool,
+    pub ops: Vec<TreeOp>,
+    pub module_docs: Vec<(u32, DocComment)>,
+}
+
+/// A change the parser made to the object tree. Types are named by path.
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) enum TreeOp {
+    Subtype {
+        #[serde(with = "location")]
+        location: Location,
+        parent: String,
+        name: String,
+        len: usize,
+    },
+    TypeDocs {
+        path: String,
+        docs: Vec<DocComment>,
+    },
+    DeclareVar {
+        path: String,
+        name: String,
+        #[serde(with = "location")]
+        location: Location,
+        docs: Vec<DocComment>,
+        var_type: VarType,
+        expression: Option<Expression>,
+    },
+    OverrideVar {
+        path: String,
+        name: String,
+        #[serde(with = "location")]
+        location: Location,
+        docs: Vec<DocComment>,
+        expression: Expression,
+    },
+    Proc {
+        path: String,
+        name: String,
+        #[serde(with = "location")]
+        location: Location,
+        kind: Option<ProcDeclKind>,
+        parameters: Vec<Parameter>,
+        code: CachedCode,
+        /// Whether the body parsed without errors.
+        good: bool,
+        docs: Vec<DocComment>,
+    },
+    /// A diagnostic registered by the parser.
+    Error(#[serde(with = "error")] DMError),
+}
+
+/// The body of a proc, as in `objtree::Code`.
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) enum CachedCode {
+    Present(Block),
+    Invalid(#[serde(with = "error")] DMError),
+    Disabled,
+}
+
+impl CachedCode {
+    pub fn new(code: &Code) -> CachedCode {
+        match code {
+            Code::Present(block) => CachedCode::Present(block.clone()),
+            Code::Invalid(error) => CachedCode::Invalid(error.clone()),
+            Code::Builtin | Code::Disabled => CachedCode::Disabled,
+        }
+    }
+
+    pub fn into_code(self) -> Code {
+        match self {
+            CachedCode::Present(block) => Code::Present(block),
+            CachedCode::Invalid(error) => Code::Invalid(error),
+            CachedCode::Disabled => Code::Disabled,
+        }
+    }
+}
+
+/// Rebuild the docs of a cached item.
+pub(crate) fn doc_collection(comments: Vec<DocComment>) -> DocCollection {
+    let mut docs = DocCollection::default();
+    for each in comments {
+        docs.push(each);
+    }
+    docs
+}
+
+impl PreprocessCache {
+    /// Read a cache file, starting empty if it is missing or unreadable.
+    pub fn read(path: &Path) -> PreprocessCache {
+        let previous: CacheFile = match File::open(path) {
+            Ok(file) => serde_json::from_reader(io::BufReader::new(file)).unwrap_or_default(),
+            Err(_) => Default::default(),
+        };
+        PreprocessCache {
+            previous,
+            .. Default::default()
+        }
+    }
+
+    /// The path of the cache file for an environment.
+    pub fn path_for(dme: &Path) -> PathBuf {
+        dme.with_file_name(FILE_NAME)
+    }
+
+    /// Write the files preprocessed in this run to a cache file.
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        let file = io::BufWriter::new(File::create(path)?);
+        serde_json::to_writer(file, &self.current).map_err(io::Error::from)
+    }
+
+    /// The number of files which were replayed from the cache.
+    pub fn hits(&self) -> usize {
+        self.hits
+    }
+
+    /// The number of files whose parse results were reused, so that they
+    /// were not parsed again.
+    pub fn parse_hits(&self) -> usize {
+        self.parse_hits
+    }
+
+    /// Whether this run's files differ from the previous run's, so that the
+    /// cache file needs to be written again.
+    pub fn is_changed(&self) -> bool {
+        self.misses > 0 || self.hits != self.previous.files.len()
+    }
+
+    /// The part of the cache used by the parser.
+    pub(crate) fn parse_cache(&self) -> ParseCache {
+        self.parse.clone()
+    }
+
+    /// Work out whether the previous run's files are still valid for an
+    /// environment and configuration.
+    pub(crate) fn prepare(&mut self, context: &Context, env_file: &Path) {
+        let mut hasher = DefaultHasher::new();
+        env_file.hash(&mut hasher);
+        context.config().source().hash(&mut hasher);
+
+        self.current = CacheFile {
+            format: FORMAT,
+            version: env!("CARGO_PKG_VERSION").to_owned(),
+            environment: hasher.finish(),
+            files: Vec::new(),
+        };
+        self.hits = 0;
+        self.parse_hits = 0;
+        self.misses = 0;
+        self.parse = ParseCache::new();
+        self.unparsed.clear();
+
+        if self.previous.format != self.current.format
+            || self.previous.version != self.current.version
+            || self.previous.environment != self.current.environment
+        {
+            self.previous = Default::default();
+        }
+
+        self.previous_files = self.previous.files.iter()
+            .enumerate()
+            .map(|(i, file)| (file.path.clone(), i))
+            .collect();
+    }
+
+    /// Get the events of reading a file, if its contents and define state
+    /// are unchanged since they were cached.
+    ///
+    /// If the parser can use the file's parse results, they are returned too,
+    /// and the file's tokens should not be passed on.
+    pub(crate) fn get(&mut self, context: &Context, file: FileId, contents: u64, defines: u64) -> Option<(VecDeque<Event>, Option<ParsedFile>)> {
+        let path = context.file_path(file);
+        let cached = &self.previous.files[*self.previous_files.get(&path)?];
+        if cached.contents != contents || cached.defines != defines {
+            return None;
+        }
+
+        let mut files = vec![file];
+        for path in cached.files.iter().skip(1) {
+            files.push(context.get_file(path)?);
+        }
+
+        let (events, files) = with_entry_files(files, || {
+            let mut events = VecDeque::with_capacity(cached.events.len());
+            for event in cached.events.iter() {
+                events.push_back(match event {
+                    CachedEvent::Token(loc, token) => Event::Token(from_cached(*loc)?, token.clone()),
+                    CachedEvent::Define(loc, name, define) => {
+                        let docs = doc_collection(define.docs.clone());
+                        let subst = define.subst.clone();
+                        let define = match define.params.as_ref() {
+                            None => Define::Constant { subst, docs },
+                            Some(params) => Define::Function {
+                                params: params.clone(),
+                                subst,
+                                variadic: define.variadic,
+                                docs,
+                            },
+                        };
+                        Event::Define(from_cached(*loc)?, name.clone(), define)
+                    }
+                    CachedEvent::Undef(loc, name) => Event::Undef(from_cached(*loc)?, name.clone()),
+                    CachedEvent::UseDefine(loc) => Event::UseDefine(from_cached(*loc)?),
+                    CachedEvent::Pragma(loc, name, level) => Event::Pragma(from_cached(*loc)?, name.clone(), level_from_u8(*level)?),
+                    CachedEvent::Ifdef(start, end, active) => Event::Ifdef(from_cached(*start)?, from_cached(*end)?, *active),
+                    CachedEvent::Error(cached_error) => Event::Error(restore_error(cached_error)?),
+                });
+            }
+            Some(events)
+        });
+        let events = events?;
+
+        // Parse results which no longer read back mean the file is parsed
+        // again, not that it must be read again.
+        let procs = self.parse.procs();
+        let parsed = match (procs, cached.parsed.as_ref()) {
+            (Some(procs), Some(value)) => with_entry_files(files, || ParsedFile::deserialize(value)).0
+                .ok()
+                .filter(|parsed| parsed.procs == procs),
+            _ => None,
+        };
+
+        if parsed.is_some() {
+            self.parse_hits += 1;
+        } else {
+            self.unparsed.push(self.current.files.len());
+        }
+        self.current.files.push(cached.clone());
+        self.hits += 1;
+        Some((events, parsed))
+    }
+
+    /// Store the events of reading a file, to be written out.
+    pub(crate) fn insert(&mut self, context: &Context, recording: Recording) {
+        self.misses += 1;
+        let recorded = recording.events;
+        let (events, files) = with_entry_files(vec![recording.file], || {
+            recorded.into_iter().map(|event| match event {
+                Event::Token(loc, token) => CachedEvent::Token(to_cached(loc), token),
+                Event::Define(loc, name, define) => {
+                    let define = match define {
+                        Define::Constant { subst, docs } => CachedDefine {
+                            params: None,
+                            subst,
+                            variadic: false,
+                            docs: docs.comments().to_vec(),
+                        },
+                        Define::Function { params, subst, variadic, docs } => CachedDefine {
+                            params: Some(params),
+                            subst,
+                            variadic,
+                            docs: docs.comments().to_vec(),
+                        },
+                    };
+                    CachedEvent::Define(to_cached(loc), name, define)
+                }
+                Event::Undef(loc, name) => CachedEvent::Undef(to_cached(loc), name),
+                Event::UseDefine(loc) => CachedEvent::UseDefine(to_cached(loc)),
+                Event::Pragma(loc, name, level) => CachedEvent::Pragma(to_cached(loc), name, level as u8),
+                Event::Ifdef(start, end, active) => CachedEvent::Ifdef(to_cached(start), to_cached(end), active),
+                Event::Error(error) => CachedEvent::Error(cache_error(&error)),
+            }).collect()
+        });
+
+        self.unparsed.push(self.current.files.len());
+        self.current.files.push(CachedFile {
+            path: context.file_path(recording.file),
+            contents: recording.contents,
+            defines: recording.defines,
+            files: files.into_iter().map(|file| context.file_path(file)).collect(),
+            events,
+            parsed: None,
+        });
+    }
+
+    /// Store what the parser recorded for the files which were read or
+    /// parsed again.
+    pub(crate) fn finish(&mut self, context: &Context) {
+        let mut recorded = match self.parse.take_recorded() {
+            Some(recorded) => recorded,
+            // No parser read every token, so nothing new is known.
+            None => return,
+        };
+        let procs = self.parse.procs().unwrap_or_default();
+        for index in std::mem::take(&mut self.unparsed) {
+            let entry = &mut self.current.files[index];
+            let files: Vec<FileId> = match entry.files.iter().map(|path| context.get_file(path)).collect() {
+                Some(files) => files,
+                None => continue,
+            };
+            let parsed = match recorded.remove(&files[0]) {
+                Some(Some(parsed)) => parsed,
+                // Results which spanned several files cannot be reused.
+                Some(None) => {
+                    if entry.parsed.take().is_some() {
+                        self.misses += 1;
+                    }
+                    continue;
+                }
+                // A file the parser never saw added nothing.
+                None => ParsedFile {
+                    procs,
+                    .. Default::default()
+                },
+            };
+            let (value, files) = with_entry_files(files, || serde_json::to_value(&parsed));
+            entry.parsed = value.ok();
+            entry.files = files.into_iter().map(|file| context.file_path(file)).collect();
+            self.misses += 1;
+        }
+    }
+}
+
+/// Hash the contents of a file, for comparison with the cache.
+pub(crate) fn hash_contents(contents: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    contents.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn severity_from_u8(severity: u8) -> Option<Severity> {
+    match severity {
+        1 => Some(Severity::Error),
+        2 => Some(Severity::Warning),
+        3 => Some(Severity::Info),
+        4 => Some(Severity::Hint),
+        _ => None,
+    }
+}
+
+fn level_from_u8(level: u8) -> Option<WarningLevel> {
+    match level {
+        1 => Some(WarningLevel::Error),
+        2 => Some(WarningLevel::Warning),
+        3 => Some(WarningLevel::Info),
+        4 => Some(WarningLevel::Hint),
+        5 => Some(WarningLevel::Disabled),
+        6 => Some(WarningLevel::Unset),
+        _ => None,
+    }
+}
+
+// ----------------------------------------------------------------------------
+// Locations
+
+thread_local! {
+    /// The file list of the entry being written or read.
+    static ENTRY_FILES: RefCell<Vec<FileId>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Convert locations to and from the file list of an entry while `f` runs,
+/// returning the list with any files it added.
+fn with_entry_files<R>(files: Vec<FileId>, f: impl FnOnce() -> R) -> (R, Vec<FileId>) {
+    let previous = ENTRY_FILES.with(|cell| cell.replace(files));
+    let result = f();
+    (result, ENTRY_FILES.with(|cell| cell.replace(previous)))
+}
+
+fn to_cached(loc: Location) -> CachedLocation {
+    if loc.is_builtins() {
+        return CachedLocation { file: None, line: loc.line, column: loc.column };
+    }
+    let index = ENTRY_FILES.with(|cell| {
+        let mut files = cell.borrow_mut();
+        match files.iter().position(|&file| file == loc.file) {
+            Some(index) => index,
+            None => {
+                files.push(loc.file);
+                files.len() - 1
+            }
+        }
+    });
+    CachedLocation { file: Some(index), line: loc.line, column: loc.column }
+}
+
+fn from_cached(loc: CachedLocation) -> Option<Location> {
+    let file = match loc.file {
+        Some(index) => ENTRY_FILES.with(|cell| cell.borrow().get(index).cloned())?,
+        None => Location::builtins().file,
+    };
+    Some(Location { file, line: loc.line, column: loc.column })
+}
+
+fn cache_error(error: &DMError) -> CachedError {
+    CachedError {
+        location: to_cached(error.location()),
+        severity: error.severity() as u8,
+        dreamchecker: error.component() == Component::DreamChecker,
+        errortype: error.errortype().map(ToOwned::to_owned),
+        description: error.description().to_owned(),
+        notes: error.notes().iter()
+            .map(|note| (to_cached(note.location()), note.description().to_owned()))
+            .collect(),
+        edits: error.suggested_edits().iter()
+            .map(|edit| (to_cached(edit.span().start), edit.original().to_owned(), edit.replacement().to_owned()))
+            .collect(),
+    }
+}
+
+fn restore_error(cached: &CachedError) -> Option<DMError> {
+    let mut error = DMError::new(from_cached(cached.location)?, cached.description.clone())
+        .set_severity(severity_from_u8(cached.severity)?);
+    if cached.dreamchecker {
+        error = error.with_component(Component::DreamChecker);
+    }
+    // An errortype no longer in the registry means the file must be read
+    // again.
+    if let Some(errortype) = cached.errortype.as_ref() {
+        error = error.with_errortype(crate::diagnostics::errortype(errortype)?);
+    }
+    for (loc, note) in cached.notes.iter() {
+        error.add_note(from_cached(*loc)?, note.clone());
+    }
+    for (loc, original, replacement) in cached.edits.iter() {
+        error = error.with_edit(from_cached(*loc)?, original, replacement.clone());
+    }
+    Some(error)
+}
+
+/// Locations in cached syntax trees, relative to the entry's file list.
+pub(crate) mod location {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(location: &Location, serializer: S) -> Result<S::Ok, S::Error> {
+        to_cached(*location).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Location, D::Error> {
+        from_cached(CachedLocation::deserialize(deserializer)?)
+            .ok_or_else(|| D::Error::custom("location in an unknown file"))
+    }
+}
+
+/// Diagnostics in cached parse results.
+mod error {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(error: &DMError, serializer: S) -> Result<S::Ok, S::Error> {
+        cache_error(error).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<DMError, D::Error> {
+        restore_error(&CachedError::deserialize(deserializer)?)
+            .ok_or_else(|| D::Error::custom("diagnostic which cannot be restored"))
+    }
+}
+
+// ----------------------------------------------------------------------------
+// Sharing with the parser
+
+/// The part of a `PreprocessCache` used by the parser reading the
+/// preprocessor's tokens, from `Preprocessor::parse_cache`.
+///
+/// Does nothing if the preprocessor is not using a cache.
+#[derive(Debug, Clone, Default)]
+pub struct ParseCache {
+    shared: Option<Rc<RefCell<ParseShared>>>,
+}
+
+#[derive(Debug, Default)]
+struct ParseShared {
+    /// Whether the parser parses proc bodies, once it has started.
+    procs: Option<bool>,
+    /// Files whose tokens were dropped, for the parser to add in order.
+    replayed: VecDeque<(FileId, ParsedFile)>,
+    /// Diagnostics registered by the preprocessor, which it caches itself.
+    preprocessor_errors: Vec<Range<usize>>,
+    /// What each file added, or `None` where that could not be recorded,
+    /// once the parser has read every token.
+    recorded: Option<HashMap<FileId, Option<ParsedFile>>>,
+}
+
+impl ParseCache {
+    fn new() -> ParseCache {
+        ParseCache {
+            shared: Some(Default::default()),
+        }
+    }
+
+    fn with<R>(&self, f: impl FnOnce(&mut ParseShared) -> R) -> Option<R> {
+        self.shared.as_ref().map(|shared| f(&mut shared.borrow_mut()))
+    }
+
+    fn procs(&self) -> Option<bool> {
+        self.with(|shared| shared.procs).flatten()
+    }
+
+    fn take_recorded(&self) -> Option<HashMap<FileId, Option<ParsedFile>>> {
+        self.with(|shared| shared.recorded.take()).flatten()
+    }
+
+    /// Hand a file's parse results to the parser in place of its tokens.
+    pub(crate) fn push_replayed(&self, file: FileId, parsed: ParsedFile) {
+        self.with(|shared| shared.replayed.push_back((file, parsed)));
+    }
+
+    /// Take the next file whose parse results replace its tokens.
+    pub(crate) fn pop_replayed(&self) -> Option<(FileId, ParsedFile)> {
+        self.with(|shared| shared.replayed.pop_front()).flatten()
+    }
+
+    /// Note diagnostics which the preprocessor registered.
+    pub(crate) fn add_preprocessor_errors(&self, range: Range<usize>) {
+        if !range.is_empty() {
+            self.with(|shared| shared.preprocessor_errors.push(range));
+        }
+    }
+}
+
+/// Records what each file adds to the object tree as the parser reads it.
+///
+/// Only top-level entries are recorded. An entry belongs to the file of its
+/// first token, and any file whose tokens end up in another file's entry is
+/// not recorded at all.
+#[derive(Debug)]
+pub(crate) struct TreeRecorder {
+    cache: ParseCache,
+    procs: bool,
+    files: HashMap<FileId, Option<ParsedFile>>,
+    /// The file of the top-level entry being parsed.
+    entry: Option<FileId>,
+    errors_seen: usize,
+    /// Diagnostics registered since the last entry, not yet recorded.
+    held_errors: Vec<DMError>,
+    /// A `}` or `;` from another file, and the file whose entry took it,
+    /// which is fine if the indent processor made it for that file's first
+    /// token.
+    foreign_punct: Option<(Location, FileId)>,
+    /// The file whose entry has peeked at the first token of the next one,
+    /// that token's file, and whether it was a doc comment, after which the
+    /// entry may peek further.
+    peeked: Option<(FileId, FileId, bool)>,
+    /// The files of doc comments not yet attached to an item.
+    doc_files: Vec<FileId>,
+}
+
+impl TreeRecorder {
+    /// Start recording, if the preprocessor is using a cache.
+    pub fn start(cache: &ParseCache, procs: bool) -> Option<TreeRecorder> {
+        cache.with(|shared| shared.procs = Some(procs))?;
+        Some(TreeRecorder {
+            cache: cache.clone(),
+            procs,
+            files: Default::default(),
+            entry: None,
+            errors_seen: 0,
+            held_errors: Vec::new(),
+            foreign_punct: None,
+            peeked: None,
+            doc_files: Vec::new(),
+        })
+    }
+
+    fn spoil(&mut self, file: FileId) {
+        self.files.insert(file, None);
+    }
+
+    /// Note a token read by the parser.
+    pub fn token(&mut self, location: Location, token: &Token) {
+        let procs = self.procs;
+        self.files.entry(location.file).or_insert_with(|| Some(ParsedFile {
+            procs,
+            .. Default::default()
+        }));
+
+        let doc = matches!(token, Token::DocComment(_));
+        if let Some((taken_by, next, docs_only)) = self.peeked.take() {
+            if self.entry == Some(taken_by) {
+                if docs_only && location.file == next {
+                    self.peeked = Some((taken_by, next, doc));
+                    return;
+                }
+                self.spoil(taken_by);
+                self.spoil(location.file);
+            }
+        }
+
+        let closer = matches!(token, Token::Punct(Punctuation::RBrace) | Token::Punct(Punctuation::Semicolon));
+        if let Some((at, taken_by)) = self.foreign_punct {
+            if location != at {
+                self.spoil(taken_by);
+                self.spoil(at.file);
+                self.foreign_punct = None;
+            } else if !closer {
+                self.foreign_punct = None;
+                if self.entry == Some(taken_by) {
+                    self.peeked = Some((taken_by, at.file, doc));
+                    return;
+                }
+            }
+        }
+
+        if let Some(entry) = self.entry {
+            if location.file != entry {
+                if closer {
+                    self.foreign_punct.get_or_insert((location, entry));
+                } else {
+                    self.spoil(entry);
+                    self.spoil(location.file);
+                }
+            }
+        }
+    }
+
+    /// Note a doc comment which will attach to an item.
+    pub fn doc(&mut self, file: FileId) {
+        match (self.entry, self.peeked) {
+            // it documents the next entry, read while peeking at it
+            (Some(entry), Some((taken_by, next, _))) if entry == taken_by && file == next => self.doc_files.push(file),
+            (Some(entry), _) if entry != file => {
+                self.spoil(entry);
+                self.spoil(file);
+            }
+            (Some(_), _) => {}
+            (None, _) => self.doc_files.push(file),
+        }
+    }
+
+    /// Start recording a top-level entry from the given file.
+    pub fn begin_entry(&mut self, context: &Context, file: FileId) {
+        self.collect_errors(context);
+        for each in std::mem::take(&mut self.doc_files) {
+            if each != file {
+                self.spoil(each);
+                self.spoil(file);
+            }
+        }
+        self.entry = Some(file);
+        self.flush_errors();
+    }
+
+    /// Finish recording a top-level entry.
+    pub fn end_entry(&mut self, context: &Context, docs_pending: bool) {
+        self.collect_errors(context);
+        self.flush_errors();
+        if let (true, Some(entry)) = (docs_pending, self.entry) {
+            self.doc_files.push(entry);
+        }
+        self.entry = None;
+        self.peeked = None;
+    }
+
+    /// Record a change to the object tree.
+    pub fn op(&mut self, context: &Context, op: impl FnOnce() -> TreeOp) {
+        self.collect_errors(context);
+        self.flush_errors();
+        if let Some(entry) = self.entry {
+            if let Some(Some(file)) = self.files.get_mut(&entry) {
+                file.ops.push(op());
+            }
+        }
+    }
+
+    /// Hold on to the diagnostics registered since the last call, except the
+    /// preprocessor's.
+    pub fn collect_errors(&mut self, context: &Context) {
+        let preprocessor_errors = self.cache.with(|shared| std::mem::take(&mut shared.preprocessor_errors))
+            .unwrap_or_default();
+        let errors = context.errors();
+        for (i, error) in errors.iter().enumerate().skip(self.errors_seen) {
+            if !preprocessor_errors.iter().any(|range| range.contains(&i)) {
+                self.held_errors.push(error.clone());
+            }
+        }
+        self.errors_seen = errors.len();
+    }
+
+    /// Pass over diagnostics which are worked out again when the tree is
+    /// rebuilt from the cache.
+    pub fn skip_errors(&mut self, context: &Context) {
+        self.errors_seen = context.errors().len();
+    }
+
+    fn flush_errors(&mut self) {
+        let entry = match self.entry {
+            Some(entry) => entry,
+            None => return,
+        };
+        for error in std::mem::take(&mut self.held_errors) {
+            let file = error.location().file;
+            if file != entry {
+                self.spoil(entry);
+                self.spoil(file);
+            } else if let Some(Some(parsed)) = self.files.get_mut(&entry) {
+                parsed.ops.push(TreeOp::Error(error));
+            }
+        }
+    }
+
+    /// Hand what was recorded to the cache, once every token has been read.
+    pub fn finish(mut self, context: &Context, module_docs: &BTreeMap<FileId, Vec<(u32, DocComment)>>) {
+        self.collect_errors(context);
+        for error in std::mem::take(&mut self.held_errors) {
+            self.spoil(error.location().file);
+        }
+        // A `}` or `;` still pending here was made for the end of the input.
+        for file in std::mem::take(&mut self.doc_files) {
+            self.spoil(file);
+        }
+        for (file, docs) in module_docs.iter() {
+            if let Some(Some(parsed)) = self.files.get_mut(file) {
+                parsed.module_docs = docs.clone();
+            }
+        }
+        let files = self.files;
+        self.cache.with(|shared| shared.recorded = Some(files));
+    }
+}
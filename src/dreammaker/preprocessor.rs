@@ -1,17 +1,22 @@
 //! The preprocessor.
-use std::collections::{HashMap, VecDeque};
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
 use std::{io, fmt};
 use std::fs::File;
+use std::time::Instant;
 use std::path::{Path, PathBuf};
 use std::borrow::Cow;
 
 use interval_tree::{IntervalTree, range};
 
 use super::{DMError, Location, HasLocation, FileId, Context, Severity};
+use super::config::WarningLevel;
 use super::lexer::*;
 use super::docs::{DocComment, DocTarget, DocCollection};
 use super::annotation::*;
 use super::ast::Ident;
+use super::preprocess_cache::{self, Event, ParseCache, PreprocessCache, Recording};
 
 /// The maximum recursion depth of macro expansion.
 const MAX_RECURSION_DEPTH: usize = 32;
@@ -101,8 +106,14 @@ impl DefineHistory {
             env_file: self.env_file.clone(),
             include_stack: Default::default(),
             include_locations: Default::default(),
+            define_state: 0,
+            define_states: Default::default(),
+            file_hashes: Default::default(),
             history: Default::default(),  // TODO: support branching a second time
             defines,
+            used_defines: Default::default(),
+            check_unused_defines: false,
+            file_dirs: Default::default(),
             maps: Default::default(),
             skins: Default::default(),
             scripts: Default::default(),
@@ -114,8 +125,12 @@ impl DefineHistory {
             danger_idents: Default::default(),
             docs_in: Default::default(),
             docs_out: Default::default(),
+            pragma_states: Default::default(),
             in_interp_string: 0,
             annotations: None,
+            cache: None,
+            recordings: Default::default(),
+            errors_seen: 0,
         }
     }
 
@@ -126,8 +141,14 @@ impl DefineHistory {
             env_file: self.env_file.clone(),
             include_stack: Default::default(),
             include_locations: Default::default(),
+            define_state: 0,
+            define_states: Default::default(),
+            file_hashes: Default::default(),
             history: Default::default(),  // TODO: support branching a second time
             defines: DefineMap::from_history(self, self.last_input_loc),
+            used_defines: Default::default(),
+            check_unused_defines: false,
+            file_dirs: Default::default(),
             maps: Default::default(),
             skins: Default::default(),
             scripts: Default::default(),
@@ -139,8 +160,12 @@ impl DefineHistory {
             danger_idents: Default::default(),
             docs_in: Default::default(),
             docs_out: Default::default(),
+            pragma_states: Default::default(),
             in_interp_string: 0,
             annotations: None,
+            cache: None,
+            recordings: Default::default(),
+            errors_seen: 0,
         }
     }
 }
@@ -171,8 +196,14 @@ pub struct DefineMap {
 
 impl DefineMap {
     pub fn with_builtins() -> DefineMap {
+        DefineMap::with_builtins_targeting(None)
+    }
+
+    /// The builtin defines as seen by a project targeting an older version
+    /// of BYOND.
+    pub fn with_builtins_targeting(target_version: Option<u32>) -> DefineMap {
         let mut this = Default::default();
-        super::builtins::default_defines(&mut this);
+        super::builtins::default_defines(&mut this, target_version);
         this
     }
 
@@ -269,25 +300,33 @@ enum Include<'ctx> {
         location: Location,
         tokens: VecDeque<Token>,
     },
+    Cached {
+        events: VecDeque<Event>,
+        /// Set when the parser adds the file's declarations from the cache,
+        /// so its tokens are not passed on.
+        skip_tokens: bool,
+    },
 }
 
 impl<'ctx> Include<'ctx> {
-    fn from_path(context: &'ctx Context, path: PathBuf) -> Result<Include<'ctx>, DMError> {
+    /// Read a file, returning it along with a hash of its contents.
+    fn from_path(context: &'ctx Context, path: PathBuf) -> Result<(Include<'ctx>, u64), DMError> {
         let idx = context.register_file(&path);
-        Ok(Include::File {
-            file: idx,
-            lexer: Lexer::from_file(context, idx, &path)?,
-            path,
-        })
+        let start_time = Instant::now();
+        let contents = buffer_file(idx, &path)?;
+        context.add_io_time(start_time.elapsed());
+        Ok(Include::from_buffer(context, path, contents.into()))
     }
 
-    fn from_buffer(context: &'ctx Context, path: PathBuf, buffer: Cow<'ctx, [u8]>) -> Include<'ctx> {
+    /// Read a buffer, returning it along with a hash of its contents.
+    fn from_buffer(context: &'ctx Context, path: PathBuf, buffer: Cow<'ctx, [u8]>) -> (Include<'ctx>, u64) {
         let idx = context.register_file(&path);
-        Include::File {
+        let hash = preprocess_cache::hash_contents(&buffer);
+        (Include::File {
             file: idx,
             lexer: Lexer::new(context, idx, buffer),
             path,
-        }
+        }, hash)
     }
 }
 
@@ -311,32 +350,6 @@ impl<'ctx> IncludeStack<'ctx> {
     }
 }
 
-impl<'ctx> Iterator for IncludeStack<'ctx> {
-    type Item = LocatedToken;
-
-    fn next(&mut self) -> Option<LocatedToken> {
-        loop {
-            match self.stack.last_mut() {
-                Some(&mut Include::File { ref mut lexer, .. }) => match lexer.next() {
-                    //Some(Err(e)) => return Some(Err(e)),
-                    Some(t) => return Some(t),
-                    None => {} // fall through
-                },
-                Some(&mut Include::Expansion {
-                    ref mut tokens,
-                    location,
-                    ..
-                }) => match tokens.pop_front() {
-                    Some(token) => return Some(LocatedToken { location, token }),
-                    None => {} // fall through
-                },
-                None => return None,
-            }
-            self.stack.pop();
-        }
-    }
-}
-
 // ----------------------------------------------------------------------------
 // The main preprocessor
 
@@ -379,6 +392,9 @@ pub struct Preprocessor<'ctx> {
 
     include_stack: IncludeStack<'ctx>,
     include_locations: HashMap<FileId, Location>,
+    define_state: u64,
+    define_states: HashMap<FileId, u64>,
+    file_hashes: HashMap<FileId, u64>,
     last_input_loc: Location,
     output: VecDeque<Token>,
     ifdef_stack: Vec<Ifdef>,
@@ -387,6 +403,9 @@ pub struct Preprocessor<'ctx> {
 
     history: InnerDefineHistory,
     defines: DefineMap,
+    used_defines: BTreeSet<Location>,
+    check_unused_defines: bool,
+    file_dirs: Vec<PathBuf>,
     maps: Vec<PathBuf>,
     skins: Vec<PathBuf>,
     scripts: Vec<PathBuf>,
@@ -397,6 +416,34 @@ pub struct Preprocessor<'ctx> {
 
     docs_in: VecDeque<(Location, DocComment)>,
     docs_out: VecDeque<(Location, DocComment)>,
+
+    pragma_states: HashMap<FileId, PragmaState>,
+
+    cache: Option<PreprocessCache>,
+    /// What each file on the include stack has done so far, if it is being
+    /// recorded for the cache.
+    recordings: Vec<Option<Recording>>,
+    errors_seen: usize,
+}
+
+/// The diagnostic levels set by `#pragma` in one file, and those saved by
+/// `#pragma push`.
+#[derive(Debug, Default)]
+struct PragmaState {
+    current: BTreeMap<String, WarningLevel>,
+    pushed: Vec<BTreeMap<String, WarningLevel>>,
+}
+
+/// The diagnostic level named in a `#pragma`.
+fn pragma_level(name: &str) -> Option<WarningLevel> {
+    match name {
+        "error" => Some(WarningLevel::Error),
+        "warning" => Some(WarningLevel::Warning),
+        "notice" | "info" => Some(WarningLevel::Info),
+        "hint" => Some(WarningLevel::Hint),
+        "ignore" | "disabled" | "off" => Some(WarningLevel::Disabled),
+        _ => None,
+    }
 }
 
 impl<'ctx> HasLocation for Preprocessor<'ctx> {
@@ -404,6 +451,7 @@ impl<'ctx> HasLocation for Preprocessor<'ctx> {
         match self.include_stack.stack.last() {
             Some(&Include::File { ref lexer, .. }) => lexer.location(),
             Some(&Include::Expansion { location, .. }) => location,
+            Some(&Include::Cached { .. }) => self.last_input_loc,
             None => Location::default()
         }
     }
@@ -414,15 +462,21 @@ impl<'ctx> Preprocessor<'ctx> {
     pub fn new(context: &'ctx Context, env_file: PathBuf) -> Result<Self, DMError> {
         // Buffer the entire environment file. Large environments take a while
         // to load and locking it for the whole time is somewhat inconvenient.
-        let include = Include::from_path(context, env_file.clone())?;
+        let (include, hash) = Include::from_path(context, env_file.clone())?;
 
-        Ok(Preprocessor {
+        let mut preprocessor = Preprocessor {
             context,
             env_file,
             include_stack: IncludeStack { stack: vec![include] },
             include_locations: Default::default(),
+            define_state: 0,
+            define_states: Default::default(),
+            file_hashes: Default::default(),
             history: Default::default(),
-            defines: DefineMap::with_builtins(),
+            defines: DefineMap::with_builtins_targeting(context.config().target_version),
+            used_defines: Default::default(),
+            check_unused_defines: true,
+            file_dirs: Default::default(),
             maps: Default::default(),
             skins: Default::default(),
             scripts: Default::default(),
@@ -434,9 +488,15 @@ impl<'ctx> Preprocessor<'ctx> {
             danger_idents: Default::default(),
             docs_in: Default::default(),
             docs_out: Default::default(),
+            pragma_states: Default::default(),
             in_interp_string: 0,
             annotations: None,
-        })
+            cache: None,
+            recordings: vec![None],
+            errors_seen: 0,
+        };
+        preprocessor.record_define_state(hash);
+        Ok(preprocessor)
     }
 
     pub fn from_buffer<S: Into<Cow<'ctx, str>>>(context: &'ctx Context, env_file: PathBuf, buffer: S) -> Self {
@@ -444,14 +504,20 @@ impl<'ctx> Preprocessor<'ctx> {
             Cow::Borrowed(s) => Cow::Borrowed(s.as_bytes()),
             Cow::Owned(s) => Cow::Owned(s.into_bytes()),
         };
-        let include = Include::from_buffer(context, env_file.clone(), cow_u8);
-        Preprocessor {
+        let (include, hash) = Include::from_buffer(context, env_file.clone(), cow_u8);
+        let mut preprocessor = Preprocessor {
             context,
             env_file,
             include_stack: IncludeStack { stack: vec![include] },
             include_locations: Default::default(),
+            define_state: 0,
+            define_states: Default::default(),
+            file_hashes: Default::default(),
             history: Default::default(),
-            defines: DefineMap::with_builtins(),
+            defines: DefineMap::with_builtins_targeting(context.config().target_version),
+            used_defines: Default::default(),
+            check_unused_defines: true,
+            file_dirs: Default::default(),
             maps: Default::default(),
             skins: Default::default(),
             scripts: Default::default(),
@@ -463,9 +529,15 @@ impl<'ctx> Preprocessor<'ctx> {
             danger_idents: Default::default(),
             docs_in: Default::default(),
             docs_out: Default::default(),
+            pragma_states: Default::default(),
             in_interp_string: 0,
             annotations: None,
-        }
+            cache: None,
+            recordings: vec![None],
+            errors_seen: 0,
+        };
+        preprocessor.record_define_state(hash);
+        preprocessor
     }
 
     /// Finalize this preprocessor into its complete define history.
@@ -491,6 +563,35 @@ impl<'ctx> Preprocessor<'ctx> {
         }
     }
 
+    /// Access the path of the environment file.
+    pub fn env_file(&self) -> &Path {
+        &self.env_file
+    }
+
+    /// Access the resource search directories added by `#define FILE_DIR`.
+    pub fn file_dirs(&self) -> &[PathBuf] {
+        &self.file_dirs
+    }
+
+    /// Access the `.dmm` files which have been included.
+    pub fn maps(&self) -> &[PathBuf] {
+        &self.maps
+    }
+
+    /// Access a hash of the defines in effect at the start of each file.
+    ///
+    /// The hash covers the whole sequence of `#define` and `#undef`
+    /// directives up to that point, so a file with the same contents and the
+    /// same define state will preprocess to the same tokens.
+    pub fn define_states(&self) -> &HashMap<FileId, u64> {
+        &self.define_states
+    }
+
+    /// Access a hash of the contents of each file which has been read.
+    pub fn file_hashes(&self) -> &HashMap<FileId, u64> {
+        &self.file_hashes
+    }
+
     /// Access the ifdef history.
     pub fn ifdef_history(&self) -> &IntervalTree<Location, bool> {
         &self.ifdef_history
@@ -509,8 +610,14 @@ impl<'ctx> Preprocessor<'ctx> {
     /// Push a DM file to the top of this preprocessor's stack.
     pub fn push_file<R: io::Read + 'static>(&mut self, path: PathBuf, read: R) -> Result<FileId, DMError> {
         let idx = self.context.register_file(&path);
+        let start_time = Instant::now();
+        let contents = buffer_read(idx, read)?;
+        self.context.add_io_time(start_time.elapsed());
+        self.define_states.insert(idx, self.define_state);
+        self.file_hashes.insert(idx, preprocess_cache::hash_contents(&contents));
+        self.recordings.push(None);
         self.include_stack.stack.push(Include::File {
-            lexer: Lexer::from_read(self.context, idx, read)?,
+            lexer: Lexer::new(self.context, idx, contents),
             file: idx,
             path,
         });
@@ -527,6 +634,30 @@ impl<'ctx> Preprocessor<'ctx> {
         self.annotations.take()
     }
 
+    /// Replay unchanged files from a cache of previous runs, and store the
+    /// files read by this run in it.
+    ///
+    /// The cache is not used while annotations are enabled. Pass
+    /// `parse_cache` to the parser so that it skips parsing them too.
+    pub fn use_cache(&mut self, mut cache: PreprocessCache) {
+        cache.prepare(self.context, &self.env_file);
+        self.cache = Some(cache);
+    }
+
+    /// The part of the cache used by the parser reading this preprocessor's
+    /// tokens, for `Parser::use_cache`.
+    pub fn parse_cache(&self) -> ParseCache {
+        self.cache.as_ref().map(PreprocessCache::parse_cache).unwrap_or_default()
+    }
+
+    /// Retrieve the cache, along with what the parser recorded, to be written
+    /// out once parsing is done.
+    pub fn take_cache(&mut self) -> Option<PreprocessCache> {
+        let mut cache = self.cache.take()?;
+        cache.finish(self.context);
+        Some(cache)
+    }
+
     // ------------------------------------------------------------------------
     // Macro definition handling
 
@@ -542,6 +673,60 @@ impl<'ctx> Preprocessor<'ctx> {
         }
     }
 
+    /// Apply a `#pragma` directive. Those which set the level of a
+    /// diagnostic, by name or code, last until the end of the file.
+    fn pragma(&mut self, location: Location, words: &[String]) {
+        let state = self.pragma_states.entry(location.file).or_default();
+        match words {
+            // All DM source is effectively `#pragma once`.
+            [word] if word == "once" => {}
+            [word] if word == "push" => state.pushed.push(state.current.clone()),
+            [word] if word == "pop" => {
+                guard!(let Some(saved) = state.pushed.pop() else {
+                    DMError::new(location, "#pragma pop without a matching #pragma push")
+                        .with_errortype("malformed_directive")
+                        .register(self.context);
+                    return;
+                });
+                let current = std::mem::replace(&mut state.current, saved);
+                let names: BTreeSet<&String> = current.keys().chain(state.current.keys()).collect();
+                let mut changed = Vec::new();
+                for name in names {
+                    let level = state.current.get(name).copied().unwrap_or(WarningLevel::Unset);
+                    if current.get(name) != state.current.get(name) {
+                        changed.push((name.clone(), level));
+                    }
+                }
+                for (name, level) in changed {
+                    self.add_pragma(location, name, level);
+                }
+            }
+            [name, level] => match pragma_level(level) {
+                Some(level) => {
+                    state.current.insert(name.clone(), level);
+                    self.add_pragma(location, name.clone(), level);
+                }
+                None => {
+                    DMError::new(location, format!("unknown #pragma level {:?}, expected error, warning, notice, or ignore", level))
+                        .with_errortype("unknown_pragma")
+                        .set_severity(Severity::Warning)
+                        .register(self.context);
+                }
+            },
+            _ => {
+                DMError::new(location, format!("unknown #pragma: {}", words.join(" ")))
+                    .with_errortype("unknown_pragma")
+                    .set_severity(Severity::Warning)
+                    .register(self.context);
+            }
+        }
+    }
+
+    fn add_pragma(&mut self, location: Location, name: String, level: WarningLevel) {
+        self.record(|| Event::Pragma(location, name.clone(), level));
+        self.context.add_pragma(location, name, level);
+    }
+
     fn in_environment(&self) -> bool {
         for include in self.include_stack.stack.iter().rev() {
             if let Include::File { ref path, .. } = *include {
@@ -558,6 +743,105 @@ impl<'ctx> Preprocessor<'ctx> {
         }
     }
 
+    fn mark_used(&mut self, name: &str) {
+        if let Some(&(location, _)) = self.defines.get(name) {
+            self.use_define(location);
+        }
+    }
+
+    fn use_define(&mut self, location: Location) {
+        self.record(|| Event::UseDefine(location));
+        self.used_defines.insert(location);
+    }
+
+    /// Define a macro, returning the location of its previous definition.
+    fn define(&mut self, name: String, location: Location, define: Define) -> Option<Location> {
+        // FILE_DIR adds to the resource search path rather than being a normal macro
+        if name == "FILE_DIR" {
+            let dir = match define.substitution() {
+                [Token::String(text)] => text.clone(),
+                other => other.iter().map(ToString::to_string).collect(),
+            };
+            let base = self.env_file.parent().unwrap_or_else(|| "".as_ref());
+            self.file_dirs.push(base.join(dir.replace("\\", "/")));
+        }
+        self.record(|| Event::Define(location, name.clone(), define.clone()));
+        self.update_define_state(&name, Some(&define));
+        self.defines.insert(name, (location, define))
+    }
+
+    /// Undefine a macro, returning false if it was not defined.
+    fn undefine(&mut self, name: &str) -> bool {
+        guard!(let Some(previous) = self.defines.remove(name) else {
+            return false;
+        });
+        let location = self.last_input_loc;
+        self.record(|| Event::Undef(location, name.to_owned()));
+        self.update_define_state(name, None);
+        self.move_to_history(name.to_owned(), previous);
+        true
+    }
+
+    /// Report macros which were never expanded or tested, if enabled.
+    fn report_unused_defines(&mut self) {
+        if !self.check_unused_defines || !self.context.config().is_opted_in("unused_macro") {
+            return;
+        }
+        self.check_unused_defines = false;
+
+        let mut unused = Vec::new();
+        for (name, stack) in self.defines.inner.iter() {
+            for &(location, _) in stack.iter() {
+                unused.push((location, name.as_str()));
+            }
+        }
+        for (range, (name, _)) in self.history.iter() {
+            unused.push((range.start, name.as_str()));
+        }
+        // DEBUG and FILE_DIR are read by the compiler itself.
+        unused.retain(|&(location, name)| {
+            !location.is_builtins()
+                && !self.used_defines.contains(&location)
+                && name != "DEBUG"
+                && name != "FILE_DIR"
+        });
+        unused.sort();
+
+        for (location, name) in unused {
+            DMError::new(location, format!("macro is never used: {}", name))
+                .set_severity(Severity::Hint)
+                .with_errortype("unused_macro")
+                .register(self.context);
+        }
+    }
+
+    fn record_define_state(&mut self, contents: u64) {
+        if let Some(&Include::File { file, .. }) = self.include_stack.stack.last() {
+            self.define_states.insert(file, self.define_state);
+            self.file_hashes.insert(file, contents);
+        }
+    }
+
+    fn update_define_state(&mut self, name: &str, define: Option<&Define>) {
+        let mut hasher = DefaultHasher::new();
+        self.define_state.hash(&mut hasher);
+        name.hash(&mut hasher);
+        match define {
+            None => 0u8.hash(&mut hasher),
+            Some(Define::Constant { subst, docs: _ }) => {
+                1u8.hash(&mut hasher);
+                hash_tokens(subst, &mut hasher);
+            }
+            Some(Define::Function { params, subst, variadic, docs: _ }) => {
+                2u8.hash(&mut hasher);
+                params.hash(&mut hasher);
+                variadic.hash(&mut hasher);
+                hash_tokens(subst, &mut hasher);
+            }
+        }
+        self.define_state = hasher.finish();
+    }
+
     fn move_to_history(&mut self, name: String, previous: (Location, Define)) {
         self.history.insert(range(previous.0, self.last_input_loc), (name, previous.1));
     }
@@ -570,10 +854,16 @@ impl<'ctx> Preprocessor<'ctx> {
     }
 
     fn pop_ifdef(&mut self) -> Option<Ifdef> {
-        self.ifdef_stack.pop().map(|ifdef| {
-            self.ifdef_history.insert(range(ifdef.location, self.last_input_loc), ifdef.active);
-            ifdef
-        })
+        let ifdef = self.ifdef_stack.pop()?;
+        self.ifdef_history.insert(range(ifdef.location, self.last_input_loc), ifdef.active);
+        if let Some(Some(recording)) = self.recordings.last_mut() {
+            // An #endif for an #if in the file which included this one.
+            if self.ifdef_stack.len() < recording.ifdef_depth {
+                recording.cacheable = false;
+            }
+            recording.events.push(Event::Ifdef(ifdef.location, self.last_input_loc, ifdef.active));
+        }
+        Some(ifdef)
     }
 
     fn evaluate_inner(&mut self) -> Result<bool, DMError> {
@@ -628,10 +918,13 @@ impl<'ctx> Preprocessor<'ctx> {
     // ------------------------------------------------------------------------
     // Internal utilities
 
-    fn prepare_include_file(&mut self, path: PathBuf) -> Result<Include<'ctx>, DMError> {
+    /// Open a file to be included, replaying it from the cache if possible,
+    /// and start recording it for the cache otherwise.
+    fn prepare_include_file(&mut self, path: PathBuf) -> Result<(Include<'ctx>, Option<Recording>), DMError> {
         // Attempt to open the file.
         let read = io::BufReader::new(File::open(&path).map_err(|e|
             DMError::new(self.last_input_loc, format!("failed to open file: #include {:?}", path))
+                .with_errortype("io_error")
                 .with_cause(e))?);
 
         // Get the path relative to the environment root, for easy lookup later.
@@ -647,12 +940,119 @@ impl<'ctx> Preprocessor<'ctx> {
                 .with_errortype("duplicate_include"))
         } else {
             self.include_locations.insert(file_id, self.last_input_loc);
-            Ok(Include::File {
+            self.define_states.insert(file_id, self.define_state);
+
+            let start_time = Instant::now();
+            let contents = buffer_read(file_id, read)?;
+            self.context.add_io_time(start_time.elapsed());
+            let hash = preprocess_cache::hash_contents(&contents);
+            self.file_hashes.insert(file_id, hash);
+
+            let mut recording = None;
+            if self.annotations.is_none() && self.docs_in.is_empty() && self.in_interp_string == 0 {
+                if let Some(cache) = self.cache.as_mut() {
+                    if let Some((events, parsed)) = cache.get(self.context, file_id, hash, self.define_state) {
+                        let skip_tokens = parsed.is_some();
+                        if let Some(parsed) = parsed {
+                            cache.parse_cache().push_replayed(file_id, parsed);
+                        }
+                        return Ok((Include::Cached { events, skip_tokens }, None));
+                    }
+                    recording = Some(Recording {
+                        file: file_id,
+                        contents: hash,
+                        defines: self.define_state,
+                        ifdef_depth: self.ifdef_stack.len(),
+                        events: Vec::new(),
+                        cacheable: true,
+                    });
+                }
+            }
+
+            Ok((Include::File {
                 path,
                 file: file_id,
-                lexer: Lexer::from_read(&self.context, file_id, read)?,
-            })
+                lexer: Lexer::new(self.context, file_id, contents),
+            }, recording))
+        }
+    }
+
+    /// Record something the current file did, if it is being recorded.
+    fn record(&mut self, event: impl FnOnce() -> Event) {
+        if let Some(Some(recording)) = self.recordings.last_mut() {
+            recording.events.push(event());
+        }
+    }
+
+    /// Record the diagnostics registered since the last call.
+    fn record_errors(&mut self) {
+        let errors = self.context.errors();
+        if let Some(Some(recording)) = self.recordings.last_mut() {
+            for error in errors.get(self.errors_seen..).unwrap_or_default() {
+                recording.events.push(Event::Error(error.clone()));
+            }
+        }
+        self.errors_seen = errors.len();
+    }
+
+    /// Finish reading the file on top of the stack, storing what it did in
+    /// the cache if it is being recorded.
+    fn finish_file(&mut self) {
+        self.record_errors();
+        if let Some(Some(mut recording)) = self.recordings.pop() {
+            // Anything left over would be replayed in the wrong place.
+            if self.ifdef_stack.len() != recording.ifdef_depth
+                || !self.docs_in.is_empty()
+                || !self.output.is_empty()
+                || self.in_interp_string != 0
+            {
+                recording.cacheable = false;
+            }
+            if let (true, Some(cache)) = (recording.cacheable, self.cache.as_mut()) {
+                cache.insert(self.context, recording);
+            }
+        }
+    }
+
+    /// Apply the next event of the cached file on top of the stack, returning
+    /// it if it was a token.
+    fn replay_next(&mut self) -> Option<LocatedToken> {
+        let (event, skip_tokens) = match self.include_stack.stack.last_mut() {
+            Some(Include::Cached { events, skip_tokens }) => (events.pop_front(), *skip_tokens),
+            _ => return None,
+        };
+        match event {
+            Some(Event::Token(location, token)) => {
+                self.last_input_loc = location;
+                if !skip_tokens {
+                    return Some(LocatedToken::new(location, token));
+                }
+            }
+            Some(Event::Define(location, name, define)) => {
+                self.define(name, location, define);
+            }
+            Some(Event::Undef(location, name)) => {
+                self.last_input_loc = location;
+                self.undefine(&name);
+            }
+            Some(Event::UseDefine(location)) => {
+                self.used_defines.insert(location);
+            }
+            Some(Event::Pragma(location, name, level)) => {
+                self.context.add_pragma(location, name, level);
+            }
+            Some(Event::Ifdef(start, end, active)) => {
+                self.ifdef_history.insert(range(start, end), active);
+            }
+            Some(Event::Error(error)) => {
+                self.context.register_error(error);
+            }
+            None => {
+                self.include_stack.stack.pop();
+                self.finish_file();
+            }
         }
+        None
     }
 
     fn check_danger_ident(&mut self, name: &str, kind: &str) {
@@ -660,12 +1060,34 @@ impl<'ctx> Preprocessor<'ctx> {
             self.context.register_error(DMError::new(*loc, format!(
                 "macro {:?} used immediately before being {}:\n\
                 https://secure.byond.com/forum/?post=2072419", name, kind
-            )).set_severity(Severity::Warning));
+            )).with_errortype("macro_used_before_change").set_severity(Severity::Warning));
         }
     }
 
     fn inner_next(&mut self) -> Option<LocatedToken> {
-        self.include_stack.next()
+        loop {
+            match self.include_stack.stack.last_mut() {
+                Some(&mut Include::File { ref mut lexer, .. }) => match lexer.next() {
+                    Some(t) => return Some(t),
+                    None => {} // fall through
+                },
+                Some(&mut Include::Expansion {
+                    ref mut tokens,
+                    location,
+                    ..
+                }) => match tokens.pop_front() {
+                    Some(token) => return Some(LocatedToken { location, token }),
+                    None => {} // fall through
+                },
+                // cached files are replayed by `next`
+                Some(&mut Include::Cached { .. }) => return None,
+                None => return None,
+            }
+            // finish each file before reading on in the one which included it
+            if let Some(Include::File { .. }) = self.include_stack.stack.pop() {
+                self.finish_file();
+            }
+        }
     }
 
     #[allow(unreachable_code)]
@@ -678,7 +1100,7 @@ impl<'ctx> Preprocessor<'ctx> {
                         _last_expected_loc = x.location;
                         x.token
                     }
-                    None => return Err(self.error("unexpected EOF")),
+                    None => return Err(self.error("unexpected EOF").with_errortype("malformed_directive")),
                 }
             };
         }
@@ -686,14 +1108,14 @@ impl<'ctx> Preprocessor<'ctx> {
             (($($i:ident),*) = $p:pat) => {
                 let ($($i,)*) = match next!() {
                     $p => ($($i,)*),
-                    other => return Err(self.error(format!("unexpected token {:?}, expecting {}", other, stringify!($p))))
+                    other => return Err(self.error(format!("unexpected token {:?}, expecting {}", other, stringify!($p))).with_errortype("malformed_directive"))
                 };
             }
         }
 
         const ALL_DIRECTIVES: &[&str] = &[
             "if", "ifdef", "ifndef", "elif", "else", "endif",
-            "include", "define", "undef", "warn", "error",
+            "include", "define", "undef", "warn", "error", "pragma",
         ];
         let disabled = !inside_condition && self.is_disabled();
         match read {
@@ -704,22 +1126,24 @@ impl<'ctx> Preprocessor<'ctx> {
                     // ifdefs
                     "endif" => {
                         self.pop_ifdef().ok_or_else(||
-                            DMError::new(self.last_input_loc, "unmatched #endif"))?;
+                            DMError::new(self.last_input_loc, "unmatched #endif").with_errortype("unmatched_conditional"))?;
                     }
                     "else" => {
                         let last = self.pop_ifdef().ok_or_else(||
-                            DMError::new(self.last_input_loc, "unmatched #else"))?;
+                            DMError::new(self.last_input_loc, "unmatched #else").with_errortype("unmatched_conditional"))?;
                         self.ifdef_stack.push(last.else_(self.last_input_loc));
                     }
                     "ifdef" => {
                         expect_token!((define_name) = Token::Ident(define_name, _));
                         expect_token!(() = Token::Punct(Punctuation::Newline));
+                        self.mark_used(&define_name);
                         let enabled = self.is_defined(&define_name);
                         self.ifdef_stack.push(Ifdef::new(self.last_input_loc, enabled));
                     }
                     "ifndef" => {
                         expect_token!((define_name) = Token::Ident(define_name, _));
                         expect_token!(() = Token::Punct(Punctuation::Newline));
+                        self.mark_used(&define_name);
                         let enabled = !self.is_defined(&define_name);
                         self.ifdef_stack.push(Ifdef::new(self.last_input_loc, enabled));
                     }
@@ -729,7 +1153,7 @@ impl<'ctx> Preprocessor<'ctx> {
                     }
                     "elif" => {
                         let last = self.pop_ifdef().ok_or_else(||
-                            DMError::new(self.last_input_loc, "unmatched #elif"))?;
+                            DMError::new(self.last_input_loc, "unmatched #elif").with_errortype("unmatched_conditional"))?;
                         let enabled = self.evaluate();
                         self.ifdef_stack.push(last.else_if(self.last_input_loc, enabled));
                     }
@@ -741,6 +1165,10 @@ impl<'ctx> Preprocessor<'ctx> {
                     "include" => {
                         expect_token!((path_str) = Token::String(path_str));
                         let include_loc = _last_expected_loc;
+                        // what this file produces now depends on another file
+                        if let Some(Some(recording)) = self.recordings.last_mut() {
+                            recording.cacheable = false;
+                        }
                         expect_token!(() = Token::Punct(Punctuation::Newline));
                         let path = PathBuf::from(path_str.replace("\\", "/"));
 
@@ -771,11 +1199,11 @@ impl<'ctx> Preprocessor<'ctx> {
                                     self.context.register_error(DMError::new(
                                         self.last_input_loc,
                                         format!("unknown extension {:?}", ext),
-                                    ));
+                                    ).with_errortype("bad_include"));
                                     return Ok(());
                                 }
                                 None => {
-                                    self.context.register_error(DMError::new(self.last_input_loc, "filename has no extension"));
+                                    self.context.register_error(DMError::new(self.last_input_loc, "filename has no extension").with_errortype("bad_include"));
                                     return Ok(());
                                 }
                             };
@@ -791,11 +1219,16 @@ impl<'ctx> Preprocessor<'ctx> {
                                 FileType::DMF => self.skins.push(candidate),
                                 FileType::DMS => self.scripts.push(candidate),
                                 FileType::DM => match self.prepare_include_file(candidate) {
-                                    Ok(include) => {
+                                    Ok((include, recording)) => {
                                         // A phantom newline keeps the include
                                         // directive being indented from making
                                         // the first line of the file indented.
-                                        self.output.push_back(Token::Punct(Punctuation::Newline));
+                                        // Cached files start with their own.
+                                        if let Include::File { .. } = include {
+                                            self.output.push_back(Token::Punct(Punctuation::Newline));
+                                        }
+                                        self.record_errors();
+                                        self.recordings.push(recording);
                                         self.include_stack.stack.push(include);
                                     },
                                     Err(e) => self.context.register_error(e),
@@ -805,7 +1238,7 @@ impl<'ctx> Preprocessor<'ctx> {
                             return Ok(());
                         }
 
-                        self.context.register_error(DMError::new(self.last_input_loc, format!("failed to find #include {:?}", path)));
+                        self.context.register_error(DMError::new(self.last_input_loc, format!("failed to find #include {:?}", path)).with_errortype("missing_include"));
                         return Ok(());
                     }
                     // both constant and function defines
@@ -853,7 +1286,7 @@ impl<'ctx> Preprocessor<'ctx> {
                                 Token::Punct(Punctuation::LParen) if !ws => {
                                     loop {
                                         if variadic {
-                                            return Err(self.error("only the last parameter of a macro may be variadic"));
+                                            return Err(self.error("only the last parameter of a macro may be variadic").with_errortype("malformed_directive"));
                                         }
                                         match next!() {
                                             Token::Ident(name, _) => params.push(name),
@@ -861,7 +1294,7 @@ impl<'ctx> Preprocessor<'ctx> {
                                                 params.push("__VA_ARGS__".to_owned());  // default
                                                 variadic = true;
                                             }
-                                            _ => return Err(self.error("malformed macro parameters, expected name")),
+                                            _ => return Err(self.error("malformed macro parameters, expected name").with_errortype("malformed_directive")),
                                         }
                                         match next!() {
                                             Token::Punct(Punctuation::Comma) => {}
@@ -870,10 +1303,10 @@ impl<'ctx> Preprocessor<'ctx> {
                                                 variadic = true;
                                                 match next!() {
                                                     Token::Punct(Punctuation::RParen) => break,
-                                                    _ => return Err(self.error("only the last parameter of a macro may be variadic"))
+                                                    _ => return Err(self.error("only the last parameter of a macro may be variadic").with_errortype("malformed_directive"))
                                                 }
                                             }
-                                            _ => return Err(self.error("malformed macro parameters, expected comma")),
+                                            _ => return Err(self.error("malformed macro parameters, expected comma").with_errortype("malformed_directive")),
                                         }
                                     }
                                 }
@@ -898,7 +1331,7 @@ impl<'ctx> Preprocessor<'ctx> {
                         };
                         // DEBUG can only be defined in the root .dme file
                         if define_name != "DEBUG" || self.in_environment() {
-                            if let Some(previous_loc) = self.defines.insert(define_name.clone(), (define_name_loc, define)) {
+                            if let Some(previous_loc) = self.define(define_name.clone(), define_name_loc, define) {
                                 // DM doesn't issue a warning for this, but it's usually a mistake, so let's.
                                 // FILE_DIR is handled specially and sometimes makes sense to define multiple times.
                                 if define_name != "FILE_DIR" {
@@ -917,9 +1350,7 @@ impl<'ctx> Preprocessor<'ctx> {
                         let define_name_loc = _last_expected_loc;
                         self.check_danger_ident(&define_name, "undefined");
                         expect_token!(() = Token::Punct(Punctuation::Newline));
-                        if let Some(previous) = self.defines.remove(&define_name) {
-                            self.move_to_history(define_name, previous);
-                        } else {
+                        if !self.undefine(&define_name) {
                             DMError::new(define_name_loc, format!("macro undefined while not defined: {}", define_name))
                                 .with_errortype("macro_undefined_no_definition")
                                 .set_severity(Severity::Warning)
@@ -930,13 +1361,28 @@ impl<'ctx> Preprocessor<'ctx> {
                     "warn" => {
                         expect_token!((text) = Token::String(text));
                         DMError::new(self.last_input_loc, format!("#{} {}", ident, text))
+                            .with_errortype("warn_directive")
                             .set_severity(Severity::Warning)
                             .register(self.context);
                     }
                     "error" if disabled => {}
                     "error" => {
                         expect_token!((text) = Token::String(text));
-                        self.context.register_error(DMError::new(self.last_input_loc, format!("#{} {}", ident, text)));
+                        self.context.register_error(DMError::new(self.last_input_loc, format!("#{} {}", ident, text)).with_errortype("error_directive"));
+                    }
+                    "pragma" if disabled => {}
+                    "pragma" => {
+                        let location = self.last_input_loc;
+                        let mut words = Vec::new();
+                        loop {
+                            match next!() {
+                                Token::Punct(Punctuation::Newline) => break,
+                                Token::Ident(word, _) => words.push(word),
+                                other => return Err(self.error(format!("unexpected token {:?} in #pragma", other)).with_errortype("malformed_directive")),
+                            }
+                        }
+                        self.context.check_target_version(location, 515, "#pragma");
+                        self.pragma(location, &words);
                     }
                     // none of this other stuff should even exist
                     other => {
@@ -947,7 +1393,7 @@ impl<'ctx> Preprocessor<'ctx> {
                             }
                         }
                         return Err(DMError::new(self.last_input_loc, format!("unknown directive: #{}{}{}", ident,
-                            if !meant.is_empty() { ", did you mean #" } else { "" }, meant)));
+                            if !meant.is_empty() { ", did you mean #" } else { "" }, meant)).with_errortype("unknown_directive"));
                     }
                 }
                 // yield a newline
@@ -981,12 +1427,16 @@ impl<'ctx> Preprocessor<'ctx> {
                     self.output.push_back(Token::Int(self.last_input_loc.line as i32));
                     return Ok(());
                 }
+                // `__PROC__`, `__TYPE__`, and `__IMPLIED_TYPE__` depend on the
+                // code surrounding them, so they pass through as identifiers
+                // and are resolved by whoever evaluates the expression.
 
                 // special case for inside a defined() call
                 if let Some(Token::Punct(Punctuation::LParen)) = self.output.back() {
                     if let Some(idx) = self.output.len().checked_sub(2) {
                         if let Some(Token::Ident(identname, _)) = self.output.get(idx) {
                             if identname.as_str() == "defined" {
+                                self.mark_used(ident);
                                 self.output.push_back(Token::Ident(ident.to_owned(), whitespace));
                                 return Ok(());
                             }
@@ -998,12 +1448,13 @@ impl<'ctx> Preprocessor<'ctx> {
                 let mut expansion = self.defines.get(ident).cloned();  // TODO: don't clone?
                 if expansion.is_some() && self.include_stack.stack.len() > MAX_RECURSION_DEPTH {
                     self.error(format!("expanding {:?} would exceed max recursion depth of {} levels",
-                        ident, MAX_RECURSION_DEPTH)).register(self.context);
+                        ident, MAX_RECURSION_DEPTH)).with_errortype("macro_recursion").register(self.context);
                     expansion = None;
                 }
 
                 match expansion {
                     Some((location, Define::Constant { subst, docs: _ })) => {
+                        self.use_define(location);
                         self.annotate_macro(ident, location);
                         self.include_stack.stack.push(Include::Expansion {
                             name: ident.to_owned(),
@@ -1028,6 +1479,7 @@ impl<'ctx> Preprocessor<'ctx> {
                             }
                         }
 
+                        self.use_define(location);
                         self.annotate_macro(ident, location);
 
                         // read arguments
@@ -1067,7 +1519,7 @@ impl<'ctx> Preprocessor<'ctx> {
                             }
                         }
                         if args.len() != params.len() {
-                            return Err(self.error("wrong number of arguments to macro call"));
+                            return Err(self.error("wrong number of arguments to macro call").with_errortype("macro_arguments"));
                         }
 
                         // paste them into the expansion
@@ -1143,10 +1595,10 @@ impl<'ctx> Preprocessor<'ctx> {
                                                 }
                                                 expansion.push_back(Token::String(string));
                                             }
-                                            None => return Err(DMError::new(self.last_input_loc, format!("can't stringify non-argument ident {:?}", argname))),
+                                            None => return Err(DMError::new(self.last_input_loc, format!("can't stringify non-argument ident {:?}", argname)).with_errortype("bad_stringify")),
                                         }
-                                        Some(tok) => return Err(DMError::new(self.last_input_loc, format!("can't stringify non-ident '{}'", tok))),
-                                        None => return Err(DMError::new(self.last_input_loc, "can't stringify EOF")),
+                                        Some(tok) => return Err(DMError::new(self.last_input_loc, format!("can't stringify non-ident '{}'", tok)).with_errortype("bad_stringify")),
+                                        None => return Err(DMError::new(self.last_input_loc, "can't stringify EOF").with_errortype("bad_stringify")),
                                     }
                                 }
                                 _ => expansion.push_back(token),
@@ -1184,6 +1636,25 @@ impl<'ctx> Iterator for Preprocessor<'ctx> {
     type Item = LocatedToken;
 
     fn next(&mut self) -> Option<LocatedToken> {
+        if self.cache.is_none() {
+            return self.next_token();
+        }
+
+        // Diagnostics registered by later stages don't belong to any file.
+        let errors_start = self.context.errors().len();
+        self.errors_seen = errors_start;
+        let token = self.next_token();
+        self.record_errors();
+        self.parse_cache().add_preprocessor_errors(errors_start..self.errors_seen);
+        if let Some(token) = token.as_ref() {
+            self.record(|| Event::Token(token.location, token.token.clone()));
+        }
+        token
+    }
+}
+
+impl<'ctx> Preprocessor<'ctx> {
+    fn next_token(&mut self) -> Option<LocatedToken> {
         loop {
             if let Some((location, doc)) = self.docs_out.pop_front() {
                 return Some(LocatedToken {
@@ -1199,6 +1670,13 @@ impl<'ctx> Iterator for Preprocessor<'ctx> {
                 });
             }
 
+            if let Some(&Include::Cached { .. }) = self.include_stack.stack.last() {
+                if let Some(token) = self.replay_next() {
+                    return Some(token);
+                }
+                continue;
+            }
+
             if let Some(tok) = self.inner_next() {
                 // linting for https://secure.byond.com/forum/?post=2072419
                 if !tok.token.is_whitespace() && tok.token != Token::Punct(Punctuation::Hash) {
@@ -1217,10 +1695,25 @@ impl<'ctx> Iterator for Preprocessor<'ctx> {
                 }
             } else {
                 while let Some(ifdef) = self.pop_ifdef() {
-                    self.context.register_error(DMError::new(ifdef.location, "unterminated #if/#ifdef"));
+                    self.context.register_error(DMError::new(ifdef.location, "unterminated #if/#ifdef").with_errortype("unmatched_conditional"));
                 }
+                self.report_unused_defines();
                 return None;
             }
         }
     }
 }
+
+/// Hash a macro's substitution, for the define state.
+fn hash_tokens(tokens: &[Token], hasher: &mut DefaultHasher) {
+    tokens.len().hash(hasher);
+    for token in tokens {
+        match *token {
+            Token::Ident(ref name, whitespace) => (0u8, name, whitespace).hash(hasher),
+            Token::Int(value) => (1u8, value).hash(hasher),
+            Token::Float(value) => (2u8, value.to_bits()).hash(hasher),
+            // The rest are told apart by how they display.
+            ref other => (3u8, other.to_string()).hash(hasher),
+        }
+    }
+}
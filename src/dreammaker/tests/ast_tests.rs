@@ -32,3 +32,99 @@ fn check_semicolon_in_proc_parameters() {
         }
     });
 }
+
+#[test]
+fn scope_operator_constants() {
+    with_code("
+/obj
+    var/health = 10
+
+/obj/item
+    name = \"item\"
+    health = 5
+
+/obj/item/proc/use()
+
+/obj/thing
+    var/a = /obj/item::name
+    var/b = src::health
+    var/c = /obj/item::proc/use
+    var/d = ::max_items
+
+var/max_items = 3
+", |context, tree| {
+        context.assert_success();
+        let thing = tree.find("/obj/thing").unwrap();
+        let value = |name: &str| thing.get().vars[name].value.constant.clone().unwrap();
+        assert_eq!(value("a"), constants::Constant::string("item"));
+        assert_eq!(value("b"), constants::Constant::Float(10.));
+        assert_eq!(value("c").to_string(), "/obj/item/proc/use");
+        assert_eq!(value("d"), constants::Constant::Float(3.));
+    });
+}
+
+#[test]
+fn type_and_nameof_constants() {
+    with_code("
+/obj
+    var/health = 10
+
+/obj/proc/use()
+
+/obj/thing
+    var/a = __TYPE__
+    var/b = nameof(/obj::proc/use)
+    var/c = nameof(src::health)
+    var/d = nameof(/obj/thing)
+", |context, tree| {
+        context.assert_success();
+        let thing = tree.find("/obj/thing").unwrap();
+        let value = |name: &str| thing.get().vars[name].value.constant.clone().unwrap();
+        assert_eq!(value("a").to_string(), "/obj/thing");
+        assert_eq!(value("b"), constants::Constant::string("use"));
+        assert_eq!(value("c"), constants::Constant::string("health"));
+        assert_eq!(value("d"), constants::Constant::string("thing"));
+    });
+}
+
+#[test]
+fn recover_from_statement_errors() {
+    with_code("
+/proc/broken()
+    var/a = 1 +
+    if(a b)
+        a = 2
+    else
+        a = 3
+    for(var/i = 1; i <= 10; i++)
+        a += )
+        a += i
+    world << a
+", |context, tree| {
+        let errors = context.errors();
+        let lines: Vec<u32> = errors.iter().map(|e| e.location().line).collect();
+        assert_eq!(lines, vec![2, 3, 8]);
+        for error in errors.iter() {
+            assert_eq!(error.errortype(), Some("syntax_error"));
+        }
+
+        let proc = tree.root().get_proc("broken").unwrap();
+        let block = match proc.get().code {
+            objtree::Code::Present(ref block) => block,
+            ref other => panic!("expected recovered code, got {:?}", other),
+        };
+        assert_eq!(block.len(), 3);
+        match block[0].elem {
+            ast::Statement::Var(ref var) => {
+                assert_eq!(var.name, "a");
+                assert!(var.value.is_none());
+            }
+            ref other => panic!("expected var placeholder, got {:?}", other),
+        }
+        match block[1].elem {
+            ast::Statement::ForLoop { ref block, .. } => assert_eq!(block.len(), 1),
+            ref other => panic!("expected for loop, got {:?}", other),
+        }
+        assert!(matches!(block[2].elem, ast::Statement::Expr(_)));
+    });
+}
@@ -110,3 +110,60 @@ fn rgb_hcy() {
         Constant::string("#000000"),
     );
 }
+
+#[test]
+fn numeric_builtins() {
+    assert_eq!(eval("min(3, 1.5, 2)").unwrap(), Constant::Float(1.5));
+    assert_eq!(eval("max(list(3, 7, 2))").unwrap(), Constant::Int(7));
+    assert_eq!(eval("round(2.7)").unwrap(), Constant::Int(2));
+    assert_eq!(eval("round(2.7, 1)").unwrap(), Constant::Int(3));
+    assert_eq!(eval("round(17, 5)").unwrap(), Constant::Int(15));
+    assert_eq!(eval("floor(-1.5)").unwrap(), Constant::Int(-2));
+    assert_eq!(eval("floor(2 ** 31)").unwrap(), Constant::Float(2147483648.));
+    assert_eq!(eval("round(2147483648)").unwrap(), Constant::Float(2147483648.));
+    assert_eq!(eval("ceil(-(2 ** 31))").unwrap(), Constant::Int(i32::MIN));
+    assert_eq!(eval("ceil(1.25)").unwrap(), Constant::Int(2));
+    assert_eq!(eval("clamp(12, 0, 10)").unwrap(), Constant::Int(10));
+    assert_eq!(eval("abs(-4)").unwrap(), Constant::Int(4));
+    assert_eq!(eval("sqrt(16)").unwrap(), Constant::Int(4));
+    assert_eq!(
+        eval("clamp(1, 2)").unwrap_err().description(),
+        "malformed clamp() call, must have 3 arguments and instead has 2",
+    );
+}
+
+#[test]
+fn text_builtins() {
+    assert_eq!(eval("text2num(\"42\")").unwrap(), Constant::Int(42));
+    assert_eq!(eval("text2num(\" 1.5px\")").unwrap(), Constant::Float(1.5));
+    assert_eq!(eval("text2num(\"ff\", 16)").unwrap(), Constant::Int(255));
+    assert_eq!(eval("text2num(\"nope\")").unwrap(), Constant::Null(None));
+    assert_eq!(eval("num2text(0.25)").unwrap(), Constant::string("0.25"));
+    assert_eq!(eval("num2text(255, 4, 16)").unwrap(), Constant::string("00ff"));
+    assert_eq!(
+        eval("num2text(1, 1e10, 16)").unwrap_err().description(),
+        "non-constant num2text() call, padding to more than 32 digits",
+    );
+    assert!(eval("num2text(1e10, 1, 16)").is_err());
+    assert_eq!(eval("length(\"abc\")").unwrap(), Constant::Int(3));
+    assert_eq!(eval("length(list(1, 2))").unwrap(), Constant::Int(2));
+    assert_eq!(eval("uppertext(\"Abc\") + lowertext(\"DeF\")").unwrap(), Constant::string("ABCdef"));
+    assert_eq!(eval("ascii2text(65)").unwrap(), Constant::string("A"));
+}
+
+#[test]
+fn list_arithmetic() {
+    assert_eq!(eval("list(1, 2) + list(3)").unwrap(), eval("list(1, 2, 3)").unwrap());
+    assert_eq!(eval("list(1, 2, 1) - 1").unwrap(), eval("list(1, 2)").unwrap());
+    assert_eq!(eval("list(1, 2) | list(2, 3)").unwrap(), eval("list(1, 2, 3)").unwrap());
+    assert_eq!(eval("list(1, 2) & list(2, 3)").unwrap(), eval("list(2)").unwrap());
+    assert_eq!(eval("list(1, 2) ^ list(2, 3)").unwrap(), eval("list(1, 3)").unwrap());
+}
+
+#[test]
+fn bitwise_operators() {
+    assert_eq!(eval("0xff & 0x0f").unwrap(), Constant::Int(15));
+    assert_eq!(eval("1 << 4").unwrap(), Constant::Int(16));
+    assert_eq!(eval("1 << 40").unwrap(), Constant::Int(0));
+    assert_eq!(eval("6.5 | 1").unwrap(), Constant::Int(7));
+}
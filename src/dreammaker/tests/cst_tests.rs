@@ -0,0 +1,84 @@
+extern crate dreammaker as dm;
+
+use dm::*;
+use dm::cst::{ConcreteSyntaxTree, TriviaKind};
+use dm::lexer::{Punctuation, Token};
+use dm::objtree::Code;
+use dm::preprocessor::Preprocessor;
+
+fn round_trip(code: &[u8]) {
+    let context = Context::default();
+    let cst = ConcreteSyntaxTree::new(&context, FileId::default(), code);
+    assert_eq!(
+        String::from_utf8_lossy(&cst.to_bytes()),
+        String::from_utf8_lossy(code),
+    );
+    assert_eq!(cst.to_bytes(), code);
+}
+
+#[test]
+fn round_trip_sources() {
+    round_trip(b"");
+    round_trip(b"/obj");
+    round_trip(b"/obj\n\tname = \"thing\"\n");
+    round_trip(b"/proc/f()  \r\n\t\t// comment\r\n\treturn 1 /* inline */ + 2\r\n");
+    round_trip(b"/* outer /* nested */ still */\n/// doc\n/obj/var/x = 1 //! enclosing\n");
+    round_trip(b"#define X(a) \\\n    (a + 1)\n\n\n  \t\n/proc/f()\n\treturn X(2)");
+    round_trip(b"var/s = \"a [1 + \"b [2]\" ] c\"\nvar/r = @{\"raw\"}\nvar/t = {\"\nblock\n\"}\nvar/i = 'icon.dmi'\n");
+    round_trip(b"var/x = 1 $ 2\nvar/y = \xe9\n");
+    round_trip(b"/proc/f()\n\tif(1) // trailing \\\n\t\treturn\n/* unterminated");
+}
+
+#[test]
+fn trivia_attachment() {
+    let context = Context::default();
+    let code = b"/proc/f()\n\tvar/x = 1 // one\n\n\treturn /* value */ x\n";
+    let cst = ConcreteSyntaxTree::new(&context, FileId::default(), &code[..]);
+    let find = |text: &[u8]| cst.tokens().iter().find(|t| cst.text(&t.span) == text).unwrap();
+
+    let one = find(b"1");
+    let kinds: Vec<_> = one.trailing.iter().map(|t| t.kind).collect();
+    assert_eq!(kinds, [TriviaKind::Whitespace, TriviaKind::LineComment]);
+    assert_eq!(cst.text(&one.trailing[1].span), b"// one");
+
+    let var = find(b"var");
+    assert_eq!(var.leading.len(), 1);
+    assert_eq!(var.leading[0].kind, TriviaKind::Indent);
+
+    let x = cst.tokens().iter().rev().find(|t| cst.text(&t.span) == b"x").unwrap();
+    let kinds: Vec<_> = x.leading.iter().map(|t| t.kind).collect();
+    assert_eq!(kinds, [TriviaKind::Whitespace, TriviaKind::BlockComment, TriviaKind::Whitespace]);
+
+    // No indentation tokens remain, and newlines are kept as tokens.
+    assert!(cst.tokens().iter().all(|t| !matches!(t.token, Token::Punct(Punctuation::Space) | Token::Punct(Punctuation::Tab))));
+    assert_eq!(cst.tokens().iter().filter(|t| t.token == Token::Punct(Punctuation::Newline)).count(), 5);
+}
+
+#[test]
+fn ast_locations_map_to_tokens() {
+    let code = "/proc/test()\n\tvar/x = 1 // one\n\treturn x + 2\n";
+    let context = Context::default();
+    let path = std::path::PathBuf::from("test.dm");
+    let pp = Preprocessor::from_buffer(&context, path.clone(), code);
+    let indents = indents::IndentProcessor::new(&context, pp);
+    let mut parser = parser::Parser::new(&context, indents);
+    parser.enable_procs();
+    let tree = parser.parse_object_tree();
+    context.assert_success();
+
+    let file = context.get_file(&path).unwrap();
+    let cst = ConcreteSyntaxTree::new(&context, file, code.as_bytes());
+    let proc = tree.root().get_proc("test").unwrap();
+    let block = match proc.get().code {
+        Code::Present(ref block) => block,
+        _ => panic!("proc has no code"),
+    };
+
+    let statement = &block[1];
+    let index = cst.index_of(statement.location).unwrap();
+    assert_eq!(cst.text(&cst.tokens()[index].span), b"return");
+
+    let tokens = cst.tokens_between(block[0].location, statement.location);
+    let text: Vec<_> = tokens.iter().map(|t| String::from_utf8_lossy(cst.text(&t.span)).into_owned()).collect();
+    assert_eq!(text, ["var", "/", "x", "=", "1", "\n"]);
+}
@@ -0,0 +1,372 @@
+extern crate dreammaker as dm;
+
+use dm::*;
+use dm::ast::*;
+use dm::lexer::Lexer;
+use dm::objtree::Code;
+use dm::preprocessor::Preprocessor;
+
+fn parse_expr(code: &str) -> Expression {
+    let context = Context::default();
+    let lexer = Lexer::new(&context, Default::default(), code.as_bytes());
+    let result = parser::parse_expression(&context, Default::default(), lexer)
+        .unwrap_or_else(|e| panic!("failed to parse {:?}: {}", code, e));
+    if let Some(error) = context.errors().iter().next() {
+        panic!("error parsing {:?}: {}", code, error);
+    }
+    result
+}
+
+fn parse_proc(body: &str) -> Block {
+    let code = format!("/proc/test()\n{}", body);
+    let context = Context::default();
+    let pp = Preprocessor::from_buffer(&context, "test.dm".into(), code.as_str());
+    let indents = indents::IndentProcessor::new(&context, pp);
+    let mut parser = parser::Parser::new(&context, indents);
+    parser.enable_procs();
+    let tree = parser.parse_object_tree_without_builtins();
+    if let Some(error) = context.errors().iter().next() {
+        panic!("error parsing:\n{}\n{}", code, error);
+    }
+    let proc = tree.root().get_proc("test").expect("proc not found");
+    match proc.get().code {
+        Code::Present(ref block) => block.clone(),
+        _ => panic!("proc has no code:\n{}", code),
+    }
+}
+
+fn round_trip_expr(code: &str) {
+    let expr = parse_expr(code);
+    let printed = expr.to_string();
+    assert_eq!(parse_expr(&printed), expr, "{:?} printed as {:?}", code, printed);
+}
+
+fn round_trip_proc(body: &str) {
+    let block = parse_proc(body);
+    let printed = FormatBlock(&block, 1).to_string();
+    assert_eq!(parse_proc(&printed), block, "printed as:\n{}", printed);
+}
+
+#[test]
+fn expressions() {
+    round_trip_expr("1 + 2 * 3 - 4 / 5 % 6 ** 7");
+    round_trip_expr("(1 + 2) * (3 - (4 - 5)) ** 2 ** 3");
+    round_trip_expr("a = b += c || d && e | f ^ g & h == i != j <> k ~= l ~! m");
+    round_trip_expr("a << 1 >> 2 < 3 <= 4 > 5 >= 6");
+    round_trip_expr("a ? b : c ? d : e");
+    round_trip_expr("a ? b ? c : d : e");
+    round_trip_expr("(a ? b : c) ? d:e : f:g");
+    round_trip_expr("a ? (f():x) : y");
+    round_trip_expr("x in list(1, 2) && y in 1 to 10");
+    round_trip_expr("-a - -b - --c + ++d++ - !~e--");
+    round_trip_expr("a.b:c?.d?:e::f[1]?[2].g(1, 2):h()");
+    round_trip_expr("/obj/item{name = \"thing\"; desc = 1 + 2}.name");
+    round_trip_expr("/obj/item::name + /datum::proc/foo + src::health");
+    round_trip_expr("::global_var + ::global_proc(1) + ..() + .(1) + .");
+    round_trip_expr("new /obj/item{name = \"x\"}(src) || new a.b:c(1) || new() || new");
+    round_trip_expr("input(usr, \"Pick\") as null|num in list(1, 2)");
+    round_trip_expr("locate(/obj) in world || locate(1, 2, 3) || as(num|text) || as()");
+    round_trip_expr("pick(10; \"a\", 20; \"b\", \"c\") + call(a, \"proc\")(1, 2)");
+    round_trip_expr("list(\"a\" = 1, b = list(), 'icon.dmi') + null");
+    round_trip_expr("1.5e20 + 0.25 - 1e-7 + 100000000 + 0 + 1.#INF");
+}
+
+#[test]
+fn strings() {
+    round_trip_expr(r#""plain \"escaped\" \[not interpolated\] \\""#);
+    round_trip_expr(r#"{"block "quoted" string"}"#);
+    round_trip_expr("{\"multiple\nlines\"}");
+    round_trip_expr(r#"@"raw [brackets]" + @'raw "quotes"' + @{"raw "quotes""} + @(END)trailing\END"#);
+    round_trip_expr(r#""[a] and [b ? "x" : "y"][]\[" + {"[a]"quoted"[1]"}"#);
+
+    assert_eq!(parse_expr(r#"@"a[b]""#).to_string(), r#"@"a[b]""#);
+    assert_eq!(parse_expr(r#"@"a\""#).to_string(), r#"@"a\""#);
+    assert_eq!(parse_expr(r#"{"a"b"}"#).to_string(), r#"{"a"b"}"#);
+}
+
+#[test]
+fn parenthesize_synthesized() {
+    let ident = |name: &str| Box::new(Expression::from(Term::Ident(name.to_owned())));
+    let binary = |op, lhs, rhs| Box::new(Expression::BinaryOp { op, lhs, rhs });
+
+    let sum = binary(BinaryOp::Add, ident("a"), ident("b"));
+    assert_eq!(binary(BinaryOp::Mul, sum.clone(), ident("c")).to_string(), "(a + b) * c");
+    assert_eq!(binary(BinaryOp::Sub, ident("c"), sum.clone()).to_string(), "c - (a + b)");
+    assert_eq!(binary(BinaryOp::Sub, sum.clone(), ident("c")).to_string(), "a + b - c");
+
+    let assign = Expression::AssignOp {
+        op: AssignOp::Assign,
+        lhs: ident("a"),
+        rhs: Box::new(Expression::TernaryOp { cond: sum, if_: ident("b"), else_: ident("c") }),
+    };
+    assert_eq!(assign.to_string(), "a = a + b ? b : c");
+    let negated = Expression::Base {
+        unary: vec![Spanned::new(Default::default(), UnaryOp::Neg), Spanned::new(Default::default(), UnaryOp::Neg)],
+        term: Box::new(Spanned::new(Default::default(), Term::Expr(Box::new(assign)))),
+        follow: vec![],
+    };
+    assert_eq!(negated.to_string(), "- -(a = a + b ? b : c)");
+}
+
+#[test]
+fn statements() {
+    round_trip_proc(r#"
+	var/a = 1, list/b = list(1, "x" = 2)
+	var/static/c
+	var/L[10]
+	a += b ? c : d:e
+	if(a)
+		return
+	else if(b in c)
+		a = -(b + 1) ** 2
+	else
+		world << "[a] and [b]"
+	if(a)
+	else
+		return a
+	while(a--)
+		continue
+	do
+		a++
+	while(a < 10)
+	for()
+		break
+	for(var/i = 0; i < 10; i++)
+		spawn(1)
+			del a
+	for(;;)
+	for(var/obj/O as obj in world)
+		O.name = "[O]"
+	for(var/x in world.contents)
+	for(x in a ? b : c)
+	for(x in 1 to 10 step 2)
+		spawn()
+	for(var/y = 1 to 5)
+	switch(a)
+		if(1, 2 to 3)
+			a = 1
+		if(4)
+		else
+			a = 2
+	try
+		throw EXCEPTION("x")
+	catch(var/exception/e)
+		CRASH(e.name)
+	try
+	catch
+		CRASH()
+	set src in view(1)
+	set name = "Test"
+	a = {"multi
+line "quoted" "}
+	return ..()
+"#);
+
+    round_trip_proc(r#"
+	var/a, b = 2, static/c = list()
+	outer:
+		for(var/i in 1 to 3)
+			for(var/j in b)
+				if(j)
+					continue outer
+				break outer
+	goto outer
+	done:
+"#);
+}
+
+// ----------------------------------------------------------------------------
+// Property test: random expressions built without regard for precedence print
+// as code which parses back to the same tree, once the parentheses added by
+// the printer are removed.
+
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self, n: usize) -> usize {
+        // xorshift64*
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        (self.0.wrapping_mul(0x2545_F491_4F6C_DD1D) >> 33) as usize % n
+    }
+
+    fn pick<T: Clone>(&mut self, items: &[T]) -> T {
+        items[self.next(items.len())].clone()
+    }
+}
+
+const BINARY_OPS: &[BinaryOp] = &[
+    BinaryOp::Add, BinaryOp::Sub, BinaryOp::Mul, BinaryOp::Div, BinaryOp::Pow, BinaryOp::Mod,
+    BinaryOp::Eq, BinaryOp::NotEq, BinaryOp::Less, BinaryOp::Greater, BinaryOp::LessEq,
+    BinaryOp::GreaterEq, BinaryOp::Equiv, BinaryOp::NotEquiv, BinaryOp::BitAnd, BinaryOp::BitXor,
+    BinaryOp::BitOr, BinaryOp::LShift, BinaryOp::RShift, BinaryOp::And, BinaryOp::Or, BinaryOp::In,
+];
+
+const ASSIGN_OPS: &[AssignOp] = &[
+    AssignOp::Assign, AssignOp::AddAssign, AssignOp::SubAssign, AssignOp::MulAssign,
+    AssignOp::DivAssign, AssignOp::ModAssign, AssignOp::AssignInto, AssignOp::BitAndAssign,
+    AssignOp::AndAssign, AssignOp::BitOrAssign, AssignOp::OrAssign, AssignOp::BitXorAssign,
+    AssignOp::LShiftAssign, AssignOp::RShiftAssign,
+];
+
+const STRINGS: &[&str] = &["", "text", "a\\\"b", "a\"b", "x[y]", "line\nbreak", "end\\", "q\"}"];
+
+fn random_args(rng: &mut Rng, depth: u32) -> Vec<Expression> {
+    (0..rng.next(3)).map(|_| random_expr(rng, depth)).collect()
+}
+
+fn random_term(rng: &mut Rng, depth: u32) -> Term {
+    let name = rng.pick(&["a", "b", "src"]).to_owned();
+    match rng.next(if depth == 0 { 6 } else { 12 }) {
+        0 => Term::Int(rng.next(1000) as i32),
+        1 => Term::Float(rng.pick(&[0.5, 1.25, 1e20, 3e-7])),
+        2 => Term::String(rng.pick(STRINGS).to_owned()),
+        3 => Term::GlobalIdent(name),
+        4 => Term::Null,
+        5 => Term::Ident(name),
+        6 => Term::Call(name, random_args(rng, depth - 1)),
+        7 => Term::List(random_args(rng, depth - 1)),
+        8 => Term::Expr(Box::new(random_expr(rng, depth - 1))),
+        9 => Term::InterpString("x".to_owned(), vec![
+            (Some(random_expr(rng, depth - 1)), " and ".to_owned()),
+            (None, rng.pick(&["", "\"quoted\" end"]).to_owned()),
+        ]),
+        10 => {
+            let mut prefab = Prefab::from(vec![(PathOp::Slash, "obj".to_owned()), (PathOp::Slash, name)]);
+            if rng.next(2) == 0 {
+                prefab.vars.insert("name".to_owned(), random_expr(rng, depth - 1));
+            }
+            Term::Prefab(Box::new(prefab))
+        }
+        _ => Term::New {
+            type_: NewType::MiniExpr { ident: name, fields: vec![] },
+            args: Some(random_args(rng, depth - 1)),
+        },
+    }
+}
+
+fn random_follow(rng: &mut Rng, depth: u32) -> Follow {
+    let kind = rng.pick(&[
+        PropertyAccessKind::Dot,
+        PropertyAccessKind::Colon,
+        PropertyAccessKind::SafeDot,
+        PropertyAccessKind::SafeColon,
+    ]);
+    match rng.next(if depth == 0 { 2 } else { 4 }) {
+        0 => Follow::Field(kind, "x".to_owned()),
+        1 => Follow::StaticField("y".to_owned()),
+        2 => Follow::Call(kind, "f".to_owned(), random_args(rng, depth - 1)),
+        _ => Follow::Index(rng.pick(&[ListAccessKind::Normal, ListAccessKind::Safe]), Box::new(random_expr(rng, depth - 1))),
+    }
+}
+
+fn random_expr(rng: &mut Rng, depth: u32) -> Expression {
+    let choice = rng.next(if depth == 0 { 1 } else { 7 });
+    match choice {
+        0..=2 => {
+            let mut unary = Vec::new();
+            for _ in 0..rng.next(3) {
+                unary.push(Spanned::new(Default::default(), rng.pick(&[UnaryOp::Neg, UnaryOp::Not, UnaryOp::BitNot, UnaryOp::PreIncr, UnaryOp::PreDecr])));
+            }
+            if rng.next(3) == 0 {
+                unary.push(Spanned::new(Default::default(), rng.pick(&[UnaryOp::PostIncr, UnaryOp::PostDecr])));
+            }
+            let term = random_term(rng, depth);
+            let follow = (0..rng.next(3))
+                .map(|_| Spanned::new(Default::default(), random_follow(rng, depth)))
+                .collect();
+            Expression::Base {
+                unary,
+                term: Box::new(Spanned::new(Default::default(), term)),
+                follow,
+            }
+        }
+        3 | 4 => {
+            let op = rng.pick(BINARY_OPS);
+            let lhs = Box::new(random_expr(rng, depth - 1));
+            let mut rhs = Box::new(random_expr(rng, depth - 1));
+            if op == BinaryOp::In && rng.next(2) == 0 {
+                rhs = Box::new(Expression::BinaryOp {
+                    op: BinaryOp::To,
+                    lhs: rhs,
+                    rhs: Box::new(random_expr(rng, depth - 1)),
+                });
+            }
+            Expression::BinaryOp { op, lhs, rhs }
+        }
+        5 => Expression::AssignOp {
+            op: rng.pick(ASSIGN_OPS),
+            lhs: Box::new(random_expr(rng, depth - 1)),
+            rhs: Box::new(random_expr(rng, depth - 1)),
+        },
+        _ => Expression::TernaryOp {
+            cond: Box::new(random_expr(rng, depth - 1)),
+            if_: Box::new(random_expr(rng, depth - 1)),
+            else_: Box::new(random_expr(rng, depth - 1)),
+        },
+    }
+}
+
+/// Remove parentheses around a whole expression or a lone term.
+fn strip_parens(expr: Expression) -> Expression {
+    let strip_all = |args: Vec<Expression>| args.into_iter().map(strip_parens).collect::<Vec<_>>();
+    match expr {
+        Expression::Base { unary, term, follow } => {
+            let term = Spanned::new(term.location, match term.elem {
+                Term::Expr(inner) if unary.is_empty() && follow.is_empty() => return strip_parens(*inner),
+                Term::Expr(inner) => match strip_parens(*inner) {
+                    Expression::Base { unary: inner_unary, term, follow: inner_follow }
+                        if inner_unary.is_empty() && inner_follow.is_empty() => term.elem,
+                    inner => Term::Expr(Box::new(inner)),
+                },
+                Term::Call(name, args) => Term::Call(name, strip_all(args)),
+                Term::List(args) => Term::List(strip_all(args)),
+                Term::New { type_, args } => Term::New { type_, args: args.map(strip_all) },
+                Term::InterpString(begin, parts) => Term::InterpString(
+                    begin,
+                    parts.into_iter().map(|(expr, part)| (expr.map(strip_parens), part)).collect(),
+                ),
+                Term::Prefab(mut prefab) => {
+                    prefab.vars = prefab.vars.into_iter().map(|(k, v)| (k, strip_parens(v))).collect();
+                    Term::Prefab(prefab)
+                }
+                other => other,
+            });
+            let follow = follow.into_iter().map(|each| Spanned::new(each.location, match each.elem {
+                Follow::Call(kind, name, args) => Follow::Call(kind, name, strip_all(args)),
+                Follow::Index(kind, expr) => Follow::Index(kind, Box::new(strip_parens(*expr))),
+                other => other,
+            })).collect();
+            Expression::Base { unary, term: Box::new(term), follow }
+        }
+        Expression::BinaryOp { op, lhs, rhs } => Expression::BinaryOp {
+            op,
+            lhs: Box::new(strip_parens(*lhs)),
+            rhs: Box::new(strip_parens(*rhs)),
+        },
+        Expression::AssignOp { op, lhs, rhs } => Expression::AssignOp {
+            op,
+            lhs: Box::new(strip_parens(*lhs)),
+            rhs: Box::new(strip_parens(*rhs)),
+        },
+        Expression::TernaryOp { cond, if_, else_ } => Expression::TernaryOp {
+            cond: Box::new(strip_parens(*cond)),
+            if_: Box::new(strip_parens(*if_)),
+            else_: Box::new(strip_parens(*else_)),
+        },
+    }
+}
+
+#[test]
+fn random_expressions_round_trip() {
+    let mut rng = Rng(0x5EED_1234_ABCD_0001);
+    for _ in 0..5000 {
+        let expr = random_expr(&mut rng, 4);
+        let printed = expr.to_string();
+        let parsed = parse_expr(&printed);
+        assert_eq!(strip_parens(parsed.clone()), strip_parens(expr), "printed as {:?}", printed);
+        // Parsed trees keep their parentheses and print unchanged.
+        assert_eq!(parsed.to_string(), printed);
+    }
+}
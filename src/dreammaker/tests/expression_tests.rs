@@ -114,3 +114,41 @@ fn bitop_precedence() {
         }
     );
 }
+
+#[test]
+fn scope_operator() {
+    // /obj/item::name
+    assert_eq!(
+        parse_expr("/obj/item::name"),
+        Expression::Base {
+            unary: vec![],
+            term: Box::new(Spanned::new(Default::default(), Term::Prefab(Box::new(Prefab::from(vec![
+                (PathOp::Slash, "obj".to_owned()),
+                (PathOp::Slash, "item".to_owned()),
+            ]))))),
+            follow: vec![Spanned::new(Default::default(), Follow::StaticField("name".to_owned()))],
+        }
+    );
+
+    let follows = |expr: Expression| match expr {
+        Expression::Base { follow, .. } => follow.into_iter().map(|each| each.elem).collect::<Vec<_>>(),
+        other => panic!("not a base expression: {:?}", other),
+    };
+    assert_eq!(
+        follows(parse_expr("parent_type::proc_name(1)")),
+        vec![Follow::Call(PropertyAccessKind::Scope, "proc_name".to_owned(), vec![Expression::from(Term::Int(1))])],
+    );
+    assert_eq!(
+        follows(parse_expr("/datum::proc/foo")),
+        vec![Follow::ProcReference(ProcDeclKind::Proc, "foo".to_owned())],
+    );
+    assert_eq!(
+        follows(parse_expr("src::health.len")),
+        vec![
+            Follow::StaticField("health".to_owned()),
+            Follow::Field(PropertyAccessKind::Dot, "len".to_owned()),
+        ],
+    );
+    assert_eq!(parse_expr("::x"), Expression::from(Term::GlobalIdent("x".to_owned())));
+    assert_eq!(parse_expr("::x()"), Expression::from(Term::GlobalCall("x".to_owned(), vec![])));
+}
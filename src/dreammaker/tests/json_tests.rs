@@ -0,0 +1,119 @@
+extern crate dreammaker as dm;
+extern crate serde_json;
+
+use dm::json::*;
+
+fn dump(code: &'static str, filter: &Filter) -> Tree {
+    let context = dm::Context::default();
+    let pp = dm::preprocessor::Preprocessor::from_buffer(&context, "test.dm".into(), code.trim());
+    let indents = dm::indents::IndentProcessor::new(&context, pp);
+    let tree = dm::parser::Parser::new(&context, indents).parse_object_tree();
+    context.assert_success();
+    Tree::new(&context, &tree, filter)
+}
+
+const CODE: &str = r#"
+/obj/item
+    /// How heavy it is.
+    var/weight = 2 ** 3
+    var/tmp/list/colors = list("red" = 1, "blue")
+    var/obj/item/spare = null
+    var/inf = 1.#INF
+    name = "item"
+
+/obj/item/sword
+    weight = 10
+    desc = {"a "sharp" thing"}
+
+/obj/item/sword/proc/swing(mob/target, force = 5, mode as text in list("a", "b"))
+    return
+
+/obj/item/sword/verb/inspect()
+    set name = "Inspect"
+
+/mob/Login()
+    ..()
+"#;
+
+#[test]
+fn serialize_types() {
+    let tree = dump(CODE, &Filter::default());
+    assert_eq!(tree.version, SCHEMA_VERSION);
+
+    let item = tree.find("/obj/item").unwrap();
+    assert_eq!(item.parent.as_deref(), Some("/obj"));
+    let weight = &item.vars["weight"];
+    assert_eq!(weight.expression.as_deref(), Some("2 ** 3"));
+    assert_eq!(weight.value, Some(Value::Int { value: 8 }));
+    assert_eq!(weight.docs.as_deref(), Some("How heavy it is."));
+    assert_eq!(weight.location.line, 3);
+
+    let colors = item.vars["colors"].declaration.as_ref().unwrap();
+    assert_eq!(colors.type_path.as_deref(), Some("/list"));
+    assert_eq!(colors.flags, vec!["tmp"]);
+
+    let spare = &item.vars["spare"];
+    assert_eq!(spare.declaration.as_ref().unwrap().type_path.as_deref(), Some("/obj/item"));
+    assert_eq!(spare.value, Some(Value::Null { type_path: Some("/obj/item".to_owned()) }));
+
+    // overrides of builtin vars carry no declaration
+    assert!(item.vars["name"].declaration.is_none());
+    assert!(!item.vars.contains_key("desc"));
+
+    let sword = tree.find("/obj/item/sword").unwrap();
+    assert_eq!(sword.vars["weight"].value, Some(Value::Int { value: 10 }));
+
+    let swing = &sword.procs["swing"];
+    assert_eq!(swing.declaration.as_ref().unwrap().kind, "proc");
+    let params = &swing.definitions[0].parameters;
+    assert_eq!(params.len(), 3);
+    assert_eq!(params[0].type_path.as_deref(), Some("/mob"));
+    assert_eq!(params[1].default.as_deref(), Some("5"));
+    assert_eq!(params[2].input_type.as_deref(), Some("text"));
+    assert_eq!(params[2].in_list.as_deref(), Some(r#"list("a", "b")"#));
+    assert_eq!(sword.procs["inspect"].declaration.as_ref().unwrap().kind, "verb");
+
+    // the builtin declaration is kept alongside the user's override
+    let login = &tree.find("/mob").unwrap().procs["Login"];
+    assert_eq!(login.definitions.len(), 1);
+    assert_eq!(login.declaration.as_ref().unwrap().location.file, "(builtins)");
+}
+
+#[test]
+fn filter_subtrees() {
+    let filter = Filter {
+        paths: vec!["/obj/item/sword".to_owned(), "/mob/".to_owned()],
+        builtins: false,
+    };
+    let tree = dump(CODE, &filter);
+    let paths: Vec<&str> = tree.types.iter().map(|ty| ty.path.as_str()).collect();
+    assert_eq!(paths, vec!["/mob", "/obj/item/sword"]);
+
+    let builtins = dump(CODE, &Filter {
+        paths: vec!["/obj".to_owned()],
+        builtins: true,
+    });
+    let obj = builtins.find("/obj").unwrap();
+    assert!(obj.vars.contains_key("layer"));
+    assert!(builtins.find("/obj/item").unwrap().vars.contains_key("weight"));
+    assert!(builtins.find("/mob").is_none());
+}
+
+#[test]
+fn json_round_trip() {
+    let tree = dump(CODE, &Filter::default());
+    let json = serde_json::to_string_pretty(&tree).unwrap();
+    let parsed: Tree = serde_json::from_str(&json).unwrap();
+    assert_eq!(parsed, tree);
+
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    let item = value["types"].as_array().unwrap().iter().find(|ty| ty["path"] == "/obj/item").unwrap();
+    assert_eq!(item["vars"]["inf"]["value"], serde_json::json!({ "kind": "float", "value": "inf" }));
+    assert_eq!(item["vars"]["colors"]["value"], serde_json::json!({
+        "kind": "list",
+        "items": [
+            { "key": { "kind": "string", "value": "red" }, "value": { "kind": "int", "value": 1 } },
+            { "key": { "kind": "string", "value": "blue" } },
+        ],
+    }));
+}
@@ -52,6 +52,18 @@ fn nested_interpolation() {
             Punct(Newline),
         ]
     );
+    assert_eq!(
+        lex(r#""A[B?[1]]E""#),
+        vec![
+            InterpStringBegin("A".into()),
+            Ident("B".into(), false),
+            Punct(SafeLBracket),
+            Int(1),
+            Punct(RBracket),
+            InterpStringEnd("E".into()),
+            Punct(Newline),
+        ]
+    );
 }
 
 #[test]
@@ -0,0 +1,239 @@
+extern crate dreammaker as dm;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use dm::indents::IndentProcessor;
+use dm::parser::Parser;
+use dm::preprocess_cache::PreprocessCache;
+use dm::preprocessor::Preprocessor;
+
+const DME: &str = r#"
+#define SHARED 1
+#include "a.dm"
+#include "b.dm"
+"#;
+
+const A: &str = r#"
+#include "c.dm"
+/obj/a
+    var/value = A_VALUE
+"#;
+
+const B: &str = r#"
+/obj/b
+    var/value = DOUBLE(A_VALUE)
+
+/obj/b/proc/broken()
+    value =
+
+/obj/c/proc/hello()
+    return 2
+#ifdef SHARED
+/obj/shared
+#endif
+#warn "from b"
+"#;
+
+const C: &str = r#"
+/// A documented macro.
+#define A_VALUE (SHARED + 2)
+#define DOUBLE(x) ((x) * 2)
+//! The c file.
+#ifdef SHARED
+/obj/c
+#else
+/obj/not_c
+#endif
+
+/// Says hello.
+/obj/c/proc/hello()
+    return "hello"
+#undef SHARED
+#undef NEVER_DEFINED
+"#;
+
+fn write_environment(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("dreammaker-preprocess-cache-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("test.dme"), DME).unwrap();
+    fs::write(dir.join("a.dm"), A).unwrap();
+    fs::write(dir.join("b.dm"), B).unwrap();
+    fs::write(dir.join("c.dm"), C).unwrap();
+    dir
+}
+
+/// Preprocess an environment, returning its tokens and diagnostics.
+fn preprocess(dme: &Path, mut cache: Option<&mut PreprocessCache>) -> (Vec<String>, Vec<String>) {
+    let context = dm::Context::default();
+    let mut pp = Preprocessor::new(&context, dme.to_owned()).unwrap();
+    if let Some(cache) = cache.as_mut() {
+        pp.use_cache(std::mem::take(*cache));
+    }
+    let tokens = pp.by_ref()
+        .map(|token| format!(
+            "{}:{}:{} {:?}",
+            context.file_path(token.location.file).display(),
+            token.location.line,
+            token.location.column,
+            token.token,
+        ))
+        .collect();
+    if let Some(cache) = cache {
+        *cache = pp.take_cache().unwrap();
+    }
+    let errors = context.errors().iter()
+        .map(|error| format!("{}:{}", context.file_path(error.location().file).display(), error))
+        .collect();
+    (tokens, errors)
+}
+
+/// Parse an environment, returning its types, module docs, and sorted
+/// diagnostics.
+fn parse(dme: &Path, mut cache: Option<&mut PreprocessCache>) -> (Vec<String>, Vec<String>, Vec<String>) {
+    let context = dm::Context::default();
+    let mut pp = Preprocessor::new(&context, dme.to_owned()).unwrap();
+    if let Some(cache) = cache.as_mut() {
+        pp.use_cache(std::mem::take(*cache));
+    }
+    let (tree, module_docs) = {
+        let parse_cache = pp.parse_cache();
+        let mut parser = Parser::new(&context, IndentProcessor::new(&context, &mut pp));
+        parser.enable_procs();
+        parser.use_cache(parse_cache);
+        parser.parse_with_module_docs()
+    };
+    if let Some(cache) = cache {
+        *cache = pp.take_cache().unwrap();
+    }
+    let types = tree.iter_types()
+        .map(|ty| format!("{:?}", *ty))
+        .collect();
+    let module_docs = module_docs.iter()
+        .map(|(file, docs)| format!("{}: {:?}", context.file_path(*file).display(), docs))
+        .collect();
+    let mut errors: Vec<String> = context.errors().iter()
+        .map(|error| format!("{}:{}", context.file_path(error.location().file).display(), error))
+        .collect();
+    errors.sort();
+    (types, module_docs, errors)
+}
+
+#[test]
+fn cached_files_are_replayed() {
+    let dir = write_environment("replay");
+    let dme = dir.join("test.dme");
+    let cache_file = dir.join(".preprocess-cache");
+    let fresh = preprocess(&dme, None);
+    assert!(!fresh.1.is_empty());
+
+    let mut cache = PreprocessCache::read(&cache_file);
+    assert_eq!(preprocess(&dme, Some(&mut cache)), fresh);
+    assert_eq!(cache.hits(), 0);
+    assert!(cache.is_changed());
+    cache.write(&cache_file).unwrap();
+
+    // a.dm includes another file, so only b.dm and c.dm are cached
+    let mut cache = PreprocessCache::read(&cache_file);
+    assert_eq!(preprocess(&dme, Some(&mut cache)), fresh);
+    assert_eq!(cache.hits(), 2);
+    assert!(!cache.is_changed());
+    cache.write(&cache_file).unwrap();
+
+    let mut cache = PreprocessCache::read(&cache_file);
+    assert_eq!(preprocess(&dme, Some(&mut cache)), fresh);
+    assert_eq!(cache.hits(), 2);
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn edited_files_are_reread() {
+    let dir = write_environment("edit");
+    let dme = dir.join("test.dme");
+    let cache_file = dir.join(".preprocess-cache");
+
+    let mut cache = PreprocessCache::read(&cache_file);
+    preprocess(&dme, Some(&mut cache));
+    cache.write(&cache_file).unwrap();
+
+    fs::write(dir.join("b.dm"), B.replace("DOUBLE(A_VALUE)", "A_VALUE")).unwrap();
+    let fresh = preprocess(&dme, None);
+    let mut cache = PreprocessCache::read(&cache_file);
+    assert_eq!(preprocess(&dme, Some(&mut cache)), fresh);
+    assert_eq!(cache.hits(), 1);
+    assert!(cache.is_changed());
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn changed_defines_invalidate_files() {
+    let dir = write_environment("defines");
+    let dme = dir.join("test.dme");
+    let cache_file = dir.join(".preprocess-cache");
+
+    let mut cache = PreprocessCache::read(&cache_file);
+    preprocess(&dme, Some(&mut cache));
+    cache.write(&cache_file).unwrap();
+
+    // every file's incoming define state depends on the .dme
+    fs::write(&dme, DME.replace("SHARED 1", "SHARED 2")).unwrap();
+    let fresh = preprocess(&dme, None);
+    let mut cache = PreprocessCache::read(&cache_file);
+    assert_eq!(preprocess(&dme, Some(&mut cache)), fresh);
+    assert_eq!(cache.hits(), 0);
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn parsed_files_are_not_parsed_again() {
+    let dir = write_environment("parse");
+    let dme = dir.join("test.dme");
+    let cache_file = dir.join(".preprocess-cache");
+    let fresh = parse(&dme, None);
+    assert!(!fresh.1.is_empty());
+    assert!(fresh.2.iter().any(|error| error.contains("duplicate definition")));
+
+    let mut cache = PreprocessCache::read(&cache_file);
+    assert_eq!(parse(&dme, Some(&mut cache)), fresh);
+    assert_eq!(cache.parse_hits(), 0);
+    cache.write(&cache_file).unwrap();
+
+    // test.dme and a.dm include other files, so they are parsed every run,
+    // but c.dm is not parsed again even though a.dm includes it
+    let mut cache = PreprocessCache::read(&cache_file);
+    assert_eq!(parse(&dme, Some(&mut cache)), fresh);
+    assert_eq!(cache.hits(), 2);
+    assert_eq!(cache.parse_hits(), 2);
+    assert!(!cache.is_changed());
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn preprocessed_files_gain_parse_results() {
+    let dir = write_environment("gain");
+    let dme = dir.join("test.dme");
+    let cache_file = dir.join(".preprocess-cache");
+
+    let mut cache = PreprocessCache::read(&cache_file);
+    preprocess(&dme, Some(&mut cache));
+    cache.write(&cache_file).unwrap();
+
+    let fresh = parse(&dme, None);
+    let mut cache = PreprocessCache::read(&cache_file);
+    assert_eq!(parse(&dme, Some(&mut cache)), fresh);
+    assert_eq!(cache.hits(), 2);
+    assert_eq!(cache.parse_hits(), 0);
+    assert!(cache.is_changed());
+    cache.write(&cache_file).unwrap();
+
+    let mut cache = PreprocessCache::read(&cache_file);
+    assert_eq!(parse(&dme, Some(&mut cache)), fresh);
+    assert_eq!(cache.parse_hits(), 2);
+
+    let _ = fs::remove_dir_all(&dir);
+}
@@ -362,12 +362,23 @@ impl<'o> WalkProc<'o> {
                 } else {
                     None
                 };
-                let mut ty = self.visit_term(term.location, &term.elem, base_type_hint);
+                let mut ty = match (&term.elem, follow.first().map(|each| &each.elem)) {
+                    // `/type::x` looks on the type named by the path.
+                    (Term::Prefab(prefab), Some(Follow::StaticField(_)))
+                    | (Term::Prefab(prefab), Some(Follow::ProcReference(..)))
+                    | (Term::Prefab(prefab), Some(Follow::Call(PropertyAccessKind::Scope, ..))) => {
+                        match self.visit_prefab(term.location, prefab) {
+                            Some(ty) => StaticType::Type(ty),
+                            None => StaticType::None,
+                        }
+                    },
+                    _ => self.visit_term(term.location, &term.elem, base_type_hint),
+                };
                 for each in follow.iter() {
                     ty = self.visit_follow(each.location, ty, &each.elem);
                 }
                 for each in unary.iter().rev() {
-                    ty = self.visit_unary(ty, *each);
+                    ty = self.visit_unary(ty, each.elem);
                 }
                 ty
             },
@@ -433,6 +444,14 @@ impl<'o> WalkProc<'o> {
                     StaticType::None
                 }
             },
+            Term::GlobalIdent(name) => {
+                if let Some(decl) = self.objtree.root().get_var_declaration(name) {
+                    self.tab.use_symbol(decl.id, location);
+                    self.static_type(location, &decl.var_type.type_path)
+                } else {
+                    StaticType::None
+                }
+            },
             Term::Call(unscoped_name, args) => {
                 let src = self.ty;
                 if let Some(proc) = self.ty.get_proc(unscoped_name) {
@@ -441,6 +460,15 @@ impl<'o> WalkProc<'o> {
                     StaticType::None
                 }
             },
+            Term::GlobalCall(name, args) => {
+                let root = self.objtree.root();
+                if let Some(proc) = root.get_proc(name) {
+                    self.visit_call(location, root, proc, args, false)
+                } else {
+                    self.visit_arguments(location, args);
+                    StaticType::None
+                }
+            },
             Term::SelfCall(args) => {
                 if let Some(proc) = self.proc {
                     let src = self.ty;
@@ -573,7 +601,8 @@ impl<'o> WalkProc<'o> {
                     _ => StaticType::None,
                 }
             },
-            Follow::Field(_, name) => {
+            Follow::Field(_, name) |
+            Follow::StaticField(name) => {
                 if let Some(ty) = lhs.basic_type() {
                     if let Some(decl) = ty.get_var_declaration(name) {
                         self.tab.use_symbol(decl.id, location);
@@ -598,6 +627,14 @@ impl<'o> WalkProc<'o> {
                     StaticType::None
                 }
             },
+            Follow::ProcReference(_, name) => {
+                if let Some(ty) = lhs.basic_type() {
+                    if let Some(decl) = ty.get_proc_declaration(name) {
+                        self.tab.use_symbol(decl.id, location);
+                    }
+                }
+                StaticType::None
+            },
         }
     }
 
@@ -345,6 +345,7 @@ impl<'a> Engine<'a> {
         let ctx = self.context;
         ctx.reset_io_time();
         ctx.autodetect_config(&environment);
+        let preprocess_cache_file = dm::preprocess_cache::PreprocessCache::path_for(&environment);
         let mut pp = match dm::preprocessor::Preprocessor::new(ctx, environment.clone()) {
             Ok(pp) => pp,
             Err(err) => {
@@ -362,18 +363,28 @@ impl<'a> Engine<'a> {
                 return Ok(());
             }
         };
+        if ctx.config().langserver.preprocess_cache {
+            pp.use_cache(dm::preprocess_cache::PreprocessCache::read(&preprocess_cache_file));
+        }
 
         let elapsed = start.elapsed(); start += elapsed;
         eprint!("setup {}.{:03}s", elapsed.as_secs(), elapsed.subsec_millis());
 
         let fatal_errored;
         {
+            let parse_cache = pp.parse_cache();
             let mut parser = dm::parser::Parser::new(ctx, dm::indents::IndentProcessor::new(ctx, &mut pp));
             parser.enable_procs();
+            parser.use_cache(parse_cache);
             let (fatal_errored_2, objtree) = parser.parse_object_tree_2();
             fatal_errored = fatal_errored_2;
             self.objtree = Arc::new(objtree);
         }
+        if let Some(cache) = pp.take_cache().filter(|cache| cache.is_changed()) {
+            if let Err(e) = cache.write(&preprocess_cache_file) {
+                eprintln!("i/o error writing {}: {}", preprocess_cache_file.display(), e);
+            }
+        }
         let elapsed = start.elapsed(); start += elapsed;
         {
             let disk = ctx.get_io_time();
@@ -423,7 +434,7 @@ impl<'a> Engine<'a> {
                 severity: Some(convert_severity(error.severity())),
                 range: location_to_range(loc),
                 source: component_to_source(error.component()),
-                code: convert_errorcode(error.errortype()),
+                code: convert_errorcode(error),
                 related_information,
                 .. Default::default()
             };
@@ -572,7 +583,7 @@ impl<'a> Engine<'a> {
                             severity: Some(convert_severity(error.severity())),
                             range: location_to_range(loc),
                             source: component_to_source(error.component()),
-                            code: convert_errorcode(error.errortype()),
+                            code: convert_errorcode(error),
                             related_information,
                             .. Default::default()
                         };
@@ -852,6 +863,20 @@ impl<'a> Engine<'a> {
                 next = ty.parent_type_without_root();
             }
         },
+        Annotation::StaticVar(parts, var_name) => {
+            if let Some(completion::TypePathResult { ty, .. }) = self.follow_type_path(&iter, parts) {
+                if let Some(decl) = ty.get_var_declaration(var_name) {
+                    symbol_id = Some(decl.id);
+                }
+            }
+        },
+        Annotation::StaticProc(parts, proc_name) => {
+            if let Some(completion::TypePathResult { ty, .. }) = self.follow_type_path(&iter, parts) {
+                if let Some(decl) = ty.get_proc_declaration(proc_name) {
+                    symbol_id = Some(decl.id);
+                }
+            }
+        },
         // TODO: macros
         }
 
@@ -1435,6 +1460,30 @@ handle_method_call! {
                 next = ty.parent_type_without_root();
             }
         },
+        Annotation::StaticVar(parts, var_name) => {
+            if let Some(completion::TypePathResult { ty, .. }) = self.follow_type_path(&iter, parts) {
+                let mut next = Some(ty);
+                while let Some(ty) = next {
+                    if let Some(var) = ty.vars.get(var_name) {
+                        results.push(self.convert_location(var.value.location, &var.value.docs, &[&ty.path, "/var/", var_name])?);
+                        break;
+                    }
+                    next = ty.parent_type_without_root();
+                }
+            }
+        },
+        Annotation::StaticProc(parts, proc_name) => {
+            if let Some(completion::TypePathResult { ty, .. }) = self.follow_type_path(&iter, parts) {
+                let mut next = Some(ty);
+                while let Some(ty) = next {
+                    if let Some(proc) = ty.procs.get(proc_name) {
+                        results.push(self.convert_location(proc.main_value().location, &proc.main_value().docs, &[&ty.path, "/proc/", proc_name])?);
+                        break;
+                    }
+                    next = ty.parent_type_without_root();
+                }
+            }
+        },
         Annotation::ParentCall => {
             if let (Some(ty), Some((proc_name, idx))) = self.find_type_context(&iter) {
                 // TODO: idx is always 0 unless there are multiple overrides in
@@ -1515,6 +1564,13 @@ handle_method_call! {
                 next = ty.parent_type_without_root();
             }
         },
+        Annotation::StaticVar(parts, var_name) => {
+            if let Some(completion::TypePathResult { ty, .. }) = self.follow_type_path(&iter, parts) {
+                if let Some(decl) = ty.get_var_declaration(var_name) {
+                    type_path = &decl.var_type.type_path;
+                }
+            }
+        },
         }
 
         if type_path.is_empty() {
@@ -2037,8 +2093,8 @@ fn convert_severity(severity: dm::Severity) -> lsp_types::DiagnosticSeverity {
     }
 }
 
-fn convert_errorcode(errortype: Option<&'static str>) -> Option<lsp_types::NumberOrString> {
-    errortype.map(|x| lsp_types::NumberOrString::String(x.to_owned()))
+fn convert_errorcode(error: &dm::DMError) -> Option<lsp_types::NumberOrString> {
+    error.code().or_else(|| error.errortype()).map(|x| lsp_types::NumberOrString::String(x.to_owned()))
 }
 
 enum UnscopedVar<'a> {
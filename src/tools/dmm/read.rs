@@ -188,7 +188,7 @@ pub fn parse_map(map: &mut Map, path: &std::path::Path) -> Result<(), DMError> {
                     max_y = max(max_y, curr_y);
                     reading_coord = Coord::Z;
                 } else {
-                    return Err(DMError::new(chars.location(), "Incorrect number of coordinates"));
+                    return Err(DMError::new(chars.location(), "Incorrect number of coordinates").with_errortype("malformed_map"));
                 }
             } else if ch == b')' {
                 assert_eq!(reading_coord, Coord::Z);
@@ -199,7 +199,7 @@ pub fn parse_map(map: &mut Map, path: &std::path::Path) -> Result<(), DMError> {
             } else {
                 match (ch as char).to_digit(10) {
                     Some(x) => curr_num = 10 * curr_num + x as usize,
-                    None => return Err(DMError::new(chars.location(), format!("bad digit {:?} in map coordinate", ch))),
+                    None => return Err(DMError::new(chars.location(), format!("bad digit {:?} in map coordinate", ch)).with_errortype("malformed_map")),
                 }
             }
         } else if in_map_string {
@@ -225,7 +225,7 @@ pub fn parse_map(map: &mut Map, path: &std::path::Path) -> Result<(), DMError> {
                     if grid.insert((curr_x, curr_y, curr_z), Key(key)).is_some() {
                         return Err(DMError::new(chars.location(), format!(
                             "multiple entries for ({}, {}, {})",
-                            curr_x, curr_y, curr_z)))
+                            curr_x, curr_y, curr_z)).with_errortype("malformed_map"))
                     }
                     max_x = max(max_x, curr_x);
                     curr_x += 1;
@@ -246,7 +246,7 @@ pub fn parse_map(map: &mut Map, path: &std::path::Path) -> Result<(), DMError> {
         } else {
             result = Err(DMError::new(chars.location(), format!(
                 "no value for tile ({}, {}, {})",
-                x + 1, y + 1, z + 1)));
+                x + 1, y + 1, z + 1)).with_errortype("malformed_map"));
             Key(0)
         }
     });
@@ -256,9 +256,9 @@ pub fn parse_map(map: &mut Map, path: &std::path::Path) -> Result<(), DMError> {
 
 fn advance_key(loc: Location, curr_key: KeyType, ch: u8) -> Result<KeyType, DMError> {
     match super::base_52_reverse(ch) {
-        Err(err) => Err(DMError::new(loc, err)),
+        Err(err) => Err(DMError::new(loc, err).with_errortype("malformed_map")),
         Ok(single) => match super::advance_key(curr_key, single) {
-            Err(err) => Err(DMError::new(loc, err)),
+            Err(err) => Err(DMError::new(loc, err).with_errortype("malformed_map")),
             Ok(key) => Ok(key),
         }
     }